@@ -29,9 +29,17 @@ mod tests {
             "app_bindings::ocel::ocel_get_event",
             "app_bindings::ocel::ocel_graph",
             "app_bindings::query::check_constraints_box",
+            "app_bindings::query::check_constraints_box_counts",
+            "app_bindings::query::validate_tree",
+            "app_bindings::query::register_calendars",
+            "app_bindings::query::parse_holidays_ics",
+            "app_bindings::query::load_cel_library",
+            "app_bindings::query::list_cel_libraries",
+            "app_bindings::query::cancel_evaluation",
             "app_bindings::query::discover_constraints",
             "app_bindings::query::export_filter_box",
             "app_bindings::query::create_db_query",
+            "app_bindings::query::run_db_query",
             "app_bindings::oc_declare::oc_declare_discover",
             "app_bindings::oc_declare::oc_declare_evaluate_arcs",
             "app_bindings::oc_declare::oc_declare_project_arcs",
@@ -1,20 +1,40 @@
 //! Binding-box queries: evaluation, constraint discovery, OCEL filtering and the SQL translation.
 //! The bindings-table byte export lives in `backend_shared::export_bindings_table_file` instead.
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
+use chrono::{DateTime, FixedOffset};
 use ocpq_core::{
     binding_box::{
-        evaluate_box_tree, filter_ocel_box_tree, BindingBoxTree, EvalPageRequest, EvalPageResponse,
-        EvaluateBoxTreeResult, EvaluateBoxTreeSummary,
+        cache::EvaluationCache,
+        control::{
+            cancel_running_evaluation, register_running_evaluation, EvaluationControl,
+            EvaluationLimits,
+        },
+        evaluate_box_tree_cached, evaluate_box_tree_controlled, evaluate_box_tree_counts,
+        filter_ocel_box_tree,
+        parameters::ParameterValues,
+        validate::{validate_tree_with_parameters, TreeValidation},
+        BindingBoxTree, EvalPageRequest, EvalPageResponse, EvaluateBoxTreeResult,
+        EvaluateBoxTreeSummary,
+    },
+    calendar::{calendar_names, holidays_from_ics, register_calendar, CalendarSpec},
+    cel::library::{cel_libraries, parse_cel_library, register_cel_library, CelLibrary},
+    db_translation::{
+        execute::{run_db_query as run_db_query_shared, DBQueryResult},
+        translate_to_sql_shared, DBTranslationInput, TranslationResult,
     },
-    db_translation::{translate_to_sql_shared, DBTranslationInput},
     discovery::{
         auto_discover_constraints_with_options, AutoDiscoverConstraintsRequest,
         AutoDiscoverConstraintsResponse,
     },
 };
 use process_mining::bindings::register_binding;
-use process_mining::core::event_data::object_centric::linked_ocel::{LinkedOCELAccess, SlimLinkedOCEL};
+use process_mining::core::event_data::object_centric::linked_ocel::{
+    LinkedOCELAccess, SlimLinkedOCEL,
+};
 
 /// Monotonic across one process, so a client paging an evaluation it no longer holds gets a clear
 /// "stale eval_version" rather than rows from a different run that happens to reuse the handle id.
@@ -22,17 +42,100 @@ static EVAL_VERSION: AtomicU64 = AtomicU64::new(0);
 
 /// Evaluate `tree` and store the result, returning a handle: a large tree's situations run to
 /// millions of rows, so the frontend pages through [`eval_results_page`] instead of getting them all.
+///
+/// With an `evaluation_id`, the run can be stopped through [`cancel_evaluation`] and reports its
+/// progress to the listener the backend installed. Nodes that stop at one of the `limits` are
+/// listed in the result's `limitHits`.
+///
+/// `source_generation` is filled in by the backend with the OCEL's generation. With it, subtrees
+/// that did not change since an earlier evaluation against the same generation are not recomputed.
+///
+/// With `explain_violations`, every violated situation also gets an explanation (the failed
+/// constraint, the offending or missing child bindings and the values of CEL sub-expressions),
+/// which the result pages and the table export include.
+///
+/// `as_of` fixes CEL's `now` and ignores all events after it, to see the compliance at that time.
+/// Without it, `now` is the time of the latest event, so re-running gives the same result.
+///
+/// With `profile_cel`, the summary lists the calls, time and errors of every CEL program, by
+/// program and by tree position.
+///
+/// `parameters` gives values to the tree's parameters; the others take their default, and the
+/// evaluation fails if one has none.
 #[register_binding(stringify_error, returns_handle)]
 pub fn check_constraints_box(
     ocel: &SlimLinkedOCEL,
     tree: BindingBoxTree,
     #[bind(default = false)] measure_performance: bool,
+    #[bind(default)] evaluation_id: Option<String>,
+    #[bind(default)] limits: EvaluationLimits,
+    #[bind(default)] source_generation: Option<u64>,
+    #[bind(default = false)] explain_violations: bool,
+    #[bind(default)] as_of: Option<DateTime<FixedOffset>>,
+    #[bind(default = false)] profile_cel: bool,
+    #[bind(default)] parameters: ParameterValues,
 ) -> Result<EvaluateBoxTreeResult, String> {
-    let mut res = evaluate_box_tree(tree, ocel, measure_performance)?;
+    let tree = tree.bind_parameters(&parameters)?;
+    let explain_tree = explain_violations.then(|| tree.clone());
+    let running = evaluation_id.map(register_running_evaluation).transpose()?;
+    let mut control = match &running {
+        Some(running) => running.control(),
+        None => EvaluationControl::default(),
+    }
+    .with_limits(limits);
+    if let Some(as_of) = as_of {
+        control = control.with_as_of(as_of);
+    }
+    if profile_cel {
+        control = control.with_cel_profiling();
+    }
+    // Generation 0 is what an object never replaced through the backend has, so it does not
+    // identify the OCEL's content. Benchmarking a cached evaluation would measure the cache, and
+    // profiling it would miss the reused subtrees. Cached results hold for the default clock only.
+    let use_cache = !measure_performance && !profile_cel && as_of.is_none();
+    let mut res = match source_generation {
+        Some(generation) if generation > 0 && use_cache => {
+            evaluate_box_tree_cached(tree, ocel, generation, EvaluationCache::shared(), &control)?
+        }
+        _ => evaluate_box_tree_controlled(tree, ocel, measure_performance, &control)?,
+    };
+    if let Some(tree) = explain_tree {
+        res.explain_violations(&tree, ocel)?;
+    }
     res.eval_version = EVAL_VERSION.fetch_add(1, Ordering::SeqCst) + 1;
     Ok(res)
 }
 
+/// Only the per-node situation and violation counts of `tree`, for when the situations themselves
+/// are not needed: nothing is stored, and memory stays bounded however many situations there are.
+/// `evaluation_id`, `limits`, `as_of`, `profile_cel` and `parameters` work as in
+/// [`check_constraints_box`].
+#[register_binding(stringify_error)]
+pub fn check_constraints_box_counts(
+    ocel: &SlimLinkedOCEL,
+    tree: BindingBoxTree,
+    #[bind(default)] evaluation_id: Option<String>,
+    #[bind(default)] limits: EvaluationLimits,
+    #[bind(default)] as_of: Option<DateTime<FixedOffset>>,
+    #[bind(default = false)] profile_cel: bool,
+    #[bind(default)] parameters: ParameterValues,
+) -> Result<EvaluateBoxTreeSummary, String> {
+    let tree = tree.bind_parameters(&parameters)?;
+    let running = evaluation_id.map(register_running_evaluation).transpose()?;
+    let mut control = match &running {
+        Some(running) => running.control(),
+        None => EvaluationControl::default(),
+    }
+    .with_limits(limits);
+    if let Some(as_of) = as_of {
+        control = control.with_as_of(as_of);
+    }
+    if profile_cel {
+        control = control.with_cel_profiling();
+    }
+    evaluate_box_tree_counts(&tree, ocel, &control)
+}
+
 /// Whether `eval` was produced from `ocel`, checked by element counts since an evaluation carries no
 /// reference to its source. An evaluation's indices are only meaningful for the log they came from.
 fn same_ocel(eval: &EvaluateBoxTreeResult, ocel: &SlimLinkedOCEL) -> Result<(), String> {
@@ -67,6 +170,65 @@ pub fn eval_results_page(
     eval.get_page(&request)
 }
 
+/// Check `tree` without an OCEL: CEL programs that do not compile, use unavailable variables,
+/// labels or child results, or filter on a non-bool value, and references to missing children or
+/// CEL libraries that are not loaded, and parameters that are unbound or of the wrong type for
+/// `parameters` (the values an evaluation would be given). Also lists the inferred result type of
+/// every CEL expression.
+#[register_binding]
+pub fn validate_tree(
+    tree: BindingBoxTree,
+    #[bind(default)] parameters: ParameterValues,
+) -> TreeValidation {
+    validate_tree_with_parameters(&tree, &parameters)
+}
+
+/// Register business calendars by name (replacing earlier ones of the same name), for the CEL
+/// calendar functions and `TimeBetweenEvents` filters. Returns the names of all calendars.
+#[register_binding(stringify_error)]
+pub fn register_calendars(calendars: HashMap<String, CalendarSpec>) -> Result<Vec<String>, String> {
+    for (name, spec) in &calendars {
+        if !spec.holiday_files.is_empty() {
+            return Err(format!(
+                "Calendar {name}: holiday files are only read from calendar files; list the holidays instead"
+            ));
+        }
+        register_calendar(name, spec)?;
+    }
+    Ok(calendar_names())
+}
+
+/// The (all-day) holidays of an iCalendar file, as `YYYY-MM-DD` dates for a [`CalendarSpec`].
+#[register_binding(stringify_error)]
+pub fn parse_holidays_ics(ics: String) -> Result<Vec<String>, String> {
+    Ok(holidays_from_ics(&ics)?
+        .into_iter()
+        .map(|d| d.to_string())
+        .collect())
+}
+
+/// Register the CEL function library of a JSON or YAML file's `content` (replacing an earlier
+/// library of the same name), so trees can call its functions. Returns all loaded libraries.
+#[register_binding(stringify_error)]
+pub fn load_cel_library(content: String) -> Result<Vec<CelLibrary>, String> {
+    register_cel_library(parse_cel_library(&content)?)?;
+    Ok(cel_libraries())
+}
+
+/// The loaded CEL function libraries, sorted by name.
+#[register_binding]
+pub fn list_cel_libraries() -> Vec<CelLibrary> {
+    cel_libraries()
+}
+
+/// Cancel the [`check_constraints_box`] (or [`check_constraints_box_counts`]) run started under
+/// `evaluation_id`; that call then fails
+/// with "Evaluation cancelled". Returns whether such a run was still going.
+#[register_binding]
+pub fn cancel_evaluation(evaluation_id: String) -> bool {
+    cancel_running_evaluation(&evaluation_id)
+}
+
 /// Discover count, eventually-follows and OR constraints, each as a named binding-box tree.
 #[register_binding]
 pub fn discover_constraints(
@@ -86,12 +248,27 @@ pub fn export_filter_box(
     filter_ocel_box_tree(tree, ocel).map(SlimLinkedOCEL::from_ocel)
 }
 
-/// Translate a binding-box tree into a SQL query against the named tables.
-#[register_binding]
-pub fn create_db_query(input: DBTranslationInput) -> String {
+/// Translate a binding-box tree, with its parameters bound to `input.parameters`, into a SQL
+/// query against the named tables, along with what of the tree the query leaves out.
+#[register_binding(stringify_error)]
+pub fn create_db_query(input: DBTranslationInput) -> Result<TranslationResult, String> {
     translate_to_sql_shared(input)
 }
 
+/// Translate `input` like [`create_db_query`] and run the query against the OCEL 2.0 SQLite or
+/// DuckDB file at `path`, which must hold `ocel`; its rows become the root node's situations.
+/// With `differential`, the tree is also evaluated in memory and the result lists every situation
+/// the two disagree on.
+#[register_binding(stringify_error)]
+pub fn run_db_query(
+    ocel: &SlimLinkedOCEL,
+    input: DBTranslationInput,
+    path: String,
+    #[bind(default = false)] differential: bool,
+) -> Result<DBQueryResult, String> {
+    run_db_query_shared(ocel, input, &path, differential)
+}
+
 /// Tests the evaluation-handle wiring (argument names, handle storage, `#[bind(handle)]`
 /// resolution) through the real registry, not `ocpq_core` itself.
 #[cfg(test)]
@@ -233,6 +410,7 @@ mod tests {
         assert_eq!(summary["nodeSummaries"][0]["situationCount"], 2);
         assert_eq!(summary["nodeSummaries"][0]["situationViolatedCount"], 1);
         assert_eq!(summary["bindingsSkipped"], false);
+        assert_eq!(summary["limitHits"], json!([]));
 
         // The version is a process-wide counter, so its value is not fixed; what matters is that
         // it is the one a page request has to carry.
@@ -279,6 +457,93 @@ mod tests {
         assert!(page(&state, &second, v2, None).is_ok());
     }
 
+    #[test]
+    fn an_evaluation_started_under_an_id_is_unlisted_once_it_finishes() {
+        let state = state();
+        let handle = invoke(
+            &state,
+            "app_bindings::query::check_constraints_box",
+            json!({ "ocel": "ocel", "tree": tree(), "evaluation_id": "eval-1" }),
+        )
+        .expect("evaluation succeeds");
+        assert!(handle.as_str().is_some_and(|h| state.contains_key(h)));
+
+        // Nothing left to cancel, and the id is free for the next run.
+        let cancelled = invoke(
+            &state,
+            "app_bindings::query::cancel_evaluation",
+            json!({ "evaluation_id": "eval-1" }),
+        )
+        .expect("cancel runs");
+        assert_eq!(cancelled, json!(false));
+        invoke(
+            &state,
+            "app_bindings::query::check_constraints_box",
+            json!({ "ocel": "ocel", "tree": tree(), "evaluation_id": "eval-1" }),
+        )
+        .expect("the id can be reused");
+    }
+
+    #[test]
+    fn a_node_stopping_at_the_binding_limit_is_reported_with_what_it_dropped() {
+        let state = state();
+        let handle = invoke(
+            &state,
+            "app_bindings::query::check_constraints_box",
+            json!({ "ocel": "ocel", "tree": tree(), "limits": { "maxBindingsPerNode": 1 } }),
+        )
+        .expect("a limit is not an error");
+        let summary = invoke(
+            &state,
+            "app_bindings::query::eval_summary",
+            json!({ "ocel": "ocel", "eval": handle }),
+        )
+        .expect("summary");
+        assert_eq!(summary["bindingsSkipped"], true);
+        assert_eq!(summary["nodeSummaries"][0]["situationCount"], 1);
+        assert_eq!(
+            summary["limitHits"],
+            json!([{ "nodeIndex": 0, "limit": "MaxBindingsPerNode", "bindingsDropped": 1 }])
+        );
+    }
+
+    #[test]
+    fn counting_returns_the_summary_without_storing_anything() {
+        let state = state();
+        let before = state.items.read().unwrap().len();
+        let summary = invoke(
+            &state,
+            "app_bindings::query::check_constraints_box_counts",
+            json!({ "ocel": "ocel", "tree": tree() }),
+        )
+        .expect("counting succeeds");
+        assert_eq!(summary["nodeSummaries"][0]["situationCount"], 2);
+        assert_eq!(summary["nodeSummaries"][0]["situationViolatedCount"], 1);
+        assert_eq!(summary["bindingsSkipped"], false);
+        assert_eq!(state.items.read().unwrap().len(), before);
+    }
+
+    #[test]
+    fn an_evaluation_with_a_source_generation_gives_the_same_result() {
+        let state = state();
+        for _ in 0..2 {
+            let handle = invoke(
+                &state,
+                "app_bindings::query::check_constraints_box",
+                json!({ "ocel": "ocel", "tree": tree(), "source_generation": 41_017 }),
+            )
+            .expect("evaluation succeeds");
+            let summary = invoke(
+                &state,
+                "app_bindings::query::eval_summary",
+                json!({ "ocel": "ocel", "eval": handle }),
+            )
+            .expect("summary");
+            assert_eq!(summary["nodeSummaries"][0]["situationCount"], 2);
+            assert_eq!(summary["nodeSummaries"][0]["situationViolatedCount"], 1);
+        }
+    }
+
     #[test]
     fn reading_a_handle_that_does_not_exist_fails_instead_of_panicking() {
         let state = state();
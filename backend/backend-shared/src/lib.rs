@@ -1,9 +1,12 @@
 pub use ocpq_core;
-pub use process_mining;
 use ocpq_core::{
-    binding_box::EvaluateBoxTreeResult,
+    binding_box::{
+        control::{cancel_running_evaluation, set_progress_listener, EvaluationProgress},
+        EvaluateBoxTreeResult,
+    },
     table_export::{export_bindings_to_writer, TableExportOptions},
 };
+pub use process_mining;
 use process_mining::{
     bindings::{self, RegistryItem, RegistryItemKind},
     core::{
@@ -19,7 +22,7 @@ use process_mining::{
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashSet;
-use std::sync::RwLock;
+use std::sync::{Arc, RwLock};
 pub mod artifact;
 pub mod meta;
 pub use meta::{ItemMeta, ItemRole, ObjMeta, Provenance};
@@ -519,6 +522,24 @@ pub fn export_object_to_path<B: Backend>(
     }
 }
 
+/// Forward the progress of every evaluation started with an `evaluation_id` to the frontend as
+/// `evaluation-progress` events. Call once, when the backend is set up.
+pub fn forward_evaluation_progress<B: Backend + Send + Sync + 'static>(backend: Arc<B>) {
+    set_progress_listener(Some(Arc::new(move |progress: &EvaluationProgress| {
+        let _ = backend.emit("evaluation-progress", progress);
+    })));
+}
+
+/// Stop the evaluation running under `evaluation_id`, the non-binding counterpart of
+/// `app_bindings::query::cancel_evaluation`.
+pub fn cancel_evaluation(evaluation_id: &str) -> Result<(), String> {
+    if cancel_running_evaluation(evaluation_id) {
+        Ok(())
+    } else {
+        Err(format!("Evaluation {evaluation_id} not found"))
+    }
+}
+
 /// Render the situations of one node of a stored evaluation as a CSV/XLSX file. Takes plain ids
 /// rather than going through [`execute_binding`]: the result is a file, not a registry/JSON value.
 pub fn export_bindings_table_file<B: Backend>(
@@ -673,6 +694,10 @@ const REGISTRY_REF: &str = "x-registry-ref";
 /// caller-chosen id instead of a generated one.
 const OUTPUT_ID_ARG: &str = "output_id";
 
+/// The argument a binding that caches by the content of its registry source takes. It is filled
+/// with the source's generation, which changes whenever the object under that id is replaced.
+const SOURCE_GENERATION_ARG: &str = "source_generation";
+
 pub fn execute_binding<B: Backend>(
     backend: &B,
     function_id: &str,
@@ -735,6 +760,19 @@ pub fn execute_binding<B: Backend>(
         }
     }
 
+    if arg_schemas
+        .iter()
+        .any(|(name, _)| name == SOURCE_GENERATION_ARG)
+    {
+        let generation = registry_ref_arg_ids(&original_args, &arg_schemas)
+            .iter()
+            .map(|id| st.meta.generation_of(id))
+            .max();
+        if let (Some(generation), Some(obj)) = (generation, args.as_object_mut()) {
+            obj.insert(SOURCE_GENERATION_ARG.to_string(), generation.into());
+        }
+    }
+
     let result = bindings::call(binding, &args, &st.inner);
 
     if let (true, Ok(bytes)) = (stores_result, &result) {
@@ -1142,6 +1180,50 @@ mod provenance_dispatch_tests {
         assert_eq!(prov.sources, vec!["ocel1".to_string()]);
         assert!(prov.op["fn"].as_str().unwrap().ends_with("test_clone_ocel"));
     }
+
+    #[register_binding]
+    fn test_echo_source_generation(
+        ocel: &process_mining::OCEL,
+        #[bind(default)] source_generation: Option<u64>,
+    ) -> Option<u64> {
+        let _ = ocel;
+        source_generation
+    }
+
+    #[test]
+    fn passes_the_generation_of_the_source_to_a_binding_that_takes_it() {
+        let b = B {
+            state: ExtendedAppState::default(),
+            events: Mutex::new(Vec::new()),
+        };
+        let empty = || process_mining::OCEL {
+            event_types: Vec::new(),
+            object_types: Vec::new(),
+            events: Vec::new(),
+            objects: Vec::new(),
+        };
+        let fid = list_functions()
+            .into_iter()
+            .map(|f| f.id)
+            .find(|id| id.ends_with("test_echo_source_generation"))
+            .expect("test binding registered");
+        let generation_seen = |b: &B| -> u64 {
+            let out = execute_binding(b, &fid, &json!({ "ocel": "ocel1" }), None).unwrap();
+            serde_json::from_slice(&out).unwrap()
+        };
+
+        mark_replaced(b.get_state(), "ocel1");
+        b.get_state().add("ocel1".to_string(), empty());
+        let first = generation_seen(&b);
+        assert_eq!(first, b.get_state().meta.generation_of("ocel1"));
+
+        // Removing the object resets its entry; the replacement must still not reuse the generation
+        // a cache may hold results for.
+        b.get_state().meta.remove("ocel1");
+        mark_replaced(b.get_state(), "ocel1");
+        b.get_state().add("ocel1".to_string(), empty());
+        assert_ne!(generation_seen(&b), first);
+    }
 }
 
 #[cfg(test)]
@@ -1216,6 +1298,8 @@ mod export_bindings_table_tests {
         let tree = BindingBoxTree {
             nodes: vec![node],
             edge_names: Map::new(),
+            cel_libraries: Vec::new(),
+            parameters: Vec::new(),
         };
         let eval = evaluate_box_tree(tree, &ocel, false).expect("evaluation succeeds");
         b.get_state().add("ocel", ocel);
@@ -1254,8 +1338,9 @@ mod export_bindings_table_tests {
     #[test]
     fn out_of_range_node_index_is_an_error() {
         let b = seeded_backend();
-        let err = export_bindings_table_file(&b, "ocel", "eval", 99, &TableExportOptions::default())
-            .unwrap_err();
+        let err =
+            export_bindings_table_file(&b, "ocel", "eval", 99, &TableExportOptions::default())
+                .unwrap_err();
         assert!(err.contains("out of range"), "got {err}");
     }
 }
@@ -2,6 +2,7 @@
 //! `process_mining`'s `AppState` so the registry stays a plain id -> item map.
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::RwLock;
 
 use serde::{Deserialize, Serialize};
@@ -34,6 +35,9 @@ pub struct ObjMeta {
     /// User-facing display labels, kept as a side-map so renaming a dataset never touches the
     /// lifecycle policy above; lives in the engine so a relabel survives a frontend reload.
     labels: RwLock<HashMap<String, String>>,
+    /// Last generation handed out, over all ids. Generations are never reused, so one identifies
+    /// a single version of an object's content even after the id's entry was removed.
+    last_generation: AtomicU64,
 }
 
 impl ObjMeta {
@@ -106,12 +110,13 @@ impl ObjMeta {
         };
     }
     pub fn bump_generation(&self, id: &str) {
+        let generation = self.last_generation.fetch_add(1, Ordering::Relaxed) + 1;
         let mut g = self.inner.write().unwrap_or_else(|e| e.into_inner());
         g.entry(id.to_string())
-            .and_modify(|m| m.generation += 1)
+            .and_modify(|m| m.generation = generation)
             .or_insert(ItemMeta {
                 role: ItemRole::Primary,
-                generation: 1,
+                generation,
                 provenance: None,
             });
     }
@@ -195,6 +200,18 @@ mod tests {
         assert_eq!(m.generation_of("s"), 2);
     }
 
+    #[test]
+    fn bump_generation_never_repeats_across_ids_or_removals() {
+        let m = ObjMeta::default();
+        m.bump_generation("a");
+        m.bump_generation("b");
+        assert_ne!(m.generation_of("a"), m.generation_of("b"));
+        let before = m.generation_of("a");
+        m.remove("a");
+        m.bump_generation("a");
+        assert!(m.generation_of("a") > before);
+    }
+
     #[test]
     fn provenance_source_gen_reads_back() {
         let m = ObjMeta::default();
@@ -0,0 +1,375 @@
+//! Aggregates over the bindings of a child, computed natively instead of through CEL.
+//!
+//! They back [`LabelFunction::aggregate`](super::structs::LabelFunction) and
+//! [`SizeFilter::Aggregate`](super::structs::SizeFilter), and never build CEL values for the
+//! child bindings.
+use std::{cmp::Ordering, collections::HashMap, collections::HashSet, sync::Arc};
+
+use chrono::{DateTime, FixedOffset};
+use itertools::Itertools;
+use ordered_float::OrderedFloat;
+use process_mining::core::event_data::object_centric::{
+    linked_ocel::{LinkedOCELAccess, SlimLinkedOCEL},
+    OCELAttributeValue,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::structs::{
+    Binding, EventVariable, LabelValue, ObjectVariable, Variable, ViolationReason,
+};
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum AggregateFunction {
+    /// Number of child bindings (with a value: those where it is not null)
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+    /// Number of different non-null values
+    DistinctCount,
+}
+
+/// What an aggregate reads from every child binding.
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type")]
+pub enum AggregateValue {
+    EventAttribute {
+        event: EventVariable,
+        attribute_name: String,
+    },
+    /// The last value the attribute changed to (by the evaluation's "now")
+    ObjectAttribute {
+        object: ObjectVariable,
+        attribute_name: String,
+    },
+    Label {
+        label: String,
+    },
+    /// The id of the bound event or object
+    Variable {
+        variable: Variable,
+    },
+}
+
+/// An aggregate of `value` over the bindings of the child `child_name`.
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChildAggregate {
+    pub child_name: String,
+    pub function: AggregateFunction,
+    /// Not needed for [`AggregateFunction::Count`]
+    #[serde(default)]
+    #[ts(optional)]
+    pub value: Option<AggregateValue>,
+}
+
+impl From<&OCELAttributeValue> for LabelValue {
+    fn from(value: &OCELAttributeValue) -> Self {
+        match value {
+            OCELAttributeValue::Float(f) => LabelValue::Float(OrderedFloat(*f)),
+            OCELAttributeValue::Integer(i) => LabelValue::Int(*i),
+            OCELAttributeValue::String(s) => LabelValue::String(Arc::new(s.clone())),
+            OCELAttributeValue::Time(t) => LabelValue::String(Arc::new(t.to_rfc3339())),
+            OCELAttributeValue::Boolean(b) => LabelValue::Bool(*b),
+            OCELAttributeValue::Null => LabelValue::Null,
+        }
+    }
+}
+
+impl AggregateValue {
+    /// The value of `b`; object attribute values set after `now` are not seen yet (see
+    /// [`super::control::EvaluationControl::as_of`]).
+    pub fn of(&self, b: &Binding, ocel: &SlimLinkedOCEL, now: DateTime<FixedOffset>) -> LabelValue {
+        match self {
+            AggregateValue::EventAttribute {
+                event,
+                attribute_name,
+            } => b
+                .get_ev(event, ocel)
+                .and_then(|e| {
+                    e.attributes
+                        .iter()
+                        .find(|at| &at.name == attribute_name)
+                        .map(|at| (&at.value).into())
+                })
+                .unwrap_or(LabelValue::Null),
+            AggregateValue::ObjectAttribute {
+                object,
+                attribute_name,
+            } => b
+                .get_ob(object, ocel)
+                .and_then(|o| {
+                    o.attributes
+                        .iter()
+                        .filter(|at| &at.name == attribute_name && at.time <= now)
+                        .max_by_key(|at| at.time)
+                        .map(|at| (&at.value).into())
+                })
+                .unwrap_or(LabelValue::Null),
+            AggregateValue::Label { label } => b
+                .get_label_value(label)
+                .cloned()
+                .unwrap_or(LabelValue::Null),
+            AggregateValue::Variable { variable } => {
+                let id = match variable {
+                    Variable::Event(ev) => b.get_ev_index(ev).map(|e| ocel.get_ev_id(e)),
+                    Variable::Object(ob) => b.get_ob_index(ob).map(|o| ocel.get_ob_id(o)),
+                };
+                id.map(|id| LabelValue::String(Arc::new(id.to_string())))
+                    .unwrap_or(LabelValue::Null)
+            }
+        }
+    }
+}
+
+/// Numbers compare by value and before everything else; other values compare within their kind.
+fn compare(a: &LabelValue, b: &LabelValue) -> Ordering {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(x), Some(y)) => OrderedFloat(x).cmp(&OrderedFloat(y)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => match (a, b) {
+            (LabelValue::String(x), LabelValue::String(y)) => x.cmp(y),
+            (LabelValue::Bool(x), LabelValue::Bool(y)) => x.cmp(y),
+            (LabelValue::String(_), _) => Ordering::Less,
+            (_, LabelValue::String(_)) => Ordering::Greater,
+            _ => Ordering::Equal,
+        },
+    }
+}
+
+impl ChildAggregate {
+    /// Aggregate over the bindings of the child in `child_res`, as of `now`. Null values are
+    /// skipped, so the aggregate of no values is `Null` (or `0` for the counts).
+    pub fn compute(
+        &self,
+        child_res: &HashMap<String, Vec<(Arc<Binding>, Option<ViolationReason>)>>,
+        ocel: &SlimLinkedOCEL,
+        now: DateTime<FixedOffset>,
+    ) -> Result<LabelValue, String> {
+        let c_res = child_res
+            .get(&self.child_name)
+            .ok_or_else(|| format!("No child named {}", self.child_name))?;
+        let values = match &self.value {
+            Some(value) => c_res
+                .iter()
+                .map(|(b, _)| value.of(b, ocel, now))
+                .filter(|v| v != &LabelValue::Null)
+                .collect_vec(),
+            None if self.function == AggregateFunction::Count => {
+                return Ok(LabelValue::Int(c_res.len() as i64));
+            }
+            None => {
+                return Err(format!(
+                    "Aggregate {:?} over child {} needs a value",
+                    self.function, self.child_name
+                ))
+            }
+        };
+        Ok(match self.function {
+            AggregateFunction::Count => LabelValue::Int(values.len() as i64),
+            AggregateFunction::DistinctCount => {
+                LabelValue::Int(values.iter().collect::<HashSet<_>>().len() as i64)
+            }
+            AggregateFunction::Sum | AggregateFunction::Avg => {
+                let numbers = values.iter().filter_map(LabelValue::as_f64).collect_vec();
+                if numbers.is_empty() {
+                    LabelValue::Null
+                } else if self.function == AggregateFunction::Avg {
+                    LabelValue::Float(OrderedFloat(
+                        numbers.iter().sum::<f64>() / numbers.len() as f64,
+                    ))
+                } else if let Some(sum) = values.iter().try_fold(0i64, |sum, v| match v {
+                    LabelValue::Int(i) => sum.checked_add(*i),
+                    _ => None,
+                }) {
+                    LabelValue::Int(sum)
+                } else {
+                    // Some values are floats, or the integers' sum overflows
+                    LabelValue::Float(OrderedFloat(numbers.iter().sum()))
+                }
+            }
+            AggregateFunction::Min => values
+                .into_iter()
+                .min_by(compare)
+                .unwrap_or(LabelValue::Null),
+            AggregateFunction::Max => values
+                .into_iter()
+                .max_by(compare)
+                .unwrap_or(LabelValue::Null),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binding_box::{
+        structs::{BindingBox, BindingBoxTreeNode, LabelFunction, SizeFilter},
+        test_utils::{bound_object_ids, ev_vars, eval, o2e, ob_vars, ocel, tree},
+    };
+
+    fn aggregate(
+        child_name: &str,
+        function: AggregateFunction,
+        value: Option<AggregateValue>,
+    ) -> ChildAggregate {
+        ChildAggregate {
+            child_name: child_name.to_string(),
+            function,
+            value,
+        }
+    }
+
+    #[test]
+    fn aggregate_labels_and_size_filters_are_computed_over_the_child_bindings() {
+        let amount = AggregateValue::EventAttribute {
+            event: EventVariable(0),
+            attribute_name: "amount".to_string(),
+        };
+        let label = |label: &str, aggregate: ChildAggregate| LabelFunction {
+            label: label.to_string(),
+            aggregate: Some(aggregate),
+            ..Default::default()
+        };
+        let root = |size_filters: Vec<SizeFilter>| {
+            BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_object_vars: ob_vars(&[(0, "order")]),
+                    size_filters,
+                    labels: vec![
+                        label("places", aggregate("A", AggregateFunction::Count, None)),
+                        label(
+                            "total",
+                            aggregate("A", AggregateFunction::Sum, Some(amount.clone())),
+                        ),
+                        label(
+                            "ships",
+                            aggregate(
+                                "B",
+                                AggregateFunction::DistinctCount,
+                                Some(AggregateValue::Variable {
+                                    variable: Variable::Event(EventVariable(1)),
+                                }),
+                            ),
+                        ),
+                        // Ships have no amount, so there is nothing to take the maximum of.
+                        label(
+                            "max_ship_amount",
+                            aggregate(
+                                "B",
+                                AggregateFunction::Max,
+                                Some(AggregateValue::EventAttribute {
+                                    event: EventVariable(1),
+                                    attribute_name: "amount".to_string(),
+                                }),
+                            ),
+                        ),
+                    ],
+                    ..Default::default()
+                },
+                vec![1, 2],
+            )
+        };
+        let child = |var: usize, ev_type: &str| {
+            BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_event_vars: ev_vars(&[(var, ev_type)]),
+                    filters: vec![o2e(0, var, None)],
+                    ..Default::default()
+                },
+                vec![],
+            )
+        };
+        let edges = [((0, 1), "A"), ((0, 2), "B")];
+
+        let res = eval(tree(
+            vec![root(vec![]), child(0, "place"), child(1, "ship")],
+            &edges,
+        ));
+        let labels = res.evaluation_results[0]
+            .situations
+            .iter()
+            .map(|(b, _)| {
+                ["places", "total", "ships", "max_ship_amount"]
+                    .map(|l| b.get_label_value(l).cloned().expect("label set"))
+            })
+            .zip(bound_object_ids(&res, 0, 0))
+            .sorted_by_key(|(_, o)| *o)
+            .map(|(labels, _)| labels)
+            .collect_vec();
+        assert_eq!(
+            labels,
+            vec![
+                [
+                    LabelValue::Int(1),
+                    LabelValue::Int(100),
+                    LabelValue::Int(2),
+                    LabelValue::Null
+                ],
+                [
+                    LabelValue::Int(1),
+                    LabelValue::Int(5),
+                    LabelValue::Int(0),
+                    LabelValue::Null
+                ],
+            ]
+        );
+
+        let res = eval(tree(
+            vec![
+                root(vec![SizeFilter::Aggregate {
+                    aggregate: aggregate("A", AggregateFunction::Avg, Some(amount.clone())),
+                    min: Some(50.0),
+                    max: None,
+                }]),
+                child(0, "place"),
+                child(1, "ship"),
+            ],
+            &edges,
+        ));
+        assert_eq!(bound_object_ids(&res, 0, 0), vec!["o1"]);
+    }
+
+    #[test]
+    fn integer_sums_that_overflow_are_summed_as_floats() {
+        let child_res = |values: &[i64]| {
+            let bindings = values
+                .iter()
+                .map(|v| {
+                    let mut b = Binding::default();
+                    b.add_label("v".to_string(), LabelValue::Int(*v));
+                    (std::sync::Arc::new(b), None)
+                })
+                .collect_vec();
+            HashMap::from([("A".to_string(), bindings)])
+        };
+        let now = chrono::Local::now().into();
+        let sum = aggregate(
+            "A",
+            AggregateFunction::Sum,
+            Some(AggregateValue::Label {
+                label: "v".to_string(),
+            }),
+        );
+        assert_eq!(
+            sum.compute(&child_res(&[i64::MAX - 1, 1]), &ocel(), now),
+            Ok(LabelValue::Int(i64::MAX))
+        );
+        assert_eq!(
+            sum.compute(&child_res(&[i64::MAX, 1]), &ocel(), now),
+            Ok(LabelValue::Float(ordered_float::OrderedFloat(
+                i64::MAX as f64 + 1.0
+            )))
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! Per-node results kept between evaluations, so re-evaluating a tree after an edit only
+//! recomputes the edited node and its ancestors.
+//!
+//! What a node yields for a given parent binding depends only on its subtree and the OCEL. Results
+//! are therefore keyed by a structural hash of the subtree, which ignores node indices and
+//! includes edge names and child hashes, plus a caller-supplied key that must change whenever the
+//! OCEL does (the backend uses the OCEL's generation), plus the parent binding.
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+
+use super::structs::{
+    Binding, BindingBoxTree, BindingBoxTreeNode, EvaluationResults, ViolationReason,
+};
+
+/// Default for [`EvaluationCache::new`]'s `max_situations`.
+pub const DEFAULT_MAX_CACHED_SITUATIONS: usize = 5_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct SubtreeKey {
+    subtree_hash: u64,
+    ocel_key: u64,
+}
+
+/// What one node yielded for one parent binding. Node indices are relative to the subtree (its
+/// pre-order position), so the entry stays valid when the node moves within the tree.
+struct CachedResult {
+    results: Vec<(usize, Arc<Binding>, Option<ViolationReason>)>,
+    violations: Vec<(Arc<Binding>, Option<ViolationReason>)>,
+}
+
+#[derive(Default)]
+struct CachedSubtree {
+    by_parent: HashMap<Binding, Arc<CachedResult>>,
+    situations: usize,
+    /// Stamp of the last evaluation that used this subtree; the least recent are evicted first.
+    last_used: u64,
+}
+
+/// Node results of past evaluations. Thread-safe, so one cache can serve concurrent evaluations.
+pub struct EvaluationCache {
+    subtrees: Mutex<HashMap<SubtreeKey, CachedSubtree>>,
+    max_situations: usize,
+    evaluations: AtomicU64,
+    hits: AtomicUsize,
+}
+
+impl Default for EvaluationCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CACHED_SITUATIONS)
+    }
+}
+
+static SHARED_CACHE: Lazy<EvaluationCache> = Lazy::new(EvaluationCache::default);
+
+impl EvaluationCache {
+    /// A cache that, after each evaluation, evicts the least recently used subtrees until at
+    /// most `max_situations` situations are kept.
+    pub fn new(max_situations: usize) -> Self {
+        Self {
+            subtrees: Mutex::new(HashMap::new()),
+            max_situations,
+            evaluations: AtomicU64::new(0),
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    /// The process-wide cache the app bindings evaluate against.
+    pub fn shared() -> &'static EvaluationCache {
+        &SHARED_CACHE
+    }
+
+    /// Number of node evaluations answered from the cache so far.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Number of situations currently kept.
+    pub fn cached_situations(&self) -> usize {
+        self.subtrees
+            .lock()
+            .map(|s| s.values().map(|c| c.situations).sum())
+            .unwrap_or(0)
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut subtrees) = self.subtrees.lock() {
+            subtrees.clear();
+        }
+    }
+
+    /// The view one evaluation of `tree` against the OCEL identified by `ocel_key` uses.
+    pub(crate) fn scope(&self, tree: &BindingBoxTree, ocel_key: u64) -> CacheScope<'_> {
+        let stamp = self.evaluations.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut subtrees: Vec<Option<Subtree>> = vec![None; tree.nodes.len()];
+        for idx in 0..tree.nodes.len() {
+            subtree_of(tree, idx, &mut subtrees, &mut HashSet::new());
+        }
+        // Redefining a CEL library function or a business calendar, or binding a parameter (which
+        // CEL reads as a variable) to another value, may change what any subtree yields
+        let mut key_hasher = DefaultHasher::new();
+        (
+            ocel_key,
+            crate::cel::library::generation(),
+            crate::calendar::generation(),
+            serde_json::to_string(&tree.parameters).unwrap_or_default(),
+        )
+            .hash(&mut key_hasher);
+        CacheScope {
+            cache: self,
+            ocel_key: key_hasher.finish(),
+            stamp,
+            subtrees: subtrees
+                .into_iter()
+                .map(Option::unwrap_or_default)
+                .collect(),
+            incomplete: AtomicBool::new(false),
+        }
+    }
+
+    fn evict(&self) {
+        let Ok(mut subtrees) = self.subtrees.lock() else {
+            return;
+        };
+        let mut total: usize = subtrees.values().map(|c| c.situations).sum();
+        if total <= self.max_situations {
+            return;
+        }
+        let by_age = subtrees
+            .iter()
+            .map(|(k, c)| (c.last_used, *k))
+            .sorted()
+            .collect_vec();
+        for (_, key) in by_age {
+            if total <= self.max_situations {
+                break;
+            }
+            if let Some(c) = subtrees.remove(&key) {
+                total -= c.situations;
+            }
+        }
+    }
+}
+
+/// Structural identity of the subtree below one node.
+#[derive(Debug, Clone, Default)]
+struct Subtree {
+    hash: u64,
+    /// Node indices of the subtree in pre-order; the position of a node is its local index.
+    nodes: Vec<usize>,
+}
+
+/// Compute (and memoize in `subtrees`) the subtree of `idx`. `on_path` guards against cycles in
+/// malformed trees, whose nodes then simply hash without the offending child.
+fn subtree_of(
+    tree: &BindingBoxTree,
+    idx: usize,
+    subtrees: &mut Vec<Option<Subtree>>,
+    on_path: &mut HashSet<usize>,
+) -> Option<Subtree> {
+    if let Some(s) = subtrees.get(idx)? {
+        return Some(s.clone());
+    }
+    if !on_path.insert(idx) {
+        return None;
+    }
+    let node = &tree.nodes[idx];
+    let mut hasher = DefaultHasher::new();
+    hash_node_content(node, &mut hasher);
+    let mut nodes = vec![idx];
+    let (_, children) = node.to_box(idx, tree);
+    for &c in children.iter() {
+        if let Some(child) = subtree_of(tree, c, subtrees, on_path) {
+            tree.edge_name(idx, c).hash(&mut hasher);
+            child.hash.hash(&mut hasher);
+            nodes.extend(child.nodes);
+        }
+    }
+    on_path.remove(&idx);
+    let subtree = Subtree {
+        hash: hasher.finish(),
+        nodes,
+    };
+    subtrees[idx] = Some(subtree.clone());
+    Some(subtree)
+}
+
+/// Hash everything about a node that affects what it yields. Variable declarations are hash
+/// maps/sets, so they are hashed sorted; the rest has a fixed order and is hashed as JSON.
+fn hash_node_content(node: &BindingBoxTreeNode, hasher: &mut DefaultHasher) {
+    match node {
+        BindingBoxTreeNode::Box(b, _) => {
+            "Box".hash(hasher);
+            for (v, types) in b.new_event_vars.iter().sorted_by_key(|(v, _)| **v) {
+                v.hash(hasher);
+                types.iter().sorted().collect_vec().hash(hasher);
+            }
+            for (v, types) in b.new_object_vars.iter().sorted_by_key(|(v, _)| **v) {
+                v.hash(hasher);
+                types.iter().sorted().collect_vec().hash(hasher);
+            }
+            serde_json::to_string(&b.filters)
+                .unwrap_or_default()
+                .hash(hasher);
+            serde_json::to_string(&b.size_filters)
+                .unwrap_or_default()
+                .hash(hasher);
+            serde_json::to_string(&b.constraints)
+                .unwrap_or_default()
+                .hash(hasher);
+            serde_json::to_string(&b.labels)
+                .unwrap_or_default()
+                .hash(hasher);
+        }
+        BindingBoxTreeNode::OR(_, _) => "OR".hash(hasher),
+        BindingBoxTreeNode::AND(_, _) => "AND".hash(hasher),
+        BindingBoxTreeNode::NOT(_) => "NOT".hash(hasher),
+    }
+}
+
+/// The cache as seen by one evaluation: the subtree of every node of the evaluated tree, and the
+/// OCEL it runs against. Evicts down to the cache's bound when the evaluation ends.
+pub(crate) struct CacheScope<'a> {
+    cache: &'a EvaluationCache,
+    ocel_key: u64,
+    stamp: u64,
+    subtrees: Vec<Subtree>,
+    /// Set once some node skipped bindings: its ancestors see incomplete child results, so from
+    /// then on nothing is stored.
+    incomplete: AtomicBool,
+}
+
+impl CacheScope<'_> {
+    fn key(&self, node_index: usize) -> SubtreeKey {
+        SubtreeKey {
+            subtree_hash: self.subtrees[node_index].hash,
+            ocel_key: self.ocel_key,
+        }
+    }
+
+    /// The results of `node_index` for `parent`, if an earlier evaluation of the same subtree
+    /// against the same OCEL produced them.
+    pub(crate) fn get(
+        &self,
+        node_index: usize,
+        parent: &Binding,
+    ) -> Option<(
+        EvaluationResults,
+        Vec<(Arc<Binding>, Option<ViolationReason>)>,
+    )> {
+        let cached = {
+            let mut subtrees = self.cache.subtrees.lock().ok()?;
+            let subtree = subtrees.get_mut(&self.key(node_index))?;
+            subtree.last_used = self.stamp;
+            Arc::clone(subtree.by_parent.get(parent)?)
+        };
+        self.cache.hits.fetch_add(1, Ordering::Relaxed);
+        let nodes = &self.subtrees[node_index].nodes;
+        let results = cached
+            .results
+            .iter()
+            .map(|(local, b, v)| (nodes[*local], Arc::clone(b), *v))
+            .collect();
+        Some((results, cached.violations.clone()))
+    }
+
+    /// Stop storing results for the rest of this evaluation.
+    pub(crate) fn mark_incomplete(&self) {
+        self.incomplete.store(true, Ordering::Relaxed);
+    }
+
+    /// Keep what `node_index` yielded for `parent`, unless the evaluation is incomplete. Results
+    /// whose node is not part of the subtree cannot be stored relative to it; then nothing is kept.
+    pub(crate) fn insert(
+        &self,
+        node_index: usize,
+        parent: Binding,
+        results: &EvaluationResults,
+        violations: &[(Arc<Binding>, Option<ViolationReason>)],
+    ) {
+        if self.incomplete.load(Ordering::Relaxed) {
+            return;
+        }
+        let nodes = &self.subtrees[node_index].nodes;
+        let local_of: HashMap<usize, usize> =
+            nodes.iter().enumerate().map(|(l, g)| (*g, l)).collect();
+        let Some(results) = results
+            .iter()
+            .map(|(g, b, v)| Some((*local_of.get(g)?, Arc::clone(b), *v)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return;
+        };
+        let Ok(mut subtrees) = self.cache.subtrees.lock() else {
+            return;
+        };
+        let subtree = subtrees.entry(self.key(node_index)).or_default();
+        subtree.last_used = self.stamp;
+        subtree.situations += results.len();
+        let previous = subtree.by_parent.insert(
+            parent,
+            Arc::new(CachedResult {
+                results,
+                violations: violations.to_vec(),
+            }),
+        );
+        if let Some(previous) = previous {
+            subtree.situations -= previous.results.len();
+        }
+    }
+}
+
+impl Drop for CacheScope<'_> {
+    fn drop(&mut self) {
+        self.cache.evict();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binding_box::{
+        control::EvaluationControl,
+        evaluate_box_tree_cached,
+        test_utils::{eval, ocel, order_payment_shipping_tree},
+        EvaluateBoxTreeSummary,
+    };
+
+    fn node_counts(summary: &EvaluateBoxTreeSummary) -> Vec<(usize, usize)> {
+        summary
+            .node_summaries
+            .iter()
+            .map(|n| (n.situation_count, n.situation_violated_count))
+            .collect()
+    }
+
+    #[test]
+    fn re_evaluating_after_an_edit_reuses_the_unchanged_subtrees() {
+        let cache = EvaluationCache::default();
+        let control = EvaluationControl::default;
+        let t = order_payment_shipping_tree();
+        evaluate_box_tree_cached(t.clone(), &ocel(), 1, &cache, &control()).unwrap();
+        assert_eq!(cache.hits(), 0);
+
+        // Editing the `ship` node changes it and the root, but not the `pay` node.
+        let mut edited = t;
+        if let BindingBoxTreeNode::Box(b, _) = &mut edited.nodes[2] {
+            b.constraints.clear();
+        }
+        let res = evaluate_box_tree_cached(edited.clone(), &ocel(), 1, &cache, &control()).unwrap();
+        assert_eq!(cache.hits(), 2, "the pay node, once per order");
+        assert_eq!(
+            node_counts(&res.summary()),
+            node_counts(&eval(edited.clone()).summary())
+        );
+
+        evaluate_box_tree_cached(edited.clone(), &ocel(), 1, &cache, &control()).unwrap();
+        assert_eq!(cache.hits(), 3, "an unchanged tree is answered at its root");
+
+        evaluate_box_tree_cached(edited, &ocel(), 2, &cache, &control()).unwrap();
+        assert_eq!(cache.hits(), 3, "nothing is reused for another OCEL");
+    }
+
+    #[test]
+    fn cached_results_follow_their_nodes_to_new_indices() {
+        let cache = EvaluationCache::default();
+        let t = order_payment_shipping_tree();
+        let first =
+            evaluate_box_tree_cached(t.clone(), &ocel(), 1, &cache, &EvaluationControl::default())
+                .unwrap();
+
+        // Same tree with the two children swapped in `nodes`; the root lists them in the same
+        // order, so the whole tree is unchanged.
+        let mut renumbered = t;
+        renumbered.nodes.swap(1, 2);
+        if let BindingBoxTreeNode::Box(_, children) = &mut renumbered.nodes[0] {
+            *children = vec![2, 1];
+        }
+        renumbered.edge_names = [((0, 2), "A".to_string()), ((0, 1), "B".to_string())]
+            .into_iter()
+            .collect();
+        let second = evaluate_box_tree_cached(
+            renumbered,
+            &ocel(),
+            1,
+            &cache,
+            &EvaluationControl::default(),
+        )
+        .unwrap();
+        assert_eq!(cache.hits(), 1);
+        let (a, b) = (
+            node_counts(&first.summary()),
+            node_counts(&second.summary()),
+        );
+        assert_eq!((b[0], b[1], b[2]), (a[0], a[2], a[1]));
+    }
+}
@@ -0,0 +1,723 @@
+//! Cooperative cancellation, progress reporting and resource limits for a running evaluation.
+//!
+//! Evaluation is synchronous CPU work on the rayon pool, so it cannot be aborted from outside; it
+//! instead polls an [`EvaluationControl`] between bindings and bails out with
+//! [`EVALUATION_CANCELLED`] once its token is cancelled. Exceeding one of the
+//! [`EvaluationLimits`] does not fail the evaluation: expansion stops, and the affected nodes are
+//! reported as [`LimitHit`]s next to the (then incomplete) results.
+//!
+//! The control also fixes the evaluation's clock (see [`EvaluationControl::as_of`]), so that CEL's
+//! `now` does not depend on when an evaluation happens to run, and collects the evaluation's CEL
+//! profile if asked to (see [`EvaluationControl::with_cel_profiling`]).
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock, RwLock,
+    },
+};
+
+use chrono::{DateTime, FixedOffset, Local};
+use once_cell::sync::Lazy;
+use process_mining::core::event_data::object_centric::linked_ocel::{
+    LinkedOCELAccess, SlimLinkedOCEL,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::{
+    structs::{Binding, BindingBoxTree},
+    time_index::TimeIndex,
+};
+use crate::{
+    cel::{
+        profile::{CelProfile, CelProfiler},
+        CelEnv,
+    },
+    timing::Timer,
+};
+
+/// Error message a cancelled evaluation fails with, so callers can tell it apart from a real error.
+pub const EVALUATION_CANCELLED: &str = "Evaluation cancelled";
+
+/// Number of expanded bindings between two progress reports.
+const PROGRESS_INTERVAL: usize = 100_000;
+
+/// Expansion steps (over all threads) between two checks of the memory/time budget.
+const BUDGET_CHECK_INTERVAL: usize = 1 << 12;
+
+/// Default for [`EvaluationLimits::max_bindings_per_node`].
+pub const DEFAULT_MAX_BINDINGS_PER_NODE: usize = 10_000_000;
+
+/// Shared flag checked by a running evaluation. Clones refer to the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationProgress {
+    /// Id the evaluation was started under, if any (see [`register_running_evaluation`]).
+    pub evaluation_id: Option<String>,
+    /// Node whose expansion crossed the last reporting threshold.
+    pub node_index: usize,
+    /// Bindings expanded so far, over all nodes.
+    pub bindings_expanded: usize,
+}
+
+pub type ProgressCallback = Arc<dyn Fn(&EvaluationProgress) + Send + Sync>;
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EvaluationLimits {
+    /// Bindings a single expansion of a node may produce; `None` for no limit.
+    pub max_bindings_per_node: Option<usize>,
+    /// Estimated size of all bindings materialized during the evaluation, in MiB.
+    pub max_memory_mb: Option<usize>,
+    /// Wall-clock time the evaluation may take. Not enforced on wasm, which has no clock.
+    pub timeout_seconds: Option<f64>,
+}
+
+impl Default for EvaluationLimits {
+    fn default() -> Self {
+        Self {
+            max_bindings_per_node: Some(DEFAULT_MAX_BINDINGS_PER_NODE),
+            max_memory_mb: None,
+            timeout_seconds: None,
+        }
+    }
+}
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+pub enum EvaluationLimitKind {
+    MaxBindingsPerNode,
+    MemoryBudget,
+    Timeout,
+}
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitHit {
+    pub node_index: usize,
+    pub limit: EvaluationLimitKind,
+    /// Bindings that were enumerated but then discarded. Enumeration stops at the limit, so the
+    /// number of bindings missing from the result can be higher.
+    pub bindings_dropped: usize,
+}
+
+/// Why, and by how much, one expansion was cut short.
+pub(crate) struct Truncation {
+    pub(crate) limit: EvaluationLimitKind,
+    pub(crate) bindings_dropped: usize,
+}
+
+/// Cancellation, progress and limit state of one evaluation, passed by reference through the
+/// evaluator.
+pub struct EvaluationControl {
+    evaluation_id: Option<String>,
+    cancel: CancellationToken,
+    on_progress: Option<ProgressCallback>,
+    bindings_expanded: AtomicUsize,
+    limits: EvaluationLimits,
+    started: Timer,
+    bytes_materialized: AtomicUsize,
+    /// Expansion steps taken, to throttle [`Self::poll_budget`].
+    budget_polls: AtomicUsize,
+    /// The first of the memory/time budgets that ran out; from then on nothing is expanded.
+    exhausted: OnceLock<EvaluationLimitKind>,
+    limit_hits: Mutex<HashMap<(usize, EvaluationLimitKind), usize>>,
+    /// The evaluation's "now"; resolved on first use unless set through [`Self::with_as_of`].
+    as_of: OnceLock<DateTime<FixedOffset>>,
+    cel_profiler: Option<CelProfiler>,
+    /// Built on first use, like `as_of`; an evaluation runs against one OCEL only.
+    time_index: OnceLock<TimeIndex>,
+}
+
+impl Default for EvaluationControl {
+    fn default() -> Self {
+        Self::new(CancellationToken::default())
+    }
+}
+
+impl EvaluationControl {
+    pub fn new(cancel: CancellationToken) -> Self {
+        Self {
+            evaluation_id: None,
+            cancel,
+            on_progress: None,
+            bindings_expanded: AtomicUsize::new(0),
+            limits: EvaluationLimits::default(),
+            started: Timer::start(),
+            bytes_materialized: AtomicUsize::new(0),
+            budget_polls: AtomicUsize::new(0),
+            exhausted: OnceLock::new(),
+            limit_hits: Mutex::new(HashMap::new()),
+            as_of: OnceLock::new(),
+            cel_profiler: None,
+            time_index: OnceLock::new(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: EvaluationLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> &EvaluationLimits {
+        &self.limits
+    }
+
+    /// Evaluate as of `as_of`: CEL's `now` is `as_of`, and events after it are not bound.
+    pub fn with_as_of(self, as_of: DateTime<FixedOffset>) -> Self {
+        let _ = self.as_of.set(as_of);
+        self
+    }
+
+    /// The time the evaluation treats as "now": the one given to [`Self::with_as_of`], or else the
+    /// time of the latest event of `ocel` (the wall-clock time for an OCEL without events).
+    pub fn as_of(&self, ocel: &SlimLinkedOCEL) -> DateTime<FixedOffset> {
+        *self
+            .as_of
+            .get_or_init(|| latest_event_time(ocel).unwrap_or_else(|| Local::now().into()))
+    }
+
+    /// The sorted lifecycles of the objects of `ocel` (see [`TimeIndex`]).
+    pub(crate) fn time_index(&self, ocel: &SlimLinkedOCEL) -> &TimeIndex {
+        self.time_index.get_or_init(|| TimeIndex::new(ocel))
+    }
+
+    /// The environment the CEL programs of node `node_index` of `tree` are evaluated in.
+    pub(crate) fn cel_env<'a>(
+        &'a self,
+        ocel: &SlimLinkedOCEL,
+        tree: &'a BindingBoxTree,
+        node_index: usize,
+    ) -> CelEnv<'a> {
+        CelEnv {
+            now: self.as_of(ocel),
+            profile: self.cel_profiler.as_ref().map(|p| (p, node_index)),
+            parameters: &tree.parameters,
+            time_index: Some(self.time_index(ocel)),
+        }
+    }
+
+    /// Record the calls of every CEL program (see [`crate::cel::profile`]). This costs a lock per
+    /// call, so it is off by default.
+    pub fn with_cel_profiling(mut self) -> Self {
+        self.cel_profiler = Some(CelProfiler::default());
+        self
+    }
+
+    /// The CEL profile of the evaluation of `tree`, if it is profiled.
+    pub fn cel_profile(&self, tree: &BindingBoxTree) -> Option<CelProfile> {
+        self.cel_profiler.as_ref().map(|p| p.profile(tree))
+    }
+
+    pub fn with_evaluation_id(mut self, evaluation_id: impl Into<String>) -> Self {
+        self.evaluation_id = Some(evaluation_id.into());
+        self
+    }
+
+    pub fn with_progress(mut self, on_progress: ProgressCallback) -> Self {
+        self.on_progress = Some(on_progress);
+        self
+    }
+
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.cancel
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    /// `Err(EVALUATION_CANCELLED)` once the token was cancelled, for use with `?`.
+    pub fn check(&self) -> Result<(), String> {
+        if self.cancel.is_cancelled() {
+            Err(EVALUATION_CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn bindings_expanded(&self) -> usize {
+        self.bindings_expanded.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn max_bindings_per_node(&self) -> usize {
+        self.limits.max_bindings_per_node.unwrap_or(usize::MAX)
+    }
+
+    /// The memory or time budget that ran out, if any.
+    pub(crate) fn exhausted_budget(&self) -> Option<EvaluationLimitKind> {
+        self.exhausted.get().copied()
+    }
+
+    /// Mark the memory or time budget as exhausted once it is exceeded.
+    pub(crate) fn check_budget(&self) {
+        if self.exhausted.get().is_some() {
+            return;
+        }
+        if let Some(timeout) = self.limits.timeout_seconds {
+            if self.started.elapsed().as_secs_f64() > timeout {
+                let _ = self.exhausted.set(EvaluationLimitKind::Timeout);
+                return;
+            }
+        }
+        if let Some(max_mb) = self.limits.max_memory_mb {
+            if self.bytes_materialized.load(Ordering::Relaxed) > max_mb.saturating_mul(1 << 20) {
+                let _ = self.exhausted.set(EvaluationLimitKind::MemoryBudget);
+            }
+        }
+    }
+
+    /// [`Self::check_budget`], but only every [`BUDGET_CHECK_INTERVAL`]th call; cheap enough to
+    /// call for every step of an expansion.
+    pub(crate) fn poll_budget(&self) {
+        if self.budget_polls.fetch_add(1, Ordering::Relaxed) % BUDGET_CHECK_INTERVAL == 0 {
+            self.check_budget();
+        }
+    }
+
+    /// Count `binding` against the memory budget as it is materialized, so that the budget also
+    /// stops the expansion that exceeds it.
+    pub(crate) fn record_materialized(&self, binding: &Binding) {
+        self.bytes_materialized
+            .fetch_add(binding.approx_size_bytes(), Ordering::Relaxed);
+    }
+
+    /// Account for one expansion of `node_index`, whose bindings were already counted by
+    /// [`Self::record_materialized`], and record the limit it hit, if any. Returns whether
+    /// bindings were skipped.
+    pub(crate) fn record_expansion(
+        &self,
+        node_index: usize,
+        expanded: &[Binding],
+        truncation: Option<Truncation>,
+    ) -> bool {
+        self.record_expanded(node_index, expanded.len());
+        self.check_budget();
+        match truncation {
+            Some(t) => {
+                self.record_limit_hit(node_index, t);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Record that an expansion of `node_index` was cut short by `truncation`.
+    pub(crate) fn record_limit_hit(&self, node_index: usize, truncation: Truncation) {
+        if let Ok(mut hits) = self.limit_hits.lock() {
+            *hits.entry((node_index, truncation.limit)).or_default() += truncation.bindings_dropped;
+        }
+    }
+
+    /// Whether any node stopped at one of the limits so far.
+    pub(crate) fn any_limit_hit(&self) -> bool {
+        self.limit_hits.lock().map_or(true, |hits| !hits.is_empty())
+    }
+
+    /// Every (node, limit) pair hit so far, ordered by node.
+    pub fn limit_hits(&self) -> Vec<LimitHit> {
+        let Ok(hits) = self.limit_hits.lock() else {
+            return Vec::new();
+        };
+        let mut hits: Vec<LimitHit> = hits
+            .iter()
+            .map(|(&(node_index, limit), &bindings_dropped)| LimitHit {
+                node_index,
+                limit,
+                bindings_dropped,
+            })
+            .collect();
+        hits.sort_by_key(|h| (h.node_index, h.limit));
+        hits
+    }
+
+    /// Count `n` newly expanded bindings of `node_index`, reporting progress whenever the total
+    /// crosses a multiple of [`PROGRESS_INTERVAL`].
+    fn record_expanded(&self, node_index: usize, n: usize) {
+        let before = self.bindings_expanded.fetch_add(n, Ordering::Relaxed);
+        let after = before + n;
+        if let Some(on_progress) = &self.on_progress {
+            if before / PROGRESS_INTERVAL != after / PROGRESS_INTERVAL {
+                on_progress(&EvaluationProgress {
+                    evaluation_id: self.evaluation_id.clone(),
+                    node_index,
+                    bindings_expanded: after,
+                });
+            }
+        }
+    }
+}
+
+/// The time of the latest event of `ocel`, if it has any.
+pub fn latest_event_time(ocel: &SlimLinkedOCEL) -> Option<DateTime<FixedOffset>> {
+    ocel.get_all_evs().map(|e| *ocel.get_ev_time(&e)).max()
+}
+
+static RUNNING_EVALUATIONS: Lazy<Mutex<HashMap<String, CancellationToken>>> =
+    Lazy::new(Default::default);
+
+static PROGRESS_LISTENER: RwLock<Option<ProgressCallback>> = RwLock::new(None);
+
+/// Keeps an evaluation listed as running; unlists it when dropped.
+pub struct RunningEvaluation {
+    id: String,
+    token: CancellationToken,
+}
+
+impl RunningEvaluation {
+    /// A control for this evaluation, reporting to the listener set by [`set_progress_listener`].
+    pub fn control(&self) -> EvaluationControl {
+        let control =
+            EvaluationControl::new(self.token.clone()).with_evaluation_id(self.id.clone());
+        match PROGRESS_LISTENER.read().ok().and_then(|l| l.clone()) {
+            Some(listener) => control.with_progress(listener),
+            None => control,
+        }
+    }
+}
+
+impl Drop for RunningEvaluation {
+    fn drop(&mut self) {
+        if let Ok(mut running) = RUNNING_EVALUATIONS.lock() {
+            running.remove(&self.id);
+        }
+    }
+}
+
+/// List an evaluation under `id` so [`cancel_running_evaluation`] can reach it. Re-using the id of
+/// a still running evaluation is an error, since only one of them could be cancelled.
+pub fn register_running_evaluation(id: impl Into<String>) -> Result<RunningEvaluation, String> {
+    let id = id.into();
+    let mut running = RUNNING_EVALUATIONS.lock().map_err(|e| e.to_string())?;
+    if running.contains_key(&id) {
+        return Err(format!("An evaluation with id {id} is already running"));
+    }
+    let token = CancellationToken::default();
+    running.insert(id.clone(), token.clone());
+    Ok(RunningEvaluation { id, token })
+}
+
+/// Cancel the running evaluation registered under `id`. Returns whether one was found.
+pub fn cancel_running_evaluation(id: &str) -> bool {
+    match RUNNING_EVALUATIONS.lock() {
+        Ok(running) => match running.get(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Install the callback that progress of registered evaluations is reported to.
+pub fn set_progress_listener(listener: Option<ProgressCallback>) {
+    if let Ok(mut l) = PROGRESS_LISTENER.write() {
+        *l = listener;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::binding_box::{
+        aggregate::{AggregateFunction, AggregateValue, ChildAggregate},
+        evaluate_box_tree_controlled,
+        structs::{
+            BindingBox, BindingBoxTreeNode, Filter, LabelFunction, ObjectVariable, SizeFilter,
+        },
+        test_utils::{
+            bound_event_ids, bound_object_ids, ev_vars, eval, o2e, ob_vars, ocel,
+            ocel_with_order_status, tree,
+        },
+        EvaluateBoxTreeResult,
+    };
+
+    #[test]
+    fn a_cancelled_evaluation_fails_instead_of_returning_partial_counts() {
+        let t = tree(
+            vec![BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_object_vars: ob_vars(&[(0, "order")]),
+                    new_event_vars: ev_vars(&[(0, "ship")]),
+                    filters: vec![o2e(0, 0, None)],
+                    ..Default::default()
+                },
+                vec![],
+            )],
+            &[],
+        );
+        let token = CancellationToken::default();
+        let control = EvaluationControl::new(token.clone());
+        let res = evaluate_box_tree_controlled(t.clone(), &ocel(), false, &control)
+            .expect("not cancelled yet");
+        assert_eq!(res.evaluation_results[0].situation_count, 2);
+        assert_eq!(control.bindings_expanded(), 2);
+
+        token.cancel();
+        let err = evaluate_box_tree_controlled(t, &ocel(), false, &control)
+            .expect_err("cancelled evaluation must not succeed");
+        assert_eq!(err, EVALUATION_CANCELLED);
+    }
+
+    #[test]
+    fn counted_children_record_the_limit_they_stopped_at() {
+        // Both orders fit the limit, but the 2 x 2 (place, ship) pairs counted for each do not.
+        let t = tree(
+            vec![
+                BindingBoxTreeNode::Box(
+                    BindingBox {
+                        new_object_vars: ob_vars(&[(0, "order")]),
+                        size_filters: vec![SizeFilter::NumChilds {
+                            child_name: "A".to_string(),
+                            min: None,
+                            max: Some(3),
+                        }],
+                        ..Default::default()
+                    },
+                    vec![1],
+                ),
+                BindingBoxTreeNode::Box(
+                    BindingBox {
+                        new_event_vars: ev_vars(&[(0, "place"), (1, "ship")]),
+                        ..Default::default()
+                    },
+                    vec![],
+                ),
+            ],
+            &[((0, 1), "A")],
+        );
+        let ocel = ocel();
+        let control = EvaluationControl::default().with_limits(EvaluationLimits {
+            max_bindings_per_node: Some(2),
+            ..Default::default()
+        });
+        let skipped = t.nodes[0]
+            .evaluate_no_descendants_in_place(
+                0,
+                &mut Binding::default(),
+                &t,
+                &ocel,
+                &t.compute_step_cache(&ocel),
+                &mut |_, _| Ok(()),
+                &control,
+            )
+            .expect("evaluation succeeds");
+        assert!(!skipped, "both orders are bound");
+        let hits = control.limit_hits();
+        assert!(!hits.is_empty());
+        assert!(
+            hits.iter()
+                .all(|h| h.node_index == 1 && h.limit == EvaluationLimitKind::MaxBindingsPerNode),
+            "{hits:?}"
+        );
+    }
+
+    #[test]
+    fn the_time_budget_stops_an_expansion_while_it_runs() {
+        // Two bindings are far too few for a budget check by leaf count, so only the check per
+        // expansion step can stop this expansion.
+        let t = tree(
+            vec![BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_object_vars: ob_vars(&[(0, "order")]),
+                    new_event_vars: ev_vars(&[(0, "ship")]),
+                    filters: vec![o2e(0, 0, None)],
+                    ..Default::default()
+                },
+                vec![],
+            )],
+            &[],
+        );
+        let control = EvaluationControl::default().with_limits(EvaluationLimits {
+            timeout_seconds: Some(0.0),
+            ..Default::default()
+        });
+        let res = evaluate_box_tree_controlled(t, &ocel(), false, &control)
+            .expect("running out of time is not an error");
+        assert_eq!(res.evaluation_results[0].situation_count, 0);
+        assert_eq!(
+            res.limit_hits
+                .iter()
+                .map(|h| (h.node_index, h.limit))
+                .collect::<Vec<_>>(),
+            vec![(0, EvaluationLimitKind::Timeout)]
+        );
+    }
+
+    #[test]
+    fn as_of_time_fixes_now_and_hides_later_events() {
+        let ships_until_now = tree(
+            vec![BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_event_vars: ev_vars(&[(0, "ship")]),
+                    filters: vec![Filter::BasicFilterCEL {
+                        cel: "e1.time() <= now".to_string(),
+                    }],
+                    labels: vec![LabelFunction {
+                        label: "clock".to_string(),
+                        cel: "string(now)".to_string(),
+                        aggregate: None,
+                    }],
+                    ..Default::default()
+                },
+                vec![],
+            )],
+            &[],
+        );
+        let now_labels = |res: &EvaluateBoxTreeResult| {
+            res.evaluation_results[0]
+                .situations
+                .iter()
+                .map(|(b, _)| b.get_label_value("clock").unwrap().to_string())
+                .unique()
+                .collect_vec()
+        };
+
+        // By default, `now` is the time of the latest event, however late the evaluation runs.
+        let res = eval(ships_until_now.clone());
+        assert_eq!(bound_event_ids(&res, 0, 0), ["e4", "e5"]);
+        assert_eq!(now_labels(&res), ["2024-01-06T00:00:00+00:00"]);
+
+        let as_of = DateTime::parse_from_rfc3339("2024-01-04T12:00:00Z").unwrap();
+        let control = EvaluationControl::default().with_as_of(as_of);
+        let res = evaluate_box_tree_controlled(ships_until_now, &ocel(), false, &control)
+            .expect("evaluation succeeds");
+        assert_eq!(bound_event_ids(&res, 0, 0), ["e4"]);
+        assert_eq!(now_labels(&res), ["2024-01-04T12:00:00+00:00"]);
+        assert_eq!(res.summary().as_of, Some(as_of));
+
+        // Navigating from an object does not reach the later events either.
+        let orders_with = |cel: &str, control: &EvaluationControl| {
+            let t = tree(
+                vec![BindingBoxTreeNode::Box(
+                    BindingBox {
+                        new_object_vars: ob_vars(&[(0, "order")]),
+                        filters: vec![Filter::BasicFilterCEL {
+                            cel: cel.to_string(),
+                        }],
+                        ..Default::default()
+                    },
+                    vec![],
+                )],
+                &[],
+            );
+            let res = evaluate_box_tree_controlled(t, &ocel(), false, control)
+                .expect("evaluation succeeds");
+            bound_object_ids(&res, 0, 0)
+                .into_iter()
+                .map(str::to_string)
+                .collect_vec()
+        };
+        let cel = "o1.events('ship').size() == 1 && o1.events().size() == 3 && numEvents() == 4";
+        assert_eq!(orders_with(cel, &control), ["o1"]);
+        assert!(orders_with(cel, &EvaluationControl::default()).is_empty());
+    }
+
+    #[test]
+    fn as_of_time_hides_later_object_attribute_values() {
+        // o1 is open from day 1 and closed at noon of day 4; its status is read once through CEL
+        // and once through an aggregate over its `place` events.
+        let t = tree(
+            vec![
+                BindingBoxTreeNode::Box(
+                    BindingBox {
+                        new_object_vars: ob_vars(&[(0, "order")]),
+                        filters: vec![Filter::BasicFilterCEL {
+                            cel: "o1.attrs().size() > 0".to_string(),
+                        }],
+                        labels: vec![
+                            LabelFunction {
+                                label: "attr".to_string(),
+                                cel: "o1.attr('status')".to_string(),
+                                aggregate: None,
+                            },
+                            LabelFunction {
+                                label: "attrs".to_string(),
+                                cel: "o1.attrs().size()".to_string(),
+                                aggregate: None,
+                            },
+                            LabelFunction {
+                                label: "latest".to_string(),
+                                aggregate: Some(ChildAggregate {
+                                    child_name: "A".to_string(),
+                                    function: AggregateFunction::Max,
+                                    value: Some(AggregateValue::ObjectAttribute {
+                                        object: ObjectVariable(0),
+                                        attribute_name: "status".to_string(),
+                                    }),
+                                }),
+                                ..Default::default()
+                            },
+                        ],
+                        ..Default::default()
+                    },
+                    vec![1],
+                ),
+                BindingBoxTreeNode::Box(
+                    BindingBox {
+                        new_event_vars: ev_vars(&[(0, "place")]),
+                        filters: vec![o2e(0, 0, None)],
+                        ..Default::default()
+                    },
+                    vec![],
+                ),
+            ],
+            &[((0, 1), "A")],
+        );
+        let status_as_of = |as_of: &str| {
+            let control = EvaluationControl::default()
+                .with_as_of(DateTime::parse_from_rfc3339(as_of).unwrap());
+            let res =
+                evaluate_box_tree_controlled(t.clone(), &ocel_with_order_status(), false, &control)
+                    .expect("evaluation succeeds");
+            res.evaluation_results[0]
+                .situations
+                .iter()
+                .map(|(b, _)| {
+                    ["attr", "attrs", "latest"].map(|l| b.get_label_value(l).unwrap().to_string())
+                })
+                .collect_vec()
+        };
+
+        assert_eq!(
+            status_as_of("2024-01-06T00:00:00Z"),
+            [["open", "2", "closed"]]
+        );
+        assert_eq!(
+            status_as_of("2024-01-04T00:00:00Z"),
+            [["open", "1", "open"]]
+        );
+        assert!(
+            status_as_of("2023-12-31T00:00:00Z").is_empty(),
+            "o1 has no status yet"
+        );
+    }
+}
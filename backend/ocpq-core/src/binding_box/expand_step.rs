@@ -6,8 +6,30 @@ use process_mining::core::event_data::object_centric::linked_ocel::{
 };
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 
-use super::structs::{Binding, BindingBox, BindingStep};
-const MAX_NUM_BINDINGS: usize = 10_000_000;
+use super::{
+    control::{EvaluationControl, EvaluationLimitKind, Truncation},
+    structs::{adjacent_event, Binding, BindingBox, BindingStep},
+};
+use crate::cel::CelEnv;
+
+/// The limit an expansion stopped at, given how many bindings it enumerated.
+fn truncation(
+    enumerated: usize,
+    max_bindings: usize,
+    control: &EvaluationControl,
+) -> Option<Truncation> {
+    if enumerated > max_bindings {
+        Some(Truncation {
+            limit: EvaluationLimitKind::MaxBindingsPerNode,
+            bindings_dropped: enumerated - max_bindings,
+        })
+    } else {
+        control.exhausted_budget().map(|limit| Truncation {
+            limit,
+            bindings_dropped: 0,
+        })
+    }
+}
 
 #[inline]
 fn passes_next_filters(
@@ -15,10 +37,11 @@ fn passes_next_filters(
     last_considered_index: usize,
     steps: &[BindingStep],
     ocel: &SlimLinkedOCEL,
+    env: CelEnv<'_>,
 ) -> bool {
     for step in steps.iter().skip(last_considered_index + 1) {
         if let BindingStep::Filter(f) = step {
-            match f.check_binding(b, ocel) {
+            match f.check_binding(b, ocel, env) {
                 Ok(true) => continue,
                 _ => return false,
             }
@@ -57,18 +80,59 @@ impl BindingBox {
         ocel: &SlimLinkedOCEL,
         steps: &[BindingStep],
     ) -> Result<(Vec<Binding>, bool), String> {
-        self.expand_with_steps_in_place(&mut parent_binding, ocel, steps)
+        self.expand_with_steps_controlled(
+            parent_binding,
+            ocel,
+            steps,
+            &EvaluationControl::default(),
+        )
     }
 
+    /// Like [`BindingBox::expand_with_steps`], but aborts with
+    /// [`super::control::EVALUATION_CANCELLED`] once `control` is cancelled.
+    pub fn expand_with_steps_controlled(
+        &self,
+        mut parent_binding: Binding,
+        ocel: &SlimLinkedOCEL,
+        steps: &[BindingStep],
+        control: &EvaluationControl,
+    ) -> Result<(Vec<Binding>, bool), String> {
+        self.expand_with_steps_in_place(
+            &mut parent_binding,
+            ocel,
+            steps,
+            control,
+            CelEnv::new(control.as_of(ocel)),
+        )
+        .map(|(bindings, truncation)| (bindings, truncation.is_some()))
+    }
+
+    /// Expand `parent_binding`, stopping at the limits of `control`, with the node's filters
+    /// evaluated in `env`. Which limit that was is returned instead of recorded, since only the
+    /// caller knows the node being expanded.
     pub(crate) fn expand_with_steps_in_place(
         &self,
         parent_binding: &mut Binding,
         ocel: &SlimLinkedOCEL,
         steps: &[BindingStep],
-    ) -> Result<(Vec<Binding>, bool), String> {
+        control: &EvaluationControl,
+        env: CelEnv<'_>,
+    ) -> Result<(Vec<Binding>, Option<Truncation>), String> {
+        control.check()?;
+        if let Some(limit) = control.exhausted_budget() {
+            return Ok((
+                Vec::new(),
+                Some(Truncation {
+                    limit,
+                    bindings_dropped: 0,
+                }),
+            ));
+        }
         if steps.is_empty() {
-            return Ok((vec![parent_binding.clone()], false));
+            control.record_materialized(parent_binding);
+            return Ok((vec![parent_binding.clone()], None));
         }
+        let max_bindings = control.max_bindings_per_node();
 
         let mut bootstrap = Vec::new();
         let bootstrap_counter = AtomicUsize::new(0);
@@ -76,18 +140,19 @@ impl BindingBox {
             parent_binding,
             &mut bootstrap,
             &bootstrap_counter,
+            control,
+            env,
             ocel,
             &steps,
             1,
             0,
         )?;
 
+        let bootstrap_enumerated = bootstrap_counter.load(Ordering::Relaxed);
         if steps.len() == 1 {
-            let bindings_skipped = bootstrap_counter.load(Ordering::Relaxed) > MAX_NUM_BINDINGS;
-            if bootstrap.len() > MAX_NUM_BINDINGS {
-                bootstrap.truncate(MAX_NUM_BINDINGS);
-            }
-            return Ok((bootstrap, bindings_skipped));
+            let truncation = truncation(bootstrap_enumerated, max_bindings, control);
+            bootstrap.truncate(max_bindings);
+            return Ok((bootstrap, truncation));
         }
 
         let pipeline_counter = AtomicUsize::new(0);
@@ -100,6 +165,8 @@ impl BindingBox {
                         &mut b,
                         &mut local_out,
                         &pipeline_counter,
+                        control,
+                        env,
                         ocel,
                         steps,
                         steps.len(),
@@ -113,30 +180,82 @@ impl BindingBox {
                 Ok(a)
             })?;
 
-        let bindings_skipped = pipeline_counter.load(Ordering::Relaxed) > MAX_NUM_BINDINGS;
-        if final_results.len() > MAX_NUM_BINDINGS {
-            final_results.truncate(MAX_NUM_BINDINGS);
+        // A capped bootstrap loses whole subtrees even if the pipeline itself stays below the cap.
+        let truncation = truncation(
+            pipeline_counter.load(Ordering::Relaxed),
+            max_bindings,
+            control,
+        )
+        .or_else(|| truncation(bootstrap_enumerated, max_bindings, control));
+        final_results.truncate(max_bindings);
+        Ok((final_results, truncation))
+    }
+
+    /// Expand `parent_binding` like [`BindingBox::expand_with_steps_in_place`], but hand the
+    /// bindings to `emit` one at a time, in the same order, instead of collecting them. Runs on
+    /// the calling thread; the limit the expansion stopped at is recorded for `node_index`, and
+    /// whether bindings were skipped returned.
+    pub(crate) fn expand_with_steps_emit<F>(
+        &self,
+        node_index: usize,
+        parent_binding: &mut Binding,
+        ocel: &SlimLinkedOCEL,
+        steps: &[BindingStep],
+        control: &EvaluationControl,
+        env: CelEnv<'_>,
+        emit: &mut F,
+    ) -> Result<bool, String>
+    where
+        F: FnMut(&Binding) -> Result<(), String>,
+    {
+        let counter = AtomicUsize::new(0);
+        let max_bindings = control.max_bindings_per_node();
+        self.apply_step_recursive_emit(
+            parent_binding,
+            &counter,
+            control,
+            env,
+            ocel,
+            steps,
+            0,
+            steps.len(),
+            max_bindings,
+            emit,
+        )?;
+        let truncation = truncation(counter.load(Ordering::Relaxed), max_bindings, control);
+        let skipped = truncation.is_some();
+        if let Some(truncation) = truncation {
+            control.record_limit_hit(node_index, truncation);
         }
-        Ok((final_results, bindings_skipped))
+        Ok(skipped)
     }
 
+    /// Count the bindings expanding `parent_binding` yields, up to `limit`, without collecting
+    /// them. Whether bindings were skipped is returned; the limit that skipped them is recorded
+    /// for `node_index`, the node being counted.
     pub(crate) fn count_with_steps_in_place(
         &self,
+        node_index: usize,
         parent_binding: &mut Binding,
         ocel: &SlimLinkedOCEL,
         steps: &[BindingStep],
         limit: usize,
+        control: &EvaluationControl,
+        env: CelEnv<'_>,
     ) -> Result<(usize, bool), String> {
         if limit == 0 {
             return Ok((0, false));
         }
 
         let counter = AtomicUsize::new(0);
-        let max_bindings = limit.min(MAX_NUM_BINDINGS);
+        let max_per_node = control.max_bindings_per_node();
+        let max_bindings = limit.min(max_per_node);
         let mut emit = |_b: &Binding| Ok(());
         self.apply_step_recursive_emit(
             parent_binding,
             &counter,
+            control,
+            env,
             ocel,
             steps,
             0,
@@ -144,8 +263,18 @@ impl BindingBox {
             max_bindings,
             &mut emit,
         )?;
-        let count = counter.load(Ordering::Relaxed).min(max_bindings);
-        Ok((count, limit > MAX_NUM_BINDINGS && count >= MAX_NUM_BINDINGS))
+        let enumerated = counter.load(Ordering::Relaxed);
+        // Stopping at the caller's `limit` skips nothing the caller asked for.
+        let truncation = if limit > max_per_node {
+            truncation(enumerated, max_per_node, control)
+        } else {
+            truncation(enumerated, usize::MAX, control)
+        };
+        let skipped = truncation.is_some();
+        if let Some(truncation) = truncation {
+            control.record_limit_hit(node_index, truncation);
+        }
+        Ok((enumerated.min(max_bindings), skipped))
     }
 
     fn apply_step_recursive(
@@ -153,22 +282,38 @@ impl BindingBox {
         b: &mut Binding,
         out: &mut Vec<Binding>,
         counter: &AtomicUsize,
+        control: &EvaluationControl,
+        env: CelEnv<'_>,
         ocel: &SlimLinkedOCEL,
         steps: &[BindingStep],
         max_depth: usize,
         idx: usize,
     ) -> Result<(), String> {
         let mut emit = |b: &Binding| {
+            control.record_materialized(b);
             out.push(b.clone());
             Ok(())
         };
-        self.apply_step_recursive_emit(b, counter, ocel, steps, idx, max_depth, MAX_NUM_BINDINGS, &mut emit)
+        self.apply_step_recursive_emit(
+            b,
+            counter,
+            control,
+            env,
+            ocel,
+            steps,
+            idx,
+            max_depth,
+            control.max_bindings_per_node(),
+            &mut emit,
+        )
     }
 
     fn apply_step_recursive_emit<F>(
         &self,
         b: &mut Binding,
         counter: &AtomicUsize,
+        control: &EvaluationControl,
+        env: CelEnv<'_>,
         ocel: &SlimLinkedOCEL,
         steps: &[BindingStep],
         idx: usize,
@@ -179,7 +324,11 @@ impl BindingBox {
     where
         F: FnMut(&Binding) -> Result<(), String>,
     {
-        if counter.load(Ordering::Relaxed) >= max_bindings {
+        control.check()?;
+        control.poll_budget();
+        // Enumerating one past `max_bindings` is what tells a truncated expansion apart from one
+        // that produced exactly `max_bindings`.
+        if counter.load(Ordering::Relaxed) > max_bindings || control.exhausted_budget().is_some() {
             return Ok(());
         }
         if idx >= max_depth {
@@ -190,6 +339,8 @@ impl BindingBox {
             return Ok(());
         }
         let step = &steps[idx];
+        // Events after the evaluation's as-of time had not happened yet, so they are never bound.
+        let now = env.now;
         // Skip a filter here only if a preceding bind step already evaluated it via `passes_next_filters`.
         if matches!(step, BindingStep::Filter(_))
             && steps[..idx]
@@ -199,6 +350,8 @@ impl BindingBox {
             return self.apply_step_recursive_emit(
                 b,
                 counter,
+                control,
+                env,
                 ocel,
                 steps,
                 idx + 1,
@@ -236,14 +389,16 @@ impl BindingBox {
                                 }
                             },
                         );
-                    if !time_ok {
+                    if !time_ok || ocel.get_ev_time(e_index) > &now {
                         continue;
                     }
                     let ins = b.extend_with_ev_in_place(*ev_var, *e_index);
-                    if passes_next_filters(b, idx, steps, ocel) {
+                    if passes_next_filters(b, idx, steps, ocel, env) {
                         self.apply_step_recursive_emit(
                             b,
                             counter,
+                            control,
+                            env,
                             ocel,
                             steps,
                             idx + 1,
@@ -253,7 +408,7 @@ impl BindingBox {
                         )?;
                     }
                     b.revert_ev(ins, *ev_var);
-                    if counter.load(Ordering::Relaxed) >= max_bindings {
+                    if counter.load(Ordering::Relaxed) > max_bindings {
                         return Ok(());
                     }
                 }
@@ -268,10 +423,12 @@ impl BindingBox {
                     .flat_map(|ob_type| ocel.get_obs_of_type(ob_type))
                 {
                     let ins = b.extend_with_ob_in_place(*ob_var, *o_index);
-                    if passes_next_filters(b, idx, steps, ocel) {
+                    if passes_next_filters(b, idx, steps, ocel, env) {
                         self.apply_step_recursive_emit(
                             b,
                             counter,
+                            control,
+                            env,
                             ocel,
                             steps,
                             idx + 1,
@@ -281,7 +438,7 @@ impl BindingBox {
                         )?;
                     }
                     b.revert_ob(ins, *ob_var);
-                    if counter.load(Ordering::Relaxed) >= max_bindings {
+                    if counter.load(Ordering::Relaxed) > max_bindings {
                         return Ok(());
                     }
                 }
@@ -305,10 +462,12 @@ impl BindingBox {
                     .dedup()
                 {
                     let ins = b.extend_with_ob_in_place(*ob_var, *o);
-                    if passes_next_filters(b, idx, steps, ocel) {
+                    if passes_next_filters(b, idx, steps, ocel, env) {
                         self.apply_step_recursive_emit(
                             b,
                             counter,
+                            control,
+                            env,
                             ocel,
                             steps,
                             idx + 1,
@@ -318,7 +477,7 @@ impl BindingBox {
                         )?;
                     }
                     b.revert_ob(ins, *ob_var);
-                    if counter.load(Ordering::Relaxed) >= max_bindings {
+                    if counter.load(Ordering::Relaxed) > max_bindings {
                         return Ok(());
                     }
                 }
@@ -352,10 +511,12 @@ impl BindingBox {
                 };
                 for to_ob_index in o2os {
                     let ins = b.extend_with_ob_in_place(*ob_var_name, *to_ob_index);
-                    if passes_next_filters(b, idx, steps, ocel) {
+                    if passes_next_filters(b, idx, steps, ocel, env) {
                         self.apply_step_recursive_emit(
                             b,
                             counter,
+                            control,
+                            env,
                             ocel,
                             steps,
                             idx + 1,
@@ -365,7 +526,7 @@ impl BindingBox {
                         )?;
                     }
                     b.revert_ob(ins, *ob_var_name);
-                    if counter.load(Ordering::Relaxed) >= max_bindings {
+                    if counter.load(Ordering::Relaxed) > max_bindings {
                         return Ok(());
                     }
                 }
@@ -378,14 +539,61 @@ impl BindingBox {
                     .new_event_vars
                     .get(ev_var_name)
                     .ok_or_else(|| format!("Could not get {ev_var_name}"))?;
-                for to_ev_index in ev_types.iter().flat_map(|ev_type| {
-                    ob_index.get_e2o_rev_evs_of_evtype(ocel, ev_type, qualifier.as_deref())
-                }) {
+                for to_ev_index in ev_types
+                    .iter()
+                    .flat_map(|ev_type| {
+                        ob_index.get_e2o_rev_evs_of_evtype(ocel, ev_type, qualifier.as_deref())
+                    })
+                    .filter(|e| ocel.get_ev_time(e) <= &now)
+                {
+                    let ins = b.extend_with_ev_in_place(*ev_var_name, *to_ev_index);
+                    if passes_next_filters(b, idx, steps, ocel, env) {
+                        self.apply_step_recursive_emit(
+                            b,
+                            counter,
+                            control,
+                            env,
+                            ocel,
+                            steps,
+                            idx + 1,
+                            max_depth,
+                            max_bindings,
+                            emit,
+                        )?;
+                    }
+                    b.revert_ev(ins, *ev_var_name);
+                    if counter.load(Ordering::Relaxed) > max_bindings {
+                        return Ok(());
+                    }
+                }
+            }
+            BindingStep::BindEvInObWindow(ev_var_name, ob_var_name, window) => {
+                let ob_index = *b
+                    .get_ob_index(ob_var_name)
+                    .ok_or_else(|| format!("Could not get {ob_var_name}"))?;
+                let ev_types = self
+                    .new_event_vars
+                    .get(ev_var_name)
+                    .ok_or_else(|| format!("Could not get {ev_var_name}"))?;
+                let windows = window.windows(ocel, &ob_index);
+                if windows.is_empty() {
+                    return Ok(());
+                }
+                let time_index = control.time_index(ocel);
+                // The windows are disjoint, so no event is bound twice.
+                for to_ev_index in ev_types
+                    .iter()
+                    .cartesian_product(&windows)
+                    .flat_map(|(ev_type, w)| time_index.evs_of_type_in(ocel, ev_type, w))
+                    .filter(|e| ocel.get_ev_time(e) <= &now)
+                {
                     let ins = b.extend_with_ev_in_place(*ev_var_name, *to_ev_index);
-                    if passes_next_filters(b, idx, steps, ocel) {
+                    if passes_next_filters(b, idx, steps, ocel, env) {
                         self.apply_step_recursive_emit(
                             b,
                             counter,
+                            control,
+                            env,
                             ocel,
                             steps,
                             idx + 1,
@@ -395,16 +603,52 @@ impl BindingBox {
                         )?;
                     }
                     b.revert_ev(ins, *ev_var_name);
-                    if counter.load(Ordering::Relaxed) >= max_bindings {
+                    if counter.load(Ordering::Relaxed) > max_bindings {
                         return Ok(());
                     }
                 }
             }
+            BindingStep::BindEvAdjacent(ev_var_name, ref_ev_var_name, ob_var_name, reversed) => {
+                let ref_ev_index = *b
+                    .get_ev_index(ref_ev_var_name)
+                    .ok_or_else(|| format!("Could not get {ref_ev_var_name}"))?;
+                let ob_index = *b
+                    .get_ob_index(ob_var_name)
+                    .ok_or_else(|| format!("Could not get {ob_var_name}"))?;
+                let ev_types = self
+                    .new_event_vars
+                    .get(ev_var_name)
+                    .ok_or_else(|| format!("Could not get {ev_var_name}"))?;
+                let lifecycle = control.time_index(ocel).lifecycle(ocel, &ob_index);
+                if let Some(to_ev_index) = adjacent_event(ocel, lifecycle, &ref_ev_index, *reversed)
+                    .filter(|e| ev_types.contains(ocel.get_ev_type_of(e)))
+                    .filter(|e| ocel.get_ev_time(e) <= &now)
+                {
+                    let ins = b.extend_with_ev_in_place(*ev_var_name, to_ev_index);
+                    if passes_next_filters(b, idx, steps, ocel, env) {
+                        self.apply_step_recursive_emit(
+                            b,
+                            counter,
+                            control,
+                            env,
+                            ocel,
+                            steps,
+                            idx + 1,
+                            max_depth,
+                            max_bindings,
+                            emit,
+                        )?;
+                    }
+                    b.revert_ev(ins, *ev_var_name);
+                }
+            }
             BindingStep::Filter(f) => {
-                if f.check_binding(b, ocel)? {
+                if f.check_binding(b, ocel, env)? {
                     self.apply_step_recursive_emit(
                         b,
                         counter,
+                        control,
+                        env,
                         ocel,
                         steps,
                         idx + 1,
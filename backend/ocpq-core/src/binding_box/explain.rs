@@ -0,0 +1,517 @@
+//! Witnesses for violated situations: which constraint failed, the child bindings that made it
+//! fail (or how many were missing) and the values of the parts of a failing CEL expression.
+//!
+//! An explanation is computed after the evaluation, by evaluating the children of the one
+//! situation again, so nothing is paid for it unless it is asked for.
+use std::{collections::HashMap, collections::HashSet, fmt::Display, sync::Arc};
+
+use chrono::{DateTime, FixedOffset, Local};
+use itertools::Itertools;
+use process_mining::core::event_data::object_centric::linked_ocel::{
+    LinkedOCELAccess, SlimLinkedOCEL,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::control::{latest_event_time, EvaluationControl};
+use super::structs::{
+    check_constraints, Binding, BindingBoxTree, BindingStep, Constraint, EventVariable, Filter,
+    LabelValue, ObjectVariable, SizeFilter, ViolationReason,
+};
+use crate::cel::{
+    ev_var_to_name, evaluate_cel, ob_var_to_name,
+    syntax::{split_top_level, strip_outer_parens},
+    CELEvalError, CelEnv,
+};
+
+/// At most this many offending bindings are listed per child; the counts cover all of them.
+pub const MAX_WITNESSES_PER_CHILD: usize = 10;
+
+type ChildResults = HashMap<String, Vec<(Arc<Binding>, Option<ViolationReason>)>>;
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ViolationExplanation {
+    /// Index of the first constraint of the box that is not satisfied
+    pub constraint: usize,
+    pub reason: ViolationReason,
+    /// The children the failed constraint looks at
+    pub children: Vec<ChildWitness>,
+    /// The failed CEL expression and its parts, each with the value it evaluated to
+    pub cel_values: Vec<CelSubExpressionValue>,
+}
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChildWitness {
+    pub child_name: String,
+    pub binding_count: usize,
+    pub satisfied_count: usize,
+    /// Child bindings that made the constraint fail (e.g., the violated ones for `SAT`), at most
+    /// [`MAX_WITNESSES_PER_CHILD`]
+    pub offending: Vec<WitnessBinding>,
+    /// How many more (satisfied) child bindings the constraint needed
+    pub missing: usize,
+}
+
+/// A child binding, with only the variables the child binds itself.
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WitnessBinding {
+    pub objects: Vec<(ObjectVariable, String)>,
+    pub events: Vec<(EventVariable, String)>,
+    pub satisfied: bool,
+}
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CelSubExpressionValue {
+    pub expression: String,
+    /// `None` if evaluating the expression failed
+    pub value: Option<LabelValue>,
+    pub error: Option<String>,
+}
+
+/// Explains violated situations of one tree against one OCEL.
+pub struct ViolationExplainer<'a> {
+    tree: &'a BindingBoxTree,
+    ocel: &'a SlimLinkedOCEL,
+    step_cache: Vec<Vec<BindingStep>>,
+    as_of: DateTime<FixedOffset>,
+}
+
+impl<'a> ViolationExplainer<'a> {
+    pub fn new(tree: &'a BindingBoxTree, ocel: &'a SlimLinkedOCEL) -> Self {
+        Self {
+            tree,
+            ocel,
+            step_cache: tree.compute_step_cache(ocel),
+            as_of: latest_event_time(ocel).unwrap_or_else(|| Local::now().into()),
+        }
+    }
+
+    /// Explain as of `as_of`, which must be the clock the situations were evaluated with (see
+    /// [`EvaluationControl::as_of`]); by default, that of an evaluation without one.
+    pub fn with_as_of(mut self, as_of: DateTime<FixedOffset>) -> Self {
+        self.as_of = as_of;
+        self
+    }
+
+    /// The environment the tree's CEL programs are evaluated in
+    fn cel_env(&self) -> CelEnv<'a> {
+        CelEnv::new(self.as_of).with_parameters(&self.tree.parameters)
+    }
+
+    /// Explain the situation `binding` of node `node_index`, or `None` if it is not violated.
+    pub fn explain(
+        &self,
+        node_index: usize,
+        binding: &Binding,
+    ) -> Result<Option<ViolationExplanation>, String> {
+        let node = self
+            .tree
+            .nodes
+            .get(node_index)
+            .ok_or_else(|| format!("node_index {node_index} out of range"))?;
+        let (bbox, children) = node.to_box(node_index, self.tree);
+        let control = EvaluationControl::default().with_as_of(self.as_of);
+        let mut child_res: ChildResults = HashMap::with_capacity(children.len());
+        for c in children.iter() {
+            let ((_, violations), _skipped) = self.tree.nodes[*c].evaluate_controlled(
+                *c,
+                binding.clone(),
+                self.tree,
+                self.ocel,
+                &self.step_cache,
+                &control,
+            )?;
+            child_res.insert(self.tree.edge_name(node_index, *c), violations);
+        }
+        let Some(reason) = check_constraints(
+            &bbox.constraints,
+            binding,
+            &child_res,
+            self.ocel,
+            self.cel_env(),
+        )?
+        else {
+            return Ok(None);
+        };
+        let mut failed = None;
+        for (i, constraint) in bbox.constraints.iter().enumerate() {
+            let single = std::slice::from_ref(constraint);
+            if check_constraints(single, binding, &child_res, self.ocel, self.cel_env())?.is_some()
+            {
+                failed = Some((i, constraint));
+                break;
+            }
+        }
+        let Some((constraint_index, constraint)) = failed else {
+            return Ok(None);
+        };
+
+        let witness = |name: &String, is_offending: &dyn Fn(bool) -> bool, missing: usize| {
+            self.child_witness(name, &child_res, binding, is_offending, missing)
+        };
+        let all_children = || {
+            child_res
+                .keys()
+                .sorted()
+                .map(|name| witness(name, &|_| false, 0))
+                .collect_vec()
+        };
+        let mut cel_values = Vec::new();
+        let children = match constraint {
+            Constraint::Filter { filter } => {
+                if let Filter::BasicFilterCEL { cel } = filter {
+                    cel_values = self.cel_values(cel, binding, None);
+                }
+                Vec::new()
+            }
+            Constraint::SizeFilter { filter } => match filter {
+                SizeFilter::NumChilds {
+                    child_name,
+                    min,
+                    max,
+                } => {
+                    let count = child_res.get(child_name).map_or(0, Vec::len);
+                    let too_many = max.is_some_and(|max| count > max);
+                    vec![witness(
+                        child_name,
+                        &|_| too_many,
+                        min.map_or(0, |min| min.saturating_sub(count)),
+                    )]
+                }
+                SizeFilter::NumChildsProj {
+                    child_name,
+                    var_name,
+                    min,
+                    max,
+                } => {
+                    let count = child_res.get(child_name).map_or(0, |c_res| {
+                        c_res
+                            .iter()
+                            .flat_map(|(b, _)| b.get_any_index(var_name))
+                            .collect::<HashSet<_>>()
+                            .len()
+                    });
+                    let too_many = max.is_some_and(|max| count > max);
+                    vec![witness(
+                        child_name,
+                        &|_| too_many,
+                        min.map_or(0, |min| min.saturating_sub(count)),
+                    )]
+                }
+                SizeFilter::NumSatisfiedChilds {
+                    child_name,
+                    min,
+                    max,
+                    max_fraction,
+                    ..
+                } => {
+                    let (satisfied, total) = child_res.get(child_name).map_or((0, 0), |c_res| {
+                        (
+                            c_res.iter().filter(|(_, v)| v.is_none()).count(),
+                            c_res.len(),
+                        )
+                    });
+                    // Too many satisfied bindings make the satisfied ones the culprits.
+                    let too_many = max.is_some_and(|max| satisfied > max)
+                        || max_fraction
+                            .is_some_and(|max| total > 0 && satisfied as f64 / total as f64 > max);
+                    vec![witness(
+                        child_name,
+                        &|sat| sat == too_many,
+                        min.map_or(0, |min| min.saturating_sub(satisfied)),
+                    )]
+                }
+                SizeFilter::Aggregate { aggregate, .. } => {
+                    cel_values.push(value_or_error(
+                        format!("{:?}({})", aggregate.function, aggregate.child_name),
+                        aggregate.compute(&child_res, self.ocel, self.as_of),
+                    ));
+                    vec![witness(&aggregate.child_name, &|_| false, 0)]
+                }
+                SizeFilter::AdvancedCEL { cel } => {
+                    cel_values = self.cel_values(cel, binding, Some(&child_res));
+                    all_children()
+                }
+                SizeFilter::BindingSetEqual { .. }
+                | SizeFilter::BindingSetProjectionEqual { .. } => all_children(),
+            },
+            Constraint::SAT { child_names }
+            | Constraint::AND { child_names }
+            | Constraint::OR { child_names } => child_names
+                .iter()
+                .map(|name| witness(name, &|sat| !sat, 0))
+                .collect(),
+            Constraint::ANY { child_names } => child_names
+                .iter()
+                .map(|name| {
+                    let has_satisfied = child_res
+                        .get(name)
+                        .is_some_and(|c_res| c_res.iter().any(|(_, v)| v.is_none()));
+                    witness(name, &|sat| !sat, usize::from(!has_satisfied))
+                })
+                .collect(),
+            Constraint::NOT { child_names } => child_names
+                .iter()
+                .map(|name| witness(name, &|sat| sat, 0))
+                .collect(),
+        };
+        Ok(Some(ViolationExplanation {
+            constraint: constraint_index,
+            reason,
+            children,
+            cel_values,
+        }))
+    }
+
+    fn child_witness(
+        &self,
+        child_name: &str,
+        child_res: &ChildResults,
+        parent: &Binding,
+        is_offending: &dyn Fn(bool) -> bool,
+        missing: usize,
+    ) -> ChildWitness {
+        let c_res = child_res.get(child_name).map_or(&[][..], Vec::as_slice);
+        ChildWitness {
+            child_name: child_name.to_string(),
+            binding_count: c_res.len(),
+            satisfied_count: c_res.iter().filter(|(_, v)| v.is_none()).count(),
+            offending: c_res
+                .iter()
+                .filter(|(_, v)| is_offending(v.is_none()))
+                .take(MAX_WITNESSES_PER_CHILD)
+                .map(|(b, v)| WitnessBinding {
+                    objects: b
+                        .object_map
+                        .iter()
+                        .filter(|(var, _)| parent.get_ob_index(var).is_none())
+                        .sorted_by_key(|(var, _)| *var)
+                        .map(|(var, idx)| (*var, self.ocel.get_ob_id(idx).to_string()))
+                        .collect(),
+                    events: b
+                        .event_map
+                        .iter()
+                        .filter(|(var, _)| parent.get_ev_index(var).is_none())
+                        .sorted_by_key(|(var, _)| *var)
+                        .map(|(var, idx)| (*var, self.ocel.get_ev_id(idx).to_string()))
+                        .collect(),
+                    satisfied: v.is_none(),
+                })
+                .collect(),
+            missing,
+        }
+    }
+
+    /// The value of `cel` and of each of its top-level `&&`/`||` operands and comparison sides.
+    fn cel_values(
+        &self,
+        cel: &str,
+        binding: &Binding,
+        child_res: Option<&ChildResults>,
+    ) -> Vec<CelSubExpressionValue> {
+        cel_sub_expressions(cel)
+            .into_iter()
+            .map(|expression| {
+                let value =
+                    evaluate_cel(&expression, binding, child_res, self.ocel, self.cel_env())
+                        .map(LabelValue::from)
+                        .map_err(|e| match e {
+                            CELEvalError::ExecError(e) => e.to_string(),
+                            CELEvalError::ParseError(e) => e,
+                        });
+                value_or_error(expression, value)
+            })
+            .collect()
+    }
+}
+
+fn value_or_error(expression: String, value: Result<LabelValue, String>) -> CelSubExpressionValue {
+    match value {
+        Ok(value) => CelSubExpressionValue {
+            expression,
+            value: Some(value),
+            error: None,
+        },
+        Err(error) => CelSubExpressionValue {
+            expression,
+            value: None,
+            error: Some(error),
+        },
+    }
+}
+
+/// `cel` itself, followed by its top-level `&&`/`||` operands and the two sides of each operand
+/// that is a comparison.
+fn cel_sub_expressions(cel: &str) -> Vec<String> {
+    let cel = cel.trim();
+    let mut res = vec![cel.to_string()];
+    let operands = split_top_level(strip_outer_parens(cel), &["&&", "||"]);
+    let many = operands.len() > 1;
+    for operand in operands {
+        let operand = strip_outer_parens(&operand).to_string();
+        // Only a plain comparison is split: `a > 1 && b` has `&&` binding looser than `>`.
+        let is_compound = split_top_level(&operand, &["&&", "||"]).len() > 1;
+        let sides = split_top_level(&operand, &["==", "!=", "<=", ">=", "<", ">"]);
+        if many {
+            res.push(operand);
+        }
+        if !is_compound && sides.len() == 2 {
+            res.extend(sides);
+        }
+    }
+    res.into_iter().filter(|e| !e.is_empty()).unique().collect()
+}
+
+impl Display for ViolationExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Constraint {} not satisfied", self.constraint)?;
+        for c in &self.children {
+            write!(
+                f,
+                "; {}: {}/{} satisfied",
+                c.child_name, c.satisfied_count, c.binding_count
+            )?;
+            if c.missing > 0 {
+                write!(f, ", {} missing", c.missing)?;
+            }
+            if !c.offending.is_empty() {
+                let witnesses = c
+                    .offending
+                    .iter()
+                    .map(|w| {
+                        w.objects
+                            .iter()
+                            .map(|(v, id)| format!("{}={id}", ob_var_to_name(v)))
+                            .chain(
+                                w.events
+                                    .iter()
+                                    .map(|(v, id)| format!("{}={id}", ev_var_to_name(v))),
+                            )
+                            .join(" ")
+                    })
+                    .join(", ");
+                write!(f, ", offending: [{witnesses}]")?;
+            }
+        }
+        for v in &self.cel_values {
+            match (&v.value, &v.error) {
+                (Some(value), _) => write!(f, "; {} = {}", v.expression, value.to_string())?,
+                (None, Some(error)) => write!(f, "; {} failed: {error}", v.expression)?,
+                (None, None) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binding_box::{
+        evaluate_box_tree,
+        structs::BindingBoxTreeNode,
+        test_utils::{ocel, order_payment_shipping_tree},
+        EvalPageRequest,
+    };
+
+    #[test]
+    fn cel_is_split_at_top_level_operators_only() {
+        assert_eq!(
+            cel_sub_expressions(r#"(a > 1 && f(b && c)) || d == "x && y""#),
+            vec![
+                r#"(a > 1 && f(b && c)) || d == "x && y""#,
+                "a > 1 && f(b && c)",
+                r#"d == "x && y""#,
+                "d",
+                r#""x && y""#,
+            ]
+        );
+        assert_eq!(
+            cel_sub_expressions("e1.time() <= e2.time()"),
+            vec!["e1.time() <= e2.time()", "e1.time()", "e2.time()"]
+        );
+    }
+
+    #[test]
+    fn explanations_name_the_failed_constraint_and_its_witnesses() {
+        let mut t = order_payment_shipping_tree();
+        if let BindingBoxTreeNode::Box(b, _) = &mut t.nodes[0] {
+            b.constraints.push(Constraint::SAT {
+                child_names: vec!["B".to_string()],
+            });
+        }
+        let ocel = ocel();
+        let mut res = evaluate_box_tree(t.clone(), &ocel, false).expect("evaluation succeeds");
+        res.explain_violations(&t, &ocel)
+            .expect("explaining succeeds");
+        let root = &res.evaluation_results[0];
+        assert_eq!(root.explanations.len(), 2, "both orders are violated");
+        let by_order: HashMap<&str, &ViolationExplanation> = root
+            .explanations
+            .iter()
+            .map(|(i, e)| {
+                let o = root.situations[*i]
+                    .0
+                    .get_ob_index(&ObjectVariable(0))
+                    .unwrap();
+                (res.object_ids[(*o).into_inner() as usize].as_str(), e)
+            })
+            .collect();
+
+        // o1 was paid, but both of its shipments violate the item constraint.
+        let o1 = by_order["o1"];
+        assert_eq!(o1.constraint, 1);
+        assert_eq!(o1.children.len(), 1);
+        assert_eq!(o1.children[0].child_name, "B");
+        assert_eq!(
+            (o1.children[0].binding_count, o1.children[0].satisfied_count),
+            (2, 0)
+        );
+        let offending = o1.children[0]
+            .offending
+            .iter()
+            .map(|w| {
+                assert!(w.objects.is_empty(), "o1 is bound by the parent");
+                w.events[0].1.as_str()
+            })
+            .sorted()
+            .collect_vec();
+        assert_eq!(offending, vec!["e4", "e5"]);
+
+        // o2 was never paid.
+        let o2 = by_order["o2"];
+        assert_eq!(o2.constraint, 0);
+        assert_eq!(o2.children[0].child_name, "A");
+        assert_eq!(o2.children[0].missing, 1);
+        assert!(o2.children[0].offending.is_empty());
+        assert!(
+            o2.to_string()
+                .starts_with("Constraint 0 not satisfied; A: 0/0 satisfied, 1 missing"),
+            "{o2}"
+        );
+
+        let page = res
+            .get_page(&EvalPageRequest {
+                eval_version: 0,
+                node_index: 0,
+                offset: 0,
+                limit: 10,
+                violated: Some(true),
+            })
+            .unwrap();
+        assert!(page.rows.iter().all(|r| r.explanation.is_some()));
+    }
+}
@@ -4,14 +4,32 @@ pub mod step_order;
 
 pub mod expand_step;
 
-use std::collections::HashSet;
+pub mod control;
+
+pub mod cache;
+
+pub mod aggregate;
+
+pub mod plan_stats;
+
+pub mod explain;
+
+pub mod validate;
+
+pub mod parameters;
+
+pub mod time_index;
+
+#[cfg(test)]
+pub(crate) mod test_utils;
+
+use std::collections::{HashMap, HashSet};
 
 // Only the benchmark path writes timings to a file, and that path does not exist on wasm.
 #[cfg(not(target_arch = "wasm32"))]
 use std::{fs::File, io::BufWriter};
 
-#[cfg(not(target_arch = "wasm32"))]
-use chrono::DateTime;
+use chrono::{DateTime, FixedOffset};
 
 use crate::timing::Timer;
 use itertools::Itertools;
@@ -34,29 +52,65 @@ pub use structs::{
 };
 use ts_rs::TS;
 
-#[derive(Debug, Default, Clone, Serialize, Deserialize, process_mining::bindings::CustomRegistryEntity)]
+use crate::cel::profile::CelProfile;
+use cache::EvaluationCache;
+use control::{EvaluationControl, LimitHit};
+use explain::{ViolationExplainer, ViolationExplanation};
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+#[derive(
+    Debug, Default, Clone, Serialize, Deserialize, process_mining::bindings::CustomRegistryEntity,
+)]
 #[serde(rename_all = "camelCase")]
 pub struct EvaluateBoxTreeResult {
     pub evaluation_results: Vec<EvaluationResultWithCount>,
     pub object_ids: Vec<String>,
     pub event_ids: Vec<String>,
     pub bindings_skipped: bool,
+    /// Which nodes stopped at which of the evaluation's limits.
+    pub limit_hits: Vec<LimitHit>,
     pub eval_version: u64,
+    /// The time the evaluation treated as "now" (see [`EvaluationControl::as_of`]).
+    #[serde(default)]
+    pub as_of: Option<DateTime<FixedOffset>>,
+    /// Only for evaluations with [`EvaluationControl::with_cel_profiling`]
+    #[serde(default)]
+    pub cel_profile: Option<CelProfile>,
 }
 #[derive(TS)]
 #[ts(export)]
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct EvaluationResultWithCount {
     pub situations: Vec<(Binding, Option<ViolationReason>)>,
     pub situation_count: usize,
     pub situation_violated_count: usize,
+    /// Explanations of the violated situations, by their index in `situations`. Only filled in
+    /// by [`EvaluateBoxTreeResult::explain_violations`].
+    #[serde(default)]
+    pub explanations: HashMap<usize, ViolationExplanation>,
 }
 
 pub fn evaluate_box_tree(
     tree: BindingBoxTree,
     ocel: &SlimLinkedOCEL,
     measure_performance: bool,
+) -> Result<EvaluateBoxTreeResult, String> {
+    evaluate_box_tree_controlled(
+        tree,
+        ocel,
+        measure_performance,
+        &EvaluationControl::default(),
+    )
+}
+
+/// Like [`evaluate_box_tree`], but can be cancelled, reports progress and is bounded by the
+/// limits of `control`.
+pub fn evaluate_box_tree_controlled(
+    tree: BindingBoxTree,
+    ocel: &SlimLinkedOCEL,
+    measure_performance: bool,
+    control: &EvaluationControl,
 ) -> Result<EvaluateBoxTreeResult, String> {
     // The benchmark path writes its timings next to the user's downloads; there is no such place
     // on wasm, so there `measure_performance` falls through to a single ordinary evaluation.
@@ -76,7 +130,12 @@ pub fn evaluate_box_tree(
         serde_json::to_writer_pretty(BufWriter::new(tree_json_file), &tree).unwrap();
         for _ in 0..n {
             let start = std::time::Instant::now();
-            let (evaluation_results_flat, bindings_skipped) = tree.evaluate(ocel)?;
+            // A fresh control per run, so one run's time and memory do not count against the next.
+            let run_control = EvaluationControl::new(control.cancellation_token().clone())
+                .with_limits(control.limits().clone())
+                .with_as_of(control.as_of(ocel));
+            let (evaluation_results_flat, bindings_skipped) =
+                tree.evaluate_controlled(ocel, &run_control)?;
             if bindings_skipped {
                 eprintln!("Evaluation skipped bindings! Reported times are inaccurate!");
             }
@@ -89,6 +148,7 @@ pub fn evaluate_box_tree(
                     situations: Vec::new(),
                     situation_count: 0,
                     situation_violated_count: 0,
+                    explanations: HashMap::new(),
                 })
                 .collect_vec();
 
@@ -115,8 +175,53 @@ pub fn evaluate_box_tree(
         );
     }
     let now = Timer::start();
-    let (evaluation_results_flat, bindings_skipped) = tree.evaluate(ocel)?;
+    let (evaluation_results_flat, bindings_skipped) = tree.evaluate_controlled(ocel, control)?;
     // println!("Tree Evaluated in {:?}", now.elapsed());
+    let res = evaluation_result(
+        &tree,
+        ocel,
+        evaluation_results_flat,
+        bindings_skipped,
+        control,
+    );
+    if !measure_performance {
+        println!(
+            "Evaluated in {:?} (Size: {})",
+            now.elapsed(),
+            res.evaluation_results.len()
+        );
+    }
+    Ok(res)
+}
+
+/// Like [`evaluate_box_tree_controlled`] without the benchmark mode, but reuses what `cache` kept
+/// from earlier evaluations against the same `ocel_key` for every subtree that did not change.
+pub fn evaluate_box_tree_cached(
+    tree: BindingBoxTree,
+    ocel: &SlimLinkedOCEL,
+    ocel_key: u64,
+    cache: &EvaluationCache,
+    control: &EvaluationControl,
+) -> Result<EvaluateBoxTreeResult, String> {
+    let (evaluation_results_flat, bindings_skipped) =
+        tree.evaluate_cached(ocel, ocel_key, cache, control)?;
+    Ok(evaluation_result(
+        &tree,
+        ocel,
+        evaluation_results_flat,
+        bindings_skipped,
+        control,
+    ))
+}
+
+/// Group the flat results of an evaluation by node.
+fn evaluation_result(
+    tree: &BindingBoxTree,
+    ocel: &SlimLinkedOCEL,
+    evaluation_results_flat: structs::EvaluationResults,
+    bindings_skipped: bool,
+    control: &EvaluationControl,
+) -> EvaluateBoxTreeResult {
     if bindings_skipped {
         println!("[!!!] Query yielded too many results. Some bindings were skipped. Reported counts are inaccurate!");
     }
@@ -127,6 +232,7 @@ pub fn evaluate_box_tree(
             situations: Vec::new(),
             situation_count: 0,
             situation_violated_count: 0,
+            explanations: HashMap::new(),
         })
         .collect_vec();
 
@@ -141,14 +247,7 @@ pub fn evaluate_box_tree(
             r.situation_violated_count += 1;
         }
     }
-    if !measure_performance {
-        println!(
-            "Evaluated in {:?} (Size: {})",
-            now.elapsed(),
-            evaluation_results.len()
-        );
-    }
-    Ok(EvaluateBoxTreeResult {
+    EvaluateBoxTreeResult {
         evaluation_results,
         object_ids: ocel
             .get_all_obs()
@@ -159,7 +258,38 @@ pub fn evaluate_box_tree(
             .map(|e| ocel.get_ev_id(&e).to_string())
             .collect(),
         bindings_skipped,
+        limit_hits: control.limit_hits(),
         eval_version: 0,
+        as_of: Some(control.as_of(ocel)),
+        cel_profile: control.cel_profile(tree),
+    }
+}
+
+/// Per-node situation and violation counts of `tree`, without keeping any situation: they are
+/// counted as the evaluation streams them (see [`BindingBoxTree::evaluate_streaming`]). The
+/// result is not stored, so its `eval_version` is 0.
+pub fn evaluate_box_tree_counts(
+    tree: &BindingBoxTree,
+    ocel: &SlimLinkedOCEL,
+    control: &EvaluationControl,
+) -> Result<EvaluateBoxTreeSummary, String> {
+    let mut node_summaries = vec![NodeSummary::default(); tree.nodes.len()];
+    let bindings_skipped =
+        tree.evaluate_streaming(ocel, control, &mut |index, _binding, viol| {
+            let s = &mut node_summaries[index];
+            s.situation_count += 1;
+            if viol.is_some() {
+                s.situation_violated_count += 1;
+            }
+            Ok(())
+        })?;
+    Ok(EvaluateBoxTreeSummary {
+        node_summaries,
+        bindings_skipped,
+        limit_hits: control.limit_hits(),
+        eval_version: 0,
+        as_of: Some(control.as_of(ocel)),
+        cel_profile: control.cel_profile(tree),
     })
 }
 
@@ -410,10 +540,19 @@ pub struct EvaluateBoxTreeSummary {
     /// One entry per evaluation node, index-aligned with `evaluation_results`.
     pub node_summaries: Vec<NodeSummary>,
     pub bindings_skipped: bool,
+    pub limit_hits: Vec<LimitHit>,
     /// Monotonic version counter. Every new evaluation bumps it; page requests
     /// must carry the version they think is current, or the server rejects.
     #[ts(type = "number")]
     pub eval_version: u64,
+    /// The time the evaluation treated as "now"; events after it were not bound.
+    #[serde(default)]
+    #[ts(optional)]
+    pub as_of: Option<DateTime<FixedOffset>>,
+    /// Calls of each CEL program, if the evaluation was profiled.
+    #[serde(default)]
+    #[ts(optional)]
+    pub cel_profile: Option<CelProfile>,
 }
 
 #[derive(TS)]
@@ -458,6 +597,10 @@ pub struct BindingRow {
     pub events: Vec<(EventVariable, String)>,
     pub labels: Vec<(String, LabelValue)>,
     pub violation: Option<ViolationReason>,
+    /// Only for violated situations of an evaluation run with explanations
+    #[serde(default)]
+    #[ts(optional)]
+    pub explanation: Option<ViolationExplanation>,
 }
 
 /// A registry handle, not a wire value: situations can run to millions of rows, so the frontend pages them instead.
@@ -486,7 +629,10 @@ impl EvaluateBoxTreeResult {
                 })
                 .collect(),
             bindings_skipped: self.bindings_skipped,
+            limit_hits: self.limit_hits.clone(),
             eval_version: self.eval_version,
+            as_of: self.as_of,
+            cel_profile: self.cel_profile.clone(),
         }
     }
 
@@ -512,13 +658,14 @@ impl EvaluateBoxTreeResult {
         let rows = node
             .situations
             .iter()
-            .filter(|(_, v)| match req.violated {
+            .enumerate()
+            .filter(|(_, (_, v))| match req.violated {
                 None => true,
                 Some(want) => v.is_some() == want,
             })
             .skip(req.offset)
             .take(limit)
-            .map(|(b, v)| self.binding_to_row(b, v))
+            .map(|(i, (b, v))| self.binding_to_row(b, v, node.explanations.get(&i)))
             .collect();
 
         Ok(EvalPageResponse {
@@ -527,7 +674,12 @@ impl EvaluateBoxTreeResult {
         })
     }
 
-    fn binding_to_row(&self, b: &Binding, v: &Option<ViolationReason>) -> BindingRow {
+    fn binding_to_row(
+        &self,
+        b: &Binding,
+        v: &Option<ViolationReason>,
+        explanation: Option<&ViolationExplanation>,
+    ) -> BindingRow {
         BindingRow {
             objects: b
                 .object_map
@@ -541,7 +693,39 @@ impl EvaluateBoxTreeResult {
                 .collect(),
             labels: b.label_map.clone(),
             violation: *v,
+            explanation: explanation.cloned(),
+        }
+    }
+
+    /// Explain every violated situation (see [`ViolationExplainer`]); `tree` and `ocel` must be
+    /// the ones this result was evaluated from. Children are evaluated again per violated situation,
+    /// as of the same time as in the evaluation.
+    pub fn explain_violations(
+        &mut self,
+        tree: &BindingBoxTree,
+        ocel: &SlimLinkedOCEL,
+    ) -> Result<(), String> {
+        let explainer = match self.as_of {
+            Some(as_of) => ViolationExplainer::new(tree, ocel).with_as_of(as_of),
+            None => ViolationExplainer::new(tree, ocel),
+        };
+        for (node_index, res) in self.evaluation_results.iter_mut().enumerate() {
+            res.explanations = res
+                .situations
+                .par_iter()
+                .enumerate()
+                .filter(|(_, (_, v))| v.is_some())
+                .map(|(i, (b, _))| {
+                    Ok(explainer
+                        .explain(node_index, b)?
+                        .map(|explanation| (i, explanation)))
+                })
+                .collect::<Result<Vec<_>, String>>()?
+                .into_iter()
+                .flatten()
+                .collect();
         }
+        Ok(())
     }
 }
 
@@ -561,6 +745,7 @@ mod tests {
                 situations,
                 situation_count: total,
                 situation_violated_count: violated,
+                explanations: HashMap::new(),
             }],
             eval_version: version,
             ..Default::default()
@@ -613,123 +798,15 @@ mod tests {
     }
 }
 
-/// Evaluation of whole trees against a shared tiny fixture OCEL (see `OCEL_JSON` below).
+/// Evaluation of whole trees against the fixture OCEL of [`test_utils`].
 #[cfg(test)]
 mod evaluation_tests {
-    use super::structs::{BindingBoxTreeNode, Constraint, Filter, SizeFilter, ValueFilter};
+    use super::structs::{BindingBoxTreeNode, Constraint, Filter, SizeFilter};
+    use super::test_utils::{
+        amount_at_least, bound_event_ids, bound_object_ids, ev_vars, eval, o2e, ob_vars, ocel,
+        order_payment_shipping_tree, place_and_ship, tree, OCEL_JSON,
+    };
     use super::*;
-    use std::collections::HashMap;
-
-    const OCEL_JSON: &str = r#"{
-        "objectTypes": [
-            { "name": "order", "attributes": [] },
-            { "name": "item", "attributes": [] }
-        ],
-        "eventTypes": [
-            { "name": "place", "attributes": [{ "name": "amount", "type": "integer" }] },
-            { "name": "pay", "attributes": [] },
-            { "name": "ship", "attributes": [] },
-            { "name": "cancel", "attributes": [] }
-        ],
-        "objects": [
-            { "id": "o1", "type": "order", "attributes": [],
-              "relationships": [{ "objectId": "i1", "qualifier": "contains" }] },
-            { "id": "o2", "type": "order", "attributes": [], "relationships": [] },
-            { "id": "i1", "type": "item", "attributes": [], "relationships": [] }
-        ],
-        "events": [
-            { "id": "e1", "type": "place", "time": "2024-01-01T00:00:00Z",
-              "attributes": [{ "name": "amount", "value": 100 }],
-              "relationships": [{ "objectId": "o1", "qualifier": "order" }] },
-            { "id": "e2", "type": "place", "time": "2024-01-02T00:00:00Z",
-              "attributes": [{ "name": "amount", "value": 5 }],
-              "relationships": [{ "objectId": "o2", "qualifier": "order" }] },
-            { "id": "e3", "type": "pay", "time": "2024-01-03T00:00:00Z", "attributes": [],
-              "relationships": [{ "objectId": "o1", "qualifier": "order" }] },
-            { "id": "e4", "type": "ship", "time": "2024-01-04T00:00:00Z", "attributes": [],
-              "relationships": [{ "objectId": "o1", "qualifier": "order" },
-                                { "objectId": "i1", "qualifier": "item" }] },
-            { "id": "e5", "type": "ship", "time": "2024-01-05T00:00:00Z", "attributes": [],
-              "relationships": [{ "objectId": "o1", "qualifier": "order" }] },
-            { "id": "e6", "type": "cancel", "time": "2024-01-06T00:00:00Z", "attributes": [],
-              "relationships": [{ "objectId": "o2", "qualifier": "order" }] }
-        ]
-    }"#;
-
-    fn ocel() -> SlimLinkedOCEL {
-        let ocel: OCEL = serde_json::from_str(OCEL_JSON).expect("fixture OCEL parses");
-        SlimLinkedOCEL::from_ocel(ocel)
-    }
-
-    fn ob_vars(vars: &[(usize, &str)]) -> structs::NewObjectVariables {
-        vars.iter()
-            .map(|(i, t)| (ObjectVariable(*i), [t.to_string()].into_iter().collect()))
-            .collect()
-    }
-
-    fn ev_vars(vars: &[(usize, &str)]) -> structs::NewEventVariables {
-        vars.iter()
-            .map(|(i, t)| (EventVariable(*i), [t.to_string()].into_iter().collect()))
-            .collect()
-    }
-
-    fn o2e(object: usize, event: usize, qualifier: Option<&str>) -> Filter {
-        Filter::O2E {
-            object: ObjectVariable(object),
-            event: EventVariable(event),
-            qualifier: qualifier.map(str::to_string),
-            filter_label: None,
-        }
-    }
-
-    /// `amount >= min` on event variable `event`.
-    fn amount_at_least(event: usize, min: i64) -> Filter {
-        Filter::EventAttributeValueFilter {
-            event: EventVariable(event),
-            attribute_name: "amount".to_string(),
-            value_filter: ValueFilter::Integer {
-                min: Some(min),
-                max: None,
-            },
-        }
-    }
-
-    fn tree(nodes: Vec<BindingBoxTreeNode>, edges: &[((usize, usize), &str)]) -> BindingBoxTree {
-        BindingBoxTree {
-            nodes,
-            edge_names: edges
-                .iter()
-                .map(|(k, v)| (*k, v.to_string()))
-                .collect::<HashMap<_, _>>(),
-        }
-    }
-
-    fn eval(tree: BindingBoxTree) -> EvaluateBoxTreeResult {
-        evaluate_box_tree(tree, &ocel(), false).expect("evaluation succeeds")
-    }
-
-    /// The object ids bound to `var` by every situation of node `node_index`, in result order.
-    fn bound_object_ids(res: &EvaluateBoxTreeResult, node_index: usize, var: usize) -> Vec<&str> {
-        res.evaluation_results[node_index]
-            .situations
-            .iter()
-            .map(|(b, _)| {
-                let idx = b.get_ob_index(&ObjectVariable(var)).expect("var bound");
-                res.object_ids[(*idx).into_inner() as usize].as_str()
-            })
-            .collect()
-    }
-
-    fn bound_event_ids(res: &EvaluateBoxTreeResult, node_index: usize, var: usize) -> Vec<&str> {
-        res.evaluation_results[node_index]
-            .situations
-            .iter()
-            .map(|(b, _)| {
-                let idx = b.get_ev_index(&EventVariable(var)).expect("var bound");
-                res.event_ids[(*idx).into_inner() as usize].as_str()
-            })
-            .collect()
-    }
 
     #[test]
     fn one_object_variable_binds_every_object_of_that_type() {
@@ -985,4 +1062,253 @@ mod evaluation_tests {
         assert_eq!(page.rows[0].events, vec![(EventVariable(0), "e2".into())]);
         assert!(page.rows[0].violation.is_some());
     }
+
+    #[test]
+    fn counting_gives_the_summary_of_the_full_evaluation() {
+        let t = order_payment_shipping_tree();
+        let full = eval(t.clone()).summary();
+        let counted = evaluate_box_tree_counts(&t, &ocel(), &EvaluationControl::default())
+            .expect("counting succeeds");
+        assert_eq!(counted.node_summaries.len(), 3);
+        for (c, f) in counted.node_summaries.iter().zip(&full.node_summaries) {
+            assert_eq!(c.situation_count, f.situation_count);
+            assert_eq!(c.situation_violated_count, f.situation_violated_count);
+        }
+        assert_eq!(
+            counted.node_summaries[2].situation_count, 2,
+            "o1 ships twice"
+        );
+        assert_eq!(counted.node_summaries[2].situation_violated_count, 2);
+        assert!(!counted.bindings_skipped);
+    }
+
+    #[test]
+    fn streamed_situations_arrive_per_node_in_the_order_of_the_collected_result() {
+        let t = order_payment_shipping_tree();
+        let full = eval(t.clone());
+        let mut streamed = vec![Vec::new(); t.nodes.len()];
+        t.evaluate_streaming(
+            &ocel(),
+            &EvaluationControl::default(),
+            &mut |index, b, v| {
+                streamed[index].push((std::sync::Arc::unwrap_or_clone(b), v));
+                Ok(())
+            },
+        )
+        .expect("streaming succeeds");
+        for (node, r) in full.evaluation_results.iter().enumerate() {
+            let expected = r
+                .situations
+                .iter()
+                .map(|(b, v)| (b.to_string(), v.is_some()));
+            let got = streamed[node]
+                .iter()
+                .map(|(b, v)| (b.to_string(), v.is_some()));
+            assert!(expected.eq(got), "node {node}");
+        }
+    }
+
+    #[test]
+    fn a_failing_sink_stops_the_streaming_evaluation_with_its_error() {
+        let err = order_payment_shipping_tree()
+            .evaluate_streaming(&ocel(), &EvaluationControl::default(), &mut |_, _, _| {
+                Err("disk full".to_string())
+            })
+            .expect_err("the sink's error is returned");
+        assert_eq!(err, "disk full");
+    }
+
+    /// At most 25 hours of working time of `calendar` from `place` to `ship`.
+    fn shipped_within_a_business_day(calendar: &str) -> Filter {
+        Filter::TimeBetweenEvents {
+            from_event: EventVariable(0),
+            to_event: EventVariable(1),
+            min_seconds: None,
+            max_seconds: Some(25.0 * 3600.0),
+            calendar: Some(calendar.to_string()),
+        }
+    }
+
+    /// A calendar in which every hour is working time.
+    fn around_the_clock() -> crate::calendar::CalendarSpec {
+        crate::calendar::CalendarSpec {
+            working_days: (1..=7).collect(),
+            working_hours: vec![crate::calendar::WorkingHours {
+                start: "00:00".to_string(),
+                end: "24:00".to_string(),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn time_between_events_measures_working_time_of_the_calendar() {
+        use crate::calendar::{register_calendar, CalendarSpec};
+
+        register_calendar("evaluation_tests_office", &CalendarSpec::default()).unwrap();
+        // e1 (Monday 0:00) to e4 (Thursday 0:00) is 24 working hours but 72 elapsed, so the
+        // planner must not bound the elapsed time by the maximum; e1 to e5 is 32 working hours.
+        let res = eval(place_and_ship(shipped_within_a_business_day(
+            "evaluation_tests_office",
+        )));
+        assert_eq!(bound_event_ids(&res, 0, 1), ["e4"]);
+
+        register_calendar("evaluation_tests_always", &around_the_clock()).unwrap();
+        let res = eval(place_and_ship(shipped_within_a_business_day(
+            "evaluation_tests_always",
+        )));
+        assert!(bound_event_ids(&res, 0, 1).is_empty());
+    }
+
+    #[test]
+    fn cel_business_duration_measures_working_time() {
+        crate::calendar::register_calendar("evaluation_tests_cel_always", &around_the_clock())
+            .unwrap();
+        let ships = |cel: &str| {
+            let res = eval(place_and_ship(Filter::BasicFilterCEL {
+                cel: cel.to_string(),
+            }));
+            bound_event_ids(&res, 0, 1)
+                .into_iter()
+                .map(str::to_string)
+                .collect_vec()
+        };
+        // Without a calendar, the default one (Monday to Friday, 9:00 to 17:00 UTC) is used.
+        assert_eq!(
+            ships("businessDuration(e1.time(), e2.time()) <= duration('25h')"),
+            ["e4"]
+        );
+        assert_eq!(
+            ships("businessDuration(e1.time(), e2.time(), 'default') == duration('32h')"),
+            ["e5"]
+        );
+        assert_eq!(
+            ships("businessDuration(e1.time(), e2.time(), 'evaluation_tests_cel_always') == duration('96h')"),
+            ["e5"]
+        );
+    }
+
+    #[test]
+    fn re_registering_a_calendar_invalidates_cached_results() {
+        use crate::calendar::{register_calendar, CalendarSpec};
+
+        let cache = EvaluationCache::default();
+        let t = place_and_ship(shipped_within_a_business_day("evaluation_tests_cached"));
+        register_calendar("evaluation_tests_cached", &CalendarSpec::default()).unwrap();
+        let first =
+            evaluate_box_tree_cached(t.clone(), &ocel(), 1, &cache, &EvaluationControl::default())
+                .unwrap();
+        assert_eq!(bound_event_ids(&first, 0, 1), ["e4"]);
+
+        register_calendar("evaluation_tests_cached", &around_the_clock()).unwrap();
+        let second =
+            evaluate_box_tree_cached(t, &ocel(), 1, &cache, &EvaluationControl::default()).unwrap();
+        assert_eq!(cache.hits(), 0);
+        assert!(bound_event_ids(&second, 0, 1).is_empty());
+    }
+
+    #[test]
+    fn cel_navigates_relationships_from_bound_variables() {
+        // The ids of the objects of `ob_type` (bound to `o1`) passing `cel`.
+        let passing = |ob_type: &str, cel: &str| {
+            let res = eval(tree(
+                vec![BindingBoxTreeNode::Box(
+                    BindingBox {
+                        new_object_vars: ob_vars(&[(0, ob_type)]),
+                        filters: vec![Filter::BasicFilterCEL {
+                            cel: cel.to_string(),
+                        }],
+                        ..Default::default()
+                    },
+                    vec![],
+                )],
+                &[],
+            ));
+            bound_object_ids(&res, 0, 0)
+                .into_iter()
+                .map(str::to_string)
+                .sorted()
+                .collect_vec()
+        };
+        assert_eq!(passing("order", "o1.objects('item').size() == 1"), ["o1"]);
+        assert_eq!(passing("order", "o1.events('ship').size() == 2"), ["o1"]);
+        assert_eq!(
+            passing(
+                "order",
+                "o1.events().map(e, e.type()) == ['place', 'pay', 'ship', 'ship']"
+            ),
+            ["o1"]
+        );
+        // Reverse O2O, then reverse E2O through a qualifier
+        assert_eq!(
+            passing("item", "o1.related('contains').size() == 1"),
+            ["i1"]
+        );
+        assert_eq!(
+            passing(
+                "order",
+                "o1.related('contains').exists(i, i.events('', 'item').size() == 1)"
+            ),
+            ["o1"]
+        );
+        assert_eq!(
+            passing(
+                "order",
+                "o1.events('ship').exists(e, e.objects('', 'item').size() > 0)"
+            ),
+            ["o1"]
+        );
+        // Without a target, the functions still list the whole OCEL
+        assert_eq!(
+            passing(
+                "order",
+                "objects('order').size() == 2 && events().size() == 6"
+            ),
+            ["o1", "o2"]
+        );
+    }
+
+    #[test]
+    fn cel_evaluations_against_different_ocels_run_side_by_side() {
+        // Here e2 is the big order instead of e1.
+        let other: OCEL = serde_json::from_str(
+            &OCEL_JSON
+                .replace(r#""value": 100"#, r#""value": 1"#)
+                .replace(r#""value": 5"#, r#""value": 500"#),
+        )
+        .expect("modified fixture OCEL parses");
+        let other = SlimLinkedOCEL::from_ocel(other);
+        let fixture = ocel();
+        let big_places = |ocel: &SlimLinkedOCEL| {
+            let t = tree(
+                vec![BindingBoxTreeNode::Box(
+                    BindingBox {
+                        new_event_vars: ev_vars(&[(0, "place")]),
+                        filters: vec![Filter::BasicFilterCEL {
+                            cel: "e1.attr('amount') >= 50".to_string(),
+                        }],
+                        ..Default::default()
+                    },
+                    vec![],
+                )],
+                &[],
+            );
+            let res = evaluate_box_tree(t, ocel, false).expect("evaluation succeeds");
+            bound_event_ids(&res, 0, 0)
+                .into_iter()
+                .map(str::to_string)
+                .collect_vec()
+        };
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..10 {
+                        // Alternating on one thread must not reuse the other OCEL either.
+                        assert_eq!(big_places(&fixture), ["e1"]);
+                        assert_eq!(big_places(&other), ["e2"]);
+                    }
+                });
+            }
+        });
+    }
 }
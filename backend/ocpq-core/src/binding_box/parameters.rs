@@ -0,0 +1,572 @@
+//! Named, typed parameters of a tree, so one query can be run with different thresholds (like a
+//! maximum number of days or a minimum amount) without editing it.
+//!
+//! A [`TreeParameter`] lists the bounds it sets: the min/max of a value filter, a
+//! `TimeBetweenEvents` filter or a size filter, a fraction of `NumSatisfiedChilds`, or the value a
+//! string or bool filter compares with. CEL expressions use it as a variable, by its name.
+//!
+//! [`BindingBoxTree::bind_parameters`] writes the values of an evaluation into those bounds and
+//! keeps them as the parameters' defaults, which CEL reads as variables (see
+//! [`ParameterValue::cel_value`]), so the text of the expressions, and with it the compiled
+//! programs, stay the same for every value. Trees that are evaluated without binding them use the
+//! parameters' defaults.
+use std::{collections::HashMap, sync::Arc};
+
+use cel_interpreter::Value;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::{
+    structs::{BindingBoxTree, BindingBoxTreeNode, Constraint, Filter, SizeFilter, ValueFilter},
+    validate::TreeLocation,
+};
+use crate::cel::{self, syntax::CelType};
+
+/// Values to evaluate a tree with, by parameter name
+pub type ParameterValues = HashMap<String, ParameterValue>;
+
+/// The type of a tree parameter
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum ParameterType {
+    Int,
+    Float,
+    String,
+    Bool,
+    Timestamp,
+    Duration,
+}
+
+/// A value of a tree parameter
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type", content = "value")]
+pub enum ParameterValue {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Timestamp(DateTime<Utc>),
+    /// In seconds
+    Duration(f64),
+}
+
+/// Which bound of its target a parameter sets
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum ParameterBound {
+    Min,
+    Max,
+    /// `min_fraction` of a `NumSatisfiedChilds` size filter
+    MinFraction,
+    /// `max_fraction` of a `NumSatisfiedChilds` size filter
+    MaxFraction,
+    /// The value a string or bool value filter compares with
+    Value,
+}
+
+/// A bound of a filter, size filter or constraint that a parameter sets
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterUse {
+    pub node_index: usize,
+    pub location: TreeLocation,
+    pub bound: ParameterBound,
+}
+
+/// A named value of a tree, given when it is evaluated
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeParameter {
+    /// Also the name of the CEL variable
+    pub name: String,
+    pub parameter_type: ParameterType,
+    /// Used if an evaluation does not give a value; without one, the parameter must be given.
+    #[serde(default)]
+    #[ts(optional)]
+    pub default: Option<ParameterValue>,
+    #[serde(default)]
+    #[ts(optional)]
+    #[ts(as = "Option<Vec<ParameterUse>>")]
+    pub uses: Vec<ParameterUse>,
+}
+
+impl ParameterType {
+    pub fn cel_type(self) -> CelType {
+        match self {
+            ParameterType::Int => CelType::Int,
+            ParameterType::Float => CelType::Float,
+            ParameterType::String => CelType::String,
+            ParameterType::Bool => CelType::Bool,
+            ParameterType::Timestamp => CelType::Timestamp,
+            ParameterType::Duration => CelType::Duration,
+        }
+    }
+
+    /// Parse a value of this type from text, like a command line argument. Timestamps are RFC 3339
+    /// and durations either seconds or CEL durations (like `72h`).
+    pub fn parse(self, text: &str) -> Result<ParameterValue, String> {
+        let invalid = |e: String| format!("`{text}` is not a valid {self:?}: {e}");
+        Ok(match self {
+            ParameterType::Int => {
+                ParameterValue::Int(text.parse().map_err(|e| invalid(format!("{e}")))?)
+            }
+            ParameterType::Float => {
+                ParameterValue::Float(text.parse().map_err(|e| invalid(format!("{e}")))?)
+            }
+            ParameterType::String => ParameterValue::String(text.to_string()),
+            ParameterType::Bool => {
+                ParameterValue::Bool(text.parse().map_err(|e| invalid(format!("{e}")))?)
+            }
+            ParameterType::Timestamp => ParameterValue::Timestamp(
+                DateTime::parse_from_rfc3339(text)
+                    .map_err(|e| invalid(format!("{e}")))?
+                    .with_timezone(&Utc),
+            ),
+            ParameterType::Duration => match text.parse::<f64>() {
+                Ok(seconds) => ParameterValue::Duration(seconds),
+                Err(_) => match cel::evaluate_literal_call("duration", text) {
+                    Some(Value::Duration(d)) => ParameterValue::Duration(cel::duration_seconds(d)),
+                    _ => return Err(invalid("neither seconds nor a CEL duration".to_string())),
+                },
+            },
+        })
+    }
+
+    /// Some value of this type, to check where a parameter of it can be used.
+    fn example(self) -> ParameterValue {
+        match self {
+            ParameterType::Int => ParameterValue::Int(0),
+            ParameterType::Float => ParameterValue::Float(0.0),
+            ParameterType::String => ParameterValue::String(String::new()),
+            ParameterType::Bool => ParameterValue::Bool(false),
+            ParameterType::Timestamp => ParameterValue::Timestamp(DateTime::UNIX_EPOCH),
+            ParameterType::Duration => ParameterValue::Duration(0.0),
+        }
+    }
+}
+
+impl ParameterValue {
+    /// Whether this is a value for a parameter of type `t`; integers are also floats.
+    pub fn has_type(&self, t: ParameterType) -> bool {
+        matches!(
+            (self, t),
+            (
+                ParameterValue::Int(_),
+                ParameterType::Int | ParameterType::Float
+            ) | (ParameterValue::Float(_), ParameterType::Float)
+                | (ParameterValue::String(_), ParameterType::String)
+                | (ParameterValue::Bool(_), ParameterType::Bool)
+                | (ParameterValue::Timestamp(_), ParameterType::Timestamp)
+                | (ParameterValue::Duration(_), ParameterType::Duration)
+        )
+    }
+
+    /// This value in CEL, for a parameter of type `t`
+    pub fn cel_value(&self, t: ParameterType) -> Value {
+        match self {
+            ParameterValue::Int(i) if t == ParameterType::Float => Value::Float(*i as f64),
+            ParameterValue::Int(i) => Value::Int(*i),
+            ParameterValue::Float(f) => Value::Float(*f),
+            ParameterValue::String(s) => Value::String(Arc::new(s.clone())),
+            ParameterValue::Bool(b) => Value::Bool(*b),
+            ParameterValue::Timestamp(t) => Value::Timestamp((*t).into()),
+            ParameterValue::Duration(seconds) => Value::Duration(chrono::Duration::microseconds(
+                (seconds * 1e6).round() as i64,
+            )),
+        }
+    }
+
+    fn as_f64(&self) -> Result<f64, String> {
+        match self {
+            ParameterValue::Int(i) => Ok(*i as f64),
+            ParameterValue::Float(f) => Ok(*f),
+            v => Err(format!("{v:?} is not a number")),
+        }
+    }
+
+    fn as_i64(&self) -> Result<i64, String> {
+        match self {
+            ParameterValue::Int(i) => Ok(*i),
+            v => Err(format!("{v:?} is not an integer")),
+        }
+    }
+
+    fn as_count(&self) -> Result<usize, String> {
+        usize::try_from(self.as_i64()?).map_err(|_| format!("{self:?} is not a count"))
+    }
+
+    /// Durations and plain numbers, as seconds
+    fn as_seconds(&self) -> Result<f64, String> {
+        match self {
+            ParameterValue::Duration(seconds) => Ok(*seconds),
+            v => v.as_f64(),
+        }
+    }
+}
+
+/// The bound of `min`/`max` that `bound` names
+fn min_or_max<'a, T>(
+    min: &'a mut Option<T>,
+    max: &'a mut Option<T>,
+    bound: ParameterBound,
+) -> Result<&'a mut Option<T>, String> {
+    match bound {
+        ParameterBound::Min => Ok(min),
+        ParameterBound::Max => Ok(max),
+        b => Err(format!("This has no {b:?} bound")),
+    }
+}
+
+fn set_value_filter_bound(
+    value_filter: &mut ValueFilter,
+    bound: ParameterBound,
+    value: &ParameterValue,
+) -> Result<(), String> {
+    match value_filter {
+        ValueFilter::Float { min, max } => *min_or_max(min, max, bound)? = Some(value.as_f64()?),
+        ValueFilter::Integer { min, max } => *min_or_max(min, max, bound)? = Some(value.as_i64()?),
+        ValueFilter::Time { from, to } => match value {
+            ParameterValue::Timestamp(t) => *min_or_max(from, to, bound)? = Some(*t),
+            v => return Err(format!("{v:?} is not a timestamp")),
+        },
+        ValueFilter::String { is_in } => match (bound, value) {
+            (ParameterBound::Value, ParameterValue::String(s)) => *is_in = vec![s.clone()],
+            (ParameterBound::Value, v) => return Err(format!("{v:?} is not a string")),
+            (b, _) => return Err(format!("String filters have no {b:?} bound")),
+        },
+        ValueFilter::Boolean { is_true } => match (bound, value) {
+            (ParameterBound::Value, ParameterValue::Bool(b)) => *is_true = *b,
+            (ParameterBound::Value, v) => return Err(format!("{v:?} is not a bool")),
+            (b, _) => return Err(format!("Bool filters have no {b:?} bound")),
+        },
+    }
+    Ok(())
+}
+
+fn set_filter_bound(
+    filter: &mut Filter,
+    bound: ParameterBound,
+    value: &ParameterValue,
+) -> Result<(), String> {
+    match filter {
+        Filter::TimeBetweenEvents {
+            min_seconds,
+            max_seconds,
+            ..
+        } => *min_or_max(min_seconds, max_seconds, bound)? = Some(value.as_seconds()?),
+        Filter::EventAttributeValueFilter { value_filter, .. }
+        | Filter::ObjectAttributeValueFilter { value_filter, .. } => {
+            set_value_filter_bound(value_filter, bound, value)?
+        }
+        _ => return Err("This filter has no bounds".to_string()),
+    }
+    Ok(())
+}
+
+fn set_size_filter_bound(
+    size_filter: &mut SizeFilter,
+    bound: ParameterBound,
+    value: &ParameterValue,
+) -> Result<(), String> {
+    match (size_filter, bound) {
+        (
+            SizeFilter::NumChilds { min, max, .. }
+            | SizeFilter::NumChildsProj { min, max, .. }
+            | SizeFilter::NumSatisfiedChilds { min, max, .. },
+            ParameterBound::Min | ParameterBound::Max,
+        ) => *min_or_max(min, max, bound)? = Some(value.as_count()?),
+        (SizeFilter::Aggregate { min, max, .. }, _) => {
+            *min_or_max(min, max, bound)? = Some(value.as_f64()?)
+        }
+        (SizeFilter::NumSatisfiedChilds { min_fraction, .. }, ParameterBound::MinFraction) => {
+            *min_fraction = Some(value.as_f64()?)
+        }
+        (SizeFilter::NumSatisfiedChilds { max_fraction, .. }, ParameterBound::MaxFraction) => {
+            *max_fraction = Some(value.as_f64()?)
+        }
+        (_, b) => return Err(format!("This size filter has no {b:?} bound")),
+    }
+    Ok(())
+}
+
+/// Write `value` into the bound of `tree` that `u` names.
+pub(crate) fn set_bound(
+    tree: &mut BindingBoxTree,
+    u: &ParameterUse,
+    value: &ParameterValue,
+) -> Result<(), String> {
+    let Some(BindingBoxTreeNode::Box(bbox, _)) = tree.nodes.get_mut(u.node_index) else {
+        return Err(format!("Node {} is not a box", u.node_index));
+    };
+    let missing = || format!("Node {} has no {:?}", u.node_index, u.location);
+    match u.location {
+        TreeLocation::Filter { index } => set_filter_bound(
+            bbox.filters.get_mut(index).ok_or_else(missing)?,
+            u.bound,
+            value,
+        ),
+        TreeLocation::SizeFilter { index } => set_size_filter_bound(
+            bbox.size_filters.get_mut(index).ok_or_else(missing)?,
+            u.bound,
+            value,
+        ),
+        TreeLocation::Constraint { index } => {
+            match bbox.constraints.get_mut(index).ok_or_else(missing)? {
+                Constraint::Filter { filter } => set_filter_bound(filter, u.bound, value),
+                Constraint::SizeFilter { filter } => set_size_filter_bound(filter, u.bound, value),
+                _ => Err("This constraint has no bounds".to_string()),
+            }
+        }
+        TreeLocation::Node | TreeLocation::Label { .. } => {
+            Err(format!("{:?} has no bounds", u.location))
+        }
+    }
+}
+
+impl TreeParameter {
+    /// The value of this parameter among `values`, or else its default.
+    pub fn value<'a>(&'a self, values: &'a ParameterValues) -> Result<&'a ParameterValue, String> {
+        let value = values
+            .get(&self.name)
+            .or(self.default.as_ref())
+            .ok_or_else(|| format!("Parameter `{}` is unbound", self.name))?;
+        if !value.has_type(self.parameter_type) {
+            return Err(format!(
+                "Parameter `{}` is a {:?}, but {value:?} is not",
+                self.name, self.parameter_type
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Whether this parameter can set the bound `u` names in `tree`
+    pub(crate) fn check_use(&self, tree: &BindingBoxTree, u: &ParameterUse) -> Result<(), String> {
+        let mut tree = tree.clone();
+        set_bound(&mut tree, u, &self.parameter_type.example())
+    }
+}
+
+impl BindingBoxTree {
+    /// This tree with the parameters bound to `values` (or their defaults): their bounds are set,
+    /// and each parameter is kept, without uses and with its value as the default, for CEL to read
+    /// as a variable. Fails if a parameter is unbound or a value is of the wrong type, or if
+    /// `values` names a parameter the tree does not have.
+    pub fn bind_parameters(&self, values: &ParameterValues) -> Result<BindingBoxTree, String> {
+        if let Some(name) = values
+            .keys()
+            .find(|name| !self.parameters.iter().any(|p| &p.name == *name))
+        {
+            return Err(format!("There is no parameter `{name}`"));
+        }
+        let mut tree = self.clone();
+        let mut parameters = std::mem::take(&mut tree.parameters);
+        for parameter in &mut parameters {
+            let value = parameter.value(values)?.clone();
+            if matches!(value, ParameterValue::Duration(seconds) if !seconds.is_finite()) {
+                return Err(format!(
+                    "Parameter `{}`: Durations must be finite",
+                    parameter.name
+                ));
+            }
+            for u in std::mem::take(&mut parameter.uses) {
+                set_bound(&mut tree, &u, &value)
+                    .map_err(|e| format!("Parameter `{}`: {e}", parameter.name))?;
+            }
+            parameter.default = Some(value);
+        }
+        tree.parameters = parameters;
+        Ok(tree)
+    }
+
+    /// This tree bound to the defaults of its parameters, if any of them sets a bound. Parameters
+    /// that only CEL reads need no binding, but must have a default.
+    pub(crate) fn with_default_parameters(&self) -> Result<Option<BindingBoxTree>, String> {
+        if self.parameters.iter().all(|p| p.uses.is_empty()) {
+            for parameter in &self.parameters {
+                parameter.value(&ParameterValues::new())?;
+            }
+            return Ok(None);
+        }
+        self.bind_parameters(&ParameterValues::new()).map(Some)
+    }
+
+    /// Values for parameters of this tree from `name=value` texts (see [`ParameterType::parse`]).
+    pub fn parse_parameter_values<'a>(
+        &self,
+        assignments: impl IntoIterator<Item = &'a str>,
+    ) -> Result<ParameterValues, String> {
+        assignments
+            .into_iter()
+            .map(|assignment| {
+                let (name, text) = assignment
+                    .split_once('=')
+                    .ok_or_else(|| format!("`{assignment}` is not of the form name=value"))?;
+                let parameter = self
+                    .parameters
+                    .iter()
+                    .find(|p| p.name == name)
+                    .ok_or_else(|| format!("There is no parameter `{name}`"))?;
+                Ok((name.to_string(), parameter.parameter_type.parse(text)?))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::binding_box::{
+        evaluate_box_tree,
+        structs::BindingBox,
+        test_utils::{amount_at_least, bound_event_ids, ev_vars, ocel, tree},
+        validate::{validate_tree, validate_tree_with_parameters},
+        EvaluateBoxTreeResult,
+    };
+
+    #[test]
+    fn parameters_set_bounds_and_cel_variables_when_bound() {
+        let mut t = tree(
+            vec![BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_event_vars: ev_vars(&[(0, "place")]),
+                    filters: vec![
+                        amount_at_least(0, 0),
+                        Filter::BasicFilterCEL {
+                            cel: "e1.attr('amount') <= max_amount".to_string(),
+                        },
+                    ],
+                    ..Default::default()
+                },
+                vec![],
+            )],
+            &[],
+        );
+        t.parameters = vec![
+            TreeParameter {
+                name: "min_amount".to_string(),
+                parameter_type: ParameterType::Int,
+                default: Some(ParameterValue::Int(50)),
+                uses: vec![ParameterUse {
+                    node_index: 0,
+                    location: TreeLocation::Filter { index: 0 },
+                    bound: ParameterBound::Min,
+                }],
+            },
+            TreeParameter {
+                name: "max_amount".to_string(),
+                parameter_type: ParameterType::Int,
+                default: None,
+                uses: vec![],
+            },
+        ];
+        let eval_with = |assignments: &[&str]| {
+            let values = t.parse_parameter_values(assignments.iter().copied())?;
+            evaluate_box_tree(t.bind_parameters(&values)?, &ocel(), false)
+        };
+        let ids = |res: Result<EvaluateBoxTreeResult, String>| {
+            let res = res.expect("evaluation succeeds");
+            let ids = bound_event_ids(&res, 0, 0)
+                .into_iter()
+                .map(str::to_string)
+                .collect_vec();
+            ids
+        };
+
+        assert_eq!(ids(eval_with(&["max_amount=1000"])), ["e1"]);
+        assert_eq!(ids(eval_with(&["max_amount=10", "min_amount=1"])), ["e2"]);
+        assert_eq!(
+            eval_with(&[]).unwrap_err(),
+            "Parameter `max_amount` is unbound"
+        );
+        assert_eq!(
+            eval_with(&["limit=3"]).unwrap_err(),
+            "There is no parameter `limit`"
+        );
+        // Evaluating without binding uses the defaults, so an unbound parameter fails the same way.
+        assert!(evaluate_box_tree(t.clone(), &ocel(), false).is_err());
+
+        let errors = |t: &BindingBoxTree, values: &[&str]| {
+            let values = t.parse_parameter_values(values.iter().copied()).unwrap();
+            validate_tree_with_parameters(t, &values)
+                .diagnostics
+                .into_iter()
+                .map(|d| (d.node_index, d.location, d.message))
+                .collect_vec()
+        };
+        assert_eq!(
+            validate_tree(&t)
+                .diagnostics
+                .into_iter()
+                .map(|d| d.message)
+                .collect_vec(),
+            ["Parameter `max_amount` is unbound"]
+        );
+        assert!(errors(&t, &["max_amount=10"]).is_empty());
+
+        // Binding keeps the CEL text; the expressions read the values as variables.
+        let bound = t
+            .bind_parameters(&t.parse_parameter_values(["max_amount=10"]).unwrap())
+            .unwrap();
+        assert!(matches!(
+            &bound.nodes[0],
+            BindingBoxTreeNode::Box(bbox, _) if matches!(
+                &bbox.filters[1],
+                Filter::BasicFilterCEL { cel } if cel == "e1.attr('amount') <= max_amount"
+            )
+        ));
+        assert_eq!(bound.parameters[1].default, Some(ParameterValue::Int(10)));
+        assert!(bound.parameters.iter().all(|p| p.uses.is_empty()));
+
+        // A comprehension variable shadows the parameter of its name.
+        if let BindingBoxTreeNode::Box(bbox, _) = &mut t.nodes[0] {
+            bbox.filters[1] = Filter::BasicFilterCEL {
+                cel: "[1, 2].all(max_amount, max_amount > 0)".to_string(),
+            };
+        }
+        assert!(errors(&t, &["max_amount=-1"]).is_empty());
+        let values = t.parse_parameter_values(["max_amount=-1"]).unwrap();
+        assert_eq!(
+            ids(evaluate_box_tree(
+                t.bind_parameters(&values).unwrap(),
+                &ocel(),
+                false
+            )),
+            ["e1"]
+        );
+
+        // A string cannot be the minimum amount, and CEL sees the parameter's type.
+        t.parameters[0].parameter_type = ParameterType::String;
+        t.parameters[0].default = Some(ParameterValue::String("50".to_string()));
+        if let BindingBoxTreeNode::Box(bbox, _) = &mut t.nodes[0] {
+            bbox.filters[1] = Filter::BasicFilterCEL {
+                cel: "max_amount".to_string(),
+            };
+        }
+        let errors = errors(&t, &["max_amount=10"]);
+        assert!(
+            errors
+                .iter()
+                .any(|(node, location, _)| *node == 0
+                    && *location == TreeLocation::Filter { index: 0 }),
+            "{errors:?}"
+        );
+        assert!(
+            errors
+                .iter()
+                .any(|(_, location, _)| *location == TreeLocation::Filter { index: 1 }),
+            "{errors:?}"
+        );
+    }
+}
@@ -0,0 +1,582 @@
+//! Statistics about an OCEL that the binding-order planner estimates the cost of steps with.
+//!
+//! They are computed from a sample of every event and object type, once per evaluation or, given
+//! the OCEL's generation, once per OCEL (see [`PlannerStatistics::for_ocel`]): the number of events and objects per type, the average
+//! E2O/O2O fan-out per qualifier and pair of types, and histograms of the attribute values.
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use process_mining::core::event_data::object_centric::{
+    linked_ocel::{LinkedOCELAccess, SlimLinkedOCEL},
+    OCELAttributeValue,
+};
+
+use super::structs::{BindingBox, BindingStep, Filter, Qualifier, ValueFilter, Variable};
+
+/// How many events and objects of each type are sampled at most.
+pub const STATS_SAMPLE_SIZE: usize = 2_000;
+/// Number of buckets of an attribute histogram.
+const HISTOGRAM_BUCKETS: usize = 32;
+/// Share of bindings assumed to pass a filter the statistics say nothing about (e.g., CEL).
+const DEFAULT_SELECTIVITY: f64 = 0.5;
+/// No estimate goes below this share, so one empty histogram does not make every plan look free.
+const MIN_SELECTIVITY: f64 = 1.0 / STATS_SAMPLE_SIZE as f64;
+/// How many OCELs statistics are kept for.
+const MAX_CACHED_STATISTICS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Relation {
+    /// From an event to its objects
+    E2O,
+    /// From an object to its events
+    O2E,
+    /// From an object to the objects it relates to
+    O2O,
+    /// From an object to the objects relating to it
+    O2ORev,
+}
+
+/// Sampled values of one attribute, in buckets that each stand for a number of samples.
+#[derive(Debug, Clone, Default)]
+struct AttributeHistogram {
+    buckets: Vec<(OCELAttributeValue, usize)>,
+    /// Sampled events or objects, including those without a value
+    samples: usize,
+}
+
+fn numeric_key(v: &OCELAttributeValue) -> Option<f64> {
+    match v {
+        OCELAttributeValue::Integer(i) => Some(*i as f64),
+        OCELAttributeValue::Float(f) => Some(*f),
+        OCELAttributeValue::Time(t) => Some(t.timestamp_millis() as f64),
+        _ => None,
+    }
+}
+
+impl AttributeHistogram {
+    /// Numbers and times get equi-depth buckets (represented by their median), other values one
+    /// bucket per value for the most frequent ones.
+    fn new(mut values: Vec<OCELAttributeValue>, samples: usize) -> Self {
+        let buckets = if values.iter().all(|v| numeric_key(v).is_some()) {
+            values.sort_by(|a, b| {
+                numeric_key(a)
+                    .partial_cmp(&numeric_key(b))
+                    .unwrap_or(Ordering::Equal)
+            });
+            let size = values.len().div_ceil(HISTOGRAM_BUCKETS).max(1);
+            values
+                .chunks(size)
+                .map(|c| (c[c.len() / 2].clone(), c.len()))
+                .collect()
+        } else {
+            values
+                .into_iter()
+                .into_group_map_by(|v| format!("{v:?}"))
+                .into_values()
+                .map(|vs| (vs[0].clone(), vs.len()))
+                .sorted_by_key(|(_, count)| std::cmp::Reverse(*count))
+                .take(HISTOGRAM_BUCKETS)
+                .collect()
+        };
+        Self { buckets, samples }
+    }
+
+    /// Share of the samples with a value passing `filter`.
+    fn selectivity(&self, filter: &ValueFilter) -> f64 {
+        if self.samples == 0 {
+            return DEFAULT_SELECTIVITY;
+        }
+        self.buckets
+            .iter()
+            .filter(|(v, _)| filter.check_value(v))
+            .map(|(_, count)| count)
+            .sum::<usize>() as f64
+            / self.samples as f64
+    }
+}
+
+/// Statistics the binding-order planner estimates how many bindings a step yields with.
+#[derive(Debug, Default)]
+pub struct PlannerStatistics {
+    ev_type_counts: HashMap<String, usize>,
+    ob_type_counts: HashMap<String, usize>,
+    /// Average number of related items of the target type per item of the source type, by
+    /// (relation, source type, qualifier, target type); the `None` qualifier counts all of them
+    fan_outs: HashMap<(Relation, String, Qualifier, String), f64>,
+    /// By (event type, attribute)
+    ev_attributes: HashMap<(String, String), AttributeHistogram>,
+    /// By (object type, attribute), with every value an object ever had
+    ob_attributes: HashMap<(String, String), AttributeHistogram>,
+    /// Seconds between the first and last sampled event
+    time_span: f64,
+}
+
+/// Statistics by the key of the OCEL they were computed for, least recently computed first
+static STATS_CACHE: Lazy<Mutex<Vec<(u64, Arc<PlannerStatistics>)>>> =
+    Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Every how manyth item to sample, so at most [`STATS_SAMPLE_SIZE`] of `count` are.
+fn sample_step(count: usize) -> usize {
+    count.div_ceil(STATS_SAMPLE_SIZE).max(1)
+}
+
+/// Add one per related item to `counts`, once under its qualifier and once under `None`.
+fn count_related(
+    counts: &mut HashMap<(Relation, Qualifier, String), usize>,
+    relation: Relation,
+    related: impl Iterator<Item = (impl ToString, impl ToString)>,
+) {
+    for (qualifier, target_type) in related {
+        for q in [Some(qualifier.to_string()), None] {
+            *counts
+                .entry((relation, q, target_type.to_string()))
+                .or_default() += 1;
+        }
+    }
+}
+
+impl PlannerStatistics {
+    /// The statistics of `ocel`. With an `ocel_key` (the backend uses the OCEL's generation, as
+    /// for [`super::cache::EvaluationCache`]), they are computed on first use and then kept for
+    /// later plans; the key must differ whenever the OCEL's content may have changed.
+    pub fn for_ocel(ocel: &SlimLinkedOCEL, ocel_key: Option<u64>) -> Arc<Self> {
+        let Some(key) = ocel_key else {
+            return Arc::new(Self::compute(ocel));
+        };
+        let cached = STATS_CACHE
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, stats)| Arc::clone(stats));
+        if let Some(stats) = cached {
+            return stats;
+        }
+        let stats = Arc::new(Self::compute(ocel));
+        let mut cache = STATS_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= MAX_CACHED_STATISTICS {
+            cache.remove(0);
+        }
+        cache.push((key, Arc::clone(&stats)));
+        stats
+    }
+
+    /// Compute the statistics from a sample of at most [`STATS_SAMPLE_SIZE`] events and objects
+    /// per type.
+    pub fn compute(ocel: &SlimLinkedOCEL) -> Self {
+        let mut stats = Self::default();
+        let mut first_last: Option<(i64, i64)> = None;
+        for ev_type in ocel.get_ev_types() {
+            let count = ocel.get_evs_of_type(ev_type).count();
+            stats.ev_type_counts.insert(ev_type.to_string(), count);
+            let sampled = ocel
+                .get_evs_of_type(ev_type)
+                .step_by(sample_step(count))
+                .collect_vec();
+            let mut related = HashMap::new();
+            let mut values: HashMap<String, Vec<OCELAttributeValue>> = HashMap::new();
+            for ev in &sampled {
+                count_related(
+                    &mut related,
+                    Relation::E2O,
+                    ocel.get_e2o(ev).map(|(q, ob)| (q, ocel.get_ob_type_of(ob))),
+                );
+                for attr in ocel.get_ev_attrs(ev) {
+                    match ocel.get_ev_attr_val(ev, attr) {
+                        Some(OCELAttributeValue::Null) | None => {}
+                        Some(v) => values.entry(attr.to_string()).or_default().push(v.clone()),
+                    }
+                }
+                let time = ocel.get_ev_time(ev).timestamp();
+                first_last = Some(match first_last {
+                    Some((first, last)) => (first.min(time), last.max(time)),
+                    None => (time, time),
+                });
+            }
+            stats.add_fan_outs(ev_type, related, sampled.len());
+            for (attr, vs) in values {
+                stats.ev_attributes.insert(
+                    (ev_type.to_string(), attr),
+                    AttributeHistogram::new(vs, sampled.len()),
+                );
+            }
+        }
+        for ob_type in ocel.get_ob_types() {
+            let count = ocel.get_obs_of_type(ob_type).count();
+            stats.ob_type_counts.insert(ob_type.to_string(), count);
+            let sampled = ocel
+                .get_obs_of_type(ob_type)
+                .step_by(sample_step(count))
+                .collect_vec();
+            let mut related = HashMap::new();
+            let mut values: HashMap<String, Vec<OCELAttributeValue>> = HashMap::new();
+            for ob in &sampled {
+                count_related(
+                    &mut related,
+                    Relation::O2E,
+                    ocel.get_e2o_rev(ob)
+                        .map(|(q, ev)| (q, ocel.get_ev_type_of(ev))),
+                );
+                count_related(
+                    &mut related,
+                    Relation::O2O,
+                    ocel.get_o2o(ob)
+                        .map(|(q, ob2)| (q, ocel.get_ob_type_of(ob2))),
+                );
+                count_related(
+                    &mut related,
+                    Relation::O2ORev,
+                    ocel.get_o2o_rev(ob)
+                        .map(|(q, ob2)| (q, ocel.get_ob_type_of(ob2))),
+                );
+                for attr in ocel.get_ob_attrs(ob) {
+                    values.entry(attr.to_string()).or_default().extend(
+                        ocel.get_ob_attr_vals(ob, attr)
+                            .filter(|(_, v)| !matches!(v, OCELAttributeValue::Null))
+                            .map(|(_, v)| v.clone()),
+                    );
+                }
+            }
+            stats.add_fan_outs(ob_type, related, sampled.len());
+            for (attr, vs) in values {
+                stats.ob_attributes.insert(
+                    (ob_type.to_string(), attr),
+                    AttributeHistogram::new(vs, sampled.len()),
+                );
+            }
+        }
+        stats.time_span = first_last.map_or(0.0, |(first, last)| (last - first) as f64);
+        stats
+    }
+
+    fn add_fan_outs(
+        &mut self,
+        source_type: &str,
+        related: HashMap<(Relation, Qualifier, String), usize>,
+        samples: usize,
+    ) {
+        for ((relation, qualifier, target_type), count) in related {
+            self.fan_outs.insert(
+                (relation, source_type.to_string(), qualifier, target_type),
+                count as f64 / samples.max(1) as f64,
+            );
+        }
+    }
+
+    /// The types a variable of `bbox` can have; `None` for variables bound by a parent, which
+    /// can have any type.
+    fn types_of<'a>(bbox: &'a BindingBox, var: &Variable) -> Option<&'a HashSet<String>> {
+        match var {
+            Variable::Event(ev) => bbox.new_event_vars.get(ev),
+            Variable::Object(ob) => bbox.new_object_vars.get(ob),
+        }
+    }
+
+    fn count_of(counts: &HashMap<String, usize>, types: Option<&HashSet<String>>) -> f64 {
+        match types {
+            Some(types) => types
+                .iter()
+                .map(|t| counts.get(t).copied().unwrap_or(0))
+                .sum::<usize>() as f64,
+            None => counts.values().sum::<usize>() as f64,
+        }
+    }
+
+    /// Number of events or objects `var` can be bound to.
+    pub fn variable_count(&self, bbox: &BindingBox, var: &Variable) -> f64 {
+        match var {
+            Variable::Event(_) => Self::count_of(&self.ev_type_counts, Self::types_of(bbox, var)),
+            Variable::Object(_) => Self::count_of(&self.ob_type_counts, Self::types_of(bbox, var)),
+        }
+    }
+
+    /// Average number of items of the `to` types related to an item of the `from` types,
+    /// weighted by how many items of each `from` type there are.
+    fn fan_out(
+        &self,
+        relation: Relation,
+        from: Option<&HashSet<String>>,
+        qualifier: &Qualifier,
+        to: Option<&HashSet<String>>,
+    ) -> f64 {
+        let (from_counts, to_counts) = match relation {
+            Relation::E2O => (&self.ev_type_counts, &self.ob_type_counts),
+            Relation::O2E => (&self.ob_type_counts, &self.ev_type_counts),
+            Relation::O2O | Relation::O2ORev => (&self.ob_type_counts, &self.ob_type_counts),
+        };
+        let from_types = from.map_or_else(
+            || from_counts.keys().collect_vec(),
+            |ts| ts.iter().collect_vec(),
+        );
+        let to_types = to.map_or_else(
+            || to_counts.keys().collect_vec(),
+            |ts| ts.iter().collect_vec(),
+        );
+        let total = from_types
+            .iter()
+            .map(|t| from_counts.get(*t).copied().unwrap_or(0))
+            .sum::<usize>();
+        if total == 0 {
+            return 0.0;
+        }
+        from_types
+            .iter()
+            .map(|from_type| {
+                let weight = from_counts.get(*from_type).copied().unwrap_or(0) as f64;
+                weight
+                    * to_types
+                        .iter()
+                        .flat_map(|to_type| {
+                            self.fan_outs.get(&(
+                                relation,
+                                from_type.to_string(),
+                                qualifier.clone(),
+                                to_type.to_string(),
+                            ))
+                        })
+                        .sum::<f64>()
+            })
+            .sum::<f64>()
+            / total as f64
+    }
+
+    /// Expected number of bindings of `to` per binding of `from`, when `to` is bound through the
+    /// E2O/O2O relation of `from` (in reverse for O2O, if `reversed`).
+    pub fn relation_fan_out(
+        &self,
+        bbox: &BindingBox,
+        from: &Variable,
+        to: &Variable,
+        qualifier: &Qualifier,
+        reversed: bool,
+    ) -> f64 {
+        let relation = match (from, to) {
+            (Variable::Event(_), Variable::Object(_)) => Relation::E2O,
+            (Variable::Object(_), Variable::Event(_)) => Relation::O2E,
+            (Variable::Object(_), Variable::Object(_)) if reversed => Relation::O2ORev,
+            (Variable::Object(_), Variable::Object(_)) => Relation::O2O,
+            (Variable::Event(_), Variable::Event(_)) => return self.variable_count(bbox, to),
+        };
+        self.fan_out(
+            relation,
+            Self::types_of(bbox, from),
+            qualifier,
+            Self::types_of(bbox, to),
+        )
+    }
+
+    /// Share of pairs of events that lie between `min_seconds` and `max_seconds` apart.
+    fn time_bound_selectivity(&self, min_seconds: Option<f64>, max_seconds: Option<f64>) -> f64 {
+        match (min_seconds, max_seconds) {
+            (None, None) => 1.0,
+            (Some(min), Some(max)) if self.time_span > 0.0 => (max - min) / self.time_span,
+            _ => DEFAULT_SELECTIVITY,
+        }
+    }
+
+    /// Share of the items of `types` whose `attribute` passes `value_filter`.
+    fn attribute_selectivity(
+        histograms: &HashMap<(String, String), AttributeHistogram>,
+        counts: &HashMap<String, usize>,
+        types: Option<&HashSet<String>>,
+        attribute: &str,
+        value_filter: &ValueFilter,
+    ) -> f64 {
+        let types = types.map_or_else(|| counts.keys().collect_vec(), |ts| ts.iter().collect_vec());
+        let total = types
+            .iter()
+            .map(|t| counts.get(*t).copied().unwrap_or(0))
+            .sum::<usize>() as f64;
+        if total == 0.0 {
+            return DEFAULT_SELECTIVITY;
+        }
+        types
+            .iter()
+            .map(|t| {
+                let count = counts.get(*t).copied().unwrap_or(0) as f64;
+                count
+                    * histograms
+                        .get(&(t.to_string(), attribute.to_string()))
+                        .map_or(0.0, |h| h.selectivity(value_filter))
+            })
+            .sum::<f64>()
+            / total
+    }
+
+    /// Share of bindings (with all its variables bound) passing `filter`.
+    pub fn filter_selectivity(&self, bbox: &BindingBox, filter: &Filter) -> f64 {
+        let selectivity = match filter {
+            Filter::O2E {
+                object,
+                event,
+                qualifier,
+                filter_label: _,
+            } => {
+                let (ob, ev) = (Variable::Object(*object), Variable::Event(*event));
+                self.relation_fan_out(bbox, &ob, &ev, qualifier, false)
+                    / self.variable_count(bbox, &ev).max(1.0)
+            }
+            Filter::O2O {
+                object,
+                other_object,
+                qualifier,
+                filter_label: _,
+            } => {
+                let (ob, other) = (Variable::Object(*object), Variable::Object(*other_object));
+                self.relation_fan_out(bbox, &ob, &other, qualifier, false)
+                    / self.variable_count(bbox, &other).max(1.0)
+            }
+            Filter::TimeBetweenEvents {
+                min_seconds,
+                max_seconds,
+                ..
+            } => self.time_bound_selectivity(*min_seconds, *max_seconds),
+            Filter::NotEqual { .. } => 1.0,
+            Filter::EventAttributeValueFilter {
+                event,
+                attribute_name,
+                value_filter,
+            } => Self::attribute_selectivity(
+                &self.ev_attributes,
+                &self.ev_type_counts,
+                bbox.new_event_vars.get(event),
+                attribute_name,
+                value_filter,
+            ),
+            Filter::ObjectAttributeValueFilter {
+                object,
+                attribute_name,
+                value_filter,
+                ..
+            } => Self::attribute_selectivity(
+                &self.ob_attributes,
+                &self.ob_type_counts,
+                bbox.new_object_vars.get(object),
+                attribute_name,
+                value_filter,
+            ),
+            Filter::DirectlyFollows { .. }
+            | Filter::NoEventBetween { .. }
+            | Filter::EventInObjectWindow { .. }
+            | Filter::IntervalRelation { .. }
+            | Filter::BasicFilterCEL { .. } => DEFAULT_SELECTIVITY,
+        };
+        selectivity.clamp(MIN_SELECTIVITY, 1.0)
+    }
+
+    /// Share of bindings passing the filters (other than E2O/O2O) that can first be checked once
+    /// `var` is bound, given which variables are bound already.
+    pub fn selectivity_once_bound(
+        &self,
+        bbox: &BindingBox,
+        var: &Variable,
+        is_bound: impl Fn(&Variable) -> bool,
+    ) -> f64 {
+        bbox.filters
+            .iter()
+            .filter(|f| !matches!(f, Filter::O2E { .. } | Filter::O2O { .. }))
+            .filter(|f| {
+                let involved = f.get_involved_variables();
+                involved.contains(var) && involved.iter().all(|v| v == var || is_bound(v))
+            })
+            .map(|f| self.filter_selectivity(bbox, f))
+            .product()
+    }
+
+    /// Expected number of bindings `step` turns one binding into (for filters, the share kept).
+    pub fn step_factor(&self, bbox: &BindingBox, step: &BindingStep) -> f64 {
+        match step {
+            BindingStep::BindEv(ev, bounds) => {
+                self.variable_count(bbox, &Variable::Event(*ev))
+                    * bounds
+                        .iter()
+                        .flatten()
+                        .map(|(_, (min, max))| {
+                            self.time_bound_selectivity(*min, *max)
+                                .clamp(MIN_SELECTIVITY, 1.0)
+                        })
+                        .product::<f64>()
+            }
+            BindingStep::BindOb(ob) => self.variable_count(bbox, &Variable::Object(*ob)),
+            BindingStep::BindObFromEv(ob, ev, qualifier) => self.relation_fan_out(
+                bbox,
+                &Variable::Event(*ev),
+                &Variable::Object(*ob),
+                qualifier,
+                false,
+            ),
+            BindingStep::BindEvFromOb(ev, ob, qualifier) => self.relation_fan_out(
+                bbox,
+                &Variable::Object(*ob),
+                &Variable::Event(*ev),
+                qualifier,
+                false,
+            ),
+            BindingStep::BindObFromOb(ob, other, qualifier, reversed) => self.relation_fan_out(
+                bbox,
+                &Variable::Object(*other),
+                &Variable::Object(*ob),
+                qualifier,
+                *reversed,
+            ),
+            BindingStep::BindEvInObWindow(ev, _, _) => {
+                self.variable_count(bbox, &Variable::Event(*ev)) * DEFAULT_SELECTIVITY
+            }
+            BindingStep::BindEvAdjacent(..) => 1.0,
+            BindingStep::Filter(f) => self.filter_selectivity(bbox, f),
+        }
+    }
+
+    /// Expected number of bindings per input binding after each prefix of `steps`.
+    pub fn estimate_cardinalities(&self, bbox: &BindingBox, steps: &[BindingStep]) -> Vec<f64> {
+        steps
+            .iter()
+            .scan(1.0, |card, step| {
+                *card *= self.step_factor(bbox, step);
+                Some(*card)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binding_box::{
+        structs::{EventVariable, ObjectVariable},
+        test_utils::{amount_at_least, ev_vars, o2e, ob_vars, ocel},
+    };
+
+    #[test]
+    fn planner_binds_through_the_relation_with_the_most_selective_filters_first() {
+        // Both kinds of events are bound from the order, with one of each per order on average,
+        // but only half of the placements have an amount of at least 50.
+        let bbox = BindingBox {
+            new_object_vars: ob_vars(&[(0, "order")]),
+            new_event_vars: ev_vars(&[(0, "ship"), (1, "place")]),
+            filters: vec![o2e(0, 0, None), o2e(0, 1, None), amount_at_least(1, 50)],
+            ..Default::default()
+        };
+        let ocel = ocel();
+        let steps = BindingStep::get_binding_order(&bbox, None, &ocel);
+        assert!(matches!(
+            steps.as_slice(),
+            [
+                BindingStep::BindOb(ObjectVariable(0)),
+                BindingStep::BindEvFromOb(EventVariable(1), ObjectVariable(0), None),
+                BindingStep::Filter(Filter::EventAttributeValueFilter { .. }),
+                BindingStep::BindEvFromOb(EventVariable(0), ObjectVariable(0), None),
+            ]
+        ));
+        let stats = PlannerStatistics::for_ocel(&ocel, None);
+        assert_eq!(
+            stats.estimate_cardinalities(&bbox, &steps),
+            vec![2.0, 2.0, 1.0, 1.0]
+        );
+    }
+}
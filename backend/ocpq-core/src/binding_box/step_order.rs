@@ -1,56 +1,67 @@
 use std::{
-    cell::RefCell,
+    cmp::Reverse,
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
 };
 
 use itertools::Itertools;
+use ordered_float::OrderedFloat;
 use process_mining::core::event_data::object_centric::linked_ocel::{
     LinkedOCELAccess, SlimLinkedOCEL,
 };
 
 use super::{
-    structs::{BindingBox, BindingStep, Filter, Qualifier, Variable},
+    plan_stats::PlannerStatistics,
+    structs::{
+        BindingBox, BindingStep, EventVariable, Filter, ObjectVariable, ObjectWindow, Qualifier,
+        Variable,
+    },
     Binding,
 };
 
+/// Per event variable, the bounds on its time relative to other event variables, in seconds.
+type TimeBounds = HashMap<EventVariable, Vec<(EventVariable, Option<f64>, Option<f64>)>>;
+
+/// Record that `to_event` happens between `min_seconds` and `max_seconds` after `from_event`, for
+/// whichever of the two gets bound second.
+fn add_time_bound(
+    bounds: &mut TimeBounds,
+    from_event: EventVariable,
+    to_event: EventVariable,
+    min_seconds: Option<f64>,
+    max_seconds: Option<f64>,
+) {
+    bounds
+        .entry(to_event)
+        .or_default()
+        .push((from_event, min_seconds, max_seconds));
+    bounds.entry(from_event).or_default().push((
+        to_event,
+        max_seconds.map(|s| -s),
+        min_seconds.map(|s| -s),
+    ));
+}
+
 impl BindingStep {
     /// Orders variable bindings so every variable is bound before a filter needs it, preferring the order that creates the fewest intermediate bindings.
+    ///
+    /// How many bindings a step creates is estimated from the [`PlannerStatistics`] of the OCEL: the
+    /// fan-out of the relation a variable is bound through (or the number of events/objects of its
+    /// types) times the share of bindings passing the filters that can be checked right after.
     pub fn get_binding_order(
         bbox: &BindingBox,
         _parent_binding_opt: Option<&Binding>,
         ocel: &SlimLinkedOCEL,
+    ) -> Vec<Self> {
+        Self::get_binding_order_with(bbox, ocel, &PlannerStatistics::for_ocel(ocel, None))
+    }
+
+    /// Like [`BindingStep::get_binding_order`], with the statistics of `ocel` already at hand.
+    pub fn get_binding_order_with(
+        bbox: &BindingBox,
+        ocel: &SlimLinkedOCEL,
+        stats: &PlannerStatistics,
     ) -> Vec<Self> {
         let mut ret = Vec::new();
-        let card_cache: RefCell<HashMap<Variable, usize>> = RefCell::new(HashMap::new());
-        let card_for_var = |v: &Variable| -> usize {
-            if let Some(&c) = card_cache.borrow().get(v) {
-                return c;
-            }
-            let c = match v {
-                Variable::Event(ev) => bbox
-                    .new_event_vars
-                    .get(ev)
-                    .map(|types| {
-                        types
-                            .iter()
-                            .map(|t| ocel.get_evs_of_type(t).count())
-                            .sum::<usize>()
-                    })
-                    .unwrap_or(0),
-                Variable::Object(ob) => bbox
-                    .new_object_vars
-                    .get(ob)
-                    .map(|types| {
-                        types
-                            .iter()
-                            .map(|t| ocel.get_obs_of_type(t).count())
-                            .sum::<usize>()
-                    })
-                    .unwrap_or(0),
-            };
-            card_cache.borrow_mut().insert(v.clone(), c);
-            c
-        };
 
         let mut var_requiring_bindings: BTreeSet<Variable> = bbox
             .new_event_vars
@@ -87,28 +98,75 @@ impl BindingStep {
             var_can_bind.insert(Variable::Object(*ob_var), BTreeSet::new());
             var_can_bind_with_qualifier.insert(Variable::Object(*ob_var), BTreeSet::new());
         }
-        let mut time_between_evs: HashMap<
-            super::structs::EventVariable,
-            Vec<(super::structs::EventVariable, Option<f64>, Option<f64>)>,
-        > = HashMap::new();
-        for f in &bbox.filters {
-            if let Filter::TimeBetweenEvents {
-                from_event,
-                to_event,
-                min_seconds,
-                max_seconds,
-            } = f
-            {
-                time_between_evs.entry(*to_event).or_default().push((
+        let mut time_between_evs: TimeBounds = HashMap::new();
+        // Event variables that can be bound as the neighbour of another one in an object's lifecycle
+        let mut directly_follows: Vec<(usize, EventVariable, EventVariable, ObjectVariable)> =
+            Vec::new();
+        // Event variables that can be bound to the events in a time window of an object
+        let mut object_windows: Vec<(usize, EventVariable, ObjectVariable, &ObjectWindow)> =
+            Vec::new();
+        for (i, f) in bbox.filters.iter().enumerate() {
+            match f {
+                Filter::TimeBetweenEvents {
+                    from_event,
+                    to_event,
+                    min_seconds,
+                    max_seconds,
+                    calendar,
+                } => add_time_bound(
+                    &mut time_between_evs,
                     *from_event,
-                    *min_seconds,
-                    *max_seconds,
-                ));
-                time_between_evs.entry(*from_event).or_default().push((
                     *to_event,
-                    max_seconds.map(|s| -s),
-                    min_seconds.map(|s| -s),
-                ));
+                    *min_seconds,
+                    // Working time is at most the elapsed time, so only its minimum bounds that.
+                    max_seconds.filter(|_| calendar.is_none()),
+                ),
+                Filter::DirectlyFollows {
+                    from_event,
+                    to_event,
+                    object,
+                } => {
+                    add_time_bound(
+                        &mut time_between_evs,
+                        *from_event,
+                        *to_event,
+                        Some(0.0),
+                        None,
+                    );
+                    directly_follows.push((i, *from_event, *to_event, *object));
+                }
+                Filter::IntervalRelation {
+                    first_start,
+                    first_end,
+                    second_start,
+                    second_end,
+                    relation,
+                } => {
+                    let ends = [*first_start, *first_end, *second_start, *second_end];
+                    for (earlier, later, equal) in relation.implied_orderings() {
+                        if ends[earlier] != ends[later] {
+                            add_time_bound(
+                                &mut time_between_evs,
+                                ends[earlier],
+                                ends[later],
+                                Some(0.0),
+                                equal.then_some(0.0),
+                            );
+                        }
+                    }
+                }
+                Filter::EventInObjectWindow {
+                    event,
+                    object,
+                    window,
+                } => {
+                    var_can_bind
+                        .entry(Variable::Object(*object))
+                        .or_default()
+                        .insert(Variable::Event(*event));
+                    object_windows.push((i, *event, *object, window));
+                }
+                _ => {}
             }
         }
 
@@ -195,6 +253,35 @@ impl BindingStep {
                 });
         }
 
+        // The bound variable (with qualifier, filter index and direction) through which `var` is
+        // bound with the fewest expected bindings
+        let cheapest_relation = |var: &Variable, bound_vars: &BTreeSet<Variable>| {
+            bound_vars
+                .iter()
+                .flat_map(|v| {
+                    var_can_bind_with_qualifier
+                        .get(v)
+                        .unwrap()
+                        .iter()
+                        .filter(|(x, _q, _filter_index, _reversed)| x == var)
+                        .map(move |t| (v, t))
+                })
+                .min_by_key(|(v, (x, qualifier, _filter_index, reversed))| {
+                    OrderedFloat(stats.relation_fan_out(bbox, v, x, qualifier, *reversed))
+                })
+                .map(|(v, t)| (v.clone(), t.clone()))
+        };
+        // Expected number of bindings per current binding after binding `var`
+        let estimated_factor = |var: &Variable, bound_vars: &BTreeSet<Variable>| -> f64 {
+            let bind_factor = match cheapest_relation(var, bound_vars) {
+                Some((v, (x, qualifier, _filter_index, reversed))) => {
+                    stats.relation_fan_out(bbox, &v, &x, &qualifier, reversed)
+                }
+                None => stats.variable_count(bbox, var),
+            };
+            bind_factor * stats.selectivity_once_bound(bbox, var, |v| bound_vars.contains(v))
+        };
+
         let mut expansion = var_can_bind
             .iter()
             .filter(|(v, _vs)| !bound_vars.contains(v))
@@ -207,8 +294,8 @@ impl BindingStep {
                     (vs.len() as i32) * 10
                         + if can_be_bound { 100 } else { 0 }
                         + if let Variable::Object(_) = v { 0 } else { 1 },
-                    std::cmp::Reverse(card_for_var(v)),
-                    std::cmp::Reverse(v.to_inner()),
+                    Reverse(OrderedFloat(estimated_factor(v, &bound_vars))),
+                    Reverse(v.to_inner()),
                 )
             })
             .map(|(k, _)| k)
@@ -218,17 +305,34 @@ impl BindingStep {
                 if bound_vars.contains(var) {
                     continue;
                 }
-                if let Some((v, (_var, qualifier, filter_index, reversed))) = bound_vars
-                    .iter()
-                    .flat_map(|v| {
-                        var_can_bind_with_qualifier
-                            .get(v)
-                            .unwrap()
-                            .iter()
-                            .find(|(x, _q, _filter_index, _reversed)| x == var)
-                            .map(|t| (v, t))
-                    })
-                    .next()
+                let adjacent = match var {
+                    Variable::Event(var_ev) => directly_follows.iter().find_map(
+                        |(filter_index, from_event, to_event, object)| {
+                            if !bound_vars.contains(&Variable::Object(*object)) {
+                                None
+                            } else if to_event == var_ev
+                                && bound_vars.contains(&Variable::Event(*from_event))
+                            {
+                                Some((*filter_index, *var_ev, *from_event, *object, false))
+                            } else if from_event == var_ev
+                                && bound_vars.contains(&Variable::Event(*to_event))
+                            {
+                                Some((*filter_index, *var_ev, *to_event, *object, true))
+                            } else {
+                                None
+                            }
+                        },
+                    ),
+                    Variable::Object(_) => None,
+                };
+                if let Some((filter_index, var_ev, ref_ev, object, reversed)) = adjacent {
+                    // The lifecycle of `object` has at most one candidate, the neighbour of `ref_ev`
+                    filter_indices_incoporated.insert(filter_index);
+                    ret.push(BindingStep::BindEvAdjacent(
+                        var_ev, ref_ev, object, reversed,
+                    ));
+                } else if let Some((v, (_var, qualifier, filter_index, reversed))) =
+                    &cheapest_relation(var, &bound_vars)
                 {
                     // `var` can be bound based on `v`!
                     filter_indices_incoporated.insert(*filter_index);
@@ -260,12 +364,26 @@ impl BindingStep {
                 } else {
                     match var {
                         Variable::Event(var_ev) => {
-                            let constraints = time_between_evs.get(var_ev).map(|cs| {
-                                cs.iter()
-                                    .map(|(ref_ev, mn, mx)| (*ref_ev, (*mn, *mx)))
-                                    .collect::<Vec<_>>()
-                            });
-                            ret.push(BindingStep::BindEv(*var_ev, constraints));
+                            if let Some((filter_index, _, object, window)) =
+                                object_windows.iter().find(|(_, event, object, _)| {
+                                    event == var_ev
+                                        && bound_vars.contains(&Variable::Object(*object))
+                                })
+                            {
+                                filter_indices_incoporated.insert(*filter_index);
+                                ret.push(BindingStep::BindEvInObWindow(
+                                    *var_ev,
+                                    *object,
+                                    (*window).clone(),
+                                ));
+                            } else {
+                                let constraints = time_between_evs.get(var_ev).map(|cs| {
+                                    cs.iter()
+                                        .map(|(ref_ev, mn, mx)| (*ref_ev, (*mn, *mx)))
+                                        .collect::<Vec<_>>()
+                                });
+                                ret.push(BindingStep::BindEv(*var_ev, constraints));
+                            }
                         }
                         Variable::Object(var_ob) => ret.push(BindingStep::BindOb(*var_ob)),
                     }
@@ -285,8 +403,8 @@ impl BindingStep {
                     .any(|bv| var_can_bind.get(bv).unwrap().contains(var));
                 (
                     if can_be_bound { 100 } else { 0 },
-                    std::cmp::Reverse(card_for_var(var)),
-                    std::cmp::Reverse(var.to_inner()),
+                    Reverse(OrderedFloat(estimated_factor(var, &bound_vars))),
+                    Reverse(var.to_inner()),
                 )
             })
         }
@@ -19,10 +19,24 @@ use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use ts_rs::TS;
 
-use crate::cel::{add_cel_label, check_cel_predicate, get_vars_in_cel_program};
+use super::{
+    aggregate::ChildAggregate,
+    cache::{CacheScope, EvaluationCache},
+    control::EvaluationControl,
+    parameters::TreeParameter,
+    plan_stats::PlannerStatistics,
+    time_index::lifecycle_of,
+};
+use crate::calendar::get_calendar;
+use crate::cel::{
+    add_cel_label, check_cel_predicate, get_vars_in_cel_program, lowering::lower_cel_filters,
+    CelEnv,
+};
 #[derive(TS)]
 #[ts(export)]
-#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[derive(
+    Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
 pub enum Variable {
     Event(EventVariable),
     Object(ObjectVariable),
@@ -39,7 +53,9 @@ impl Variable {
 
 #[derive(TS)]
 #[ts(export)]
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord, JsonSchema)]
+#[derive(
+    Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord, JsonSchema,
+)]
 pub struct EventVariable(pub usize);
 impl From<usize> for EventVariable {
     fn from(value: usize) -> Self {
@@ -48,7 +64,9 @@ impl From<usize> for EventVariable {
 }
 #[derive(TS)]
 #[ts(export)]
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord, JsonSchema)]
+#[derive(
+    Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize, PartialOrd, Ord, JsonSchema,
+)]
 pub struct ObjectVariable(pub usize);
 impl From<usize> for ObjectVariable {
     fn from(value: usize) -> Self {
@@ -60,16 +78,18 @@ pub type Qualifier = Option<String>;
 
 #[derive(TS)]
 #[ts(export)]
-#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Binding {
     // #[ts(as = "BTreeMap<EventVariable, usize>")]
     // pub event_map: FxHashMap<EventVariable, EventIndex>,
     #[ts(as = "Vec<(EventVariable, usize)>")]
+    #[schemars(with = "Vec<(EventVariable, usize)>")]
     pub event_map: Vec<(EventVariable, EventIndex)>,
     // #[ts(as = "BTreeMap<ObjectVariable, usize>")]
     // pub object_map: FxHashMap<ObjectVariable, ObjectIndex>,
     #[ts(as = "Vec<(ObjectVariable, usize)>")]
+    #[schemars(with = "Vec<(ObjectVariable, usize)>")]
     pub object_map: Vec<(ObjectVariable, ObjectIndex)>,
     // pub label_map: FxHashMap<String, LabelValue>,
     pub label_map: Vec<(String, LabelValue)>,
@@ -83,7 +103,11 @@ pub struct Binding {
 pub enum LabelValue {
     String(std::sync::Arc<String>),
     Int(i64),
-    Float(#[ts(as = "f64")] #[schemars(with = "f64")] OrderedFloat<f64>),
+    Float(
+        #[ts(as = "f64")]
+        #[schemars(with = "f64")]
+        OrderedFloat<f64>,
+    ),
     Bool(bool),
     Null,
 }
@@ -98,6 +122,15 @@ impl LabelValue {
             LabelValue::Null => "null".to_string(),
         }
     }
+
+    /// The value as a number, if it is one.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            LabelValue::Int(i) => Some(*i as f64),
+            LabelValue::Float(f) => Some(f.0),
+            _ => None,
+        }
+    }
 }
 
 pub(crate) enum EvInsertion {
@@ -267,6 +300,27 @@ impl Binding {
                 .map(|r: &ObjectIndex| EventOrObjectIndex::Object(*r)),
         }
     }
+
+    /// Rough heap plus inline size, for the memory budget of [`super::control::EvaluationLimits`].
+    /// Label strings count by their length only.
+    pub(crate) fn approx_size_bytes(&self) -> usize {
+        use std::mem::size_of;
+        size_of::<Self>()
+            + self.event_map.capacity() * size_of::<(EventVariable, EventIndex)>()
+            + self.object_map.capacity() * size_of::<(ObjectVariable, ObjectIndex)>()
+            + self
+                .label_map
+                .iter()
+                .map(|(k, v)| {
+                    size_of::<(String, LabelValue)>()
+                        + k.len()
+                        + match v {
+                            LabelValue::String(s) => s.len(),
+                            _ => 0,
+                        }
+                })
+                .sum::<usize>()
+    }
 }
 
 /// Maps a variable to the set of types it may be bound to.
@@ -313,7 +367,41 @@ pub enum FilterLabel {
 #[serde(rename_all = "camelCase")]
 pub struct LabelFunction {
     pub label: String,
+    /// CEL expression computing the label; not used if `aggregate` is set
+    #[serde(default)]
     pub cel: String,
+    /// Native aggregate over a child's bindings, computed instead of `cel`
+    #[serde(default)]
+    #[ts(optional)]
+    pub aggregate: Option<ChildAggregate>,
+}
+
+impl LabelFunction {
+    /// Compute the label of `binding` and add it there (as `Null` if computing it fails).
+    pub fn apply(
+        &self,
+        binding: &mut Binding,
+        child_res: &HashMap<String, Vec<(std::sync::Arc<Binding>, Option<ViolationReason>)>>,
+        ocel: &SlimLinkedOCEL,
+        env: CelEnv<'_>,
+    ) -> Result<(), String> {
+        let Some(aggregate) = &self.aggregate else {
+            return add_cel_label(binding, Some(child_res), ocel, self, env);
+        };
+        match aggregate.compute(child_res, ocel, env.now) {
+            Ok(v) => {
+                binding.add_label(self.label.clone(), v);
+                Ok(())
+            }
+            Err(e) => {
+                binding.add_label(self.label.clone(), LabelValue::Null);
+                Err(format!(
+                    "Error while computing binding label {} with error {e}",
+                    self.label
+                ))
+            }
+        }
+    }
 }
 
 #[derive(TS)]
@@ -327,8 +415,19 @@ pub struct BindingBoxTree {
     #[ts(as = "Vec<((usize, usize), String)>")]
     #[schemars(with = "Vec<((usize, usize), String)>")]
     pub edge_names: HashMap<(usize, usize), String>, // #[serde_as(as = "Vec<(_, _)>")]
-                                                     // #[ts(as = "Vec<((usize, usize), (Option<usize>, Option<usize>))>")]
-                                                     // pub size_constraints: HashMap<(usize, usize), (Option<usize>, Option<usize>)>,
+    // #[ts(as = "Vec<((usize, usize), (Option<usize>, Option<usize>))>")]
+    // pub size_constraints: HashMap<(usize, usize), (Option<usize>, Option<usize>)>,
+    /// Names of the CEL libraries (see [`crate::cel::library`]) the tree's expressions call
+    /// functions of; evaluating fails if one of them is not loaded.
+    #[serde(default)]
+    #[ts(optional)]
+    #[ts(as = "Option<Vec<String>>")]
+    pub cel_libraries: Vec<String>,
+    /// Named values the tree is evaluated with (see [`super::parameters`])
+    #[serde(default)]
+    #[ts(optional)]
+    #[ts(as = "Option<Vec<TreeParameter>>")]
+    pub parameters: Vec<TreeParameter>,
 }
 
 impl BindingBoxTree {
@@ -341,59 +440,187 @@ impl BindingBoxTree {
     }
 
     pub fn compute_step_cache(&self, ocel: &SlimLinkedOCEL) -> Vec<Vec<BindingStep>> {
+        self.compute_step_cache_with(ocel, &PlannerStatistics::for_ocel(ocel, None))
+    }
+
+    /// Like [`BindingBoxTree::compute_step_cache`], with the statistics of `ocel` already at hand.
+    pub fn compute_step_cache_with(
+        &self,
+        ocel: &SlimLinkedOCEL,
+        stats: &PlannerStatistics,
+    ) -> Vec<Vec<BindingStep>> {
         self.nodes
             .iter()
             .enumerate()
             .map(|(idx, n)| {
                 let (bbox, _children) = n.to_box(idx, self);
-                BindingStep::get_binding_order(&bbox, None, ocel)
+                // Simple CEL comparisons are checked as the native filters they are, which the
+                // planner can also bind through.
+                let bbox = BindingBox {
+                    filters: lower_cel_filters(&bbox.filters, &self.parameters),
+                    ..bbox.into_owned()
+                };
+                BindingStep::get_binding_order_with(&bbox, ocel, stats)
             })
             .collect()
     }
 
     pub fn evaluate(&self, ocel: &SlimLinkedOCEL) -> Result<(EvaluationResults, bool), String> {
+        self.evaluate_controlled(ocel, &EvaluationControl::default())
+    }
+
+    /// Like [`BindingBoxTree::evaluate`], but stops with [`super::control::EVALUATION_CANCELLED`]
+    /// once `control` is cancelled and reports its progress to it.
+    pub fn evaluate_controlled(
+        &self,
+        ocel: &SlimLinkedOCEL,
+        control: &EvaluationControl,
+    ) -> Result<(EvaluationResults, bool), String> {
+        self.evaluate_with_cache(ocel, control, None)
+    }
+
+    fn check_cel_libraries(&self) -> Result<(), String> {
+        match crate::cel::library::missing_cel_libraries(&self.cel_libraries).as_slice() {
+            [] => Ok(()),
+            missing => Err(format!("CEL library {} is not loaded", missing.join(", "))),
+        }
+    }
+
+    /// Like [`BindingBoxTree::evaluate_controlled`], but reuses the results `cache` holds for
+    /// unchanged subtrees and stores the ones it computes. `ocel_key` identifies the OCEL's
+    /// content and must differ whenever that content may have changed.
+    pub fn evaluate_cached(
+        &self,
+        ocel: &SlimLinkedOCEL,
+        ocel_key: u64,
+        cache: &EvaluationCache,
+        control: &EvaluationControl,
+    ) -> Result<(EvaluationResults, bool), String> {
+        self.evaluate_with_cache(ocel, control, Some((cache, ocel_key)))
+    }
+
+    fn evaluate_with_cache(
+        &self,
+        ocel: &SlimLinkedOCEL,
+        control: &EvaluationControl,
+        cache: Option<(&EvaluationCache, u64)>,
+    ) -> Result<(EvaluationResults, bool), String> {
         if self.nodes.is_empty() {
             return Ok((vec![], false));
         }
-        let mut is_child = vec![false; self.nodes.len()];
-        for node in &self.nodes {
+        if let Some(tree) = self.with_default_parameters()? {
+            return tree.evaluate_with_cache(ocel, control, cache);
+        }
+        self.check_cel_libraries()?;
+        let stats = PlannerStatistics::for_ocel(ocel, cache.map(|(_, ocel_key)| ocel_key));
+        let step_cache = self.compute_step_cache_with(ocel, &stats);
+        let scope = cache.map(|(cache, ocel_key)| cache.scope(self, ocel_key));
+        let cache = scope.as_ref();
+        let mut combined = Vec::new();
+        let mut any_skipped = false;
+        for idx in self.root_indices() {
+            let ((ret, _violation), skipped) = self.nodes[idx].evaluate_in_place(
+                idx,
+                &mut Binding::default(),
+                self,
+                ocel,
+                &step_cache,
+                control,
+                cache,
+            )?;
+            combined.extend(ret);
+            any_skipped = any_skipped || skipped;
+        }
+        Ok((combined, any_skipped))
+    }
+
+    /// Like [`BindingBoxTree::evaluate_controlled`], but hands every situation to `sink` instead
+    /// of collecting them. Root bindings are enumerated one at a time and evaluated in chunks of
+    /// [`STREAM_CHUNK_SIZE`], so the root's expansion is never held in full. Below the root,
+    /// nodes are evaluated as by `evaluate_controlled`: memory is bounded by one chunk of root
+    /// bindings with all the situations below them, not by the chunk size alone. Per node,
+    /// situations arrive in the same order as in the collected result.
+    pub fn evaluate_streaming(
+        &self,
+        ocel: &SlimLinkedOCEL,
+        control: &EvaluationControl,
+        sink: &mut SituationSink<'_>,
+    ) -> Result<bool, String> {
+        if let Some(tree) = self.with_default_parameters()? {
+            return tree.evaluate_streaming(ocel, control, sink);
+        }
+        self.check_cel_libraries()?;
+        let step_cache = self.compute_step_cache(ocel);
+        let mut any_skipped = false;
+        for idx in self.root_indices() {
+            let skipped = self.nodes[idx].evaluate_streaming_in_place(
+                idx,
+                &mut Binding::default(),
+                self,
+                ocel,
+                &step_cache,
+                control,
+                sink,
+            )?;
+            any_skipped = any_skipped || skipped;
+        }
+        Ok(any_skipped)
+    }
+
+    /// For every node, the node it is a child of (`None` for roots).
+    pub fn parent_indices(&self) -> Vec<Option<usize>> {
+        let mut parents = vec![None; self.nodes.len()];
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let mut set_parent = |c: usize| {
+                if let Some(p) = parents.get_mut(c) {
+                    *p = Some(idx);
+                }
+            };
             match node {
                 BindingBoxTreeNode::Box(_, children) => {
-                    for &c in children {
-                        if c < is_child.len() {
-                            is_child[c] = true;
-                        }
-                    }
+                    children.iter().for_each(|&c| set_parent(c))
                 }
                 BindingBoxTreeNode::OR(a, b) | BindingBoxTreeNode::AND(a, b) => {
-                    if *a < is_child.len() {
-                        is_child[*a] = true;
-                    }
-                    if *b < is_child.len() {
-                        is_child[*b] = true;
-                    }
-                }
-                BindingBoxTreeNode::NOT(a) => {
-                    if *a < is_child.len() {
-                        is_child[*a] = true;
-                    }
+                    set_parent(*a);
+                    set_parent(*b);
                 }
+                BindingBoxTreeNode::NOT(a) => set_parent(*a),
             }
         }
+        parents
+    }
 
-        let step_cache = self.compute_step_cache(ocel);
-        let mut combined = Vec::new();
-        let mut any_skipped = false;
-        for (idx, node) in self.nodes.iter().enumerate() {
-            if is_child[idx] {
-                continue;
+    fn root_indices(&self) -> Vec<usize> {
+        self.parent_indices()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(idx, parent)| parent.is_none().then_some(idx))
+            .collect()
+    }
+
+    /// Event and object variables bound in the situations of `node_index`: its own new
+    /// variables and those of all its ancestors, with the types each may take.
+    pub fn variables_in_scope(&self, node_index: usize) -> (NewEventVariables, NewObjectVariables) {
+        let parents = self.parent_indices();
+        let mut ev_vars = NewEventVariables::new();
+        let mut ob_vars = NewObjectVariables::new();
+        let mut current = Some(node_index);
+        // Bounded by the node count, so a malformed tree with a cycle cannot loop forever.
+        for _ in 0..self.nodes.len() {
+            let Some(idx) = current else {
+                break;
+            };
+            if let Some(BindingBoxTreeNode::Box(b, _)) = self.nodes.get(idx) {
+                for (v, types) in &b.new_event_vars {
+                    ev_vars.entry(*v).or_default().extend(types.iter().cloned());
+                }
+                for (v, types) in &b.new_object_vars {
+                    ob_vars.entry(*v).or_default().extend(types.iter().cloned());
+                }
             }
-            let ((ret, _violation), skipped) =
-                node.evaluate(idx, Binding::default(), self, ocel, &step_cache)?;
-            combined.extend(ret);
-            any_skipped = any_skipped || skipped;
+            current = parents.get(idx).copied().flatten();
         }
-        Ok((combined, any_skipped))
+        (ev_vars, ob_vars)
     }
 
     pub fn get_ev_vars(&self) -> HashSet<EventVariable> {
@@ -497,32 +724,86 @@ pub enum ViolationReason {
 
     ConstraintNotSatisfied(usize),
     UnknownChildSet,
+    /// The constraint at index `constraint` (a [`SizeFilter::NumSatisfiedChilds`]) saw `satisfied`
+    /// of the `total` child bindings satisfied
+    SatisfiedChildsOutOfRange {
+        constraint: usize,
+        satisfied: usize,
+        total: usize,
+    },
 }
 
 pub type EvaluationResult = (usize, std::sync::Arc<Binding>, Option<ViolationReason>);
 pub type EvaluationResults = Vec<EvaluationResult>;
+
+/// Receives the situations of a streaming evaluation as `(node_index, binding, violation)`.
+/// Returning an error stops the evaluation with it.
+pub type SituationSink<'a> =
+    dyn FnMut(usize, std::sync::Arc<Binding>, Option<ViolationReason>) -> Result<(), String> + 'a;
+
+/// Number of root bindings a streaming evaluation decides before handing their situations on.
+pub const STREAM_CHUNK_SIZE: usize = 4096;
+
+/// Situations of the children of a node's bindings beyond which the node stops evaluating its
+/// remaining bindings.
+const MAX_CHILD_SITUATIONS: usize = 150_000_000;
+
+/// Whether `child_edges` children yielding `c_res_len` situations each, for every one of
+/// `expanded_len` bindings, are more than [`MAX_CHILD_SITUATIONS`].
+fn too_much_to_handle(child_edges: usize, c_res_len: usize, expanded_len: usize) -> bool {
+    let total = child_edges * c_res_len * expanded_len;
+    if total > MAX_CHILD_SITUATIONS {
+        println!("Too much to handle! {child_edges}*{c_res_len}*{expanded_len}={total}");
+    }
+    total > MAX_CHILD_SITUATIONS
+}
+
+/// Outcome of one expanded binding, together with the situations of its descendants.
+enum BindingResult {
+    FilteredOutBySizeFilter(std::sync::Arc<Binding>, EvaluationResults),
+    Sat(std::sync::Arc<Binding>, EvaluationResults),
+    Viol(std::sync::Arc<Binding>, ViolationReason, EvaluationResults),
+}
+
+impl BindingResult {
+    fn into_situations(self) -> EvaluationResults {
+        match self {
+            BindingResult::FilteredOutBySizeFilter(_, r)
+            | BindingResult::Sat(_, r)
+            | BindingResult::Viol(_, _, r) => r,
+        }
+    }
+}
 use rayon::prelude::*;
 
-fn check_constraints(
+pub(crate) fn check_constraints(
     constraints: &[Constraint],
     binding: &Binding,
     child_res: &HashMap<String, Vec<(std::sync::Arc<Binding>, Option<ViolationReason>)>>,
     ocel: &SlimLinkedOCEL,
+    env: CelEnv<'_>,
 ) -> Result<Option<ViolationReason>, String> {
     for (constr_index, constr) in constraints.iter().enumerate() {
         let viol = match constr {
             Constraint::Filter { filter } => {
-                if filter.check_binding(binding, ocel)? {
+                if filter.check_binding(binding, ocel, env)? {
                     None
                 } else {
                     Some(ViolationReason::ConstraintNotSatisfied(constr_index))
                 }
             }
             Constraint::SizeFilter { filter } => {
-                if filter.check(binding, child_res, ocel)? {
+                if filter.check(binding, child_res, ocel, env)? {
                     None
                 } else {
-                    Some(ViolationReason::ConstraintNotSatisfied(constr_index))
+                    Some(match filter.satisfied_childs(child_res) {
+                        Some((satisfied, total)) => ViolationReason::SatisfiedChildsOutOfRange {
+                            constraint: constr_index,
+                            satisfied,
+                            total,
+                        },
+                        None => ViolationReason::ConstraintNotSatisfied(constr_index),
+                    })
                 }
             }
             Constraint::SAT { child_names } => {
@@ -703,10 +984,11 @@ fn check_constraints_with_counts(
     binding: &Binding,
     child_counts: &HashMap<String, usize>,
     ocel: &SlimLinkedOCEL,
+    env: CelEnv<'_>,
 ) -> Result<Option<ViolationReason>, String> {
     for (constr_index, constr) in constraints.iter().enumerate() {
         let violated = match constr {
-            Constraint::Filter { filter } => !filter.check_binding(binding, ocel)?,
+            Constraint::Filter { filter } => !filter.check_binding(binding, ocel, env)?,
             Constraint::SizeFilter { filter } => {
                 !check_size_filter_with_counts(filter, child_counts)
             }
@@ -719,6 +1001,52 @@ fn check_constraints_with_counts(
     Ok(None)
 }
 
+/// Evaluate the children of one expanded binding `b` of `own_index`, then apply its labels, size
+/// filters and constraints. `on_child_results` is told the number of situations of each child.
+fn evaluate_expanded_binding(
+    own_index: usize,
+    bbox: &BindingBox,
+    mut b: Binding,
+    child_edges: &[(usize, String)],
+    tree: &BindingBoxTree,
+    ocel: &SlimLinkedOCEL,
+    step_cache: &[Vec<BindingStep>],
+    control: &EvaluationControl,
+    cache: Option<&CacheScope>,
+    on_child_results: &dyn Fn(usize),
+) -> Result<BindingResult, String> {
+    use std::sync::Arc;
+    let mut all_res = Vec::new();
+    let mut child_res = HashMap::with_capacity(child_edges.len());
+    for (c, c_name) in child_edges {
+        let ((c_res, violations), _c_skipped) =
+            tree.nodes[*c].evaluate_in_place(*c, &mut b, tree, ocel, step_cache, control, cache)?;
+        child_res.insert(c_name.clone(), violations);
+        on_child_results(c_res.len());
+        all_res.extend(c_res);
+    }
+    let env = control.cel_env(ocel, tree, own_index);
+    for label_fun in &bbox.labels {
+        label_fun.apply(&mut b, &child_res, ocel, env)?;
+    }
+    for sf in &bbox.size_filters {
+        if !sf.check(&b, &child_res, ocel, env)? {
+            return Ok(BindingResult::FilteredOutBySizeFilter(
+                Arc::new(b),
+                Vec::default(),
+            ));
+        }
+    }
+    if let Some(vr) = check_constraints(&bbox.constraints, &b, &child_res, ocel, env)? {
+        let arc_b = Arc::new(b);
+        all_res.push((own_index, Arc::clone(&arc_b), Some(vr)));
+        return Ok(BindingResult::Viol(arc_b, vr, all_res));
+    }
+    let arc_b = Arc::new(b);
+    all_res.push((own_index, Arc::clone(&arc_b), None));
+    Ok(BindingResult::Sat(arc_b, all_res))
+}
+
 impl BindingBoxTreeNode {
     pub fn evaluate(
         &self,
@@ -737,7 +1065,43 @@ impl BindingBoxTreeNode {
         ),
         String,
     > {
-        self.evaluate_in_place(own_index, &mut parent_binding, tree, ocel, step_cache)
+        self.evaluate_controlled(
+            own_index,
+            parent_binding,
+            tree,
+            ocel,
+            step_cache,
+            &EvaluationControl::default(),
+        )
+    }
+
+    pub fn evaluate_controlled(
+        &self,
+        own_index: usize,
+        mut parent_binding: Binding,
+        tree: &BindingBoxTree,
+        ocel: &SlimLinkedOCEL,
+        step_cache: &[Vec<BindingStep>],
+        control: &EvaluationControl,
+    ) -> Result<
+        (
+            (
+                EvaluationResults,
+                Vec<(std::sync::Arc<Binding>, Option<ViolationReason>)>,
+            ),
+            bool,
+        ),
+        String,
+    > {
+        self.evaluate_in_place(
+            own_index,
+            &mut parent_binding,
+            tree,
+            ocel,
+            step_cache,
+            control,
+            None,
+        )
     }
 
     fn evaluate_in_place(
@@ -747,6 +1111,8 @@ impl BindingBoxTreeNode {
         tree: &BindingBoxTree,
         ocel: &SlimLinkedOCEL,
         step_cache: &[Vec<BindingStep>],
+        control: &EvaluationControl,
+        cache: Option<&CacheScope>,
     ) -> Result<
         (
             (
@@ -757,64 +1123,49 @@ impl BindingBoxTreeNode {
         ),
         String,
     > {
-        use std::sync::Arc;
+        if let Some(hit) = cache.and_then(|c| c.get(own_index, parent_binding)) {
+            return Ok((hit, false));
+        }
+        let parent_key = cache.map(|_| parent_binding.clone());
         let (bbox, children) = self.to_box(own_index, tree);
         let child_edges: Vec<(usize, String)> = children
             .iter()
             .map(|c| (*c, tree.edge_name(own_index, *c)))
             .collect();
-        let (expanded, expanding_skipped_bindings): (Vec<Binding>, bool) =
-            bbox.expand_with_steps_in_place(parent_binding, ocel, &step_cache[own_index])?;
-        enum BindingResult {
-            FilteredOutBySizeFilter(std::sync::Arc<Binding>, EvaluationResults),
-            Sat(std::sync::Arc<Binding>, EvaluationResults),
-            Viol(std::sync::Arc<Binding>, ViolationReason, EvaluationResults),
-        }
+        let (expanded, truncation) = bbox.expand_with_steps_in_place(
+            parent_binding,
+            ocel,
+            &step_cache[own_index],
+            control,
+            control.cel_env(ocel, tree, own_index),
+        )?;
+        let expanding_skipped_bindings = control.record_expansion(own_index, &expanded, truncation);
         let expanded_len = expanded.len();
         let it = rayon_cancel::CancelAdapter::new(expanded.into_par_iter().with_min_len(256));
         let canceller = it.canceller();
         let re: Vec<BindingResult> = it
-            .map(|mut b| {
-                let mut all_res = Vec::new();
-                let mut child_res = HashMap::with_capacity(child_edges.len());
-                for (c, c_name) in &child_edges {
-                    let ((c_res, violations), _c_skipped) =
-                        tree.nodes[*c].evaluate_in_place(*c, &mut b, tree, ocel, step_cache)?;
-                    child_res.insert(c_name.clone(), violations);
-                    if child_edges.len() * c_res.len() * expanded_len > 150_000_000 {
-                        canceller.cancel();
-                        println!(
-                            "Too much to handle! {}*{}*{}={}",
-                            child_res.len(),
-                            c_res.len(),
-                            expanded_len,
-                            child_edges.len() * c_res.len() * expanded_len
-                        );
-                    }
-                    all_res.extend(c_res);
-                }
-                for label_fun in &bbox.labels {
-                    add_cel_label(&mut b, Some(&child_res), ocel, label_fun)?;
-                }
-                for sf in &bbox.size_filters {
-                    if !sf.check(&b, &child_res, ocel)? {
-                        return Ok::<BindingResult, String>(
-                            BindingResult::FilteredOutBySizeFilter(Arc::new(b), Vec::default()),
-                        );
-                    }
-                }
-                if let Some(vr) = check_constraints(&bbox.constraints, &b, &child_res, ocel)? {
-                    let arc_b = Arc::new(b);
-                    all_res.push((own_index, Arc::clone(&arc_b), Some(vr)));
-                    return Ok(BindingResult::Viol(arc_b, vr, all_res));
-                }
-                let arc_b = Arc::new(b);
-                all_res.push((own_index, Arc::clone(&arc_b), None));
-                Ok(BindingResult::Sat(arc_b, all_res))
+            .map(|b| {
+                control.check()?;
+                evaluate_expanded_binding(
+                    own_index,
+                    &bbox,
+                    b,
+                    &child_edges,
+                    tree,
+                    ocel,
+                    step_cache,
+                    control,
+                    cache,
+                    &|c_res_len| {
+                        if too_much_to_handle(child_edges.len(), c_res_len, expanded_len) {
+                            canceller.cancel();
+                        }
+                    },
+                )
             })
             .collect::<Result<_, _>>()?;
         let recursive_calls_cancelled = canceller.is_cancelled();
-        Ok((
+        let res = (
             re.into_par_iter()
                 .fold(
                     || (EvaluationResults::new(), Vec::new()),
@@ -844,11 +1195,106 @@ impl BindingBoxTreeNode {
                     },
                 ),
             expanding_skipped_bindings || recursive_calls_cancelled,
-        ))
+        );
+        if let (Some(cache), Some(parent)) = (cache, parent_key) {
+            if res.1 {
+                cache.mark_incomplete();
+            } else if !control.any_limit_hit() {
+                cache.insert(own_index, parent, &res.0 .0, &res.0 .1);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Streaming counterpart of `evaluate_in_place`: the bindings of this node are decided
+    /// chunk by chunk as the expansion enumerates them, and each chunk's situations (its
+    /// descendants' first, as in the collected result) are passed to `sink` before the next chunk
+    /// is started. At most one chunk of this node's bindings is held at a time, together with the
+    /// situations of all their descendants, which are still collected (see
+    /// [`BindingBoxTree::evaluate_streaming`]).
+    fn evaluate_streaming_in_place(
+        &self,
+        own_index: usize,
+        parent_binding: &mut Binding,
+        tree: &BindingBoxTree,
+        ocel: &SlimLinkedOCEL,
+        step_cache: &[Vec<BindingStep>],
+        control: &EvaluationControl,
+        sink: &mut SituationSink<'_>,
+    ) -> Result<bool, String> {
+        let (bbox, children) = self.to_box(own_index, tree);
+        let child_edges: Vec<(usize, String)> = children
+            .iter()
+            .map(|c| (*c, tree.edge_name(own_index, *c)))
+            .collect();
+        // Decide `chunk` and pass its situations on; whether it had too much to handle
+        let decide_chunk =
+            |chunk: Vec<Binding>, sink: &mut SituationSink<'_>| -> Result<bool, String> {
+                control.record_expansion(own_index, &chunk, None);
+                let chunk_len = chunk.len();
+                let it = rayon_cancel::CancelAdapter::new(chunk.into_par_iter().with_min_len(256));
+                let canceller = it.canceller();
+                let re: Vec<BindingResult> = it
+                    .map(|b| {
+                        control.check()?;
+                        evaluate_expanded_binding(
+                            own_index,
+                            &bbox,
+                            b,
+                            &child_edges,
+                            tree,
+                            ocel,
+                            step_cache,
+                            control,
+                            None,
+                            &|c_res_len| {
+                                if too_much_to_handle(child_edges.len(), c_res_len, chunk_len) {
+                                    canceller.cancel();
+                                }
+                            },
+                        )
+                    })
+                    .collect::<Result<_, String>>()?;
+                for (node_index, binding, violation) in
+                    re.into_iter().flat_map(BindingResult::into_situations)
+                {
+                    sink(node_index, binding, violation)?;
+                }
+                Ok(canceller.is_cancelled())
+            };
+        let mut chunk = Vec::with_capacity(STREAM_CHUNK_SIZE);
+        // Once a chunk had too much to handle, the remaining bindings are skipped, as
+        // `evaluate_in_place` skips them. Only one chunk's situations are held at a time, so that
+        // is what is measured against `MAX_CHILD_SITUATIONS`.
+        let mut cancelled = false;
+        let expanding_skipped_bindings = bbox.expand_with_steps_emit(
+            own_index,
+            parent_binding,
+            ocel,
+            &step_cache[own_index],
+            control,
+            control.cel_env(ocel, tree, own_index),
+            &mut |b: &Binding| {
+                if cancelled {
+                    return Ok(());
+                }
+                control.record_materialized(b);
+                chunk.push(b.clone());
+                if chunk.len() >= STREAM_CHUNK_SIZE {
+                    cancelled = decide_chunk(std::mem::take(&mut chunk), sink)?;
+                }
+                Ok(())
+            },
+        )?;
+        if !cancelled && !chunk.is_empty() {
+            cancelled = decide_chunk(chunk, sink)?;
+        }
+        Ok(expanding_skipped_bindings || cancelled)
     }
 
     fn evaluate_no_descendants_binding(
         &self,
+        own_index: usize,
         bbox: &BindingBox,
         binding: &mut Binding,
         tree: &BindingBoxTree,
@@ -856,9 +1302,11 @@ impl BindingBoxTreeNode {
         step_cache: &[Vec<BindingStep>],
         child_edges: &[(usize, String)],
         child_demand: &ChildDemand,
+        control: &EvaluationControl,
     ) -> Result<BindingEmission, String> {
         use std::sync::Arc;
 
+        let env = control.cel_env(ocel, tree, own_index);
         match child_demand {
             ChildDemand::Full => {
                 let mut child_res = HashMap::with_capacity(child_edges.len());
@@ -876,19 +1324,20 @@ impl BindingBoxTreeNode {
                         ocel,
                         step_cache,
                         &mut child_sink,
+                        control,
                     )?;
                     child_res.insert(c_name.clone(), violations);
                 }
                 for label_fun in &bbox.labels {
-                    add_cel_label(binding, Some(&child_res), ocel, label_fun)?;
+                    label_fun.apply(binding, &child_res, ocel, env)?;
                 }
                 for sf in &bbox.size_filters {
-                    if !sf.check(binding, &child_res, ocel)? {
+                    if !sf.check(binding, &child_res, ocel, env)? {
                         return Ok(BindingEmission::FilteredOut);
                     }
                 }
                 Ok(
-                    match check_constraints(&bbox.constraints, binding, &child_res, ocel)? {
+                    match check_constraints(&bbox.constraints, binding, &child_res, ocel, env)? {
                         Some(vr) => BindingEmission::Emit(Some(vr)),
                         None => BindingEmission::Emit(None),
                     },
@@ -900,7 +1349,7 @@ impl BindingBoxTreeNode {
                     if let Some(&limit) = count_limits.get(c_name) {
                         let (count, _skipped) = tree.nodes[*c]
                             .evaluate_no_descendants_count_limited(
-                                *c, binding, tree, ocel, step_cache, limit,
+                                *c, binding, tree, ocel, step_cache, limit, control,
                             )?;
                         child_counts.insert(c_name.clone(), count);
                     }
@@ -916,6 +1365,7 @@ impl BindingBoxTreeNode {
                         binding,
                         &child_counts,
                         ocel,
+                        env,
                     )? {
                         Some(vr) => BindingEmission::Emit(Some(vr)),
                         None => BindingEmission::Emit(None),
@@ -925,7 +1375,7 @@ impl BindingBoxTreeNode {
             ChildDemand::None => {
                 let child_res = HashMap::new();
                 Ok(
-                    match check_constraints(&bbox.constraints, binding, &child_res, ocel)? {
+                    match check_constraints(&bbox.constraints, binding, &child_res, ocel, env)? {
                         Some(vr) => BindingEmission::Emit(Some(vr)),
                         None => BindingEmission::Emit(None),
                     },
@@ -942,6 +1392,7 @@ impl BindingBoxTreeNode {
         ocel: &SlimLinkedOCEL,
         step_cache: &[Vec<BindingStep>],
         limit: usize,
+        control: &EvaluationControl,
     ) -> Result<(usize, bool), String> {
         if limit == 0 {
             return Ok((0, false));
@@ -954,10 +1405,13 @@ impl BindingBoxTreeNode {
             && bbox.constraints.is_empty()
         {
             return bbox.count_with_steps_in_place(
+                own_index,
                 parent_binding,
                 ocel,
                 &step_cache[own_index],
                 limit,
+                control,
+                control.cel_env(ocel, tree, own_index),
             );
         }
 
@@ -966,13 +1420,20 @@ impl BindingBoxTreeNode {
             .map(|c| (*c, tree.edge_name(own_index, *c)))
             .collect();
         let child_demand = ChildDemand::required_for(&bbox);
-        let (expanded, expanding_skipped_bindings): (Vec<Binding>, bool) =
-            bbox.expand_with_steps_in_place(parent_binding, ocel, &step_cache[own_index])?;
+        let (expanded, truncation) = bbox.expand_with_steps_in_place(
+            parent_binding,
+            ocel,
+            &step_cache[own_index],
+            control,
+            control.cel_env(ocel, tree, own_index),
+        )?;
+        let expanding_skipped_bindings = control.record_expansion(own_index, &expanded, truncation);
 
         let mut count = 0;
         for mut b in expanded {
             if matches!(
                 self.evaluate_no_descendants_binding(
+                    own_index,
                     bbox.as_ref(),
                     &mut b,
                     tree,
@@ -980,6 +1441,7 @@ impl BindingBoxTreeNode {
                     step_cache,
                     &child_edges,
                     &child_demand,
+                    control,
                 )?,
                 BindingEmission::Emit(_)
             ) {
@@ -1013,11 +1475,12 @@ impl BindingBoxTreeNode {
             ocel,
             step_cache,
             sink,
+            &EvaluationControl::default(),
         )
     }
 
     // `sink` is type-erased (`&mut dyn FnMut`) to prevent issues with recursion limits
-    fn evaluate_no_descendants_in_place(
+    pub(crate) fn evaluate_no_descendants_in_place(
         &self,
         own_index: usize,
         parent_binding: &mut Binding,
@@ -1028,6 +1491,7 @@ impl BindingBoxTreeNode {
             std::sync::Arc<Binding>,
             Option<ViolationReason>,
         ) -> Result<(), String>,
+        control: &EvaluationControl,
     ) -> Result<bool, String> {
         use std::sync::Arc;
         let (bbox, children) = self.to_box(own_index, tree);
@@ -1036,8 +1500,14 @@ impl BindingBoxTreeNode {
             .iter()
             .map(|c| (*c, tree.edge_name(own_index, *c)))
             .collect();
-        let (expanded, expanding_skipped_bindings): (Vec<Binding>, bool) =
-            bbox.expand_with_steps_in_place(parent_binding, ocel, &step_cache[own_index])?;
+        let (expanded, truncation) = bbox.expand_with_steps_in_place(
+            parent_binding,
+            ocel,
+            &step_cache[own_index],
+            control,
+            control.cel_env(ocel, tree, own_index),
+        )?;
+        let expanding_skipped_bindings = control.record_expansion(own_index, &expanded, truncation);
         enum BindingResult {
             FilteredOutBySizeFilter,
             Sat(std::sync::Arc<Binding>),
@@ -1059,6 +1529,7 @@ impl BindingBoxTreeNode {
         let re: Vec<BindingResult> = it
             .map(|mut b| {
                 match self.evaluate_no_descendants_binding(
+                    own_index,
                     bbox.as_ref(),
                     &mut b,
                     tree,
@@ -1066,6 +1537,7 @@ impl BindingBoxTreeNode {
                     step_cache,
                     &child_edges,
                     &child_demand,
+                    control,
                 )? {
                     BindingEmission::FilteredOut => {
                         Ok::<BindingResult, String>(BindingResult::FilteredOutBySizeFilter)
@@ -1122,6 +1594,39 @@ pub enum Filter {
         to_event: EventVariable,
         min_seconds: Option<f64>,
         max_seconds: Option<f64>,
+        /// Measure only the working time of this business calendar (see [`crate::calendar`])
+        #[serde(default)]
+        #[ts(optional)]
+        calendar: Option<String>,
+    },
+    /// to_event is the next event of object after from_event, in the object's lifecycle (see [`object_lifecycle`])
+    DirectlyFollows {
+        from_event: EventVariable,
+        to_event: EventVariable,
+        object: ObjectVariable,
+    },
+    /// No event of one of the event types (of any type, if empty) happens strictly between event1 and event2; with an object, only the events of that object count
+    NoEventBetween {
+        from_event: EventVariable,
+        to_event: EventVariable,
+        event_types: Vec<String>,
+        #[serde(default)]
+        #[ts(optional)]
+        object: Option<ObjectVariable>,
+    },
+    /// event happens within a time window of object (see [`ObjectWindow`])
+    EventInObjectWindow {
+        event: EventVariable,
+        object: ObjectVariable,
+        window: ObjectWindow,
+    },
+    /// The interval from first_start to first_end stands in the Allen relation to the interval from second_start to second_end (using one event variable as start and end gives a point interval)
+    IntervalRelation {
+        first_start: EventVariable,
+        first_end: EventVariable,
+        second_start: EventVariable,
+        second_end: EventVariable,
+        relation: AllenRelation,
     },
     NotEqual {
         var_1: Variable,
@@ -1144,7 +1649,13 @@ pub enum Filter {
 }
 
 impl Filter {
-    pub fn check_binding(&self, b: &Binding, ocel: &SlimLinkedOCEL) -> Result<bool, String> {
+    /// Whether `b` passes this filter; CEL filters are evaluated in `env`.
+    pub fn check_binding(
+        &self,
+        b: &Binding,
+        ocel: &SlimLinkedOCEL,
+        env: CelEnv<'_>,
+    ) -> Result<bool, String> {
         match self {
             Filter::O2E {
                 object,
@@ -1185,6 +1696,7 @@ impl Filter {
                 to_event: ev_var_2,
                 min_seconds: min_sec,
                 max_seconds: max_sec,
+                calendar,
             } => {
                 let e1 = b
                     .get_ev_index(ev_var_1)
@@ -1194,7 +1706,10 @@ impl Filter {
                     .ok_or_else(|| format!("Event Variable {ev_var_2} without value"))?;
                 let e1_time = e1.get_time(ocel);
                 let e2_time = e2.get_time(ocel);
-                let diff = *e2_time - e1_time;
+                let diff = match calendar {
+                    Some(name) => get_calendar(name)?.business_duration(*e1_time, *e2_time),
+                    None => *e2_time - e1_time,
+                };
                 let duration_diff = match diff.num_microseconds() {
                     Some(us) => us as f64 / 1_000_000.0,
                     None => diff.num_milliseconds() as f64 / 1000.0,
@@ -1202,6 +1717,99 @@ impl Filter {
                 Ok(!min_sec.is_some_and(|min_sec| duration_diff < min_sec)
                     && !max_sec.is_some_and(|max_sec| duration_diff > max_sec))
             }
+            Filter::DirectlyFollows {
+                from_event,
+                to_event,
+                object,
+            } => {
+                let e1 = b
+                    .get_ev_index(from_event)
+                    .ok_or_else(|| format!("Event Variable {from_event} without value"))?;
+                let e2 = b
+                    .get_ev_index(to_event)
+                    .ok_or_else(|| format!("Event Variable {to_event} without value"))?;
+                let ob = b
+                    .get_ob_index(object)
+                    .ok_or_else(|| format!("Object Variable {object} without value"))?;
+                let lifecycle = lifecycle_of(ocel, ob, env.time_index);
+                Ok(adjacent_event(ocel, &lifecycle, e1, false).is_some_and(|e| &e == e2))
+            }
+            Filter::NoEventBetween {
+                from_event,
+                to_event,
+                event_types,
+                object,
+            } => {
+                let e1 = b
+                    .get_ev_index(from_event)
+                    .ok_or_else(|| format!("Event Variable {from_event} without value"))?;
+                let e2 = b
+                    .get_ev_index(to_event)
+                    .ok_or_else(|| format!("Event Variable {to_event} without value"))?;
+                let (start, end) = (ocel.get_ev_time(e1), ocel.get_ev_time(e2));
+                let (start, end) = if start <= end {
+                    (start, end)
+                } else {
+                    (end, start)
+                };
+                let is_between = |e: &EventIndex| {
+                    let time = ocel.get_ev_time(e);
+                    start < time
+                        && time < end
+                        && (event_types.is_empty()
+                            || event_types.iter().any(|t| t == ocel.get_ev_type_of(e)))
+                };
+                match object {
+                    Some(object) => {
+                        let ob = b
+                            .get_ob_index(object)
+                            .ok_or_else(|| format!("Object Variable {object} without value"))?;
+                        // The object's events strictly between both times, found by bisection
+                        let lifecycle = lifecycle_of(ocel, ob, env.time_index);
+                        let from = lifecycle.partition_point(|e| ocel.get_ev_time(e) <= start);
+                        let to = lifecycle.partition_point(|e| ocel.get_ev_time(e) < end);
+                        Ok(!lifecycle[from..to.max(from)].iter().any(is_between))
+                    }
+                    None if event_types.is_empty() => {
+                        Ok(!ocel.get_all_evs().any(|e| is_between(&e)))
+                    }
+                    None => Ok(!event_types
+                        .iter()
+                        .flat_map(|t| ocel.get_evs_of_type(t))
+                        .any(is_between)),
+                }
+            }
+            Filter::IntervalRelation {
+                first_start,
+                first_end,
+                second_start,
+                second_end,
+                relation,
+            } => {
+                let time_of = |ev_var: &EventVariable| {
+                    b.get_ev_index(ev_var)
+                        .map(|e| *ocel.get_ev_time(e))
+                        .ok_or_else(|| format!("Event Variable {ev_var} without value"))
+                };
+                Ok(relation.holds(
+                    (time_of(first_start)?, time_of(first_end)?),
+                    (time_of(second_start)?, time_of(second_end)?),
+                ))
+            }
+            Filter::EventInObjectWindow {
+                event,
+                object,
+                window,
+            } => {
+                let ev = b
+                    .get_ev_index(event)
+                    .ok_or_else(|| format!("Event Variable {event} without value"))?;
+                let ob = b
+                    .get_ob_index(object)
+                    .ok_or_else(|| format!("Object Variable {object} without value"))?;
+                let time = ocel.get_ev_time(ev);
+                Ok(window.windows(ocel, ob).iter().any(|w| w.contains(time)))
+            }
             Filter::NotEqual { var_1, var_2 } => {
                 let val_1 = b.get_any_index(var_1);
                 let val_2 = b.get_any_index(var_2);
@@ -1281,13 +1889,218 @@ impl Filter {
                     Ok(false)
                 }
             }
-            Filter::BasicFilterCEL { cel } => {
-                Ok(check_cel_predicate(cel, b, None, ocel)?)
+            Filter::BasicFilterCEL { cel } => Ok(check_cel_predicate(cel, b, None, ocel, env)?),
+        }
+    }
+}
+
+/// The events of `ob` in lifecycle order: by time, and by position in the OCEL for equal times.
+pub fn object_lifecycle(ocel: &SlimLinkedOCEL, ob: &ObjectIndex) -> Vec<EventIndex> {
+    ocel.get_e2o_rev(ob)
+        .map(|(_q, e)| *e)
+        .sorted_by_key(|e| lifecycle_key(ocel, e))
+        .dedup()
+        .collect()
+}
+
+fn lifecycle_key(
+    ocel: &SlimLinkedOCEL,
+    ev: &EventIndex,
+) -> (chrono::DateTime<chrono::FixedOffset>, usize) {
+    (*ocel.get_ev_time(ev), ev.into_inner() as usize)
+}
+
+/// The event directly after (or, if `reversed`, directly before) `ev` in `lifecycle`, an object's
+/// [`object_lifecycle`]. `None` if there is none, or if `ev` is not an event of the object.
+pub(crate) fn adjacent_event(
+    ocel: &SlimLinkedOCEL,
+    lifecycle: &[EventIndex],
+    ev: &EventIndex,
+    reversed: bool,
+) -> Option<EventIndex> {
+    let pos = lifecycle
+        .binary_search_by_key(&lifecycle_key(ocel, ev), |e| lifecycle_key(ocel, e))
+        .ok()?;
+    if reversed {
+        pos.checked_sub(1).map(|p| lifecycle[p])
+    } else {
+        lifecycle.get(pos + 1).copied()
+    }
+}
+
+/// Time windows derived from the history of an object.
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type")]
+pub enum ObjectWindow {
+    /// From the first to the last event of the object (both included)
+    Lifespan,
+    /// Every period in which the last value the attribute changed to satisfies the value filter
+    /// (from the change to the next change of that attribute, or open-ended after the last one)
+    WhileAttribute {
+        attribute_name: String,
+        value_filter: ValueFilter,
+    },
+}
+
+/// A window `[from, to]`, or `[from, to)` if `to` is excluded; without `to`, it never ends.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeWindow {
+    pub from: chrono::DateTime<chrono::FixedOffset>,
+    pub to: Option<chrono::DateTime<chrono::FixedOffset>>,
+    pub to_included: bool,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, time: &chrono::DateTime<chrono::FixedOffset>) -> bool {
+        &self.from <= time
+            && self.to.is_none_or(|to| {
+                if self.to_included {
+                    time <= &to
+                } else {
+                    time < &to
+                }
+            })
+    }
+}
+
+impl ObjectWindow {
+    /// The windows of `ob`, disjoint and ordered by start. Empty if the object has no events (for
+    /// [`ObjectWindow::Lifespan`]) or never has a matching attribute value.
+    pub fn windows(&self, ocel: &SlimLinkedOCEL, ob: &ObjectIndex) -> Vec<TimeWindow> {
+        match self {
+            ObjectWindow::Lifespan => {
+                match ocel
+                    .get_e2o_rev(ob)
+                    .map(|(_q, e)| ocel.get_ev_time(e))
+                    .minmax()
+                    .into_option()
+                {
+                    Some((first, last)) => vec![TimeWindow {
+                        from: *first,
+                        to: Some(*last),
+                        to_included: true,
+                    }],
+                    None => Vec::new(),
+                }
+            }
+            ObjectWindow::WhileAttribute {
+                attribute_name,
+                value_filter,
+            } => {
+                let changes = ocel
+                    .get_full_ob(ob)
+                    .attributes
+                    .iter()
+                    .filter(|at| &at.name == attribute_name)
+                    .map(|at| (at.time, value_filter.check_value(&at.value)))
+                    .sorted_by_key(|(time, _)| *time)
+                    .collect_vec();
+                changes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (_, matches))| *matches)
+                    .map(|(i, (from, _))| TimeWindow {
+                        from: *from,
+                        to: changes.get(i + 1).map(|(to, _)| *to),
+                        to_included: false,
+                    })
+                    .collect()
             }
         }
     }
 }
 
+/// The 13 relations of Allen's interval algebra, read as "first `relation` second".
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum AllenRelation {
+    Before,
+    Meets,
+    Overlaps,
+    Starts,
+    During,
+    Finishes,
+    Equals,
+    After,
+    MetBy,
+    OverlappedBy,
+    StartedBy,
+    Contains,
+    FinishedBy,
+}
+
+impl AllenRelation {
+    /// Whether the `(start, end)` intervals `first` and `second` stand in this relation. An
+    /// interval ending before it starts stands in none.
+    pub fn holds<T: Ord + Copy>(&self, first: (T, T), second: (T, T)) -> bool {
+        let ((s1, e1), (s2, e2)) = (first, second);
+        if s1 > e1 || s2 > e2 {
+            return false;
+        }
+        match self {
+            AllenRelation::Before => e1 < s2,
+            AllenRelation::Meets => e1 == s2,
+            AllenRelation::Overlaps => s1 < s2 && s2 < e1 && e1 < e2,
+            AllenRelation::Starts => s1 == s2 && e1 < e2,
+            AllenRelation::During => s2 < s1 && e1 < e2,
+            AllenRelation::Finishes => e1 == e2 && s2 < s1,
+            AllenRelation::Equals => s1 == s2 && e1 == e2,
+            inverse => inverse.inverse().holds(second, first),
+        }
+    }
+
+    /// The relation that holds with first and second interval swapped.
+    pub fn inverse(&self) -> Self {
+        match self {
+            AllenRelation::Before => AllenRelation::After,
+            AllenRelation::Meets => AllenRelation::MetBy,
+            AllenRelation::Overlaps => AllenRelation::OverlappedBy,
+            AllenRelation::Starts => AllenRelation::StartedBy,
+            AllenRelation::During => AllenRelation::Contains,
+            AllenRelation::Finishes => AllenRelation::FinishedBy,
+            AllenRelation::Equals => AllenRelation::Equals,
+            AllenRelation::After => AllenRelation::Before,
+            AllenRelation::MetBy => AllenRelation::Meets,
+            AllenRelation::OverlappedBy => AllenRelation::Overlaps,
+            AllenRelation::StartedBy => AllenRelation::Starts,
+            AllenRelation::Contains => AllenRelation::During,
+            AllenRelation::FinishedBy => AllenRelation::Finishes,
+        }
+    }
+
+    /// Orderings of the interval ends implied by this relation, as `(earlier, later, equal)`
+    /// over the positions in `[first_start, first_end, second_start, second_end]`. They are
+    /// necessary, not sufficient: binding uses them to skip events early, the filter still decides.
+    pub(crate) fn implied_orderings(&self) -> Vec<(usize, usize, bool)> {
+        let mut orderings = vec![(0, 1, false), (2, 3, false)];
+        let relative = match self {
+            AllenRelation::Before => vec![(1, 2, false)],
+            AllenRelation::Meets => vec![(1, 2, true)],
+            AllenRelation::Overlaps => vec![(0, 2, false), (2, 1, false), (1, 3, false)],
+            AllenRelation::Starts => vec![(0, 2, true), (1, 3, false)],
+            AllenRelation::During => vec![(2, 0, false), (1, 3, false)],
+            AllenRelation::Finishes => vec![(1, 3, true), (2, 0, false)],
+            AllenRelation::Equals => vec![(0, 2, true), (1, 3, true)],
+            inverse => {
+                // Swapping the intervals maps position i to (i + 2) % 4.
+                let swap = |i: usize| (i + 2) % 4;
+                inverse
+                    .inverse()
+                    .implied_orderings()
+                    .into_iter()
+                    .skip(2)
+                    .map(|(earlier, later, equal)| (swap(earlier), swap(later), equal))
+                    .collect()
+            }
+        };
+        orderings.extend(relative);
+        orderings
+    }
+}
+
 #[derive(TS, Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[ts(export)]
 #[serde(tag = "type")]
@@ -1393,6 +2206,22 @@ pub enum SizeFilter {
     AdvancedCEL {
         cel: String,
     },
+    /// The aggregate over the child's bindings is a number in the interval (min,max) (where None represents no restriction)
+    Aggregate {
+        aggregate: ChildAggregate,
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    /// The number of satisfied bindings of the child is in the interval (min,max), and their share
+    /// of all bindings of the child is in the interval (min_fraction,max_fraction)
+    /// (where None represents no restriction). A child without bindings has a share of 1.
+    NumSatisfiedChilds {
+        child_name: NodeEdgeName,
+        min: Option<usize>,
+        max: Option<usize>,
+        min_fraction: Option<f64>,
+        max_fraction: Option<f64>,
+    },
 }
 
 impl SizeFilter {
@@ -1401,6 +2230,7 @@ impl SizeFilter {
         binding: &Binding,
         child_res: &HashMap<String, Vec<(std::sync::Arc<Binding>, Option<ViolationReason>)>>,
         ocel: &SlimLinkedOCEL,
+        env: CelEnv<'_>,
     ) -> Result<bool, String> {
         match self {
             SizeFilter::NumChilds {
@@ -1502,9 +2332,73 @@ impl SizeFilter {
                     Ok(false)
                 }
             }
-            SizeFilter::AdvancedCEL { cel } => {
-                Ok(check_cel_predicate(cel, binding, Some(child_res), ocel)?)
+            SizeFilter::AdvancedCEL { cel } => Ok(check_cel_predicate(
+                cel,
+                binding,
+                Some(child_res),
+                ocel,
+                env,
+            )?),
+            SizeFilter::Aggregate {
+                aggregate,
+                min,
+                max,
+            } => {
+                if !child_res.contains_key(&aggregate.child_name) {
+                    return Ok(false);
+                }
+                Ok(aggregate
+                    .compute(child_res, ocel, env.now)?
+                    .as_f64()
+                    .is_some_and(|v| {
+                        !min.is_some_and(|min| v < min) && !max.is_some_and(|max| v > max)
+                    }))
             }
+            SizeFilter::NumSatisfiedChilds { .. } => Ok(self
+                .satisfied_childs(child_res)
+                .is_some_and(|(satisfied, total)| {
+                    self.satisfied_childs_in_range(satisfied, total)
+                })),
+        }
+    }
+
+    /// `(satisfied, total)` bindings of the child of a [`SizeFilter::NumSatisfiedChilds`], or
+    /// `None` if there is no such child
+    fn satisfied_childs(
+        &self,
+        child_res: &HashMap<String, Vec<(std::sync::Arc<Binding>, Option<ViolationReason>)>>,
+    ) -> Option<(usize, usize)> {
+        let SizeFilter::NumSatisfiedChilds { child_name, .. } = self else {
+            return None;
+        };
+        child_res.get(child_name).map(|c_res| {
+            (
+                c_res.iter().filter(|(_b, v)| v.is_none()).count(),
+                c_res.len(),
+            )
+        })
+    }
+
+    fn satisfied_childs_in_range(&self, satisfied: usize, total: usize) -> bool {
+        match self {
+            SizeFilter::NumSatisfiedChilds {
+                min,
+                max,
+                min_fraction,
+                max_fraction,
+                ..
+            } => {
+                let fraction = if total == 0 {
+                    1.0
+                } else {
+                    satisfied as f64 / total as f64
+                };
+                !min.is_some_and(|min| satisfied < min)
+                    && !max.is_some_and(|max| satisfied > max)
+                    && !min_fraction.is_some_and(|min| fraction < min)
+                    && !max_fraction.is_some_and(|max| fraction > max)
+            }
+            _ => false,
         }
     }
 }
@@ -1549,9 +2443,47 @@ impl Filter {
                 to_event,
                 min_seconds: _,
                 max_seconds: _,
+                calendar: _,
             } => vec![Variable::Event(*from_event), Variable::Event(*to_event)]
                 .into_iter()
                 .collect(),
+            Filter::DirectlyFollows {
+                from_event,
+                to_event,
+                object,
+            } => vec![
+                Variable::Event(*from_event),
+                Variable::Event(*to_event),
+                Variable::Object(*object),
+            ]
+            .into_iter()
+            .collect(),
+            Filter::NoEventBetween {
+                from_event,
+                to_event,
+                event_types: _,
+                object,
+            } => vec![Variable::Event(*from_event), Variable::Event(*to_event)]
+                .into_iter()
+                .chain(object.map(Variable::Object))
+                .collect(),
+            Filter::EventInObjectWindow {
+                event,
+                object,
+                window: _,
+            } => vec![Variable::Event(*event), Variable::Object(*object)]
+                .into_iter()
+                .collect(),
+            Filter::IntervalRelation {
+                first_start,
+                first_end,
+                second_start,
+                second_end,
+                relation: _,
+            } => [first_start, first_end, second_start, second_end]
+                .into_iter()
+                .map(|e| Variable::Event(*e))
+                .collect(),
             Filter::NotEqual { var_1, var_2 } => {
                 vec![var_1.clone(), var_2.clone()].into_iter().collect()
             }
@@ -1591,6 +2523,10 @@ pub enum BindingStep {
     // bool: reversed?
     BindObFromOb(ObjectVariable, ObjectVariable, Qualifier, bool),
     BindEvFromOb(EventVariable, ObjectVariable, Qualifier),
+    /// Bind the events that happen within a time window of a bound object
+    BindEvInObWindow(EventVariable, ObjectVariable, ObjectWindow),
+    /// Bind the event directly following (bool: reversed? then directly preceding) a bound event in the lifecycle of a bound object
+    BindEvAdjacent(EventVariable, EventVariable, ObjectVariable, bool),
     Filter(Filter),
 }
 
@@ -1632,3 +2568,319 @@ impl Display for EventVariable {
         write!(f, "e{}", self.0 + 1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binding_box::{
+        evaluate_box_tree,
+        test_utils::{
+            amount_at_least, bound_event_ids, bound_object_ids, ev_vars, eval, o2e, ob_vars, ocel,
+            ocel_with_order_status, tree,
+        },
+        EvaluateBoxTreeResult,
+    };
+
+    /// An order with its `place` event (variable 0) and an event of `next_type` (variable 1)
+    /// related only through `filter`.
+    fn place_then(next_type: &str, filter: Filter) -> BindingBox {
+        BindingBox {
+            new_object_vars: ob_vars(&[(0, "order")]),
+            new_event_vars: ev_vars(&[(0, "place"), (1, next_type)]),
+            filters: vec![o2e(0, 0, None), filter],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn directly_follows_binds_only_the_next_event_of_the_object() {
+        let follows = Filter::DirectlyFollows {
+            from_event: EventVariable(0),
+            to_event: EventVariable(1),
+            object: ObjectVariable(0),
+        };
+        // The order is bound first, so the follower is looked up in its lifecycle instead of
+        // being enumerated over all events of its type.
+        let bbox = place_then("pay", follows.clone());
+        assert!(BindingStep::get_binding_order(&bbox, None, &ocel())
+            .iter()
+            .any(|s| matches!(s, BindingStep::BindEvAdjacent(EventVariable(1), ..))));
+
+        // o1: place (e1), pay (e3), ship (e4), ship (e5). o2: place (e2), cancel (e6).
+        for (next_type, expected) in [
+            ("pay", vec!["e3"]),
+            ("ship", vec![]),
+            ("cancel", vec!["e6"]),
+        ] {
+            let res = eval(tree(
+                vec![BindingBoxTreeNode::Box(
+                    place_then(next_type, follows.clone()),
+                    vec![],
+                )],
+                &[],
+            ));
+            assert_eq!(bound_event_ids(&res, 0, 1), expected, "{next_type}");
+        }
+    }
+
+    #[test]
+    fn no_event_between_only_counts_the_given_types_and_object() {
+        let no_event_between = |event_types: &[&str], object: Option<usize>| {
+            let mut bbox = place_then(
+                "ship",
+                Filter::NoEventBetween {
+                    from_event: EventVariable(0),
+                    to_event: EventVariable(1),
+                    event_types: event_types.iter().map(|t| t.to_string()).collect(),
+                    object: object.map(ObjectVariable),
+                },
+            );
+            bbox.filters.push(o2e(0, 1, None));
+            eval(tree(vec![BindingBoxTreeNode::Box(bbox, vec![])], &[])).evaluation_results[0]
+                .situation_count
+        };
+        // Both ships of o1 come after its payment, and before o2's cancellation.
+        assert_eq!(no_event_between(&["pay"], Some(0)), 0);
+        assert_eq!(no_event_between(&["cancel"], None), 2);
+        // Without types, any event of o1 counts, and its payment lies between both pairs.
+        assert_eq!(no_event_between(&[], Some(0)), 0);
+        assert_eq!(
+            no_event_between(&["place"], None),
+            0,
+            "o2 was placed on day 2"
+        );
+    }
+
+    #[test]
+    fn interval_relation_compares_the_intervals_of_bound_events() {
+        // Is the payment (a point interval) during the span from placing to shipping?
+        let during = |relation: AllenRelation| {
+            let mut bbox = place_then(
+                "ship",
+                Filter::IntervalRelation {
+                    first_start: EventVariable(2),
+                    first_end: EventVariable(2),
+                    second_start: EventVariable(0),
+                    second_end: EventVariable(1),
+                    relation,
+                },
+            );
+            bbox.new_event_vars.extend(ev_vars(&[(2, "pay")]));
+            bbox.filters.extend([o2e(0, 1, None), o2e(0, 2, None)]);
+            eval(tree(vec![BindingBoxTreeNode::Box(bbox, vec![])], &[])).evaluation_results[0]
+                .situation_count
+        };
+        assert_eq!(during(AllenRelation::During), 2);
+        assert_eq!(during(AllenRelation::Before), 0);
+        assert_eq!(during(AllenRelation::After), 0);
+    }
+
+    #[test]
+    fn exactly_one_allen_relation_holds_and_its_implied_orderings_too() {
+        use AllenRelation::*;
+        let relations = [
+            Before,
+            Meets,
+            Overlaps,
+            Starts,
+            During,
+            Finishes,
+            Equals,
+            After,
+            MetBy,
+            OverlappedBy,
+            StartedBy,
+            Contains,
+            FinishedBy,
+        ];
+        let intervals = (0..4).flat_map(|s| (s + 1..5).map(move |e| (s, e)));
+        for (first, second) in intervals.clone().cartesian_product(intervals) {
+            let holding = relations
+                .iter()
+                .filter(|r| r.holds(first, second))
+                .collect_vec();
+            assert_eq!(holding.len(), 1, "{first:?} {second:?}: {holding:?}");
+            let ends = [first.0, first.1, second.0, second.1];
+            for (earlier, later, equal) in holding[0].implied_orderings() {
+                assert!(ends[earlier] <= ends[later]);
+                assert!(!equal || ends[earlier] == ends[later]);
+            }
+            assert!(holding[0].inverse().holds(second, first));
+        }
+    }
+
+    #[test]
+    fn point_intervals_can_meet_and_equal_at_once() {
+        use AllenRelation::*;
+        let relations = [
+            Before,
+            Meets,
+            Overlaps,
+            Starts,
+            During,
+            Finishes,
+            Equals,
+            After,
+            MetBy,
+            OverlappedBy,
+            StartedBy,
+            Contains,
+            FinishedBy,
+        ];
+        let holding = |first: (i32, i32), second: (i32, i32)| {
+            relations
+                .into_iter()
+                .filter(|r| r.holds(first, second))
+                .collect_vec()
+        };
+        // Two events at the same time
+        assert_eq!(holding((2, 2), (2, 2)), [Meets, Equals, MetBy]);
+        // An event at the start, inside, and at the end of a proper interval
+        assert_eq!(holding((1, 1), (1, 3)), [Meets, Starts]);
+        assert_eq!(holding((2, 2), (1, 3)), [During]);
+        assert_eq!(holding((3, 3), (1, 3)), [Finishes, MetBy]);
+        assert_eq!(holding((1, 3), (3, 3)), [Meets, FinishedBy]);
+    }
+
+    /// An order and the events of `ev_type` within `window` of it.
+    fn events_in_window(ev_type: &str, window: ObjectWindow) -> BindingBox {
+        BindingBox {
+            new_object_vars: ob_vars(&[(0, "order")]),
+            new_event_vars: ev_vars(&[(0, ev_type)]),
+            filters: vec![Filter::EventInObjectWindow {
+                event: EventVariable(0),
+                object: ObjectVariable(0),
+                window,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn lifespan_window_binds_the_events_between_the_first_and_last_event_of_the_object() {
+        let bbox = events_in_window("place", ObjectWindow::Lifespan);
+        assert!(BindingStep::get_binding_order(&bbox, None, &ocel())
+            .iter()
+            .any(|s| matches!(s, BindingStep::BindEvInObWindow(EventVariable(0), ..))));
+
+        // o1 lives from day 1 to day 5, o2 from day 2 to day 6; both places happen on day 1 and 2.
+        let res = eval(tree(vec![BindingBoxTreeNode::Box(bbox, vec![])], &[]));
+        let pairs = bound_object_ids(&res, 0, 0)
+            .into_iter()
+            .zip(bound_event_ids(&res, 0, 0))
+            .sorted()
+            .collect_vec();
+        assert_eq!(pairs, vec![("o1", "e1"), ("o1", "e2"), ("o2", "e2")]);
+    }
+
+    #[test]
+    fn attribute_window_ends_at_the_next_change_of_the_attribute() {
+        // o1 is open from day 1 until noon of day 4, so only the first ship (e4) falls in it; o2
+        // has no status at all.
+        let t = tree(
+            vec![BindingBoxTreeNode::Box(
+                events_in_window(
+                    "ship",
+                    ObjectWindow::WhileAttribute {
+                        attribute_name: "status".to_string(),
+                        value_filter: ValueFilter::String {
+                            is_in: vec!["open".to_string()],
+                        },
+                    },
+                ),
+                vec![],
+            )],
+            &[],
+        );
+        let res = evaluate_box_tree(t, &ocel_with_order_status(), false).unwrap();
+        assert_eq!(bound_event_ids(&res, 0, 0), vec!["e4"]);
+        assert_eq!(bound_object_ids(&res, 0, 0), vec!["o1"]);
+    }
+
+    /// Only `place` events carry an amount, so o1 has one of its four child bindings satisfied
+    /// and o2 its only one.
+    #[test]
+    fn num_satisfied_childs_bounds_count_and_share_of_satisfied_children() {
+        let check = |min: Option<usize>, min_fraction: Option<f64>| {
+            eval(tree(
+                vec![
+                    BindingBoxTreeNode::Box(
+                        BindingBox {
+                            new_object_vars: ob_vars(&[(0, "order")]),
+                            constraints: vec![Constraint::SizeFilter {
+                                filter: SizeFilter::NumSatisfiedChilds {
+                                    child_name: "A".to_string(),
+                                    min,
+                                    max: None,
+                                    min_fraction,
+                                    max_fraction: None,
+                                },
+                            }],
+                            ..Default::default()
+                        },
+                        vec![1],
+                    ),
+                    BindingBoxTreeNode::Box(
+                        BindingBox {
+                            new_event_vars: [(
+                                EventVariable(1),
+                                ["place", "pay", "ship"].map(str::to_string).into(),
+                            )]
+                            .into_iter()
+                            .collect(),
+                            filters: vec![o2e(0, 1, None)],
+                            constraints: vec![Constraint::Filter {
+                                filter: amount_at_least(1, 1),
+                            }],
+                            ..Default::default()
+                        },
+                        vec![],
+                    ),
+                ],
+                &[((0, 1), "A")],
+            ))
+        };
+        let violations = |res: &EvaluateBoxTreeResult| {
+            res.evaluation_results[0]
+                .situations
+                .iter()
+                .map(|(_, v)| *v)
+                .zip(bound_object_ids(res, 0, 0))
+                .sorted_by_key(|(_, o)| *o)
+                .map(|(v, _)| v)
+                .collect_vec()
+        };
+
+        let res = check(Some(1), None);
+        assert!(violations(&res).iter().all(Option::is_none));
+
+        let res = check(Some(1), Some(0.5));
+        let v = violations(&res);
+        assert!(
+            matches!(
+                v[0],
+                Some(ViolationReason::SatisfiedChildsOutOfRange {
+                    constraint: 0,
+                    satisfied: 1,
+                    total: 4
+                })
+            ),
+            "{v:?}"
+        );
+        assert!(v[1].is_none(), "{v:?}");
+
+        let res = check(Some(2), None);
+        assert!(
+            matches!(
+                violations(&res)[1],
+                Some(ViolationReason::SatisfiedChildsOutOfRange {
+                    satisfied: 1,
+                    total: 1,
+                    ..
+                })
+            ),
+            "{:?}",
+            violations(&res)
+        );
+    }
+}
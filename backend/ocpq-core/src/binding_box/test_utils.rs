@@ -0,0 +1,209 @@
+//! The tiny OCEL the evaluation tests run against, and shorthands for building trees over it.
+use std::collections::HashMap;
+
+use process_mining::{core::event_data::object_centric::linked_ocel::SlimLinkedOCEL, OCEL};
+
+use super::{
+    evaluate_box_tree,
+    structs::{
+        BindingBox, BindingBoxTree, BindingBoxTreeNode, Constraint, EventVariable, Filter,
+        NewEventVariables, NewObjectVariables, ObjectVariable, SizeFilter, ValueFilter,
+    },
+    EvaluateBoxTreeResult,
+};
+
+pub(crate) const OCEL_JSON: &str = r#"{
+    "objectTypes": [
+        { "name": "order", "attributes": [] },
+        { "name": "item", "attributes": [] }
+    ],
+    "eventTypes": [
+        { "name": "place", "attributes": [{ "name": "amount", "type": "integer" }] },
+        { "name": "pay", "attributes": [] },
+        { "name": "ship", "attributes": [] },
+        { "name": "cancel", "attributes": [] }
+    ],
+    "objects": [
+        { "id": "o1", "type": "order", "attributes": [],
+          "relationships": [{ "objectId": "i1", "qualifier": "contains" }] },
+        { "id": "o2", "type": "order", "attributes": [], "relationships": [] },
+        { "id": "i1", "type": "item", "attributes": [], "relationships": [] }
+    ],
+    "events": [
+        { "id": "e1", "type": "place", "time": "2024-01-01T00:00:00Z",
+          "attributes": [{ "name": "amount", "value": 100 }],
+          "relationships": [{ "objectId": "o1", "qualifier": "order" }] },
+        { "id": "e2", "type": "place", "time": "2024-01-02T00:00:00Z",
+          "attributes": [{ "name": "amount", "value": 5 }],
+          "relationships": [{ "objectId": "o2", "qualifier": "order" }] },
+        { "id": "e3", "type": "pay", "time": "2024-01-03T00:00:00Z", "attributes": [],
+          "relationships": [{ "objectId": "o1", "qualifier": "order" }] },
+        { "id": "e4", "type": "ship", "time": "2024-01-04T00:00:00Z", "attributes": [],
+          "relationships": [{ "objectId": "o1", "qualifier": "order" },
+                            { "objectId": "i1", "qualifier": "item" }] },
+        { "id": "e5", "type": "ship", "time": "2024-01-05T00:00:00Z", "attributes": [],
+          "relationships": [{ "objectId": "o1", "qualifier": "order" }] },
+        { "id": "e6", "type": "cancel", "time": "2024-01-06T00:00:00Z", "attributes": [],
+          "relationships": [{ "objectId": "o2", "qualifier": "order" }] }
+    ]
+}"#;
+
+pub(crate) fn ocel() -> SlimLinkedOCEL {
+    let ocel: OCEL = serde_json::from_str(OCEL_JSON).expect("fixture OCEL parses");
+    SlimLinkedOCEL::from_ocel(ocel)
+}
+
+pub(crate) fn ob_vars(vars: &[(usize, &str)]) -> NewObjectVariables {
+    vars.iter()
+        .map(|(i, t)| (ObjectVariable(*i), [t.to_string()].into_iter().collect()))
+        .collect()
+}
+
+pub(crate) fn ev_vars(vars: &[(usize, &str)]) -> NewEventVariables {
+    vars.iter()
+        .map(|(i, t)| (EventVariable(*i), [t.to_string()].into_iter().collect()))
+        .collect()
+}
+
+pub(crate) fn o2e(object: usize, event: usize, qualifier: Option<&str>) -> Filter {
+    Filter::O2E {
+        object: ObjectVariable(object),
+        event: EventVariable(event),
+        qualifier: qualifier.map(str::to_string),
+        filter_label: None,
+    }
+}
+
+/// `amount >= min` on event variable `event`.
+pub(crate) fn amount_at_least(event: usize, min: i64) -> Filter {
+    Filter::EventAttributeValueFilter {
+        event: EventVariable(event),
+        attribute_name: "amount".to_string(),
+        value_filter: ValueFilter::Integer {
+            min: Some(min),
+            max: None,
+        },
+    }
+}
+
+pub(crate) fn tree(
+    nodes: Vec<BindingBoxTreeNode>,
+    edges: &[((usize, usize), &str)],
+) -> BindingBoxTree {
+    BindingBoxTree {
+        nodes,
+        edge_names: edges
+            .iter()
+            .map(|(k, v)| (*k, v.to_string()))
+            .collect::<HashMap<_, _>>(),
+        cel_libraries: Vec::new(),
+        parameters: Vec::new(),
+    }
+}
+
+pub(crate) fn eval(tree: BindingBoxTree) -> EvaluateBoxTreeResult {
+    evaluate_box_tree(tree, &ocel(), false).expect("evaluation succeeds")
+}
+
+/// The object ids bound to `var` by every situation of node `node_index`, in result order.
+pub(crate) fn bound_object_ids(
+    res: &EvaluateBoxTreeResult,
+    node_index: usize,
+    var: usize,
+) -> Vec<&str> {
+    res.evaluation_results[node_index]
+        .situations
+        .iter()
+        .map(|(b, _)| {
+            let idx = b.get_ob_index(&ObjectVariable(var)).expect("var bound");
+            res.object_ids[(*idx).into_inner() as usize].as_str()
+        })
+        .collect()
+}
+
+pub(crate) fn bound_event_ids(
+    res: &EvaluateBoxTreeResult,
+    node_index: usize,
+    var: usize,
+) -> Vec<&str> {
+    res.evaluation_results[node_index]
+        .situations
+        .iter()
+        .map(|(b, _)| {
+            let idx = b.get_ev_index(&EventVariable(var)).expect("var bound");
+            res.event_ids[(*idx).into_inner() as usize].as_str()
+        })
+        .collect()
+}
+
+/// Root: every order, with a `NumChilds` constraint on its `pay` events, and under it a
+/// constrained `ship` node, so there are violations at more than one level.
+pub(crate) fn order_payment_shipping_tree() -> BindingBoxTree {
+    tree(
+        vec![
+            BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_object_vars: ob_vars(&[(0, "order")]),
+                    constraints: vec![Constraint::SizeFilter {
+                        filter: SizeFilter::NumChilds {
+                            child_name: "A".to_string(),
+                            min: Some(1),
+                            max: None,
+                        },
+                    }],
+                    ..Default::default()
+                },
+                vec![1, 2],
+            ),
+            BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_event_vars: ev_vars(&[(0, "pay")]),
+                    filters: vec![o2e(0, 0, None)],
+                    ..Default::default()
+                },
+                vec![],
+            ),
+            BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_event_vars: ev_vars(&[(1, "ship")]),
+                    filters: vec![o2e(0, 1, None)],
+                    constraints: vec![Constraint::Filter {
+                        filter: o2e(0, 1, Some("item")),
+                    }],
+                    ..Default::default()
+                },
+                vec![],
+            ),
+        ],
+        &[((0, 1), "A"), ((0, 2), "B")],
+    )
+}
+
+/// The `place` (variable 0) and `ship` (variable 1) events of an order passing `filter`.
+pub(crate) fn place_and_ship(filter: Filter) -> BindingBoxTree {
+    tree(
+        vec![BindingBoxTreeNode::Box(
+            BindingBox {
+                new_object_vars: ob_vars(&[(0, "order")]),
+                new_event_vars: ev_vars(&[(0, "place"), (1, "ship")]),
+                filters: vec![o2e(0, 0, None), o2e(0, 1, None), filter],
+                ..Default::default()
+            },
+            vec![],
+        )],
+        &[],
+    )
+}
+
+/// The fixture OCEL with a `status` of orders: o1 is open from day 1 and closed from noon of
+/// day 4, o2 has none.
+pub(crate) fn ocel_with_order_status() -> SlimLinkedOCEL {
+    let mut ocel: serde_json::Value = serde_json::from_str(OCEL_JSON).expect("fixture OCEL parses");
+    ocel["objectTypes"][0]["attributes"] =
+        serde_json::json!([{ "name": "status", "type": "string" }]);
+    ocel["objects"][0]["attributes"] = serde_json::json!([
+        { "name": "status", "time": "2024-01-01T00:00:00Z", "value": "open" },
+        { "name": "status", "time": "2024-01-04T12:00:00Z", "value": "closed" }
+    ]);
+    SlimLinkedOCEL::from_ocel(serde_json::from_value(ocel).expect("fixture OCEL parses"))
+}
@@ -0,0 +1,75 @@
+//! Events in time order, sorted at most once per evaluation: the lifecycle of each object and the
+//! events of each type are sorted the first time a step or filter needs them, and from then on
+//! binary-searched instead of collected and sorted (or scanned) again for every binding.
+use std::{borrow::Cow, collections::HashMap, sync::OnceLock};
+
+use process_mining::core::event_data::object_centric::linked_ocel::{
+    slim_linked_ocel::{EventIndex, ObjectIndex},
+    LinkedOCELAccess, SlimLinkedOCEL,
+};
+
+use super::structs::{object_lifecycle, TimeWindow};
+
+/// The lifecycles (see [`object_lifecycle`]) of the objects of one OCEL and its events by type,
+/// each sorted on first use.
+#[derive(Debug)]
+pub struct TimeIndex {
+    lifecycles: Vec<OnceLock<Vec<EventIndex>>>,
+    evs_of_type: HashMap<String, OnceLock<Vec<EventIndex>>>,
+}
+
+impl TimeIndex {
+    pub fn new(ocel: &SlimLinkedOCEL) -> Self {
+        Self {
+            lifecycles: (0..ocel.get_num_obs()).map(|_| OnceLock::new()).collect(),
+            evs_of_type: ocel
+                .get_ev_types()
+                .map(|t| (t.to_string(), OnceLock::new()))
+                .collect(),
+        }
+    }
+
+    /// The events of `ob` in lifecycle order
+    pub fn lifecycle(&self, ocel: &SlimLinkedOCEL, ob: &ObjectIndex) -> &[EventIndex] {
+        match self.lifecycles.get(ob.into_inner() as usize) {
+            Some(lifecycle) => lifecycle.get_or_init(|| object_lifecycle(ocel, ob)),
+            None => &[],
+        }
+    }
+
+    /// The events of type `ev_type` in `window`, by time
+    pub fn evs_of_type_in(
+        &self,
+        ocel: &SlimLinkedOCEL,
+        ev_type: &str,
+        window: &TimeWindow,
+    ) -> &[EventIndex] {
+        let Some(evs) = self.evs_of_type.get(ev_type) else {
+            return &[];
+        };
+        let evs = evs.get_or_init(|| {
+            let mut evs = ocel.get_evs_of_type(ev_type).copied().collect::<Vec<_>>();
+            evs.sort_by_key(|e| *ocel.get_ev_time(e));
+            evs
+        });
+        let from = evs.partition_point(|e| ocel.get_ev_time(e) < &window.from);
+        let to = match window.to {
+            Some(to) if window.to_included => evs.partition_point(|e| ocel.get_ev_time(e) <= &to),
+            Some(to) => evs.partition_point(|e| ocel.get_ev_time(e) < &to),
+            None => evs.len(),
+        };
+        &evs[from..to.max(from)]
+    }
+}
+
+/// The lifecycle of `ob`, from `index` if there is one and sorted on the spot otherwise.
+pub fn lifecycle_of<'a>(
+    ocel: &SlimLinkedOCEL,
+    ob: &ObjectIndex,
+    index: Option<&'a TimeIndex>,
+) -> Cow<'a, [EventIndex]> {
+    match index {
+        Some(index) => Cow::Borrowed(index.lifecycle(ocel, ob)),
+        None => Cow::Owned(object_lifecycle(ocel, ob)),
+    }
+}
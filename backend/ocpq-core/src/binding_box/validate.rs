@@ -0,0 +1,664 @@
+//! Checks of a tree that do not need an OCEL: every CEL program compiles, only uses variables,
+//! labels and child results that are available where it is evaluated, and filters evaluate to a
+//! bool; every referenced child exists, every listed CEL library is loaded and every parameter is
+//! bound and only sets bounds that take its type.
+//!
+//! Without them, such mistakes only show up as evaluation errors, often deep into a long run.
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::{
+    aggregate::{AggregateFunction, AggregateValue, ChildAggregate},
+    parameters::ParameterValues,
+    structs::{
+        BindingBox, BindingBoxTree, BindingBoxTreeNode, Constraint, Filter, LabelFunction,
+        SizeFilter, Variable,
+    },
+};
+use crate::cel::{
+    ev_var_to_name, get_identifiers_in_cel_program, get_vars_in_cel_program, ob_var_to_name,
+    string_to_var,
+    syntax::{infer_cel_type, macro_variables, CelType},
+};
+
+/// Where in a node a diagnostic or CEL expression is
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "type")]
+pub enum TreeLocation {
+    /// The node itself
+    Node,
+    Filter {
+        index: usize,
+    },
+    SizeFilter {
+        index: usize,
+    },
+    Constraint {
+        index: usize,
+    },
+    Label {
+        index: usize,
+    },
+}
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum DiagnosticSeverity {
+    /// Evaluating the tree fails (or a filter can never be satisfied)
+    Error,
+    /// Evaluating the tree works, but likely not as intended
+    Warning,
+}
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeDiagnostic {
+    pub node_index: usize,
+    pub location: TreeLocation,
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+}
+
+/// The inferred result type of one CEL expression of the tree
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CelResultType {
+    pub node_index: usize,
+    pub location: TreeLocation,
+    pub cel: String,
+    pub result_type: CelType,
+}
+
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeValidation {
+    pub diagnostics: Vec<TreeDiagnostic>,
+    pub cel_types: Vec<CelResultType>,
+}
+
+impl TreeValidation {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|d| d.severity == DiagnosticSeverity::Error)
+    }
+}
+
+/// Check `tree` without evaluating it (see the module documentation), with the defaults of its
+/// parameters.
+pub fn validate_tree(tree: &BindingBoxTree) -> TreeValidation {
+    validate_tree_with_parameters(tree, &ParameterValues::new())
+}
+
+/// Like [`validate_tree`], for an evaluation with the parameter `values`.
+pub fn validate_tree_with_parameters(
+    tree: &BindingBoxTree,
+    values: &ParameterValues,
+) -> TreeValidation {
+    let mut res = TreeValidation::default();
+    check_parameters(tree, values, &mut res);
+    for library in crate::cel::library::missing_cel_libraries(&tree.cel_libraries) {
+        res.diagnostics.push(TreeDiagnostic {
+            node_index: 0,
+            location: TreeLocation::Node,
+            severity: DiagnosticSeverity::Error,
+            message: format!("CEL library {library} is not loaded"),
+        });
+    }
+    for (node_index, node) in tree.nodes.iter().enumerate() {
+        let children = match node {
+            BindingBoxTreeNode::Box(_, children) => children.clone(),
+            BindingBoxTreeNode::OR(a, b) | BindingBoxTreeNode::AND(a, b) => vec![*a, *b],
+            BindingBoxTreeNode::NOT(a) => vec![*a],
+        };
+        let missing = children
+            .iter()
+            .filter(|c| **c >= tree.nodes.len())
+            .collect_vec();
+        for c in &missing {
+            res.diagnostics.push(TreeDiagnostic {
+                node_index,
+                location: TreeLocation::Node,
+                severity: DiagnosticSeverity::Error,
+                message: format!("Child node {c} does not exist"),
+            });
+        }
+        if let (BindingBoxTreeNode::Box(bbox, _), true) = (node, missing.is_empty()) {
+            NodeValidator::new(tree, node_index, &children, &mut res).validate(bbox);
+        }
+    }
+    res
+}
+
+fn check_parameters(tree: &BindingBoxTree, values: &ParameterValues, res: &mut TreeValidation) {
+    let mut report = |node_index, location, message| {
+        res.diagnostics.push(TreeDiagnostic {
+            node_index,
+            location,
+            severity: DiagnosticSeverity::Error,
+            message,
+        })
+    };
+    for name in values.keys().sorted() {
+        if !tree.parameters.iter().any(|p| &p.name == name) {
+            report(
+                0,
+                TreeLocation::Node,
+                format!("There is no parameter `{name}`"),
+            );
+        }
+    }
+    // Names CEL expressions already use for something else
+    let mut taken: HashMap<String, usize> = HashMap::new();
+    for (node_index, node) in tree.nodes.iter().enumerate() {
+        if let BindingBoxTreeNode::Box(bbox, children) = node {
+            for label in &bbox.labels {
+                taken.entry(label.label.clone()).or_insert(node_index);
+            }
+            for child in children {
+                taken
+                    .entry(tree.edge_name(node_index, *child))
+                    .or_insert(node_index);
+            }
+        }
+    }
+    let mut seen = HashSet::new();
+    for parameter in &tree.parameters {
+        let name = &parameter.name;
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier || name == "now" || string_to_var(name).is_some() {
+            report(
+                0,
+                TreeLocation::Node,
+                format!("`{name}` cannot be the name of a parameter"),
+            );
+        }
+        if !seen.insert(name) {
+            report(
+                0,
+                TreeLocation::Node,
+                format!("Parameter `{name}` is declared more than once"),
+            );
+        }
+        if let Some(node_index) = taken.get(name) {
+            report(
+                *node_index,
+                TreeLocation::Node,
+                format!("Parameter `{name}` has the name of a label or child"),
+            );
+        }
+        if let Err(e) = parameter.value(values) {
+            report(0, TreeLocation::Node, e);
+        }
+        for u in &parameter.uses {
+            if let Err(e) = parameter.check_use(tree, u) {
+                report(
+                    u.node_index,
+                    u.location,
+                    format!("Parameter `{name}` ({:?}): {e}", parameter.parameter_type),
+                );
+            }
+        }
+    }
+}
+
+struct NodeValidator<'a> {
+    tree: &'a BindingBoxTree,
+    node_index: usize,
+    in_scope: HashSet<Variable>,
+    /// Child node of each edge name
+    children: HashMap<String, usize>,
+    label_names: HashSet<String>,
+    res: &'a mut TreeValidation,
+}
+
+impl<'a> NodeValidator<'a> {
+    fn new(
+        tree: &'a BindingBoxTree,
+        node_index: usize,
+        children: &[usize],
+        res: &'a mut TreeValidation,
+    ) -> Self {
+        Self {
+            tree,
+            node_index,
+            in_scope: variables_in_scope(tree, node_index),
+            children: children
+                .iter()
+                .map(|c| (tree.edge_name(node_index, *c), *c))
+                .collect(),
+            label_names: HashSet::new(),
+            res,
+        }
+    }
+
+    fn validate(mut self, bbox: &BindingBox) {
+        self.label_names = bbox.labels.iter().map(|l| l.label.clone()).collect();
+
+        // Filters are checked while binding, before there are child results or labels.
+        let mut env: HashMap<String, CelType> = self
+            .in_scope
+            .iter()
+            .map(|v| (var_name(v), CelType::String))
+            .collect();
+        env.insert("now".to_string(), CelType::Timestamp);
+        for parameter in &self.tree.parameters {
+            env.insert(parameter.name.clone(), parameter.parameter_type.cel_type());
+        }
+        for (index, filter) in bbox.filters.iter().enumerate() {
+            self.check_filter(TreeLocation::Filter { index }, filter, &env);
+        }
+
+        let constraint_filter_env_base = env.clone();
+        env.extend(self.children.keys().map(|c| (c.clone(), CelType::List)));
+        let mut label_types = HashMap::new();
+        for (index, label) in bbox.labels.iter().enumerate() {
+            let location = TreeLocation::Label { index };
+            if label_types.contains_key(&label.label) {
+                self.report(
+                    location,
+                    DiagnosticSeverity::Warning,
+                    format!(
+                        "Label `{}` is computed more than once; the last value is used",
+                        label.label
+                    ),
+                );
+            }
+            let label_type = self.check_label(location, label, &env);
+            env.insert(label.label.clone(), label_type);
+            label_types.insert(label.label.clone(), label_type);
+        }
+
+        for (index, size_filter) in bbox.size_filters.iter().enumerate() {
+            self.check_size_filter(TreeLocation::SizeFilter { index }, size_filter, &env);
+        }
+
+        // Filter constraints see the labels, but not the child results.
+        let mut constraint_filter_env = constraint_filter_env_base;
+        constraint_filter_env.extend(label_types);
+        for (index, constraint) in bbox.constraints.iter().enumerate() {
+            let location = TreeLocation::Constraint { index };
+            match constraint {
+                Constraint::Filter { filter } => {
+                    self.check_filter(location, filter, &constraint_filter_env)
+                }
+                Constraint::SizeFilter { filter } => self.check_size_filter(location, filter, &env),
+                Constraint::SAT { child_names }
+                | Constraint::ANY { child_names }
+                | Constraint::NOT { child_names }
+                | Constraint::OR { child_names }
+                | Constraint::AND { child_names } => {
+                    for child_name in child_names {
+                        self.check_child(location, child_name);
+                    }
+                }
+            }
+        }
+    }
+
+    fn report(&mut self, location: TreeLocation, severity: DiagnosticSeverity, message: String) {
+        self.res.diagnostics.push(TreeDiagnostic {
+            node_index: self.node_index,
+            location,
+            severity,
+            message,
+        });
+    }
+
+    fn check_variables(
+        &mut self,
+        location: TreeLocation,
+        vars: impl IntoIterator<Item = Variable>,
+    ) {
+        for var in vars.into_iter().sorted() {
+            if !self.in_scope.contains(&var) {
+                self.report(
+                    location,
+                    DiagnosticSeverity::Error,
+                    format!(
+                        "Variable {} is not bound in this node or one of its ancestors",
+                        var_name(&var)
+                    ),
+                );
+            }
+        }
+    }
+
+    /// The child node named `child_name`, reporting an error if there is none.
+    fn check_child(&mut self, location: TreeLocation, child_name: &str) -> Option<usize> {
+        let child = self.children.get(child_name).copied();
+        if child.is_none() {
+            self.report(
+                location,
+                DiagnosticSeverity::Error,
+                format!("There is no child named `{child_name}`"),
+            );
+        }
+        child
+    }
+
+    fn check_child_variable(&mut self, location: TreeLocation, child_name: &str, var: &Variable) {
+        if let Some(child) = self.check_child(location, child_name) {
+            if !variables_in_scope(self.tree, child).contains(var) {
+                self.report(
+                    location,
+                    DiagnosticSeverity::Error,
+                    format!(
+                        "Variable {} is not bound in child `{child_name}`",
+                        var_name(var)
+                    ),
+                );
+            }
+        }
+    }
+
+    fn check_filter(
+        &mut self,
+        location: TreeLocation,
+        filter: &Filter,
+        env: &HashMap<String, CelType>,
+    ) {
+        match filter {
+            Filter::BasicFilterCEL { cel } => {
+                self.check_cel(location, cel, env, true);
+            }
+            _ => self.check_variables(location, filter.get_involved_variables()),
+        }
+    }
+
+    fn check_size_filter(
+        &mut self,
+        location: TreeLocation,
+        size_filter: &SizeFilter,
+        env: &HashMap<String, CelType>,
+    ) {
+        match size_filter {
+            SizeFilter::NumChilds { child_name, .. }
+            | SizeFilter::NumSatisfiedChilds { child_name, .. } => {
+                self.check_child(location, child_name);
+            }
+            SizeFilter::BindingSetEqual { child_names } => {
+                for child_name in child_names {
+                    self.check_child(location, child_name);
+                }
+            }
+            SizeFilter::BindingSetProjectionEqual {
+                child_name_with_var_name,
+            } => {
+                for (child_name, var) in child_name_with_var_name {
+                    self.check_child_variable(location, child_name, var);
+                }
+            }
+            SizeFilter::NumChildsProj {
+                child_name,
+                var_name,
+                ..
+            } => self.check_child_variable(location, child_name, var_name),
+            SizeFilter::AdvancedCEL { cel } => {
+                self.check_cel(location, cel, env, true);
+            }
+            SizeFilter::Aggregate { aggregate, .. } => self.check_aggregate(location, aggregate),
+        }
+    }
+
+    fn check_label(
+        &mut self,
+        location: TreeLocation,
+        label: &LabelFunction,
+        env: &HashMap<String, CelType>,
+    ) -> CelType {
+        let Some(aggregate) = &label.aggregate else {
+            return self.check_cel(location, &label.cel, env, false);
+        };
+        self.check_aggregate(location, aggregate);
+        match aggregate.function {
+            AggregateFunction::Count | AggregateFunction::DistinctCount => CelType::Int,
+            AggregateFunction::Avg => CelType::Float,
+            AggregateFunction::Sum | AggregateFunction::Min | AggregateFunction::Max => {
+                CelType::Unknown
+            }
+        }
+    }
+
+    fn check_aggregate(&mut self, location: TreeLocation, aggregate: &ChildAggregate) {
+        let Some(child) = self.check_child(location, &aggregate.child_name) else {
+            return;
+        };
+        let child_name = &aggregate.child_name;
+        match &aggregate.value {
+            None if aggregate.function != AggregateFunction::Count => self.report(
+                location,
+                DiagnosticSeverity::Error,
+                format!(
+                    "Aggregate {:?} over child `{child_name}` needs a value",
+                    aggregate.function
+                ),
+            ),
+            None => {}
+            Some(AggregateValue::EventAttribute { event, .. }) => {
+                self.check_child_variable(location, child_name, &Variable::Event(*event))
+            }
+            Some(AggregateValue::ObjectAttribute { object, .. }) => {
+                self.check_child_variable(location, child_name, &Variable::Object(*object))
+            }
+            Some(AggregateValue::Variable { variable }) => {
+                self.check_child_variable(location, child_name, variable)
+            }
+            Some(AggregateValue::Label { label }) => {
+                if let BindingBoxTreeNode::Box(child_box, _) = &self.tree.nodes[child] {
+                    if !child_box.labels.iter().any(|l| &l.label == label) {
+                        self.report(
+                            location,
+                            DiagnosticSeverity::Error,
+                            format!("Child `{child_name}` has no label `{label}`"),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Compile `cel`, check the identifiers it uses against `env` and record its inferred result
+    /// type, which is returned.
+    fn check_cel(
+        &mut self,
+        location: TreeLocation,
+        cel: &str,
+        env: &HashMap<String, CelType>,
+        expect_bool: bool,
+    ) -> CelType {
+        let identifiers = match get_identifiers_in_cel_program(cel) {
+            Ok(identifiers) => identifiers,
+            Err(e) => {
+                self.report(location, DiagnosticSeverity::Error, e);
+                return CelType::Unknown;
+            }
+        };
+        let vars = get_vars_in_cel_program(cel);
+        let var_names: HashSet<String> = vars.iter().map(var_name).collect();
+        self.check_variables(location, vars);
+
+        let macro_vars = macro_variables(cel);
+        for identifier in identifiers.iter().sorted() {
+            if env.contains_key(identifier)
+                || var_names.contains(identifier)
+                || macro_vars.contains(identifier)
+            {
+                continue;
+            }
+            let message = if self.label_names.contains(identifier) {
+                format!("Label `{identifier}` is not computed yet where this is evaluated")
+            } else if self.children.contains_key(identifier) {
+                format!(
+                    "The bindings of child `{identifier}` are not available where this is evaluated"
+                )
+            } else {
+                format!("Unknown identifier `{identifier}`")
+            };
+            self.report(location, DiagnosticSeverity::Error, message);
+        }
+
+        let result_type = infer_cel_type(cel, env);
+        if expect_bool && !matches!(result_type, CelType::Bool | CelType::Unknown) {
+            self.report(
+                location,
+                DiagnosticSeverity::Error,
+                format!("Expected a bool, but this evaluates to {result_type:?}"),
+            );
+        }
+        self.res.cel_types.push(CelResultType {
+            node_index: self.node_index,
+            location,
+            cel: cel.to_string(),
+            result_type,
+        });
+        result_type
+    }
+}
+
+fn variables_in_scope(tree: &BindingBoxTree, node_index: usize) -> HashSet<Variable> {
+    let (ev_vars, ob_vars) = tree.variables_in_scope(node_index);
+    ev_vars
+        .into_keys()
+        .map(Variable::Event)
+        .chain(ob_vars.into_keys().map(Variable::Object))
+        .collect()
+}
+
+fn var_name(var: &Variable) -> String {
+    match var {
+        Variable::Event(ev) => ev_var_to_name(ev),
+        Variable::Object(ob) => ob_var_to_name(ob),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binding_box::test_utils::{
+        ev_vars, o2e, ob_vars, order_payment_shipping_tree, tree,
+    };
+
+    #[test]
+    fn validation_reports_unavailable_identifiers_unknown_children_and_non_bool_filters() {
+        let cel = |cel: &str| Filter::BasicFilterCEL {
+            cel: cel.to_string(),
+        };
+        let label = |label: &str, cel: &str| LabelFunction {
+            label: label.to_string(),
+            cel: cel.to_string(),
+            aggregate: None,
+        };
+        let t = tree(
+            vec![
+                BindingBoxTreeNode::Box(
+                    BindingBox {
+                        new_object_vars: ob_vars(&[(0, "order")]),
+                        // e1 is only bound in the child
+                        filters: vec![cel("o1.attr('status') == 'open' && e1.time() < now")],
+                        labels: vec![
+                            label("n", "size(A) + later"),
+                            label("later", "A.exists(x, x.satisfied)"),
+                        ],
+                        size_filters: vec![SizeFilter::AdvancedCEL {
+                            cel: "size(B)".to_string(),
+                        }],
+                        constraints: vec![
+                            Constraint::Filter {
+                                filter: cel("later && size(A) > 0"),
+                            },
+                            Constraint::SAT {
+                                child_names: vec!["C".to_string()],
+                            },
+                        ],
+                        ..Default::default()
+                    },
+                    vec![1],
+                ),
+                BindingBoxTreeNode::Box(
+                    BindingBox {
+                        new_event_vars: ev_vars(&[(0, "pay")]),
+                        filters: vec![o2e(0, 0, None), cel("e1.attr('amount') >")],
+                        ..Default::default()
+                    },
+                    vec![],
+                ),
+            ],
+            &[((0, 1), "A")],
+        );
+        let validation = validate_tree(&t);
+        assert!(validation.has_errors());
+        let diagnostics = validation
+            .diagnostics
+            .iter()
+            .map(|d| (d.node_index, d.location, d.message.as_str()))
+            .collect_vec();
+        assert_eq!(
+            diagnostics[..6],
+            [
+                (
+                    0,
+                    TreeLocation::Filter { index: 0 },
+                    "Variable e1 is not bound in this node or one of its ancestors"
+                ),
+                (
+                    0,
+                    TreeLocation::Label { index: 0 },
+                    "Label `later` is not computed yet where this is evaluated"
+                ),
+                (
+                    0,
+                    TreeLocation::SizeFilter { index: 0 },
+                    "Unknown identifier `B`"
+                ),
+                (
+                    0,
+                    TreeLocation::SizeFilter { index: 0 },
+                    "Expected a bool, but this evaluates to Int"
+                ),
+                (
+                    0,
+                    TreeLocation::Constraint { index: 0 },
+                    "The bindings of child `A` are not available where this is evaluated"
+                ),
+                (
+                    0,
+                    TreeLocation::Constraint { index: 1 },
+                    "There is no child named `C`"
+                ),
+            ]
+        );
+        assert_eq!(diagnostics.len(), 7);
+        assert_eq!(diagnostics[6].0, 1);
+        assert_eq!(diagnostics[6].1, TreeLocation::Filter { index: 1 });
+        assert!(diagnostics[6].2.starts_with("Failed to compile CEL"));
+
+        let label_type = |index: usize| {
+            validation
+                .cel_types
+                .iter()
+                .find(|c| c.node_index == 0 && c.location == TreeLocation::Label { index })
+                .map(|c| c.result_type)
+        };
+        assert_eq!(label_type(1), Some(CelType::Bool));
+
+        assert_eq!(
+            validate_tree(&order_payment_shipping_tree()).diagnostics,
+            vec![]
+        );
+    }
+}
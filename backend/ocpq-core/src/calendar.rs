@@ -0,0 +1,467 @@
+//! Business calendars (working days, working hours and holidays) for SLA-style time constraints,
+//! like "within 2 business days".
+//!
+//! Calendars are registered under a name and then referenced by it, from CEL functions like
+//! `businessDuration(t1, t2, 'office')` and from [`Filter::TimeBetweenEvents`](crate::binding_box::structs::Filter).
+//! The calendar [`DEFAULT_CALENDAR`] (Monday to Friday, 9:00 to 17:00 UTC, no holidays) is always
+//! registered.
+//!
+//! Time zones are fixed UTC offsets (like `+01:00`); daylight saving time is not modelled.
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate};
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+pub const DEFAULT_CALENDAR: &str = "default";
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// A business calendar as it is written down (see [`BusinessCalendar`])
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CalendarSpec {
+    /// Fixed UTC offset the working hours and days are in, like `+01:00` (or `UTC`)
+    pub timezone: String,
+    /// ISO weekday numbers (1 is Monday, 7 is Sunday)
+    pub working_days: Vec<u32>,
+    pub working_hours: Vec<WorkingHours>,
+    pub holidays: Vec<NaiveDate>,
+    /// `.ics` or `.json` (a list of dates) files with more holidays, relative to the calendar
+    /// file; only read by [`load_calendar_file`]
+    #[ts(optional)]
+    #[ts(as = "Option<Vec<String>>")]
+    pub holiday_files: Vec<String>,
+}
+
+impl Default for CalendarSpec {
+    fn default() -> Self {
+        Self {
+            timezone: "UTC".to_string(),
+            working_days: vec![1, 2, 3, 4, 5],
+            working_hours: vec![WorkingHours {
+                start: "09:00".to_string(),
+                end: "17:00".to_string(),
+            }],
+            holidays: Vec::new(),
+            holiday_files: Vec::new(),
+        }
+    }
+}
+
+/// Working hours of a day, like `09:00` to `17:00` (`24:00` ends at midnight)
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WorkingHours {
+    pub start: String,
+    pub end: String,
+}
+
+/// A validated [`CalendarSpec`]
+#[derive(Debug, Clone)]
+pub struct BusinessCalendar {
+    offset: FixedOffset,
+    /// Indexed by the number of days from Monday
+    working_days: [bool; 7],
+    /// Sorted, non-overlapping (start, end) seconds of the day
+    working_hours: Vec<(u32, u32)>,
+    holidays: HashSet<NaiveDate>,
+}
+
+impl Default for BusinessCalendar {
+    fn default() -> Self {
+        Self::from_spec(&CalendarSpec::default()).expect("default calendar is valid")
+    }
+}
+
+impl BusinessCalendar {
+    pub fn from_spec(spec: &CalendarSpec) -> Result<Self, String> {
+        let mut working_days = [false; 7];
+        for day in &spec.working_days {
+            match day {
+                1..=7 => working_days[*day as usize - 1] = true,
+                _ => return Err(format!("Invalid weekday {day} (1 is Monday, 7 is Sunday)")),
+            }
+        }
+        if !working_days.contains(&true) {
+            return Err("A calendar needs at least one working day".to_string());
+        }
+        let mut working_hours = spec
+            .working_hours
+            .iter()
+            .map(|h| {
+                let (start, end) = (parse_time_of_day(&h.start)?, parse_time_of_day(&h.end)?);
+                if start >= end {
+                    return Err(format!(
+                        "Working hours {} to {} do not end after they start",
+                        h.start, h.end
+                    ));
+                }
+                Ok((start, end))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        working_hours.sort_unstable();
+        if working_hours.windows(2).any(|w| w[0].1 > w[1].0) {
+            return Err("Working hours overlap".to_string());
+        }
+        Ok(Self {
+            offset: parse_offset(&spec.timezone)?,
+            working_days,
+            working_hours,
+            holidays: spec.holidays.iter().copied().collect(),
+        })
+    }
+
+    pub fn offset(&self) -> FixedOffset {
+        self.offset
+    }
+
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        self.working_days[date.weekday().num_days_from_monday() as usize]
+            && !self.holidays.contains(&date)
+    }
+
+    /// The working time between `from` and `to`, negative if `to` is before `from`.
+    pub fn business_duration(
+        &self,
+        from: DateTime<FixedOffset>,
+        to: DateTime<FixedOffset>,
+    ) -> Duration {
+        if to < from {
+            return -self.business_duration(to, from);
+        }
+        let mut total = Duration::zero();
+        let last = to.with_timezone(&self.offset).date_naive();
+        let mut date = from.with_timezone(&self.offset).date_naive();
+        while date <= last {
+            if self.is_business_day(date) {
+                for (start, end) in &self.working_hours {
+                    let start = self.at(date, *start).max(from);
+                    let end = self.at(date, *end).min(to);
+                    if end > start {
+                        total += end - start;
+                    }
+                }
+            }
+            let Some(next) = date.succ_opt() else { break };
+            date = next;
+        }
+        total
+    }
+
+    /// `t` moved by `days` business days (backwards if negative), at the same time of day.
+    pub fn add_business_days(&self, t: DateTime<FixedOffset>, days: i64) -> DateTime<FixedOffset> {
+        let local = t.with_timezone(&self.offset);
+        let mut date = local.date_naive();
+        let mut remaining = days.unsigned_abs();
+        while remaining > 0 {
+            let next = if days > 0 {
+                date.succ_opt()
+            } else {
+                date.pred_opt()
+            };
+            let Some(next) = next else { break };
+            date = next;
+            if self.is_business_day(date) {
+                remaining -= 1;
+            }
+        }
+        local + (date - local.date_naive())
+    }
+
+    /// Start of the second `seconds` of `date` (in the calendar's time zone).
+    fn at(&self, date: NaiveDate, seconds: u32) -> DateTime<FixedOffset> {
+        start_of_date(date, self.offset) + Duration::seconds(seconds.into())
+    }
+}
+
+fn start_of_date(date: NaiveDate, offset: FixedOffset) -> DateTime<FixedOffset> {
+    let utc = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time")
+        - Duration::seconds(offset.local_minus_utc().into());
+    utc.and_utc().with_timezone(&offset)
+}
+
+/// Midnight at the start of the day of `t` in the time zone `offset`.
+pub fn start_of_day(t: DateTime<FixedOffset>, offset: FixedOffset) -> DateTime<FixedOffset> {
+    start_of_date(t.with_timezone(&offset).date_naive(), offset)
+}
+
+/// The ISO weekday number (1 is Monday, 7 is Sunday) of `t` in the time zone `offset`.
+pub fn weekday(t: DateTime<FixedOffset>, offset: FixedOffset) -> u32 {
+    t.with_timezone(&offset).weekday().number_from_monday()
+}
+
+/// Seconds since midnight of a time of day like `09:30`, `09:30:15` or `24:00`.
+fn parse_time_of_day(s: &str) -> Result<u32, String> {
+    let parts = s
+        .trim()
+        .split(':')
+        .map(|p| p.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>()
+        .filter(|p| (2..=3).contains(&p.len()) && p[1] < 60 && p.get(2).is_none_or(|s| *s < 60))
+        .ok_or_else(|| format!("Invalid time of day {s} (expected HH:MM)"))?;
+    let seconds = parts[0] * 3600 + parts[1] * 60 + parts.get(2).copied().unwrap_or_default();
+    if seconds > SECONDS_PER_DAY {
+        return Err(format!("Invalid time of day {s} (at most 24:00)"));
+    }
+    Ok(seconds)
+}
+
+/// A fixed UTC offset like `UTC`, `Z`, `+01:00`, `-0530` or `+02`.
+pub fn parse_offset(s: &str) -> Result<FixedOffset, String> {
+    let s = s.trim();
+    if s.is_empty() || s.eq_ignore_ascii_case("utc") || s == "Z" {
+        return Ok(FixedOffset::east_opt(0).expect("zero offset is valid"));
+    }
+    let invalid = || format!("Invalid time zone {s} (expected a UTC offset like +01:00)");
+    let (sign, rest) = match s.as_bytes().first() {
+        Some(b'+') => (1, &s[1..]),
+        Some(b'-') => (-1, &s[1..]),
+        _ => return Err(invalid()),
+    };
+    let digits = rest.replace(':', "");
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok(), Some(0)),
+        4 => (
+            digits[..2].parse::<i32>().ok(),
+            digits[2..].parse::<i32>().ok(),
+        ),
+        _ => (None, None),
+    };
+    let (Some(hours), Some(minutes)) = (hours, minutes) else {
+        return Err(invalid());
+    };
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+/// The (all-day) holidays of the events in an iCalendar file: every date from an event's `DTSTART`
+/// up to its `DTEND` (exclusive, as in iCalendar).
+pub fn holidays_from_ics(ics: &str) -> Result<Vec<NaiveDate>, String> {
+    // Long lines are folded onto continuation lines starting with a space or tab.
+    let unfolded = ics
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    let mut holidays = Vec::new();
+    let mut start: Option<NaiveDate> = None;
+    let mut end: Option<NaiveDate> = None;
+    for line in unfolded.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let property = name.split(';').next().unwrap_or_default();
+        match property.to_ascii_uppercase().as_str() {
+            "BEGIN" if value.eq_ignore_ascii_case("VEVENT") => (start, end) = (None, None),
+            "DTSTART" => start = Some(parse_ics_date(value)?),
+            "DTEND" => end = Some(parse_ics_date(value)?),
+            "END" if value.eq_ignore_ascii_case("VEVENT") => {
+                let first = start.ok_or("Event without DTSTART in iCalendar file")?;
+                let end = end.unwrap_or(first + Duration::days(1));
+                holidays.extend(first.iter_days().take_while(|d| *d < end));
+                // An event that ends on the day it starts still is a holiday.
+                if end <= first {
+                    holidays.push(first);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(holidays)
+}
+
+/// The date of an iCalendar `DATE` (`20241225`) or `DATE-TIME` (`20241225T000000Z`) value.
+fn parse_ics_date(value: &str) -> Result<NaiveDate, String> {
+    let date = value.trim().get(..8).unwrap_or_default();
+    NaiveDate::parse_from_str(date, "%Y%m%d")
+        .map_err(|e| format!("Invalid iCalendar date {value}: {e}"))
+}
+
+static CALENDARS: Lazy<RwLock<HashMap<String, Arc<BusinessCalendar>>>> = Lazy::new(|| {
+    RwLock::new(HashMap::from([(
+        DEFAULT_CALENDAR.to_string(),
+        Arc::new(BusinessCalendar::default()),
+    )]))
+});
+
+/// Bumped whenever a calendar is registered, so cached results computed with the calendars
+/// before are not reused.
+static CALENDAR_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn generation() -> u64 {
+    CALENDAR_GENERATION.load(Ordering::Acquire)
+}
+
+/// Register `spec` under `name`, replacing an earlier calendar of that name. Its `holiday_files`
+/// are not read (see [`load_calendar_file`]).
+pub fn register_calendar(name: &str, spec: &CalendarSpec) -> Result<(), String> {
+    let calendar =
+        BusinessCalendar::from_spec(spec).map_err(|e| format!("Calendar {name}: {e}"))?;
+    CALENDARS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_string(), Arc::new(calendar));
+    CALENDAR_GENERATION.fetch_add(1, Ordering::Release);
+    Ok(())
+}
+
+/// The calendar registered under `name`.
+pub fn get_calendar(name: &str) -> Result<Arc<BusinessCalendar>, String> {
+    CALENDARS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+        .ok_or_else(|| format!("Unknown calendar {name}"))
+}
+
+/// The names of all registered calendars, sorted.
+pub fn calendar_names() -> Vec<String> {
+    let mut names: Vec<String> = CALENDARS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .keys()
+        .cloned()
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+/// The time zone a CEL function argument names: a UTC offset (see [`parse_offset`]) or the name
+/// of a registered calendar, whose time zone is used.
+pub fn resolve_offset(tz: &str) -> Result<FixedOffset, String> {
+    match get_calendar(tz) {
+        Ok(calendar) => Ok(calendar.offset()),
+        Err(_) => parse_offset(tz),
+    }
+}
+
+/// Register the calendars of a JSON file mapping names to [`CalendarSpec`]s, with the holidays of
+/// their `holiday_files` added. Returns the names of the registered calendars.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_calendar_file(path: &std::path::Path) -> Result<Vec<String>, String> {
+    let read = |p: &std::path::Path| {
+        std::fs::read_to_string(p).map_err(|e| format!("read calendar file {p:?}: {e}"))
+    };
+    let specs: HashMap<String, CalendarSpec> = serde_json::from_str(&read(path)?)
+        .map_err(|e| format!("parse calendar file {path:?}: {e}"))?;
+    let dir = path.parent().unwrap_or(std::path::Path::new("."));
+    let mut names = Vec::with_capacity(specs.len());
+    for (name, mut spec) in specs {
+        for file in std::mem::take(&mut spec.holiday_files) {
+            let file = dir.join(file);
+            let content = read(&file)?;
+            let holidays = if file
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case("ics"))
+            {
+                holidays_from_ics(&content)?
+            } else {
+                serde_json::from_str::<Vec<NaiveDate>>(&content)
+                    .map_err(|e| format!("parse holiday file {file:?}: {e}"))?
+            };
+            spec.holidays.extend(holidays);
+        }
+        register_calendar(&name, &spec)?;
+        names.push(name);
+    }
+    names.sort_unstable();
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn t(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
+    }
+
+    fn office() -> BusinessCalendar {
+        BusinessCalendar::from_spec(&CalendarSpec {
+            timezone: "+01:00".to_string(),
+            holidays: vec![NaiveDate::from_ymd_opt(2024, 12, 25).unwrap()],
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn business_duration_counts_working_hours_of_business_days_only() {
+        let cal = office();
+        // Friday 16:00 to Monday 10:00 (local): one hour on Friday and one on Monday.
+        let d = cal.business_duration(
+            t("2024-12-13T16:00:00+01:00"),
+            t("2024-12-16T10:00:00+01:00"),
+        );
+        assert_eq!(d, Duration::hours(2));
+        // Measured in UTC, the same instants give the same duration.
+        let d = cal.business_duration(t("2024-12-13T15:00:00Z"), t("2024-12-16T09:00:00Z"));
+        assert_eq!(d, Duration::hours(2));
+        // Christmas (a Wednesday) is a holiday.
+        let d = cal.business_duration(
+            t("2024-12-24T00:00:00+01:00"),
+            t("2024-12-27T00:00:00+01:00"),
+        );
+        assert_eq!(d, Duration::hours(16));
+        assert_eq!(
+            cal.business_duration(
+                t("2024-12-16T10:00:00+01:00"),
+                t("2024-12-13T16:00:00+01:00")
+            ),
+            Duration::hours(-2)
+        );
+    }
+
+    #[test]
+    fn add_business_days_skips_weekends_and_holidays() {
+        let cal = office();
+        assert_eq!(
+            cal.add_business_days(t("2024-12-20T11:00:00+01:00"), 2),
+            t("2024-12-24T11:00:00+01:00")
+        );
+        assert_eq!(
+            cal.add_business_days(t("2024-12-24T11:00:00+01:00"), 1),
+            t("2024-12-26T11:00:00+01:00")
+        );
+        assert_eq!(
+            cal.add_business_days(t("2024-12-23T11:00:00+01:00"), -1),
+            t("2024-12-20T11:00:00+01:00")
+        );
+    }
+
+    #[test]
+    fn days_are_taken_in_the_given_time_zone() {
+        let late = t("2024-12-15T23:30:00Z");
+        assert_eq!(weekday(late, parse_offset("UTC").unwrap()), 7);
+        assert_eq!(weekday(late, parse_offset("+01:00").unwrap()), 1);
+        assert_eq!(
+            start_of_day(late, parse_offset("+0100").unwrap()),
+            t("2024-12-16T00:00:00+01:00")
+        );
+        assert!(parse_offset("Europe/Berlin").is_err());
+    }
+
+    #[test]
+    fn ics_events_become_holidays() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Christmas\r\nDTSTART;VALUE=DATE:20241225\r\nDTEND;VALUE=DATE:20241227\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART:20250101T000000Z\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        assert_eq!(
+            holidays_from_ics(ics).unwrap(),
+            [(2024, 12, 25), (2024, 12, 26), (2025, 1, 1)]
+                .map(|(y, m, d)| NaiveDate::from_ymd_opt(y, m, d).unwrap())
+        );
+    }
+}
@@ -0,0 +1,403 @@
+//! Libraries of named, parameterised CEL functions, so snippets that many queries repeat (like
+//! the total of an order) are written down once.
+//!
+//! A library is loaded from a JSON or YAML file like
+//!
+//! ```yaml
+//! name: sales
+//! functions:
+//!   - name: orderTotal
+//!     params: [order]
+//!     body: "order.attr('price') * order.attr('quantity')"
+//! ```
+//!
+//! and registered under its name. From then on, its functions can be called from every CEL
+//! expression, like `orderTotal(o1) > 100` or, with the first argument as target,
+//! `o1.orderTotal() > 100`. A tree lists the libraries it needs in
+//! [`BindingBoxTree::cel_libraries`](crate::binding_box::BindingBoxTree), so evaluating it fails
+//! clearly if one of them is not loaded.
+//!
+//! Function bodies are compiled once, when the library is registered. A body only sees its
+//! parameters and `now`, but can call the built-in functions and those of every library. Calls
+//! must not be recursive.
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use cel_interpreter::{extractors::Arguments, Context, FunctionContext, Program, ResolveResult};
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::syntax::{function_result_type, macro_variables};
+
+/// A named set of CEL functions (see the module documentation)
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CelLibrary {
+    pub name: String,
+    #[serde(default)]
+    pub functions: Vec<CelFunctionDef>,
+}
+
+/// A CEL function of a [`CelLibrary`]: `body`, evaluated with `params` bound to the arguments
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CelFunctionDef {
+    pub name: String,
+    #[serde(default)]
+    pub params: Vec<String>,
+    pub body: String,
+    #[serde(default)]
+    #[ts(optional)]
+    pub description: Option<String>,
+}
+
+struct CompiledFunction {
+    def: CelFunctionDef,
+    program: Program,
+}
+
+struct RegisteredLibrary {
+    library: CelLibrary,
+    functions: Vec<Arc<CompiledFunction>>,
+}
+
+/// Built-in functions whose result type is not known to [`function_result_type`]
+const UNTYPED_BUILTINS: [&str; 7] = [
+    "min",
+    "max",
+    "attr",
+    "attrAt",
+    "regexFind",
+    "median",
+    "percentile",
+];
+
+static CEL_LIBRARIES: Lazy<RwLock<HashMap<String, RegisteredLibrary>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Bumped whenever a library is registered, so the CEL contexts built before know to add its
+/// functions.
+static LIBRARY_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn generation() -> u64 {
+    LIBRARY_GENERATION.load(Ordering::Acquire)
+}
+
+/// Parse a library from JSON (if `content` starts with `{`) or YAML.
+pub fn parse_cel_library(content: &str) -> Result<CelLibrary, String> {
+    if content.trim_start().starts_with('{') {
+        serde_json::from_str(content).map_err(|e| format!("parse CEL library JSON: {e}"))
+    } else {
+        serde_yaml::from_str(content).map_err(|e| format!("parse CEL library YAML: {e}"))
+    }
+}
+
+/// Register `library`, replacing an earlier library of the same name.
+pub fn register_cel_library(library: CelLibrary) -> Result<(), String> {
+    if library.name.is_empty() {
+        return Err("A CEL library needs a name".to_string());
+    }
+    let mut libraries = CEL_LIBRARIES.write().unwrap_or_else(|e| e.into_inner());
+    let functions = library
+        .functions
+        .iter()
+        .map(|def| compile_function(&library.name, def))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut defined_by: HashMap<&str, &str> = HashMap::new();
+    for (name, lib) in libraries.iter().filter(|(name, _)| **name != library.name) {
+        for f in &lib.functions {
+            defined_by.insert(f.def.name.as_str(), name.as_str());
+        }
+    }
+    for f in &functions {
+        let name = f.def.name.as_str();
+        if let Some(other) = defined_by.insert(name, library.name.as_str()) {
+            return Err(if other == library.name {
+                format!(
+                    "CEL library {}: function {name} is defined twice",
+                    library.name
+                )
+            } else {
+                format!(
+                    "CEL library {}: function {name} is already defined by library {other}",
+                    library.name
+                )
+            });
+        }
+    }
+
+    // Calls between library functions, of all libraries as they would be after registering
+    let calls: HashMap<&str, Vec<String>> = libraries
+        .iter()
+        .filter(|(name, _)| **name != library.name)
+        .flat_map(|(_, lib)| &lib.functions)
+        .chain(&functions)
+        .map(|f| {
+            let called = f.program.references().functions();
+            (
+                f.def.name.as_str(),
+                called.into_iter().map(str::to_string).collect_vec(),
+            )
+        })
+        .collect();
+    if let Some(cycle) = find_cycle(&calls) {
+        return Err(format!(
+            "CEL library {}: recursive calls {}",
+            library.name,
+            cycle.join(" -> ")
+        ));
+    }
+
+    let name = library.name.clone();
+    libraries.insert(name, RegisteredLibrary { library, functions });
+    LIBRARY_GENERATION.fetch_add(1, Ordering::Release);
+    Ok(())
+}
+
+/// All registered libraries, sorted by name.
+pub fn cel_libraries() -> Vec<CelLibrary> {
+    CEL_LIBRARIES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .values()
+        .map(|lib| lib.library.clone())
+        .sorted_by(|a, b| a.name.cmp(&b.name))
+        .collect()
+}
+
+/// Those of `names` that are not registered.
+pub fn missing_cel_libraries<'a>(names: &'a [String]) -> Vec<&'a str> {
+    let libraries = CEL_LIBRARIES.read().unwrap_or_else(|e| e.into_inner());
+    names
+        .iter()
+        .filter(|name| !libraries.contains_key(*name))
+        .map(String::as_str)
+        .collect()
+}
+
+/// Register the library of a JSON or YAML file. Returns its name.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_cel_library_file(path: &std::path::Path) -> Result<String, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("read CEL library {path:?}: {e}"))?;
+    let library = parse_cel_library(&content)?;
+    let name = library.name.clone();
+    register_cel_library(library)?;
+    Ok(name)
+}
+
+/// Add the functions of all registered libraries to `context`.
+pub(crate) fn add_library_functions(context: &mut Context<'static>) {
+    let libraries = CEL_LIBRARIES.read().unwrap_or_else(|e| e.into_inner());
+    for f in libraries.values().flat_map(|lib| &lib.functions) {
+        let f = Arc::clone(f);
+        let name = f.def.name.clone();
+        context.add_function(
+            name.as_str(),
+            move |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+                call_function(ftx, &f, &args)
+            },
+        );
+    }
+}
+
+fn call_function(
+    ftx: &FunctionContext,
+    f: &CompiledFunction,
+    args: &[cel_interpreter::Value],
+) -> ResolveResult {
+    // `o1.orderTotal()` is `orderTotal(o1)`
+    let args = ftx.this.iter().chain(args).collect_vec();
+    if args.len() != f.def.params.len() {
+        return Err(ftx.error(format!(
+            "{} takes {} arguments, got {}",
+            f.def.name,
+            f.def.params.len(),
+            args.len()
+        )));
+    }
+    let mut scope = ftx.ptx.new_inner_scope();
+    for (param, value) in f.def.params.iter().zip(args) {
+        scope.add_variable_from_value(param.clone(), value.clone());
+    }
+    f.program.execute(&scope)
+}
+
+fn compile_function(library: &str, def: &CelFunctionDef) -> Result<Arc<CompiledFunction>, String> {
+    let err = |msg: String| format!("CEL library {library}, function {}: {msg}", def.name);
+    if !is_identifier(&def.name) {
+        return Err(err("the name is not a valid identifier".to_string()));
+    }
+    if function_result_type(&def.name).is_some() || UNTYPED_BUILTINS.contains(&def.name.as_str()) {
+        return Err(err("the name is taken by a built-in function".to_string()));
+    }
+    let mut params = HashSet::new();
+    for param in &def.params {
+        if !is_identifier(param) || param == "now" {
+            return Err(err(format!("{param} is not a valid parameter name")));
+        }
+        if !params.insert(param.as_str()) {
+            return Err(err(format!("parameter {param} is listed twice")));
+        }
+    }
+    let program = Program::compile(&def.body).map_err(|e| err(format!("{e}")))?;
+    let macro_vars = macro_variables(&def.body);
+    let unknown = program
+        .references()
+        .variables()
+        .into_iter()
+        .filter(|v| *v != "now" && !params.contains(v) && !macro_vars.contains(*v))
+        .sorted()
+        .collect_vec();
+    if !unknown.is_empty() {
+        return Err(err(format!(
+            "the body uses {}, which is not a parameter",
+            unknown.join(", ")
+        )));
+    }
+    Ok(Arc::new(CompiledFunction {
+        def: def.clone(),
+        program,
+    }))
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A cycle of calls between the functions of `calls` (which lists the functions each of them
+/// calls), if there is one.
+fn find_cycle(calls: &HashMap<&str, Vec<String>>) -> Option<Vec<String>> {
+    fn visit<'a>(
+        f: &'a str,
+        calls: &'a HashMap<&str, Vec<String>>,
+        path: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> Option<Vec<String>> {
+        if let Some(pos) = path.iter().position(|p| *p == f) {
+            let mut cycle = path[pos..].iter().map(|p| p.to_string()).collect_vec();
+            cycle.push(f.to_string());
+            return Some(cycle);
+        }
+        if done.contains(f) {
+            return None;
+        }
+        path.push(f);
+        for called in calls.get(f).into_iter().flatten() {
+            if calls.contains_key(called.as_str()) {
+                if let Some(cycle) = visit(called, calls, path, done) {
+                    return Some(cycle);
+                }
+            }
+        }
+        path.pop();
+        done.insert(f);
+        None
+    }
+    let mut done = HashSet::new();
+    calls
+        .keys()
+        .sorted()
+        .find_map(|f| visit(f, calls, &mut Vec::new(), &mut done))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binding_box::{
+        evaluate_box_tree,
+        structs::{BindingBox, BindingBoxTreeNode, Filter},
+        test_utils::{bound_event_ids, ev_vars, eval, ocel, tree},
+    };
+
+    fn function(name: &str, params: &[&str], body: &str) -> CelFunctionDef {
+        CelFunctionDef {
+            name: name.to_string(),
+            params: params.iter().map(|p| p.to_string()).collect(),
+            body: body.to_string(),
+            description: None,
+        }
+    }
+
+    #[test]
+    fn libraries_parse_from_yaml_and_json() {
+        let yaml = "name: y\nfunctions:\n  - name: twice\n    params: [x]\n    body: x * 2\n";
+        let json = r#"{ "name": "j", "functions": [{ "name": "one", "body": "1" }] }"#;
+        let from_yaml = parse_cel_library(yaml).unwrap();
+        assert_eq!(from_yaml.functions[0].params, ["x"]);
+        assert_eq!(from_yaml.functions[0].body, "x * 2");
+        assert!(parse_cel_library(json).unwrap().functions[0]
+            .params
+            .is_empty());
+    }
+
+    #[test]
+    fn invalid_functions_are_rejected_with_the_reason() {
+        let register = |name: &str, functions: Vec<CelFunctionDef>| {
+            register_cel_library(CelLibrary {
+                name: name.to_string(),
+                functions,
+            })
+            .unwrap_err()
+        };
+        assert!(register("t1", vec![function("f", &["x"], "x + y")]).contains("uses y"));
+        assert!(register("t2", vec![function("size", &[], "1")]).contains("built-in"));
+        assert!(register("t3", vec![function("f", &["x", "x"], "x")]).contains("twice"));
+        assert!(register(
+            "t4",
+            vec![
+                function("ping", &["x"], "pong(x)"),
+                function("pong", &["x"], "ping(x)")
+            ]
+        )
+        .contains("recursive calls ping -> pong -> ping"));
+        assert_eq!(missing_cel_libraries(&["t1".to_string()]), ["t1"]);
+    }
+
+    #[test]
+    fn trees_call_functions_of_the_cel_libraries_they_list() {
+        let mut big_placements = tree(
+            vec![BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_event_vars: ev_vars(&[(0, "place")]),
+                    filters: vec![Filter::BasicFilterCEL {
+                        cel: "e1.isBigPlacement(50)".to_string(),
+                    }],
+                    ..Default::default()
+                },
+                vec![],
+            )],
+            &[],
+        );
+        big_placements.cel_libraries = vec!["eval-test-sales".to_string()];
+        let err = evaluate_box_tree(big_placements.clone(), &ocel(), false).unwrap_err();
+        assert!(
+            err.contains("CEL library eval-test-sales is not loaded"),
+            "{err}"
+        );
+
+        let library = parse_cel_library(
+            "name: eval-test-sales\nfunctions:\n  - name: isBigPlacement\n    params: [e, min]\n    body: e.attr('amount') >= min\n",
+        )
+        .unwrap();
+        register_cel_library(library).unwrap();
+        assert_eq!(bound_event_ids(&eval(big_placements), 0, 0), ["e1"]);
+    }
+}
@@ -0,0 +1,492 @@
+//! Rewriting simple CEL filters into the native [`Filter`]s they are equivalent to, so they can
+//! be checked without the CEL interpreter and bound through by the planner (see
+//! [`crate::binding_box::structs::BindingBoxTree::compute_step_cache`]), and translated by the
+//! database translators (see [`crate::db_translation`]), which cannot translate CEL.
+//!
+//! A filter is lowered conjunct by conjunct (at top-level `&&`s); a conjunct is lowered if it
+//! compares
+//!
+//! - an event attribute (`e1.attr('price')`) or the attribute of an object at the time of an
+//!   event (`o1.attrAt('status', e2.time())`) with a literal or a parameter of the tree, through `==`, `<`, `<=`, `>`, `>=`
+//!   or, for a list of strings, `in`,
+//! - the time of an event (`e1.time()`) with a `timestamp('...')` literal,
+//! - the times of two events (`e1.time() < e2.time()`), or
+//! - the time between two events (`e2.time() - e1.time()`) with a `duration('...')` literal.
+//!
+//! All other conjuncts stay CEL filters. `o1.attr(...)` is not lowered: it reads the first value
+//! the object had, which no [`ObjectValueFilterTimepoint`] stands for.
+//!
+//! Lowered filters accept the same bindings, except that a binding whose attribute is missing or
+//! of a type the literal cannot be compared with is filtered out, where CEL fails with an error.
+use cel_interpreter::Value;
+use cel_parser::{ArithmeticOp, Atom, Expression, RelationOp, UnaryOp};
+use chrono::{DateTime, Utc};
+
+use super::{
+    duration_seconds, evaluate_literal_call, string_to_var,
+    syntax::{split_top_level, strip_outer_parens},
+};
+use crate::binding_box::{
+    parameters::{ParameterType, ParameterValue, TreeParameter},
+    structs::{
+        EventVariable, Filter, ObjectValueFilterTimepoint, ObjectVariable, ValueFilter, Variable,
+    },
+};
+
+/// Integers up to this magnitude convert to `f64` exactly.
+const MAX_EXACT_FLOAT_INT: i64 = 1 << 53;
+/// [`Filter::TimeBetweenEvents`] measures in microseconds, so this is the smallest time between
+/// two events that is more than none.
+const TIME_RESOLUTION_SECONDS: f64 = 1e-6;
+
+/// A side of a comparison that lowering understands
+enum Operand {
+    EventAttr(EventVariable, String),
+    ObjectAttrAt(ObjectVariable, String, EventVariable),
+    EventTime(EventVariable),
+    /// The time from the first to the second event (`e2.time() - e1.time()`)
+    TimeBetween(EventVariable, EventVariable),
+    Literal(Literal),
+}
+
+enum Literal {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    Strings(Vec<String>),
+    Timestamp(DateTime<Utc>),
+    /// In seconds
+    Duration(f64),
+}
+
+/// `filters`, with each CEL filter replaced by the native filters of its conjuncts that can be
+/// lowered (see the module documentation) and CEL filters for the rest. `parameters` are those of
+/// the tree, whose defaults the CEL filters read (see [`crate::binding_box::parameters`]).
+pub fn lower_cel_filters(filters: &[Filter], parameters: &[TreeParameter]) -> Vec<Filter> {
+    filters
+        .iter()
+        .flat_map(|f| match f {
+            Filter::BasicFilterCEL { cel } => lower_cel_filter(cel, parameters),
+            f => vec![f.clone()],
+        })
+        .collect()
+}
+
+/// The filters the CEL filter `cel` is equivalent to; just that CEL filter if none of its
+/// conjuncts can be lowered.
+pub fn lower_cel_filter(cel: &str, parameters: &[TreeParameter]) -> Vec<Filter> {
+    let conjuncts = conjuncts(cel);
+    let lowered: Vec<Option<Filter>> = conjuncts
+        .iter()
+        .map(|c| lower_comparison(c, parameters))
+        .collect();
+    if lowered.iter().all(Option::is_none) {
+        return vec![Filter::BasicFilterCEL {
+            cel: cel.to_string(),
+        }];
+    }
+    lowered
+        .into_iter()
+        .zip(conjuncts)
+        .map(|(lowered, cel)| lowered.unwrap_or(Filter::BasicFilterCEL { cel }))
+        .collect()
+}
+
+/// The top-level conjuncts of `cel`, with nested conjunctions (`(a && b) && c`) flattened.
+fn conjuncts(cel: &str) -> Vec<String> {
+    let cel = strip_outer_parens(cel.trim());
+    // `||` and `?:` bind less tightly than `&&`, so `a && b || c` is no conjunction.
+    if split_top_level(cel, &["||", "?"]).len() > 1 {
+        return vec![cel.to_string()];
+    }
+    let parts = split_top_level(cel, &["&&"]);
+    if parts.len() == 1 {
+        return parts;
+    }
+    parts.iter().flat_map(|p| conjuncts(p)).collect()
+}
+
+fn lower_comparison(cel: &str, parameters: &[TreeParameter]) -> Option<Filter> {
+    let Expression::Relation(left, op, right) = cel_parser::parse(cel).ok()? else {
+        return None;
+    };
+    let (subject, op, other) = match (operand(&left, parameters)?, operand(&right, parameters)?) {
+        (Operand::Literal(_), Operand::Literal(_)) => return None,
+        (Operand::Literal(lit), subject) => (subject, flip(op)?, Operand::Literal(lit)),
+        (subject, other) => (subject, op, other),
+    };
+    match (subject, other) {
+        (Operand::EventAttr(event, attribute_name), Operand::Literal(lit)) => {
+            Some(Filter::EventAttributeValueFilter {
+                event,
+                attribute_name,
+                value_filter: value_filter(&op, lit)?,
+            })
+        }
+        (Operand::ObjectAttrAt(object, attribute_name, event), Operand::Literal(lit)) => {
+            Some(Filter::ObjectAttributeValueFilter {
+                object,
+                attribute_name,
+                at_time: ObjectValueFilterTimepoint::AtEvent { event },
+                value_filter: value_filter(&op, lit)?,
+            })
+        }
+        (Operand::EventTime(event), Operand::Literal(lit @ Literal::Timestamp(_))) => {
+            Some(Filter::EventAttributeValueFilter {
+                event,
+                attribute_name: "ocel:time".to_string(),
+                value_filter: value_filter(&op, lit)?,
+            })
+        }
+        (Operand::EventTime(first), Operand::EventTime(second)) => {
+            // `first OP second` as the time from one to the other
+            let (from_event, to_event, min_seconds, max_seconds) = match op {
+                RelationOp::LessThan => (first, second, Some(TIME_RESOLUTION_SECONDS), None),
+                RelationOp::LessThanEq => (first, second, Some(0.0), None),
+                RelationOp::GreaterThan => (second, first, Some(TIME_RESOLUTION_SECONDS), None),
+                RelationOp::GreaterThanEq => (second, first, Some(0.0), None),
+                RelationOp::Equals => (first, second, Some(0.0), Some(0.0)),
+                _ => return None,
+            };
+            Some(time_between(from_event, to_event, min_seconds, max_seconds))
+        }
+        (Operand::TimeBetween(from_event, to_event), Operand::Literal(Literal::Duration(secs))) => {
+            let (min_seconds, max_seconds) = match op {
+                RelationOp::LessThan => (None, Some(secs - TIME_RESOLUTION_SECONDS)),
+                RelationOp::LessThanEq => (None, Some(secs)),
+                RelationOp::GreaterThan => (Some(secs + TIME_RESOLUTION_SECONDS), None),
+                RelationOp::GreaterThanEq => (Some(secs), None),
+                RelationOp::Equals => (Some(secs), Some(secs)),
+                _ => return None,
+            };
+            Some(time_between(from_event, to_event, min_seconds, max_seconds))
+        }
+        _ => None,
+    }
+}
+
+fn time_between(
+    from_event: EventVariable,
+    to_event: EventVariable,
+    min_seconds: Option<f64>,
+    max_seconds: Option<f64>,
+) -> Filter {
+    Filter::TimeBetweenEvents {
+        from_event,
+        to_event,
+        min_seconds,
+        max_seconds,
+        calendar: None,
+    }
+}
+
+/// `op` with its sides swapped (`a < b` is `b > a`)
+fn flip(op: RelationOp) -> Option<RelationOp> {
+    Some(match op {
+        RelationOp::LessThan => RelationOp::GreaterThan,
+        RelationOp::LessThanEq => RelationOp::GreaterThanEq,
+        RelationOp::GreaterThan => RelationOp::LessThan,
+        RelationOp::GreaterThanEq => RelationOp::LessThanEq,
+        RelationOp::Equals => RelationOp::Equals,
+        // `'a' in l` has no flipped form, and `!=` no native one.
+        _ => return None,
+    })
+}
+
+/// The value filter that `value OP lit` holds for; `ValueFilter` bounds are inclusive, so strict
+/// comparisons use the next representable value.
+fn value_filter(op: &RelationOp, lit: Literal) -> Option<ValueFilter> {
+    let bounds = |op: &RelationOp| -> Option<(bool, bool, bool)> {
+        // (bounded below, bounded above, strict)
+        Some(match op {
+            RelationOp::LessThan => (false, true, true),
+            RelationOp::LessThanEq => (false, true, false),
+            RelationOp::GreaterThan => (true, false, true),
+            RelationOp::GreaterThanEq => (true, false, false),
+            RelationOp::Equals => (true, true, false),
+            _ => return None,
+        })
+    };
+    Some(match lit {
+        Literal::String(s) if matches!(op, RelationOp::Equals) => {
+            ValueFilter::String { is_in: vec![s] }
+        }
+        Literal::Strings(is_in) if matches!(op, RelationOp::In) => ValueFilter::String { is_in },
+        Literal::Bool(is_true) if matches!(op, RelationOp::Equals) => {
+            ValueFilter::Boolean { is_true }
+        }
+        Literal::Int(v) => match bounds(op)? {
+            (min, max, false) => ValueFilter::Integer {
+                min: min.then_some(v),
+                max: max.then_some(v),
+            },
+            // `v + 1` would not do for float attributes, so compare as floats.
+            (min, max, true) if v.abs() <= MAX_EXACT_FLOAT_INT => ValueFilter::Float {
+                min: min.then(|| next_float(v as f64, true)),
+                max: max.then(|| next_float(v as f64, false)),
+            },
+            _ => return None,
+        },
+        Literal::Float(v) if v.is_finite() => {
+            let (min, max, strict) = bounds(op)?;
+            ValueFilter::Float {
+                min: min.then(|| if strict { next_float(v, true) } else { v }),
+                max: max.then(|| if strict { next_float(v, false) } else { v }),
+            }
+        }
+        Literal::Timestamp(t) => {
+            let (min, max, strict) = bounds(op)?;
+            let step = chrono::Duration::nanoseconds(i64::from(strict));
+            ValueFilter::Time {
+                from: min.then(|| t + step),
+                to: max.then(|| t - step),
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// The float next to `v` towards positive (`up`) or negative infinity, for finite `v`.
+fn next_float(v: f64, up: bool) -> f64 {
+    if v == 0.0 {
+        let smallest = f64::from_bits(1);
+        return if up { smallest } else { -smallest };
+    }
+    let bits = v.to_bits();
+    if (v > 0.0) == up {
+        f64::from_bits(bits + 1)
+    } else {
+        f64::from_bits(bits - 1)
+    }
+}
+
+fn operand(expr: &Expression, parameters: &[TreeParameter]) -> Option<Operand> {
+    match expr {
+        // Comprehension variables are out of scope here, so the name is the parameter's.
+        Expression::Ident(name) => {
+            let parameter = parameters.iter().find(|p| p.name == name.as_str())?;
+            parameter_literal(parameter).map(Operand::Literal)
+        }
+        Expression::FunctionCall(name, Some(target), args) => {
+            let Expression::Ident(name) = name.as_ref() else {
+                return None;
+            };
+            match (name.as_str(), args.as_slice()) {
+                ("attr", [Expression::Atom(Atom::String(attr))]) => {
+                    Some(Operand::EventAttr(event_var(target)?, attr.to_string()))
+                }
+                ("attrAt", [Expression::Atom(Atom::String(attr)), at]) => {
+                    let Expression::Ident(object) = target.as_ref() else {
+                        return None;
+                    };
+                    let Some(Variable::Object(object)) = string_to_var(object) else {
+                        return None;
+                    };
+                    let Operand::EventTime(event) = operand(at, parameters)? else {
+                        return None;
+                    };
+                    Some(Operand::ObjectAttrAt(object, attr.to_string(), event))
+                }
+                ("time", []) => Some(Operand::EventTime(event_var(target)?)),
+                _ => None,
+            }
+        }
+        Expression::FunctionCall(name, None, args) => {
+            let (Expression::Ident(name), [Expression::Atom(Atom::String(arg))]) =
+                (name.as_ref(), args.as_slice())
+            else {
+                return None;
+            };
+            match (name.as_str(), evaluate_literal_call(name, arg)?) {
+                ("timestamp", Value::Timestamp(t)) => {
+                    Some(Operand::Literal(Literal::Timestamp(t.with_timezone(&Utc))))
+                }
+                ("duration", Value::Duration(d)) => {
+                    Some(Operand::Literal(Literal::Duration(duration_seconds(d))))
+                }
+                _ => None,
+            }
+        }
+        Expression::Arithmetic(to, ArithmeticOp::Subtract, from) => {
+            match (operand(to, parameters)?, operand(from, parameters)?) {
+                (Operand::EventTime(to), Operand::EventTime(from)) => {
+                    Some(Operand::TimeBetween(from, to))
+                }
+                _ => None,
+            }
+        }
+        Expression::Unary(UnaryOp::Minus, inner) => match inner.as_ref() {
+            Expression::Atom(Atom::Int(i)) => {
+                Some(Operand::Literal(Literal::Int(i.checked_neg()?)))
+            }
+            Expression::Atom(Atom::Float(f)) => Some(Operand::Literal(Literal::Float(-f))),
+            _ => None,
+        },
+        Expression::Atom(atom) => Some(Operand::Literal(match atom {
+            Atom::Int(i) => Literal::Int(*i),
+            Atom::Float(f) => Literal::Float(*f),
+            Atom::String(s) => Literal::String(s.to_string()),
+            Atom::Bool(b) => Literal::Bool(*b),
+            _ => return None,
+        })),
+        Expression::List(items) => Some(Operand::Literal(Literal::Strings(
+            items
+                .iter()
+                .map(|item| match item {
+                    Expression::Atom(Atom::String(s)) => Some(s.to_string()),
+                    _ => None,
+                })
+                .collect::<Option<_>>()?,
+        ))),
+        _ => None,
+    }
+}
+
+/// The value `parameter` is bound to, as a literal
+fn parameter_literal(parameter: &TreeParameter) -> Option<Literal> {
+    Some(
+        match (parameter.default.as_ref()?, parameter.parameter_type) {
+            (ParameterValue::Int(i), ParameterType::Float) => Literal::Float(*i as f64),
+            (ParameterValue::Int(i), _) => Literal::Int(*i),
+            (ParameterValue::Float(f), _) => Literal::Float(*f),
+            (ParameterValue::String(s), _) => Literal::String(s.clone()),
+            (ParameterValue::Bool(b), _) => Literal::Bool(*b),
+            (ParameterValue::Timestamp(t), _) => Literal::Timestamp(*t),
+            (ParameterValue::Duration(seconds), _) => Literal::Duration(*seconds),
+        },
+    )
+}
+
+fn event_var(expr: &Expression) -> Option<EventVariable> {
+    match expr {
+        Expression::Ident(name) => match string_to_var(name)? {
+            Variable::Event(ev) => Some(ev),
+            Variable::Object(_) => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::binding_box::{
+        evaluate_box_tree,
+        structs::{BindingBox, BindingBoxTreeNode, BindingStep},
+        test_utils::{
+            bound_event_ids, bound_object_ids, ev_vars, eval, ob_vars, ocel,
+            ocel_with_order_status, place_and_ship, tree,
+        },
+    };
+
+    #[test]
+    fn simple_cel_filters_are_evaluated_as_native_filters_with_the_same_results() {
+        let with_cel = |cel: &str| {
+            let mut t = place_and_ship(Filter::BasicFilterCEL {
+                cel: cel.to_string(),
+            });
+            t.parameters = vec![TreeParameter {
+                name: "min_amount".to_string(),
+                parameter_type: ParameterType::Int,
+                default: Some(ParameterValue::Int(50)),
+                uses: vec![],
+            }];
+            t
+        };
+        let is_cel =
+            |s: &BindingStep| matches!(s, BindingStep::Filter(Filter::BasicFilterCEL { .. }));
+        let lowered = with_cel(
+            "e1.attr('amount') >= min_amount && e1.time() < e2.time() && e2.time() - e1.time() <= duration('72h')",
+        );
+        // The same conditions, written so that they stay CEL
+        let kept = with_cel(
+            "!(e1.attr('amount') < min_amount) && !(e1.time() >= e2.time()) && !(e2.time() - e1.time() > duration('72h'))",
+        );
+        assert!(!lowered.compute_step_cache(&ocel())[0].iter().any(is_cel));
+        assert!(kept.compute_step_cache(&ocel())[0].iter().any(is_cel));
+
+        let (lowered, kept) = (eval(lowered), eval(kept));
+        assert_eq!(bound_event_ids(&lowered, 0, 1), ["e4"]);
+        assert_eq!(
+            bound_event_ids(&lowered, 0, 1),
+            bound_event_ids(&kept, 0, 1)
+        );
+        assert_eq!(
+            bound_object_ids(&lowered, 0, 0),
+            bound_object_ids(&kept, 0, 0)
+        );
+    }
+
+    #[test]
+    fn lowered_cel_filters_bind_what_the_cel_filters_bind() {
+        // (event id, event id, object id) of every binding of place x ship x order passing `filters`
+        let ocel = ocel_with_order_status();
+        let bound = |filters: Vec<Filter>| {
+            let t = tree(
+                vec![BindingBoxTreeNode::Box(
+                    BindingBox {
+                        new_event_vars: ev_vars(&[(0, "place"), (1, "ship")]),
+                        new_object_vars: ob_vars(&[(0, "order")]),
+                        filters,
+                        ..Default::default()
+                    },
+                    vec![],
+                )],
+                &[],
+            );
+            let res = evaluate_box_tree(t, &ocel, false).unwrap();
+            let (e1, e2, o1) = (
+                bound_event_ids(&res, 0, 0),
+                bound_event_ids(&res, 0, 1),
+                bound_object_ids(&res, 0, 0),
+            );
+            (0..e1.len())
+                .map(|i| (e1[i].to_string(), e2[i].to_string(), o1[i].to_string()))
+                .sorted()
+                .collect_vec()
+        };
+        for cel in [
+            "e1.attr('amount') > 5",
+            "e1.attr('amount') >= 5",
+            "100 > e1.attr('amount')",
+            "e1.attr('amount') == 100",
+            "e1.attr('amount') < 5.5",
+            "e1.time() < e2.time() && e2.time() - e1.time() > duration('72h')",
+            "e2.time() - e1.time() <= duration('72h')",
+            "e1.time() >= timestamp('2024-01-02T00:00:00Z')",
+            "o1.attrAt('status', e2.time()) == 'open'",
+            "o1.attrAt('status', e2.time()) in ['closed', 'cancelled']",
+        ] {
+            let lowered = lower_cel_filter(cel, &[]);
+            assert!(
+                !lowered
+                    .iter()
+                    .any(|f| matches!(f, Filter::BasicFilterCEL { .. })),
+                "{cel} is lowered completely: {lowered:?}"
+            );
+            assert_eq!(
+                bound(lowered),
+                bound(vec![Filter::BasicFilterCEL {
+                    cel: cel.to_string()
+                }]),
+                "{cel}"
+            );
+        }
+        // Only the conjuncts that can be lowered are, and `o1.attr` reads the first value.
+        assert!(matches!(
+            lower_cel_filter("e1.attr('amount') > 5 && o1.attr('status') == 'open'", &[])
+                .as_slice(),
+            [
+                Filter::EventAttributeValueFilter { .. },
+                Filter::BasicFilterCEL { cel },
+            ] if cel == "o1.attr('status') == 'open'"
+        ));
+        assert!(matches!(
+            lower_cel_filter("e1.attr('amount') > 5 || e1.attr('amount') < 1", &[]).as_slice(),
+            [Filter::BasicFilterCEL { .. }]
+        ));
+    }
+}
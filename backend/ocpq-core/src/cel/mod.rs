@@ -1,15 +1,17 @@
 use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
+    rc::Rc,
     sync::{Arc, RwLock},
     usize,
 };
 
 use cel_interpreter::{
-    extractors::This, objects::Map, Context, ExecutionError, FunctionContext, Program,
-    ResolveResult, Value,
+    extractors::{Arguments, This},
+    objects::Map,
+    Context, ExecutionError, FunctionContext, Program, ResolveResult, Value,
 };
-use chrono::{DateTime, FixedOffset, Local};
+use chrono::{DateTime, FixedOffset, TimeZone};
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use process_mining::core::event_data::object_centric::{
@@ -19,14 +21,25 @@ use process_mining::core::event_data::object_centric::{
     },
     OCELAttributeValue,
 };
+use scoped_tls::scoped_thread_local;
 
 use crate::{
     binding_box::{
+        parameters::TreeParameter,
         structs::{EventVariable, LabelFunction, LabelValue, ObjectVariable, Variable},
+        time_index::TimeIndex,
         Binding, ViolationReason,
     },
+    calendar::{self, get_calendar, resolve_offset, BusinessCalendar, DEFAULT_CALENDAR},
     preprocessing::linked_ocel::{event_or_object_from_index, OCELNode},
+    timing::Timer,
 };
+use profile::CelProfiler;
+
+pub mod library;
+pub mod lowering;
+pub mod profile;
+pub mod syntax;
 
 fn string_to_index(s: &str) -> Option<EventOrObjectIndex> {
     // ob_ and ev_ are the prefixes we reserve
@@ -41,33 +54,21 @@ fn string_to_index(s: &str) -> Option<EventOrObjectIndex> {
     }
 }
 
-struct RawBindingContextPtr<'a, T>(*mut &'a T);
-
-unsafe impl<T> Send for RawBindingContextPtr<'_, T> {}
-unsafe impl<T> Sync for RawBindingContextPtr<'_, T> {}
-impl<T> Clone for RawBindingContextPtr<'_, T> {
-    fn clone(&self) -> Self {
-        *self
-    }
-}
-
-impl<T> Copy for RawBindingContextPtr<'_, T> {}
-
 fn index_string_to_val(s: &str, ocel: &SlimLinkedOCEL) -> Option<OCELNode> {
     let index = string_to_index(s)?;
     let ret = event_or_object_from_index(index, ocel);
     Some(ret)
 }
 
-unsafe fn index_string_to_val_raw<'a>(
-    s: &str,
-    ocel: RawBindingContextPtr<'a, SlimLinkedOCEL>,
-) -> Option<OCELNode> {
-    index_string_to_val(s, ocel.0.as_ref().unwrap())
-}
-
-unsafe fn get_ocel_raw(ocel: RawBindingContextPtr<'_, SlimLinkedOCEL>) -> &SlimLinkedOCEL {
-    ocel.0.as_ref().unwrap()
+/// Run `f` with the OCEL of the evaluation running on this thread (see [`EVAL_OCEL`]).
+fn with_ocel(
+    ftx: &FunctionContext,
+    f: impl FnOnce(&SlimLinkedOCEL) -> ResolveResult,
+) -> ResolveResult {
+    if !EVAL_OCEL.is_set() {
+        return Err(ftx.error("OCEL functions can only be used while evaluating against an OCEL"));
+    }
+    EVAL_OCEL.with(f)
 }
 
 pub static CEL_PROGRAM_CACHE: Lazy<RwLock<HashMap<String, Program>>> = Lazy::new(|| {
@@ -99,8 +100,36 @@ pub fn ob_index_to_name(ob_index: &ObjectIndex) -> String {
     format!("ob_{}", ob_index.into_inner())
 }
 
+scoped_thread_local!(
+    /// The OCEL that CEL functions like `attr` read from. It is only set while [`evaluate_cel`]
+    /// executes a program, so the shared [`CEL_BASE_CTX`] does not hold on to any OCEL and
+    /// evaluations against different OCELs can run in parallel.
+    static EVAL_OCEL: SlimLinkedOCEL
+);
+
+scoped_thread_local!(
+    /// The clock of the evaluation while [`evaluate_cel`] executes a program. The navigation
+    /// functions hide the events after it (see
+    /// [`crate::binding_box::control::EvaluationControl::as_of`]).
+    static EVAL_NOW: DateTime<FixedOffset>
+);
+
+/// Whether `ev` has happened by the clock of the evaluation running on this thread (see
+/// [`EVAL_NOW`]).
+fn happened(ocel: &SlimLinkedOCEL, ev: &EventIndex) -> bool {
+    !EVAL_NOW.is_set() || EVAL_NOW.with(|now| ocel.get_ev_time(ev) <= now)
+}
+
+/// Whether an object attribute value set at `time` is visible by the clock of the evaluation
+/// running on this thread, like the events of [`happened`].
+fn set_by_now<Tz: TimeZone>(time: &DateTime<Tz>) -> bool {
+    !EVAL_NOW.is_set() || EVAL_NOW.with(|now| time <= now)
+}
+
 thread_local! {
-    static CEL_BASE_CTX: RefCell<Option<(CelCacheKey, Context<'static>)>> = const { RefCell::new(None) };
+    /// The functions of CEL programs, with those of the [`library`] generation they were built
+    /// for. They do not depend on the OCEL (see [`EVAL_OCEL`]).
+    static CEL_BASE_CTX: RefCell<Option<(u64, Rc<Context<'static>>)>> = const { RefCell::new(None) };
 
     static EV_INDEX_NAME_CACHE: RefCell<Option<IndexNameCache>> = const { RefCell::new(None) };
     static OB_INDEX_NAME_CACHE: RefCell<Option<IndexNameCache>> = const { RefCell::new(None) };
@@ -172,93 +201,86 @@ fn cached_ob_index_name(idx: &ObjectIndex, key: CelCacheKey) -> Arc<String> {
     })
 }
 
-fn build_base_cel_context(ocel: &SlimLinkedOCEL) -> Context<'static> {
-    let mut context: Context<'static> = Context::default();
-
-    let ocel_raw = RawBindingContextPtr(unsafe {
-        std::mem::transmute::<*mut &SlimLinkedOCEL, *mut &'static SlimLinkedOCEL>(Box::into_raw(
-            Box::new(ocel),
-        ))
+/// Run `f` with this thread's [`CEL_BASE_CTX`], first rebuilding it if a CEL library was
+/// registered since.
+fn with_base_cel_context<R>(f: impl FnOnce(&Context<'static>) -> R) -> R {
+    let generation = library::generation();
+    let base = CEL_BASE_CTX.with(|cell| {
+        let mut slot = cell.borrow_mut();
+        match slot.as_ref() {
+            Some((built_for, base)) if *built_for == generation => Rc::clone(base),
+            _ => {
+                let base = Rc::new(build_base_cel_context());
+                *slot = Some((generation, Rc::clone(&base)));
+                base
+            }
+        }
     });
+    f(&base)
+}
+
+fn build_base_cel_context() -> Context<'static> {
+    let mut context: Context<'static> = Context::default();
 
     context.add_function(
         "type",
         move |ftx: &FunctionContext, This(variable): This<Arc<String>>| -> ResolveResult {
-            let val = unsafe { index_string_to_val_raw(&variable, ocel_raw) };
+            with_ocel(ftx, |ocel| {
+                let val = index_string_to_val(&variable, ocel);
 
-            match val {
-                Some(val_ref) => {
-                    let ocel_type = match val_ref {
-                        OCELNode::Event(ev) => ev.event_type,
-                        OCELNode::Object(ob) => ob.object_type,
-                    };
-                    Ok(ocel_type.into())
-                }
+                match val {
+                    Some(val_ref) => {
+                        let ocel_type = match val_ref {
+                            OCELNode::Event(ev) => ev.event_type,
+                            OCELNode::Object(ob) => ob.object_type,
+                        };
+                        Ok(ocel_type.into())
+                    }
 
-                None => ftx.error("Event or Object not found.").into(),
-            }
+                    None => ftx.error("Event or Object not found.").into(),
+                }
+            })
         },
     );
 
-    context.add_function("min", |cel_interpreter::extractors::Arguments(args): cel_interpreter::extractors::Arguments| -> Result<Value,ExecutionError> {
-            // If items is a list of values, then operate on the list
-            let items = if args.len() == 1 {
-                match &args[0] {
-                    Value::List(values) => values,
-                    _ => return Ok(args[0].clone()),
-                }
-            } else {
-                &args
-            };
-            items
-                .iter()
-                .skip(1)
-                .try_fold(items.first().unwrap_or(&Value::Null), |acc, x| {
-                    match acc.partial_cmp(x) {
-                        Some(std::cmp::Ordering::Less) => Ok(acc),
-                        Some(_) => Ok(x),
-                        None => Err(ExecutionError::ValuesNotComparable(acc.clone(), x.clone())),
-                    }
-                })
-                .cloned()
-        });
-
     context.add_function(
         "attr",
         move |ftx: &FunctionContext,
               This(variable): This<Arc<String>>,
               attr_name: Arc<String>|
               -> ResolveResult {
-            let val = unsafe { index_string_to_val_raw(&variable, ocel_raw) };
-            let res = match val {
-                Some(val_ref) => {
-                    let attr_val = match val_ref {
-                        OCELNode::Event(ev) => ev
-                            .attributes
-                            .into_iter()
-                            .find(|a| &a.name == attr_name.as_ref())
-                            .map(|a| a.value),
-                        OCELNode::Object(ob) => ob
-                            .attributes
-                            .into_iter()
-                            .find(|a| &a.name == attr_name.as_ref())
-                            .map(|a| a.value),
+            with_ocel(ftx, |ocel| {
+                let val = index_string_to_val(&variable, ocel);
+                let res = match val {
+                    Some(val_ref) => {
+                        let attr_val = match val_ref {
+                            OCELNode::Event(ev) => ev
+                                .attributes
+                                .into_iter()
+                                .find(|a| &a.name == attr_name.as_ref())
+                                .map(|a| a.value),
+                            OCELNode::Object(ob) => ob
+                                .attributes
+                                .into_iter()
+                                .find(|a| &a.name == attr_name.as_ref() && set_by_now(&a.time))
+                                .map(|a| a.value),
+                        }
+                        .unwrap_or(OCELAttributeValue::Null);
+                        let cel_val = match attr_val {
+                            OCELAttributeValue::Float(f) => (f).into(),
+                            OCELAttributeValue::Integer(i) => (i).into(),
+                            OCELAttributeValue::String(s) => s.into(),
+                            OCELAttributeValue::Time(t) => t.fixed_offset().into(),
+                            OCELAttributeValue::Boolean(b) => (b).into(),
+                            OCELAttributeValue::Null => Value::Null,
+                        };
+                        Ok(cel_val)
                     }
-                    .unwrap_or(OCELAttributeValue::Null);
-                    let cel_val = match attr_val {
-                        OCELAttributeValue::Float(f) => (f).into(),
-                        OCELAttributeValue::Integer(i) => (i).into(),
-                        OCELAttributeValue::String(s) => s.into(),
-                        OCELAttributeValue::Time(t) => t.fixed_offset().into(),
-                        OCELAttributeValue::Boolean(b) => (b).into(),
-                        OCELAttributeValue::Null => Value::Null,
-                    };
-                    Ok(cel_val)
-                }
 
-                None => ftx.error("Event or Object not found.").into(),
-            };
-            res
+                    None => ftx.error("Event or Object not found.").into(),
+                };
+                res
+            })
         },
     );
 
@@ -269,132 +291,246 @@ fn build_base_cel_context(ocel: &SlimLinkedOCEL) -> Context<'static> {
               attr_name: Arc<String>,
               at: DateTime<FixedOffset>|
               -> ResolveResult {
-            let val = unsafe { index_string_to_val_raw(&variable, ocel_raw) };
-            let res = match val {
-                Some(val_ref) => {
-                    let attr_val = match val_ref {
-                        OCELNode::Event(ev) => ev
-                            .attributes
-                            .into_iter()
-                            .find(|a| &a.name == attr_name.as_ref())
-                            .map(|a| a.value),
-                        OCELNode::Object(ob) => ob
-                            .attributes
-                            .into_iter()
-                            .filter(|a| &a.name == attr_name.as_ref())
-                            .sorted_by_key(|a| a.time)
-                            .rfind(|a| a.time <= at)
-                            .map(|a| a.value),
+            with_ocel(ftx, |ocel| {
+                let val = index_string_to_val(&variable, ocel);
+                let res = match val {
+                    Some(val_ref) => {
+                        let attr_val = match val_ref {
+                            OCELNode::Event(ev) => ev
+                                .attributes
+                                .into_iter()
+                                .find(|a| &a.name == attr_name.as_ref())
+                                .map(|a| a.value),
+                            OCELNode::Object(ob) => ob
+                                .attributes
+                                .into_iter()
+                                .filter(|a| &a.name == attr_name.as_ref())
+                                .sorted_by_key(|a| a.time)
+                                .rfind(|a| a.time <= at)
+                                .map(|a| a.value),
+                        }
+                        .unwrap_or(OCELAttributeValue::Null);
+                        Ok(ocel_val_to_cel_val(attr_val))
                     }
-                    .unwrap_or(OCELAttributeValue::Null);
-                    Ok(ocel_val_to_cel_val(attr_val))
-                }
 
-                None => ftx.error("Event or Object not found.").into(),
-            };
-            res
+                    None => ftx.error("Event or Object not found.").into(),
+                };
+                res
+            })
         },
     );
 
     context.add_function(
         "id",
         move |ftx: &FunctionContext, This(variable): This<Arc<String>>| -> ResolveResult {
-            let val = unsafe { index_string_to_val_raw(&variable, ocel_raw) };
+            with_ocel(ftx, |ocel| {
+                let val = index_string_to_val(&variable, ocel);
 
-            match val {
-                Some(val_ref) => {
-                    let attr_val = match val_ref {
-                        OCELNode::Event(ev) => ev.id,
-                        OCELNode::Object(ob) => ob.id,
-                    };
-                    Ok(attr_val.into())
-                }
+                match val {
+                    Some(val_ref) => {
+                        let attr_val = match val_ref {
+                            OCELNode::Event(ev) => ev.id,
+                            OCELNode::Object(ob) => ob.id,
+                        };
+                        Ok(attr_val.into())
+                    }
 
-                None => ftx.error("Event or Object not found.").into(),
-            }
+                    None => ftx.error("Event or Object not found.").into(),
+                }
+            })
         },
     );
 
     context.add_function(
         "attrs",
         move |ftx: &FunctionContext, This(variable): This<Arc<String>>| -> ResolveResult {
-            let val = unsafe { index_string_to_val_raw(&variable, ocel_raw) };
-
-            match val {
-                Some(val_ref) => {
-                    let attr_val: Vec<Vec<Value>> = match val_ref {
-                        OCELNode::Event(ev) => ev
-                            .attributes
-                            .into_iter()
-                            .map(|a| {
-                                vec![
-                                    a.name.clone().into(),
-                                    ocel_val_to_cel_val(a.value),
-                                    Value::Null,
-                                ]
-                            })
-                            .collect(),
-                        OCELNode::Object(ob) => ob
-                            .attributes
-                            .into_iter()
-                            .map(|a| {
-                                vec![
-                                    a.name.clone().into(),
-                                    ocel_val_to_cel_val(a.value),
-                                    a.time.fixed_offset().into(),
-                                ]
-                            })
-                            .collect(),
-                    };
-                    Ok(attr_val.into())
-                }
+            with_ocel(ftx, |ocel| {
+                let val = index_string_to_val(&variable, ocel);
 
-                None => ftx.error("Event or Object not found.").into(),
-            }
+                match val {
+                    Some(val_ref) => {
+                        let attr_val: Vec<Vec<Value>> = match val_ref {
+                            OCELNode::Event(ev) => ev
+                                .attributes
+                                .into_iter()
+                                .map(|a| {
+                                    vec![
+                                        a.name.clone().into(),
+                                        ocel_val_to_cel_val(a.value),
+                                        Value::Null,
+                                    ]
+                                })
+                                .collect(),
+                            OCELNode::Object(ob) => ob
+                                .attributes
+                                .into_iter()
+                                .filter(|a| set_by_now(&a.time))
+                                .map(|a| {
+                                    vec![
+                                        a.name.clone().into(),
+                                        ocel_val_to_cel_val(a.value),
+                                        a.time.fixed_offset().into(),
+                                    ]
+                                })
+                                .collect(),
+                        };
+                        Ok(attr_val.into())
+                    }
+
+                    None => ftx.error("Event or Object not found.").into(),
+                }
+            })
         },
     );
 
     context.add_function(
         "time",
         move |ftx: &FunctionContext, This(variable): This<Arc<String>>| -> ResolveResult {
-            let s = variable.as_str();
-            if let Some(rest) = s.strip_prefix("ev_") {
-                if let Ok(idx) = rest.parse::<InnerIndex>() {
-                    let ocel_ref = unsafe { get_ocel_raw(ocel_raw) };
-                    let ev_index: EventIndex = idx.into();
-                    return Ok((*ocel_ref.get_ev_time(&ev_index)).into());
+            with_ocel(ftx, |ocel| {
+                let s = variable.as_str();
+                if let Some(rest) = s.strip_prefix("ev_") {
+                    if let Ok(idx) = rest.parse::<InnerIndex>() {
+                        let ev_index: EventIndex = idx.into();
+                        return Ok((*ocel.get_ev_time(&ev_index)).into());
+                    }
                 }
-            }
-            ftx.error("Event not found.").into()
+                ftx.error("Event not found.").into()
+            })
         },
     );
 
-    context.add_function("numEvents", move || -> ResolveResult {
-        unsafe { Ok((get_ocel_raw(ocel_raw).get_all_evs().count() as u64).into()) }
-    });
-    context.add_function("numObjects", move || -> ResolveResult {
-        unsafe { Ok((get_ocel_raw(ocel_raw).get_all_obs().count() as u64).into()) }
+    context.add_function("numEvents", move |ftx: &FunctionContext| -> ResolveResult {
+        with_ocel(ftx, |ocel| {
+            Ok((ocel.get_all_evs().filter(|e| happened(ocel, e)).count() as u64).into())
+        })
     });
+    context.add_function(
+        "numObjects",
+        move |ftx: &FunctionContext| -> ResolveResult {
+            with_ocel(ftx, |ocel| Ok((ocel.get_all_obs().count() as u64).into()))
+        },
+    );
 
-    context.add_function("events", move || -> ResolveResult {
-        unsafe {
-            Ok((get_ocel_raw(ocel_raw)
-                .get_all_evs()
-                .map(|x| ev_index_to_name(&x)))
-            .collect_vec()
-            .into())
-        }
-    });
+    // Without a target: all events (of a type) of the OCEL. On an object: its events (E2O).
+    context.add_function(
+        "events",
+        move |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            with_ocel(ftx, |ocel| {
+                let (ev_type, qualifier) = navigation_filters(ftx, &args)?;
+                let related: Vec<(&str, EventIndex)> =
+                    match navigation_target(ftx)? {
+                        None if qualifier.is_none() => {
+                            return Ok(ocel
+                                .get_all_evs()
+                                .filter(|e| {
+                                    happened(ocel, e) && keeps(&ev_type, ocel.get_ev_type_of(e))
+                                })
+                                .map(|e| ev_index_to_name(&e))
+                                .collect_vec()
+                                .into())
+                        }
+                        Some(EventOrObjectIndex::Object(ob)) => ocel
+                            .get_e2o_rev(&ob)
+                            .map(|(q, e)| {
+                                let q: &str = q;
+                                (q, *e)
+                            })
+                            .collect(),
+                        _ => return ftx
+                            .error(
+                                "events(type, qualifier) needs an object, as in o1.events('pay')",
+                            )
+                            .into(),
+                    };
+                Ok(related
+                    .into_iter()
+                    .filter(|(q, e)| {
+                        happened(ocel, e)
+                            && keeps(&qualifier, q)
+                            && keeps(&ev_type, ocel.get_ev_type_of(e))
+                    })
+                    .map(|(_, e)| e)
+                    .unique()
+                    .sorted_by_key(|e| *ocel.get_ev_time(e))
+                    .map(|e| ev_index_to_name(&e))
+                    .collect_vec()
+                    .into())
+            })
+        },
+    );
 
-    context.add_function("objects", move || -> ResolveResult {
-        unsafe {
-            Ok((get_ocel_raw(ocel_raw)
-                .get_all_obs()
-                .map(|x| ob_index_to_name(&x)))
-            .collect_vec()
-            .into())
-        }
-    });
+    // Without a target: all objects (of a type) of the OCEL. On an event: its objects (E2O); on an
+    // object: the objects it relates to (O2O).
+    context.add_function(
+        "objects",
+        move |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            with_ocel(ftx, |ocel| {
+                let (ob_type, qualifier) = navigation_filters(ftx, &args)?;
+                let related: Vec<(&str, ObjectIndex)> = match navigation_target(ftx)? {
+                    None if qualifier.is_none() => {
+                        return Ok(ocel
+                            .get_all_obs()
+                            .filter(|o| keeps(&ob_type, ocel.get_ob_type_of(o)))
+                            .map(|o| ob_index_to_name(&o))
+                            .collect_vec()
+                            .into())
+                    }
+                    None => {
+                        return ftx
+                            .error(
+                                "objects(type, qualifier) needs an event or object, as in e1.objects('item')",
+                            )
+                            .into()
+                    }
+                    // An event that has not happened yet has no objects so far
+                    Some(EventOrObjectIndex::Event(ev)) if !happened(ocel, &ev) => Vec::new(),
+                    Some(EventOrObjectIndex::Event(ev)) => ocel
+                        .get_e2o(&ev)
+                        .map(|(q, o)| {
+                            let q: &str = q;
+                            (q, *o)
+                        })
+                        .collect(),
+                    Some(EventOrObjectIndex::Object(ob)) => ocel
+                        .get_o2o(&ob)
+                        .map(|(q, o)| {
+                            let q: &str = q;
+                            (q, *o)
+                        })
+                        .collect(),
+                };
+                Ok(related_object_names(ocel, related, &ob_type, &qualifier).into())
+            })
+        },
+    );
+
+    // The objects an object relates to or is related from (O2O in both directions)
+    context.add_function(
+        "related",
+        move |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            with_ocel(ftx, |ocel| {
+                // Relationships are looked up by qualifier first
+                let (qualifier, ob_type) = navigation_filters(ftx, &args)?;
+                let Some(EventOrObjectIndex::Object(ob)) = navigation_target(ftx)? else {
+                    return ftx
+                    .error("related(qualifier, type) needs an object, as in o1.related('contains')")
+                    .into();
+                };
+                let related = ocel
+                    .get_o2o(&ob)
+                    .map(|(q, o)| {
+                        let q: &str = q;
+                        (q, *o)
+                    })
+                    .chain(ocel.get_o2o_rev(&ob).map(|(q, o)| {
+                        let q: &str = q;
+                        (q, *o)
+                    }))
+                    .collect_vec();
+                Ok(related_object_names(ocel, related, &ob_type, &qualifier).into())
+            })
+        },
+    );
 
     context.add_function(
         "sum",
@@ -413,15 +549,286 @@ fn build_base_cel_context(ocel: &SlimLinkedOCEL) -> Context<'static> {
             Ok((sum / count as f64).into())
         },
     );
+
+    // Business calendars (see `crate::calendar`); a missing calendar argument means the default one.
+    context.add_function(
+        "businessDuration",
+        move |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            let from = timestamp_arg(ftx, &args, 0)?;
+            let to = timestamp_arg(ftx, &args, 1)?;
+            let calendar = calendar_arg(ftx, &args, 2)?;
+            Ok(Value::Duration(calendar.business_duration(from, to)))
+        },
+    );
+    context.add_function(
+        "addBusinessDays",
+        move |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            let t = timestamp_arg(ftx, &args, 0)?;
+            let Some(Value::Int(days)) = args.get(1) else {
+                return ftx
+                    .error("addBusinessDays(t, days, calendar) needs a number of days")
+                    .into();
+            };
+            let calendar = calendar_arg(ftx, &args, 2)?;
+            Ok(Value::Timestamp(calendar.add_business_days(t, *days)))
+        },
+    );
+    context.add_function(
+        "isBusinessDay",
+        move |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            let t = timestamp_arg(ftx, &args, 0)?;
+            let calendar = calendar_arg(ftx, &args, 1)?;
+            let date = t.with_timezone(&calendar.offset()).date_naive();
+            Ok(calendar.is_business_day(date).into())
+        },
+    );
+    // ISO weekday (1 is Monday, 7 is Sunday)
+    context.add_function(
+        "weekday",
+        move |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            let t = timestamp_arg(ftx, &args, 0)?;
+            let offset = offset_arg(ftx, &args, 1)?;
+            Ok(Value::Int(calendar::weekday(t, offset).into()))
+        },
+    );
+    context.add_function(
+        "startOfDay",
+        move |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            let t = timestamp_arg(ftx, &args, 0)?;
+            let offset = offset_arg(ftx, &args, 1)?;
+            Ok(Value::Timestamp(calendar::start_of_day(t, offset)))
+        },
+    );
+    add_standard_extensions(&mut context);
+    library::add_library_functions(&mut context);
     context
 }
 
+/// Functions beyond CEL's standard definitions that do not need an OCEL. All of them can also be
+/// called with the first argument as target (`s.split(',')` is `split(s, ',')`).
+///
+/// * `min(..)`, `max(..)`: the least/greatest of the arguments or, for a single list argument,
+///   of its elements. Numbers (also mixed `int`/`double`), strings, bools, timestamps and
+///   durations compare; `null` for an empty list.
+/// * Regular expressions (Rust `regex` syntax):
+///   `s.regexFind(re)` (first match or `null`), `s.regexFindAll(re)` (all matches),
+///   `s.regexCapture(re)` (capture groups of the first match, `''` for groups that did not take
+///   part, `[]` without a match) and `s.regexReplace(re, replacement)` (all matches, with `$1` or
+///   `${name}` referring to groups).
+/// * Strings: `s.lower()`, `s.upper()`, `s.trim()`, `s.equalsIgnoreCase(t)`, `s.split(sep)` and
+///   `list.join(sep)` (`sep` defaults to `''`).
+/// * Lists as sets, keeping the order of first occurrence: `l.distinct()`, `a.union(b)`,
+///   `a.intersect(b)` and `a.difference(b)`.
+/// * `l.median()` and `l.percentile(p)` (`p` from 0 to 100, interpolating linearly between the
+///   closest ranks) of a list of numbers (a `double`) or of durations (a `duration`); `null`
+///   for an empty list.
+fn add_standard_extensions(context: &mut Context<'static>) {
+    context.add_function(
+        "min",
+        |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            extreme_value(ftx, &args, std::cmp::Ordering::Less)
+        },
+    );
+    context.add_function(
+        "max",
+        |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            extreme_value(ftx, &args, std::cmp::Ordering::Greater)
+        },
+    );
+
+    context.add_function(
+        "regexFind",
+        |ftx: &FunctionContext, This(s): This<Arc<String>>, re: Arc<String>| -> ResolveResult {
+            let re = cached_regex(ftx, &re)?;
+            Ok(re
+                .find(&s)
+                .map_or(Value::Null, |m| m.as_str().to_string().into()))
+        },
+    );
+    context.add_function(
+        "regexFindAll",
+        |ftx: &FunctionContext, This(s): This<Arc<String>>, re: Arc<String>| -> ResolveResult {
+            let re = cached_regex(ftx, &re)?;
+            Ok(re
+                .find_iter(&s)
+                .map(|m| m.as_str().to_string())
+                .collect_vec()
+                .into())
+        },
+    );
+    context.add_function(
+        "regexCapture",
+        |ftx: &FunctionContext, This(s): This<Arc<String>>, re: Arc<String>| -> ResolveResult {
+            let re = cached_regex(ftx, &re)?;
+            let groups = match re.captures(&s) {
+                Some(caps) => caps
+                    .iter()
+                    .skip(1)
+                    .map(|g| g.map_or_else(String::new, |g| g.as_str().to_string()))
+                    .collect_vec(),
+                None => Vec::new(),
+            };
+            Ok(groups.into())
+        },
+    );
+    context.add_function(
+        "regexReplace",
+        |ftx: &FunctionContext,
+         This(s): This<Arc<String>>,
+         re: Arc<String>,
+         replacement: Arc<String>|
+         -> ResolveResult {
+            let re = cached_regex(ftx, &re)?;
+            Ok(re.replace_all(&s, replacement.as_str()).into_owned().into())
+        },
+    );
+
+    context.add_function("lower", |This(s): This<Arc<String>>| -> String {
+        s.to_lowercase()
+    });
+    context.add_function("upper", |This(s): This<Arc<String>>| -> String {
+        s.to_uppercase()
+    });
+    context.add_function("trim", |This(s): This<Arc<String>>| -> String {
+        s.trim().to_string()
+    });
+    context.add_function(
+        "equalsIgnoreCase",
+        |This(s): This<Arc<String>>, other: Arc<String>| -> bool {
+            s.to_lowercase() == other.to_lowercase()
+        },
+    );
+    context.add_function(
+        "split",
+        |This(s): This<Arc<String>>, sep: Arc<String>| -> Vec<String> {
+            s.split(sep.as_str()).map(str::to_string).collect()
+        },
+    );
+    context.add_function(
+        "join",
+        |ftx: &FunctionContext, Arguments(args): Arguments| -> ResolveResult {
+            // The separator is optional, so the list may be the target or the first argument
+            let args = ftx.this.iter().chain(args.iter()).cloned().collect_vec();
+            let Some(Value::List(list)) = args.first() else {
+                return ftx.error("join: the target must be a list").into();
+            };
+            let sep = string_arg(ftx, &args, 1)?;
+            let parts = list
+                .iter()
+                .map(|v| match v {
+                    Value::String(s) => Ok(s.as_str()),
+                    _ => Err(ftx.error("join: the list must only contain strings")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(parts.join(sep.as_deref().map_or("", String::as_str)).into())
+        },
+    );
+
+    context.add_function("distinct", |This(list): This<Arc<Vec<Value>>>| -> Value {
+        distinct_values(list.iter()).into()
+    });
+    context.add_function(
+        "union",
+        |This(a): This<Arc<Vec<Value>>>, b: Arc<Vec<Value>>| -> Value {
+            distinct_values(a.iter().chain(b.iter())).into()
+        },
+    );
+    context.add_function(
+        "intersect",
+        |This(a): This<Arc<Vec<Value>>>, b: Arc<Vec<Value>>| -> Value {
+            distinct_values(a.iter().filter(|v| b.contains(v))).into()
+        },
+    );
+    context.add_function(
+        "difference",
+        |This(a): This<Arc<Vec<Value>>>, b: Arc<Vec<Value>>| -> Value {
+            distinct_values(a.iter().filter(|v| !b.contains(v))).into()
+        },
+    );
+
+    context.add_function(
+        "median",
+        |ftx: &FunctionContext, This(list): This<Arc<Vec<Value>>>| -> ResolveResult {
+            percentile(ftx, &list, 50.0)
+        },
+    );
+    context.add_function(
+        "percentile",
+        |ftx: &FunctionContext, This(list): This<Arc<Vec<Value>>>, p: Value| -> ResolveResult {
+            let p = match p {
+                Value::Int(i) => i as f64,
+                Value::UInt(u) => u as f64,
+                Value::Float(f) => f,
+                _ => return ftx.error("percentile: p must be a number").into(),
+            };
+            if !(0.0..=100.0).contains(&p) {
+                return ftx.error("percentile: p must be between 0 and 100").into();
+            }
+            percentile(ftx, &list, p)
+        },
+    );
+}
+
+/// What the CEL programs of one node are evaluated with.
+#[derive(Debug, Clone, Copy)]
+pub struct CelEnv<'a> {
+    /// The evaluation's clock, bound to `now` (see
+    /// [`crate::binding_box::control::EvaluationControl::as_of`])
+    pub now: DateTime<FixedOffset>,
+    /// Where calls are recorded (for which node), if the evaluation is profiled
+    pub(crate) profile: Option<(&'a CelProfiler, usize)>,
+    /// The parameters of the tree, bound to their defaults (see
+    /// [`crate::binding_box::parameters`])
+    pub parameters: &'a [TreeParameter],
+    /// The evaluation's sorted lifecycles, which filters on the order of an object's events
+    /// search instead of sorting them per binding
+    pub(crate) time_index: Option<&'a TimeIndex>,
+}
+
+impl CelEnv<'static> {
+    /// An environment that does not profile, without parameters
+    pub fn new(now: DateTime<FixedOffset>) -> Self {
+        Self {
+            now,
+            profile: None,
+            parameters: &[],
+            time_index: None,
+        }
+    }
+}
+
+impl<'a> CelEnv<'a> {
+    /// This environment, with the variables of `parameters`
+    pub fn with_parameters(self, parameters: &'a [TreeParameter]) -> Self {
+        Self { parameters, ..self }
+    }
+}
+
+/// Evaluate `cel` for `binding` in `env`.
 pub fn evaluate_cel<'a>(
     cel: &str,
     binding: &'a Binding,
     child_res: Option<&HashMap<String, Vec<(Arc<Binding>, Option<ViolationReason>)>>>,
     ocel: &'a SlimLinkedOCEL,
+    env: CelEnv<'_>,
+) -> Result<Value, CELEvalError> {
+    let Some((profiler, node_index)) = env.profile else {
+        return execute_cel(cel, binding, child_res, ocel, env);
+    };
+    let timer = Timer::start();
+    let res = execute_cel(cel, binding, child_res, ocel, env);
+    profiler.record(node_index, cel, timer.elapsed(), res.is_err());
+    res
+}
+
+fn execute_cel<'a>(
+    cel: &str,
+    binding: &'a Binding,
+    child_res: Option<&HashMap<String, Vec<(Arc<Binding>, Option<ViolationReason>)>>>,
+    ocel: &'a SlimLinkedOCEL,
+    env: CelEnv<'_>,
 ) -> Result<Value, CELEvalError> {
+    let now = env.now;
     lazy_compile_and_insert_into_cache(cel).map_err(CELEvalError::ParseError)?;
     let cache_read = CEL_PROGRAM_CACHE.read().unwrap();
     let p = match cache_read.get(cel) {
@@ -435,19 +842,7 @@ pub fn evaluate_cel<'a>(
 
     let ocel_key = CelCacheKey::from_ocel(ocel);
 
-    CEL_BASE_CTX.with(|cell| {
-        let needs_rebuild = cell
-            .borrow()
-            .as_ref()
-            .map(|(k, _)| *k != ocel_key)
-            .unwrap_or(true);
-        if needs_rebuild {
-            *cell.borrow_mut() = Some((ocel_key, build_base_cel_context(ocel)));
-        }
-
-        let cached = cell.borrow();
-        let base = &cached.as_ref().unwrap().1;
-
+    with_base_cel_context(|base| {
         let mut context = base.new_inner_scope();
 
         for (e_var, e_index) in binding.event_map.iter() {
@@ -464,7 +859,15 @@ pub fn evaluate_cel<'a>(
                 Into::<cel_interpreter::Value>::into(value.clone()),
             );
         }
-        context.add_variable_from_value("now", Value::Timestamp(Local::now().into()));
+        context.add_variable_from_value("now", Value::Timestamp(now));
+        for parameter in env.parameters {
+            if let Some(value) = &parameter.default {
+                context.add_variable_from_value(
+                    parameter.name.clone(),
+                    value.cel_value(parameter.parameter_type),
+                );
+            }
+        }
 
         if let Some(child_res) = child_res {
             for (child_name, child_out) in child_res {
@@ -502,7 +905,7 @@ pub fn evaluate_cel<'a>(
             }
         }
 
-        Ok(p.execute(&context)?)
+        Ok(EVAL_NOW.set(&now, || EVAL_OCEL.set(ocel, || p.execute(&context)))?)
     })
 }
 
@@ -523,8 +926,9 @@ pub fn check_cel_predicate<'a>(
     binding: &'a Binding,
     child_res: Option<&HashMap<String, Vec<(Arc<Binding>, Option<ViolationReason>)>>>,
     ocel: &'a SlimLinkedOCEL,
+    env: CelEnv<'_>,
 ) -> Result<bool, String> {
-    match evaluate_cel(cel, binding, child_res, ocel) {
+    match evaluate_cel(cel, binding, child_res, ocel, env) {
         Ok(Value::Bool(b)) => Ok(b),
         Ok(_) => Err("Got non-bool CEL result!".to_string()),
         Err(CELEvalError::ExecError(e)) => Err(e.to_string()),
@@ -537,8 +941,9 @@ pub fn add_cel_label<'a>(
     child_res: Option<&HashMap<String, Vec<(Arc<Binding>, Option<ViolationReason>)>>>,
     ocel: &'a SlimLinkedOCEL,
     label_fun: &'a LabelFunction,
+    env: CelEnv<'_>,
 ) -> Result<(), String> {
-    match evaluate_cel(&label_fun.cel, binding, child_res, ocel) {
+    match evaluate_cel(&label_fun.cel, binding, child_res, ocel, env) {
         Ok(v) => {
             binding.add_label(label_fun.label.clone(), v.into());
             Ok(())
@@ -553,6 +958,225 @@ pub fn add_cel_label<'a>(
     }
 }
 
+/// The target of a navigation function like `o1.objects()`, if it is called on one.
+fn navigation_target(ftx: &FunctionContext) -> Result<Option<EventOrObjectIndex>, ExecutionError> {
+    match &ftx.this {
+        None => Ok(None),
+        Some(Value::String(s)) => string_to_index(s)
+            .map(Some)
+            .ok_or_else(|| ftx.error("Event or Object not found.")),
+        Some(_) => Err(ftx.error("Expected an event or object.")),
+    }
+}
+
+/// The (at most two) optional string arguments of a navigation function, like the type and
+/// qualifier of `o1.objects('item', 'contains')`. Empty strings and `null` keep everything.
+fn navigation_filters(
+    ftx: &FunctionContext,
+    args: &[Value],
+) -> Result<(Option<Arc<String>>, Option<Arc<String>>), ExecutionError> {
+    if args.len() > 2 {
+        return Err(ftx.error(format!(
+            "{} takes at most two arguments, got {}",
+            ftx.name,
+            args.len()
+        )));
+    }
+    let mut filters = args.iter().map(|arg| match arg {
+        Value::String(s) if !s.is_empty() => Ok(Some(s.clone())),
+        Value::String(_) | Value::Null => Ok(None),
+        _ => Err(ftx.error(format!("Arguments of {} must be strings", ftx.name))),
+    });
+    let first = filters.next().transpose()?.flatten();
+    let second = filters.next().transpose()?.flatten();
+    Ok((first, second))
+}
+
+fn keeps(filter: &Option<Arc<String>>, value: &str) -> bool {
+    filter.as_ref().is_none_or(|f| f.as_str() == value)
+}
+
+/// Names of the related objects of `ob_type` through `qualifier`, each once.
+fn related_object_names(
+    ocel: &SlimLinkedOCEL,
+    related: Vec<(&str, ObjectIndex)>,
+    ob_type: &Option<Arc<String>>,
+    qualifier: &Option<Arc<String>>,
+) -> Vec<String> {
+    related
+        .into_iter()
+        .filter(|(q, o)| keeps(qualifier, q) && keeps(ob_type, ocel.get_ob_type_of(o)))
+        .map(|(_, o)| o)
+        .unique()
+        .map(|o| ob_index_to_name(&o))
+        .collect_vec()
+}
+
+fn timestamp_arg(
+    ftx: &FunctionContext,
+    args: &[Value],
+    i: usize,
+) -> Result<DateTime<FixedOffset>, ExecutionError> {
+    match args.get(i) {
+        Some(Value::Timestamp(t)) => Ok(*t),
+        _ => Err(ftx.error(format!(
+            "{}: argument {} must be a timestamp",
+            ftx.name,
+            i + 1
+        ))),
+    }
+}
+
+fn string_arg(
+    ftx: &FunctionContext,
+    args: &[Value],
+    i: usize,
+) -> Result<Option<Arc<String>>, ExecutionError> {
+    match args.get(i) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(ftx.error(format!("{}: argument {} must be a string", ftx.name, i + 1))),
+    }
+}
+
+/// The business calendar named by argument `i` (the default calendar, if there is none).
+fn calendar_arg(
+    ftx: &FunctionContext,
+    args: &[Value],
+    i: usize,
+) -> Result<Arc<BusinessCalendar>, ExecutionError> {
+    let name = string_arg(ftx, args, i)?;
+    get_calendar(name.as_deref().map_or(DEFAULT_CALENDAR, String::as_str)).map_err(|e| ftx.error(e))
+}
+
+/// The time zone of argument `i`: a UTC offset or the name of a calendar (UTC, if there is none).
+fn offset_arg(
+    ftx: &FunctionContext,
+    args: &[Value],
+    i: usize,
+) -> Result<FixedOffset, ExecutionError> {
+    match string_arg(ftx, args, i)? {
+        None => Ok(FixedOffset::east_opt(0).expect("zero offset is valid")),
+        Some(tz) => resolve_offset(&tz).map_err(|e| ftx.error(e)),
+    }
+}
+
+/// How many compiled patterns [`REGEX_CACHE`] keeps. Patterns built from bound values can differ
+/// for every binding, so the cache is cleared once it is full instead of growing with them.
+const MAX_CACHED_REGEXES: usize = 256;
+
+/// Compiled regular expressions of the CEL regex functions, by pattern
+static REGEX_CACHE: Lazy<RwLock<HashMap<String, regex::Regex>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+fn cached_regex(ftx: &FunctionContext, pattern: &str) -> Result<regex::Regex, ExecutionError> {
+    if let Some(re) = REGEX_CACHE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(pattern)
+    {
+        return Ok(re.clone());
+    }
+    let re = regex::Regex::new(pattern)
+        .map_err(|e| ftx.error(format!("{}: invalid regex: {e}", ftx.name)))?;
+    let mut cache = REGEX_CACHE.write().unwrap_or_else(|e| e.into_inner());
+    if cache.len() >= MAX_CACHED_REGEXES {
+        cache.clear();
+    }
+    cache.insert(pattern.to_string(), re.clone());
+    Ok(re)
+}
+
+/// Like [`Value::partial_cmp`], but also compares `int`s with `double`s.
+fn compare_values(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (
+            Value::Int(_) | Value::UInt(_) | Value::Float(_),
+            Value::Int(_) | Value::UInt(_) | Value::Float(_),
+        ) => match (a, b) {
+            (Value::Int(x), Value::Int(y)) => Some(x.cmp(y)),
+            (Value::UInt(x), Value::UInt(y)) => Some(x.cmp(y)),
+            _ => value_to_float(a).partial_cmp(&value_to_float(b)),
+        },
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+        (Value::Timestamp(x), Value::Timestamp(y)) => Some(x.cmp(y)),
+        (Value::Duration(x), Value::Duration(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+/// The first of the arguments (or, for a single list argument, of its elements) that no other
+/// one is `wanted` to.
+fn extreme_value(
+    ftx: &FunctionContext,
+    args: &[Value],
+    wanted: std::cmp::Ordering,
+) -> ResolveResult {
+    // `[1, 2].min()` has the list as target
+    let args = ftx.this.iter().chain(args).cloned().collect_vec();
+    let items = match args.as_slice() {
+        [Value::List(values)] => values.as_slice(),
+        _ => args.as_slice(),
+    };
+    let mut best = match items.first() {
+        Some(first) => first,
+        None => return Ok(Value::Null),
+    };
+    for x in &items[1..] {
+        match compare_values(x, best) {
+            Some(ord) if ord == wanted => best = x,
+            Some(_) => {}
+            None => {
+                return Err(ftx.error(format!(
+                    "{}: {best:?} and {x:?} are not comparable",
+                    ftx.name
+                )))
+            }
+        }
+    }
+    Ok(best.clone())
+}
+
+/// The values of `values`, without repetitions
+fn distinct_values<'a>(values: impl Iterator<Item = &'a Value>) -> Vec<Value> {
+    let mut res: Vec<Value> = Vec::new();
+    for v in values {
+        if !res.contains(v) {
+            res.push(v.clone());
+        }
+    }
+    res
+}
+
+/// The `p`-th percentile (0 to 100) of a list of numbers or of durations
+fn percentile(ftx: &FunctionContext, list: &[Value], p: f64) -> ResolveResult {
+    let durations = list.iter().all(|v| matches!(v, Value::Duration(_)));
+    let mut sorted = list
+        .iter()
+        .map(|v| match v {
+            Value::Duration(d) if durations => Ok(d.num_milliseconds() as f64),
+            Value::Int(_) | Value::UInt(_) | Value::Float(_) if !durations => Ok(value_to_float(v)),
+            _ => Err(ftx.error(format!(
+                "{}: the list must only contain numbers or only durations",
+                ftx.name
+            ))),
+        })
+        .collect::<Result<Vec<f64>, _>>()?;
+    if sorted.is_empty() {
+        return Ok(Value::Null);
+    }
+    sorted.sort_by(f64::total_cmp);
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lower, upper) = (sorted[rank.floor() as usize], sorted[rank.ceil() as usize]);
+    let res = lower + (upper - lower) * rank.fract();
+    Ok(if durations {
+        Value::Duration(chrono::Duration::milliseconds(res.round() as i64))
+    } else {
+        Value::Float(res)
+    })
+}
+
 fn value_to_float(val: &Value) -> f64 {
     match val {
         Value::Int(i) => *i as f64,
@@ -573,30 +1197,61 @@ fn ocel_val_to_cel_val(val: OCELAttributeValue) -> Value {
         OCELAttributeValue::Null => Value::Null,
     }
 }
-fn string_to_var(s: &str) -> Variable {
-    let (typ, num) = s.split_at(1);
-    let num = num.parse::<usize>().map(|v| v - 1).unwrap_or_default();
-    if typ == "o" {
-        Variable::Object(ObjectVariable(num))
+/// The variable named `s` (like `e1` or `o2`), or `None` for other identifiers (like labels).
+pub(crate) fn string_to_var(s: &str) -> Option<Variable> {
+    let num = s.get(1..)?.parse::<usize>().ok()?.checked_sub(1)?;
+    // `e01` is not how `e1` is named in the CEL context.
+    if s.starts_with('o') && ob_var_to_name(&ObjectVariable(num)) == s {
+        Some(Variable::Object(ObjectVariable(num)))
+    } else if s.starts_with('e') && ev_var_to_name(&EventVariable(num)) == s {
+        Some(Variable::Event(EventVariable(num)))
     } else {
-        Variable::Event(EventVariable(num))
+        None
     }
 }
 
-pub fn get_vars_in_cel_program(cel: &str) -> HashSet<Variable> {
-    if lazy_compile_and_insert_into_cache(cel).is_ok() {
-        let r_lock = CEL_PROGRAM_CACHE.read().unwrap();
-        let p = r_lock.get(cel).unwrap();
-        p.references()
-            .variables()
-            .into_iter()
-            .map(string_to_var)
-            .collect()
-    } else {
-        HashSet::default()
+/// The value of `name(arg)` (like `duration('1h')`), computed by CEL itself so literals are read
+/// exactly as in a program.
+pub(crate) fn evaluate_literal_call(name: &str, arg: &str) -> Option<Value> {
+    let mut context = Context::default();
+    context.add_variable_from_value("arg", Value::String(arg.to_string().into()));
+    Program::compile(&format!("{name}(arg)"))
+        .ok()?
+        .execute(&context)
+        .ok()
+}
+
+/// `d` in seconds, to the microsecond (like `TimeBetweenEvents` filters measure).
+pub(crate) fn duration_seconds(d: chrono::Duration) -> f64 {
+    match d.num_microseconds() {
+        Some(us) => us as f64 / 1_000_000.0,
+        None => d.num_milliseconds() as f64 / 1000.0,
     }
 }
 
+pub fn get_vars_in_cel_program(cel: &str) -> HashSet<Variable> {
+    get_identifiers_in_cel_program(cel)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|s| string_to_var(s))
+        .collect()
+}
+
+/// All identifiers `cel` references: variables, labels, child names and `now`, but also the
+/// variables of comprehension macros (see [`syntax::macro_variables`]).
+pub fn get_identifiers_in_cel_program(cel: &str) -> Result<HashSet<String>, String> {
+    lazy_compile_and_insert_into_cache(cel)?;
+    let r_lock = CEL_PROGRAM_CACHE.read().unwrap();
+    let p = r_lock
+        .get(cel)
+        .ok_or_else(|| String::from("Could not parse CEL"))?;
+    Ok(p.references()
+        .variables()
+        .into_iter()
+        .map(|s| s.to_string())
+        .collect())
+}
+
 impl From<cel_interpreter::Value> for LabelValue {
     fn from(value: cel_interpreter::Value) -> Self {
         match value {
@@ -623,3 +1278,80 @@ impl From<LabelValue> for cel_interpreter::Value {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(cel: &str) -> Value {
+        Program::compile(cel)
+            .unwrap()
+            .execute(&build_base_cel_context())
+            .unwrap_or_else(|e| panic!("{cel}: {e}"))
+    }
+
+    #[test]
+    fn the_regex_cache_stays_bounded_by_distinct_patterns() {
+        for i in 0..2 * MAX_CACHED_REGEXES {
+            assert_eq!(
+                run(&format!("'ORD-{i}'.regexFind('^ORD-{i}$') == 'ORD-{i}'")),
+                Value::Bool(true)
+            );
+        }
+        let cached = REGEX_CACHE.read().unwrap_or_else(|e| e.into_inner()).len();
+        assert!(cached <= MAX_CACHED_REGEXES, "{cached} patterns cached");
+    }
+
+    #[test]
+    fn standard_extensions_cover_regex_strings_sets_and_percentiles() {
+        for (cel, expected) in [
+            ("'ORD-2024-17'.regexFind('[0-9]+') == '2024'", true),
+            ("'no digits'.regexFind('[0-9]+') == null", true),
+            ("'a1b22c333'.regexFindAll('[0-9]+') == ['1', '22', '333']", true),
+            (
+                "'ORD-2024-17'.regexCapture('([A-Z]+)-(\\\\d+)-(x)?') == ['ORD', '2024', '']",
+                true,
+            ),
+            ("regexReplace('a-b-c', '-', '$0$0') == 'a--b--c'", true),
+            ("'Paid'.equalsIgnoreCase('PAID') && ' x '.trim().upper() == 'X'", true),
+            ("'a,b,,c'.split(',') == ['a', 'b', '', 'c']", true),
+            ("['a', 'b'].join(', ') == 'a, b' && join(['a', 'b']) == 'ab'", true),
+            ("[3, 1, 3, 2, 1].distinct() == [3, 1, 2]", true),
+            ("[1, 2].union([2, 3]) == [1, 2, 3]", true),
+            ("[1, 2, 3, 2].intersect([3, 2]) == [2, 3]", true),
+            ("[1, 2, 3].difference([2]) == [1, 3]", true),
+            ("[4, 1, 3, 2].median() == 2.5 && [1, 2, 3, 4, 5].percentile(25) == 2.0", true),
+            (
+                "[duration('1h'), duration('3h'), duration('2h')].median() == duration('2h')",
+                true,
+            ),
+            ("[].median() == null", true),
+            ("min([3, 1.5, 2]) == 1.5 && max(3, 7, 5) == 7", true),
+            (
+                "max([timestamp('2024-01-02T00:00:00Z'), timestamp('2024-01-03T00:00:00Z')]) == timestamp('2024-01-03T00:00:00Z')",
+                true,
+            ),
+            ("min(duration('2h'), duration('90m')) == duration('90m')", true),
+        ] {
+            assert_eq!(run(cel), Value::Bool(expected), "{cel}");
+        }
+    }
+
+    #[test]
+    fn standard_extensions_report_invalid_arguments() {
+        let context = build_base_cel_context();
+        for (cel, message) in [
+            ("'x'.regexFind('(')", "invalid regex"),
+            ("[1, 'a'].min()", "not comparable"),
+            ("[1, 2].join(',')", "only contain strings"),
+            ("[1, duration('1h')].median()", "only durations"),
+            ("[1].percentile(101)", "between 0 and 100"),
+        ] {
+            let err = Program::compile(cel)
+                .unwrap()
+                .execute(&context)
+                .unwrap_err();
+            assert!(err.to_string().contains(message), "{cel}: {err}");
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Optional profiling of CEL evaluation: how often each program of a tree ran, how long it took
+//! and how often it failed, to tell which one dominates a slow query.
+//!
+//! Recording is enabled per evaluation through
+//! [`EvaluationControl::with_cel_profiling`](crate::binding_box::control::EvaluationControl) and
+//! keyed by node and program text; the locations within a node are attributed afterwards (see
+//! [`CelProfiler::profile`]). Times come from [`crate::timing::Timer`], so they are zero on wasm.
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use itertools::Itertools;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use crate::binding_box::{
+    validate::{validate_tree, TreeLocation},
+    BindingBoxTree,
+};
+
+#[derive(Debug, Default, Clone, Copy)]
+struct CallStats {
+    calls: u64,
+    errors: u64,
+    total: Duration,
+}
+
+impl CallStats {
+    fn add(&mut self, other: &CallStats) {
+        self.calls += other.calls;
+        self.errors += other.errors;
+        self.total += other.total;
+    }
+}
+
+/// Collects the calls of one evaluation's CEL programs.
+#[derive(Debug, Default)]
+pub struct CelProfiler {
+    /// By node index, then program text
+    calls: Mutex<HashMap<usize, HashMap<String, CallStats>>>,
+}
+
+/// Calls of one CEL program, over all positions it has in the tree
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CelProgramProfile {
+    pub cel: String,
+    #[ts(type = "number")]
+    pub calls: u64,
+    #[ts(type = "number")]
+    pub errors: u64,
+    pub total_ms: f64,
+}
+
+/// Calls of one CEL program of one node
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CelPositionProfile {
+    pub node_index: usize,
+    /// Where the program is in the node; several if it is written there more than once, which
+    /// then share these counts.
+    pub locations: Vec<TreeLocation>,
+    pub cel: String,
+    #[ts(type = "number")]
+    pub calls: u64,
+    #[ts(type = "number")]
+    pub errors: u64,
+    pub total_ms: f64,
+}
+
+/// The CEL profile of an evaluation, both sorted by descending total time.
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CelProfile {
+    pub programs: Vec<CelProgramProfile>,
+    pub positions: Vec<CelPositionProfile>,
+}
+
+impl CelProfiler {
+    pub(crate) fn record(&self, node_index: usize, cel: &str, elapsed: Duration, failed: bool) {
+        let Ok(mut calls) = self.calls.lock() else {
+            return;
+        };
+        let by_program = calls.entry(node_index).or_default();
+        let stats = match by_program.get_mut(cel) {
+            Some(stats) => stats,
+            None => by_program.entry(cel.to_string()).or_default(),
+        };
+        stats.calls += 1;
+        stats.errors += u64::from(failed);
+        stats.total += elapsed;
+    }
+
+    /// What was recorded so far, with the positions of the programs in `tree`.
+    pub fn profile(&self, tree: &BindingBoxTree) -> CelProfile {
+        let calls = self.calls.lock().map(|c| c.clone()).unwrap_or_default();
+        let cel_types = validate_tree(tree).cel_types;
+        let mut programs: HashMap<&str, CallStats> = HashMap::new();
+        let mut positions = Vec::new();
+        for (node_index, by_program) in &calls {
+            for (cel, stats) in by_program {
+                programs.entry(cel).or_default().add(stats);
+                positions.push(CelPositionProfile {
+                    node_index: *node_index,
+                    locations: cel_types
+                        .iter()
+                        .filter(|t| t.node_index == *node_index && t.cel == *cel)
+                        .map(|t| t.location)
+                        .collect(),
+                    cel: cel.clone(),
+                    calls: stats.calls,
+                    errors: stats.errors,
+                    total_ms: millis(stats.total),
+                });
+            }
+        }
+        CelProfile {
+            programs: programs
+                .into_iter()
+                .map(|(cel, stats)| CelProgramProfile {
+                    cel: cel.to_string(),
+                    calls: stats.calls,
+                    errors: stats.errors,
+                    total_ms: millis(stats.total),
+                })
+                .sorted_by(|a, b| b.total_ms.total_cmp(&a.total_ms).then(a.cel.cmp(&b.cel)))
+                .collect(),
+            positions: positions
+                .into_iter()
+                .sorted_by(|a, b| {
+                    b.total_ms
+                        .total_cmp(&a.total_ms)
+                        .then(a.node_index.cmp(&b.node_index))
+                        .then(a.cel.cmp(&b.cel))
+                })
+                .collect(),
+        }
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::binding_box::{
+        control::EvaluationControl,
+        evaluate_box_tree_controlled,
+        structs::{BindingBox, BindingBoxTreeNode, Filter, LabelFunction},
+        test_utils::{ev_vars, eval, ocel, tree},
+    };
+
+    #[test]
+    fn cel_profile_counts_calls_and_errors_per_program_and_position() {
+        let placements = tree(
+            vec![BindingBoxTreeNode::Box(
+                BindingBox {
+                    new_event_vars: ev_vars(&[(0, "place")]),
+                    filters: vec![Filter::BasicFilterCEL {
+                        cel: "e1.time() <= now".to_string(),
+                    }],
+                    labels: vec![LabelFunction {
+                        label: "amount".to_string(),
+                        cel: "e1.attr('amount') + 1".to_string(),
+                        aggregate: None,
+                    }],
+                    ..Default::default()
+                },
+                vec![],
+            )],
+            &[],
+        );
+        let control = EvaluationControl::default().with_cel_profiling();
+        let res = evaluate_box_tree_controlled(placements.clone(), &ocel(), false, &control)
+            .expect("evaluation succeeds");
+        let profile = res.summary().cel_profile.expect("profiled");
+        let calls = |cel: &str| {
+            let p = profile.programs.iter().find(|p| p.cel == cel).unwrap();
+            (p.calls, p.errors)
+        };
+        assert_eq!(calls("e1.time() <= now"), (2, 0));
+        assert_eq!(calls("e1.attr('amount') + 1"), (2, 0));
+        let label = profile
+            .positions
+            .iter()
+            .find(|p| p.cel == "e1.attr('amount') + 1")
+            .unwrap();
+        assert_eq!(
+            (label.node_index, label.locations.as_slice()),
+            (0, [TreeLocation::Label { index: 0 }].as_slice())
+        );
+
+        assert_eq!(eval(placements).cel_profile, None);
+    }
+}
@@ -0,0 +1,355 @@
+//! Best-effort inspection of CEL source text that does not need an OCEL: splitting expressions at
+//! top-level operators, finding comprehension variables, replacing identifiers and inferring
+//! result types.
+use std::collections::{HashMap, HashSet};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+/// Result type of a CEL expression, as far as it can be told without evaluating it
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum CelType {
+    Bool,
+    Int,
+    Float,
+    String,
+    Timestamp,
+    Duration,
+    List,
+    Map,
+    Null,
+    /// Depends on the OCEL (e.g., attribute values) or could not be inferred
+    Unknown,
+}
+
+impl CelType {
+    fn is_number(self) -> bool {
+        matches!(self, CelType::Int | CelType::Float)
+    }
+}
+
+/// Macros that introduce a variable as their first argument (e.g., `x` in `l.exists(x, x > 1)`)
+const COMPREHENSION_MACROS: [&str; 5] = ["all", "exists", "exists_one", "map", "filter"];
+
+/// Functions whose result type does not depend on their arguments.
+pub(crate) fn function_result_type(name: &str) -> Option<CelType> {
+    Some(match name {
+        "all" | "exists" | "exists_one" | "contains" | "startsWith" | "endsWith" | "matches"
+        | "has" | "bool" | "isBusinessDay" | "equalsIgnoreCase" => CelType::Bool,
+        "size" | "int" | "uint" | "numEvents" | "numObjects" | "weekday" => CelType::Int,
+        "double" | "sum" | "avg" => CelType::Float,
+        "string" | "type" | "id" | "regexReplace" | "lower" | "upper" | "trim" | "join" => {
+            CelType::String
+        }
+        "timestamp" | "time" | "addBusinessDays" | "startOfDay" => CelType::Timestamp,
+        "duration" | "businessDuration" => CelType::Duration,
+        "map" | "filter" | "events" | "objects" | "related" | "regexFindAll" | "regexCapture"
+        | "split" | "distinct" | "union" | "intersect" | "difference" => CelType::List,
+        "attrs" => CelType::Map,
+        _ => return None,
+    })
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Split `expr` at the operators in `ops` that are outside of parentheses, brackets, braces and
+/// string literals.
+pub(crate) fn split_top_level(expr: &str, ops: &[&str]) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0_i32;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+    let mut i = 0;
+    while i < expr.len() {
+        let c = expr[i..].chars().next().unwrap_or_default();
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+        } else {
+            match c {
+                '"' | '\'' => quote = Some(c),
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ if depth == 0 => {
+                    if let Some(op) = ops.iter().find(|op| expr[i..].starts_with(**op)) {
+                        parts.push(expr[start..i].trim().to_string());
+                        i += op.len();
+                        start = i;
+                        continue;
+                    }
+                }
+                _ => {}
+            }
+        }
+        i += c.len_utf8();
+    }
+    parts.push(expr[start..].trim().to_string());
+    parts
+}
+
+/// `expr` without parentheses that enclose all of it.
+pub(crate) fn strip_outer_parens(mut expr: &str) -> &str {
+    while expr.starts_with('(') && expr.ends_with(')') {
+        let inner = &expr[1..expr.len() - 1];
+        // `(a) && (b)` starts and ends with a parenthesis, but they do not belong together.
+        let mut depth = 0_i32;
+        let mut quote: Option<char> = None;
+        let mut escaped = false;
+        for c in inner.chars() {
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            match c {
+                '"' | '\'' => quote = Some(c),
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+            if depth < 0 {
+                return expr;
+            }
+        }
+        expr = inner.trim();
+    }
+    expr
+}
+
+/// Position of the bracket that opens the group closed by the last character of `expr`, if `expr`
+/// ends with a closing bracket.
+fn opening_of_last_group(expr: &str) -> Option<usize> {
+    if !expr.ends_with([')', ']', '}']) {
+        return None;
+    }
+    let mut open = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in expr.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' | '{' => open.push(i),
+            ')' | ']' | '}' => {
+                let start = open.pop()?;
+                if i + c.len_utf8() == expr.len() {
+                    return Some(start);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The variables that comprehension macros in `cel` introduce, like `x` in `l.exists(x, x > 1)`.
+/// They are only in scope inside the macro, but are reported as references of the whole program.
+pub fn macro_variables(cel: &str) -> HashSet<String> {
+    let mut res = HashSet::new();
+    for name in COMPREHENSION_MACROS {
+        let pattern = format!(".{name}(");
+        for (pos, _) in cel.match_indices(&pattern) {
+            let rest = cel[pos + pattern.len()..].trim_start();
+            let var: String = rest
+                .chars()
+                .take_while(|c| is_identifier_char(*c))
+                .collect();
+            if !var.is_empty() && rest[var.len()..].trim_start().starts_with(',') {
+                res.insert(var);
+            }
+        }
+    }
+    res
+}
+
+/// Infer the result type of `cel` from its text, where `env` holds the types of the identifiers
+/// it may use (variables, labels and child results). Gives [`CelType::Unknown`] whenever the type
+/// depends on the OCEL or on something the inference does not understand.
+pub fn infer_cel_type(cel: &str, env: &HashMap<String, CelType>) -> CelType {
+    let expr = strip_outer_parens(cel.trim());
+    if expr.is_empty() {
+        return CelType::Unknown;
+    }
+    // Before splitting at `-`, which also occurs in a literal like `1e-3` on its own.
+    if let Some(number) = number_literal_type(expr) {
+        return number;
+    }
+
+    let ternary = split_top_level(expr, &["?"]);
+    if ternary.len() == 2 {
+        let branches = split_top_level(&ternary[1], &[":"]);
+        if branches.len() != 2 {
+            return CelType::Unknown;
+        }
+        let (a, b) = (
+            infer_cel_type(&branches[0], env),
+            infer_cel_type(&branches[1], env),
+        );
+        return if a == b { a } else { CelType::Unknown };
+    }
+    if split_top_level(expr, &["||", "&&"]).len() > 1
+        || split_top_level(expr, &["==", "!=", "<=", ">=", "<", ">", " in "]).len() > 1
+    {
+        return CelType::Bool;
+    }
+
+    for ops in [&["+", "-"][..], &["*", "/", "%"][..]] {
+        let mut operands = split_top_level(expr, ops);
+        if operands.len() > 1 {
+            // A leading empty operand is a unary minus.
+            if operands[0].is_empty() {
+                operands.remove(0);
+            }
+            return operands
+                .iter()
+                .map(|o| {
+                    if o.is_empty() {
+                        CelType::Unknown
+                    } else {
+                        infer_cel_type(o, env)
+                    }
+                })
+                .reduce(arithmetic_result_type)
+                .unwrap_or(CelType::Unknown);
+        }
+    }
+
+    if let Some(negated) = expr.strip_prefix('!') {
+        return match infer_cel_type(negated, env) {
+            CelType::Bool | CelType::Unknown => CelType::Bool,
+            _ => CelType::Unknown,
+        };
+    }
+    match expr {
+        "true" | "false" => return CelType::Bool,
+        "null" => return CelType::Null,
+        _ => {}
+    }
+    if expr.len() >= 2
+        && ((expr.starts_with('"') && expr.ends_with('"'))
+            || (expr.starts_with('\'') && expr.ends_with('\'')))
+    {
+        return CelType::String;
+    }
+    if expr.chars().all(is_identifier_char) {
+        return env.get(expr).copied().unwrap_or(CelType::Unknown);
+    }
+
+    match opening_of_last_group(expr) {
+        Some(0) if expr.starts_with('[') => CelType::List,
+        Some(0) if expr.starts_with('{') => CelType::Map,
+        Some(start) if expr.ends_with(')') => {
+            let name: String = expr[..start]
+                .chars()
+                .rev()
+                .take_while(|c| is_identifier_char(*c))
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .collect();
+            function_result_type(&name).unwrap_or(CelType::Unknown)
+        }
+        _ => CelType::Unknown,
+    }
+}
+
+fn number_literal_type(expr: &str) -> Option<CelType> {
+    if expr.parse::<i64>().is_ok()
+        || expr
+            .strip_suffix(['u', 'U'])
+            .is_some_and(|n| n.parse::<u64>().is_ok())
+        || expr
+            .strip_prefix("0x")
+            .is_some_and(|n| i64::from_str_radix(n, 16).is_ok())
+    {
+        Some(CelType::Int)
+    } else if expr.parse::<f64>().is_ok() {
+        Some(CelType::Float)
+    } else {
+        None
+    }
+}
+
+fn arithmetic_result_type(a: CelType, b: CelType) -> CelType {
+    match (a, b) {
+        (CelType::Int, CelType::Int) => CelType::Int,
+        (a, b) if a.is_number() && b.is_number() => CelType::Float,
+        (CelType::String, CelType::String) => CelType::String,
+        (CelType::List, CelType::List) => CelType::List,
+        (CelType::Timestamp, CelType::Timestamp) => CelType::Duration,
+        (CelType::Timestamp, CelType::Duration) | (CelType::Duration, CelType::Timestamp) => {
+            CelType::Timestamp
+        }
+        (CelType::Duration, CelType::Duration) => CelType::Duration,
+        _ => CelType::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infers_types_from_operators_literals_and_functions() {
+        let env = HashMap::from([
+            ("e1".to_string(), CelType::String),
+            ("now".to_string(), CelType::Timestamp),
+            ("total".to_string(), CelType::Int),
+            ("A".to_string(), CelType::List),
+        ]);
+        for (cel, expected) in [
+            ("e1.attr('amount') > 10 && true", CelType::Bool),
+            ("!(A.exists(x, x.satisfied))", CelType::Bool),
+            ("(total + 1) * 2", CelType::Int),
+            ("total / 2.5", CelType::Float),
+            ("1e-3", CelType::Float),
+            ("-total", CelType::Int),
+            ("'a' + e1.type()", CelType::String),
+            ("size(A)", CelType::Int),
+            ("A.map(x, x.e1)", CelType::List),
+            ("now - e1.time()", CelType::Duration),
+            ("now + duration('1h')", CelType::Timestamp),
+            ("total > 1 ? 'big' : 'small'", CelType::String),
+            ("total > 1 ? 'big' : 1", CelType::Unknown),
+            ("e1.attr('amount')", CelType::Unknown),
+            ("{'a': 1}", CelType::Map),
+            ("[1, 2][0]", CelType::Unknown),
+            ("unknown_label", CelType::Unknown),
+        ] {
+            assert_eq!(infer_cel_type(cel, &env), expected, "type of {cel}");
+        }
+    }
+
+    #[test]
+    fn finds_comprehension_variables() {
+        assert_eq!(
+            macro_variables("A.exists(x, x.satisfied) && B.map( y , y.o1).all(z, z != o1)"),
+            HashSet::from(["x".to_string(), "y".to_string(), "z".to_string()])
+        );
+    }
+}
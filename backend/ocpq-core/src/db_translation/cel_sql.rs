@@ -0,0 +1,1026 @@
+//! Compiling CEL to SQL expressions, for the CEL filters, size filters and constraints the SQL
+//! translation cannot express as native filters (see [`lower_cel_filters`]), and for the labels
+//! these use.
+//!
+//! The supported subset is
+//!
+//! - literals (numbers, strings, booleans, `null` and, after `in`, lists of these),
+//!   `timestamp('...')` and `duration('...')`,
+//! - the variables in scope, through `e1.attr('price')`, `o1.attr('status')` (the object's first
+//!   value, as in its canonical row), `o1.attrAt('status', e1.time())`, `e1.time()`, `e1.id()`
+//!   and `e1.type()`, and compared with each other through `==` and `!=`,
+//! - arithmetic, comparisons, `&&`, `||`, `!`, `?:`, `in`, `size()`, `double()`, `lower()`,
+//!   `upper()`, `trim()`, `startsWith()`, `endsWith()`, `contains()` and `min`/`max` of several
+//!   values,
+//! - in size filters, size filter constraints and labels, the results of a child `A`: `size(A)`,
+//!   `A.all(c, ...)`, `A.exists(c, ...)`, `A.exists_one(c, ...)`, `A.filter(c, ...)` and
+//!   `A.map(c, ...)` with `sum()`, `avg()`, `min()` or `max()`, where a binding `c` of the child
+//!   has `c.satisfied` and its variables (`c.e2` or `c['e2']`),
+//! - the labels of the node, which are compiled where they are used, as the query does not
+//!   select them, and
+//! - the parameters of the tree, as the literals of the values they are bound to.
+//!
+//! Times are seconds since the epoch and durations seconds. Anything else (like `now`, or the
+//! labels of a child's bindings) is an error that names it, and the filter or constraint is left
+//! out of the query and reported (see [`TranslationResult::unsupported`]), so a translated query
+//! is never silently more permissive than the tree. Where CEL fails on a missing attribute or a
+//! value of the wrong type, SQL compares `NULL`, so the binding is filtered out (or violates the
+//! constraint).
+//!
+//! [`lower_cel_filters`]: crate::cel::lowering::lower_cel_filters
+//! [`TranslationResult::unsupported`]: super::TranslationResult::unsupported
+use cel_interpreter::Value;
+use cel_parser::{ArithmeticOp, Atom, Expression, Member, RelationOp, UnaryOp};
+use chrono::{DateTime, Utc};
+
+use super::{
+    attribute_value_as, child_key_columns, e_alias, first_type, map_eventttables, map_objecttables,
+    map_time_literal, map_timestamp, o_alias, AttributeType, DatabaseType, SqlParts,
+};
+use crate::binding_box::{
+    parameters::{ParameterType, ParameterValue, TreeParameter},
+    structs::Variable,
+};
+use crate::cel::{duration_seconds, evaluate_literal_call, string_to_var};
+
+/// Where a CEL expression is evaluated, which decides what it can use
+#[derive(Debug, Clone, Copy)]
+pub(super) enum CelScope {
+    /// A filter, checked while binding: only the variables
+    Filter,
+    /// A filter constraint: the variables and the labels
+    ConstraintFilter,
+    /// A size filter or size filter constraint: also the child results
+    SizeFilter,
+}
+
+/// `cel` as an SQL condition on the rows of `sql_parts`, or an error naming what of it cannot be
+/// translated
+pub(super) fn cel_condition(
+    sql_parts: &mut SqlParts,
+    cel: &str,
+    scope: CelScope,
+) -> Result<String, String> {
+    let expr = cel_parser::parse(cel).map_err(|_| format!("`{cel}` is no valid CEL"))?;
+    let labels = match scope {
+        CelScope::Filter => 0,
+        CelScope::ConstraintFilter | CelScope::SizeFilter => sql_parts.node.labels.len(),
+    };
+    let mut compiler = Compiler {
+        sql_parts,
+        children: matches!(scope, CelScope::SizeFilter),
+        labels,
+        elements: Vec::new(),
+    };
+    compiler
+        .condition(&expr)
+        .map_err(|e| format!("Cannot translate `{cel}` to SQL: {e}"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SqlType {
+    Bool,
+    Number,
+    String,
+    /// Seconds since the epoch
+    Time,
+    /// In seconds
+    Duration,
+    /// The id of an event or object
+    Id,
+    Null,
+}
+
+impl SqlType {
+    fn name(self) -> &'static str {
+        match self {
+            SqlType::Bool => "bool",
+            SqlType::Number => "number",
+            SqlType::String => "string",
+            SqlType::Time => "timestamp",
+            SqlType::Duration => "duration",
+            SqlType::Id => "event or object",
+            SqlType::Null => "null",
+        }
+    }
+}
+
+/// A compiled subexpression
+enum Term {
+    Value(String, SqlType),
+    /// An attribute, read as the type it is compared or computed with
+    Attribute(Attribute),
+    Var(VarRef),
+    /// The bindings of a child, possibly filtered and mapped
+    Rows(Rows),
+    /// A binding of a child, bound by a comprehension (an index into `Compiler::elements`)
+    Element(usize),
+    List(Vec<(String, SqlType)>),
+}
+
+/// An event or object variable
+#[derive(Clone)]
+struct VarRef {
+    /// As written in CEL, for error messages
+    name: String,
+    event: bool,
+    /// The SQL of its id
+    id: String,
+    /// The alias of its row, if it is bound in this query (and not in a child's binding)
+    row: Option<String>,
+    ocel_type: Option<String>,
+}
+
+struct Attribute {
+    owner: VarRef,
+    name: String,
+    /// For objects, the time (in seconds) to read the value at
+    at: Option<String>,
+}
+
+struct Rows {
+    child: usize,
+    alias: String,
+    conditions: Vec<String>,
+    value: Option<Box<Term>>,
+}
+
+/// A comprehension variable, ranging over the bindings of a child
+struct Element {
+    name: String,
+    child: usize,
+    alias: String,
+}
+
+struct Compiler<'p, 'a> {
+    sql_parts: &'p mut SqlParts<'a>,
+    /// Whether the child results are available
+    children: bool,
+    /// How many of the node's labels are available (those computed before)
+    labels: usize,
+    elements: Vec<Element>,
+}
+
+fn string_literal(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+/// The string `index` names a field with, as in `c['e2']`
+fn string_index(index: &Expression) -> Option<String> {
+    match index {
+        Expression::Atom(Atom::String(s)) => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+impl Compiler<'_, '_> {
+    fn condition(&mut self, expr: &Expression) -> Result<String, String> {
+        match self.value_of(expr, Some(SqlType::Bool))? {
+            (sql, SqlType::Bool) => Ok(sql),
+            (_, ty) => Err(format!("expected a condition, got a {}", ty.name())),
+        }
+    }
+
+    fn value_of(
+        &mut self,
+        expr: &Expression,
+        hint: Option<SqlType>,
+    ) -> Result<(String, SqlType), String> {
+        let term = self.term(expr)?;
+        self.value(term, hint)
+    }
+
+    /// `term` as an SQL value; an attribute is read as `hint` (as a string without one)
+    fn value(&mut self, term: Term, hint: Option<SqlType>) -> Result<(String, SqlType), String> {
+        match term {
+            Term::Value(sql, ty) => Ok((sql, ty)),
+            Term::Attribute(attribute) => self.attribute(&attribute, hint),
+            Term::Var(var) => Ok((var.id, SqlType::Id)),
+            Term::Rows(_) => Err(
+                "the results of a child can only be used through size(), all(), exists(), \
+                 exists_one(), filter() and map()"
+                    .to_string(),
+            ),
+            Term::Element(i) => Err(format!(
+                "`{}` can only be used through its fields",
+                self.elements[i].name
+            )),
+            Term::List(_) => Err("lists can only be used after `in`".to_string()),
+        }
+    }
+
+    fn typed(&mut self, expr: &Expression, ty: SqlType) -> Result<String, String> {
+        match self.value_of(expr, Some(ty))? {
+            (sql, actual) if actual == ty => Ok(sql),
+            (_, actual) => Err(format!("expected a {}, got a {}", ty.name(), actual.name())),
+        }
+    }
+
+    /// The values of `left` and `right`, where an attribute on one side is read as `hint` gives
+    /// for the type of the other side (and as `default` if both are attributes)
+    fn pair(
+        &mut self,
+        left: &Expression,
+        right: &Expression,
+        hint: impl Fn(SqlType) -> Option<SqlType>,
+        default: Option<SqlType>,
+    ) -> Result<((String, SqlType), (String, SqlType)), String> {
+        let left = self.term(left)?;
+        let right = self.term(right)?;
+        match (left, right) {
+            (left @ Term::Attribute(_), right @ Term::Attribute(_)) => {
+                let left = self.value(left, default)?;
+                let right = self.value(right, default)?;
+                Ok((left, right))
+            }
+            (left @ Term::Attribute(_), right) => {
+                let right = self.value(right, None)?;
+                let left = self.value(left, hint(right.1))?;
+                Ok((left, right))
+            }
+            (left, right) => {
+                let left = self.value(left, None)?;
+                let right = self.value(right, hint(left.1))?;
+                Ok((left, right))
+            }
+        }
+    }
+
+    fn term(&mut self, expr: &Expression) -> Result<Term, String> {
+        match expr {
+            Expression::Atom(atom) => atom_term(atom),
+            Expression::Ident(name) => self.ident(name),
+            Expression::List(items) => items
+                .iter()
+                .map(|item| self.value_of(item, None))
+                .collect::<Result<_, _>>()
+                .map(Term::List),
+            Expression::Member(target, member) => self.member(target, member),
+            Expression::FunctionCall(name, target, args) => {
+                let Expression::Ident(name) = name.as_ref() else {
+                    return Err("only calls of named functions can be translated".to_string());
+                };
+                self.call(name, target.as_deref(), args)
+            }
+            Expression::Relation(left, op, right) => {
+                Ok(Term::Value(self.relation(left, op, right)?, SqlType::Bool))
+            }
+            Expression::Arithmetic(left, op, right) => self.arithmetic(left, op, right),
+            Expression::Unary(op, inner) => self.unary(op, inner),
+            Expression::And(left, right) => Ok(Term::Value(
+                format!("({} AND {})", self.condition(left)?, self.condition(right)?),
+                SqlType::Bool,
+            )),
+            Expression::Or(left, right) => Ok(Term::Value(
+                format!("({} OR {})", self.condition(left)?, self.condition(right)?),
+                SqlType::Bool,
+            )),
+            Expression::Ternary(cond, then, otherwise) => {
+                let cond = self.condition(cond)?;
+                let ((then, then_ty), (otherwise, otherwise_ty)) =
+                    self.pair(then, otherwise, Some, None)?;
+                let ty = match (then_ty, otherwise_ty) {
+                    (ty, SqlType::Null) | (SqlType::Null, ty) => ty,
+                    (a, b) if a == b => a,
+                    (a, b) => {
+                        return Err(format!(
+                            "the branches of `?:` are a {} and a {}",
+                            a.name(),
+                            b.name()
+                        ))
+                    }
+                };
+                Ok(Term::Value(
+                    format!("(CASE WHEN {cond} THEN {then} ELSE {otherwise} END)"),
+                    ty,
+                ))
+            }
+            _ => Err("maps and messages cannot be translated".to_string()),
+        }
+    }
+
+    fn ident(&mut self, name: &str) -> Result<Term, String> {
+        if let Some(i) = self.elements.iter().rposition(|e| e.name == name) {
+            return Ok(Term::Element(i));
+        }
+        if let Some(var) = string_to_var(name) {
+            return self.var(name, var).map(Term::Var);
+        }
+        if name == "now" {
+            return Err(
+                "`now` is the clock of the evaluation, which the database does not know"
+                    .to_string(),
+            );
+        }
+        if let Some(parameter) = self
+            .sql_parts
+            .node
+            .parameters
+            .iter()
+            .find(|p| p.name == name)
+        {
+            return self.parameter(parameter);
+        }
+        if self.children {
+            if let Some(child) = self
+                .sql_parts
+                .child_sql
+                .iter()
+                .position(|(_, child_name)| child_name == name)
+            {
+                let alias = self.sql_parts.next_alias("CC");
+                return Ok(Term::Rows(Rows {
+                    child,
+                    alias,
+                    conditions: Vec::new(),
+                    value: None,
+                }));
+            }
+        }
+        match self.label(name)? {
+            Some(term) => Ok(term),
+            None => Err(format!("`{name}` is not available here")),
+        }
+    }
+
+    /// The value `parameter` is bound to, as a literal
+    fn parameter(&self, parameter: &TreeParameter) -> Result<Term, String> {
+        let value = parameter
+            .default
+            .as_ref()
+            .ok_or_else(|| format!("parameter `{}` is unbound", parameter.name))?;
+        // Negative numbers are parenthesized, so `a - -1` does not start an SQL comment.
+        let number = |n: String| {
+            if n.starts_with('-') {
+                format!("({n})")
+            } else {
+                n
+            }
+        };
+        Ok(match value {
+            ParameterValue::Int(i) if parameter.parameter_type == ParameterType::Float => {
+                Term::Value(number(format!("{:?}", *i as f64)), SqlType::Number)
+            }
+            ParameterValue::Int(i) => Term::Value(number(i.to_string()), SqlType::Number),
+            ParameterValue::Float(f) if f.is_finite() => {
+                Term::Value(number(format!("{f:?}")), SqlType::Number)
+            }
+            ParameterValue::Float(f) => return Err(format!("{f} is not a finite number")),
+            ParameterValue::String(s) => Term::Value(string_literal(s), SqlType::String),
+            ParameterValue::Bool(b) => atom_term(&Atom::Bool(*b))?,
+            ParameterValue::Timestamp(t) => Term::Value(self.time_literal(t), SqlType::Time),
+            ParameterValue::Duration(seconds) => {
+                Term::Value(number(format!("{seconds:?}")), SqlType::Duration)
+            }
+        })
+    }
+
+    /// The variable `var` (named `name`) of this query's rows
+    fn var(&self, name: &str, var: Variable) -> Result<VarRef, String> {
+        let (row, event) = match var {
+            Variable::Event(e) => (format!("E{}", e_alias(e.0)), true),
+            Variable::Object(o) => (format!("O{}", o_alias(o.0)), false),
+        };
+        if !self.sql_parts.used_keys.contains(&row) {
+            return Err(format!("`{name}` is not bound here"));
+        }
+        Ok(VarRef {
+            name: name.to_string(),
+            event,
+            id: format!("{row}.ocel_id"),
+            ocel_type: self.sql_parts.alias_type_map.get(&row).cloned(),
+            row: Some(row),
+        })
+    }
+
+    /// The label `name`, compiled as where it is computed: after the child results and the labels
+    /// before it
+    fn label(&mut self, name: &str) -> Result<Option<Term>, String> {
+        let Some(index) = self.sql_parts.node.labels[..self.labels]
+            .iter()
+            .rposition(|l| l.label == name)
+        else {
+            return Ok(None);
+        };
+        let label = self.sql_parts.node.labels[index].clone();
+        if label.aggregate.is_some() {
+            return Err(format!(
+                "the label `{name}` is a native aggregate, which cannot be used in CEL here"
+            ));
+        }
+        let expr = cel_parser::parse(&label.cel)
+            .map_err(|_| format!("the label `{name}` is no valid CEL"))?;
+        let outer = (
+            std::mem::take(&mut self.elements),
+            self.labels,
+            self.children,
+        );
+        (self.labels, self.children) = (index, true);
+        let term = self.term(&expr);
+        (self.elements, self.labels, self.children) = outer;
+        match term.map_err(|e| format!("in the label `{name}`: {e}"))? {
+            // Labels keep these as text (see `LabelValue`), which compares differently.
+            Term::Value(_, ty @ (SqlType::Time | SqlType::Duration)) => Err(format!(
+                "the label `{name}` is a {}, which labels hold as text",
+                ty.name()
+            )),
+            Term::Rows(_) | Term::List(_) | Term::Element(_) => {
+                Err(format!("the label `{name}` is a list"))
+            }
+            term => Ok(Some(term)),
+        }
+    }
+
+    fn member(&mut self, target: &Expression, member: &Member) -> Result<Term, String> {
+        let field = match member {
+            Member::Attribute(field) => field.to_string(),
+            Member::Index(index) => string_index(index)
+                .ok_or("only fields named by a string literal can be translated")?,
+            _ => return Err("message fields cannot be translated".to_string()),
+        };
+        let Term::Element(i) = self.term(target)? else {
+            return Err(format!(
+                "`{field}` can only be read from the bindings of a child"
+            ));
+        };
+        let element = &self.elements[i];
+        if field == "satisfied" {
+            return Ok(Term::Value(
+                format!("({}.satisfied = 1)", element.alias),
+                SqlType::Bool,
+            ));
+        }
+        let Some(var) = string_to_var(&field) else {
+            return Err(format!(
+                "the label `{field}` of the bindings of a child cannot be translated"
+            ));
+        };
+        let child_node = &self.sql_parts.node.children[element.child].0;
+        let (key, types) = match var {
+            Variable::Event(e) => (
+                format!("key_e{}", e_alias(e.0)),
+                child_node.event_vars.get(&e),
+            ),
+            Variable::Object(o) => (
+                format!("key_o{}", o_alias(o.0)),
+                child_node.object_vars.get(&o),
+            ),
+        };
+        match types {
+            Some(types) => Ok(Term::Var(VarRef {
+                name: format!("{}.{field}", element.name),
+                event: matches!(var, Variable::Event(_)),
+                id: format!("{}.{key}", element.alias),
+                row: None,
+                ocel_type: first_type(types).cloned(),
+            })),
+            // Bound above the child, so the same in all of its bindings
+            None => self.var(&field, var).map(Term::Var),
+        }
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        target: Option<&Expression>,
+        args: &[Expression],
+    ) -> Result<Term, String> {
+        match (name, target, args) {
+            ("attr", Some(target), [Expression::Atom(Atom::String(attribute))]) => {
+                Ok(Term::Attribute(Attribute {
+                    owner: self.owner(target)?,
+                    name: attribute.to_string(),
+                    at: None,
+                }))
+            }
+            ("attrAt", Some(target), [Expression::Atom(Atom::String(attribute)), at]) => {
+                let owner = self.owner(target)?;
+                let at = self.typed(at, SqlType::Time)?;
+                Ok(Term::Attribute(Attribute {
+                    // Events have no attribute history.
+                    at: (!owner.event).then_some(at),
+                    owner,
+                    name: attribute.to_string(),
+                }))
+            }
+            ("time", Some(target), []) => {
+                let owner = self.owner(target)?;
+                Ok(Term::Value(self.time_of(&owner)?, SqlType::Time))
+            }
+            ("id", Some(target), []) => Ok(Term::Value(self.owner(target)?.id, SqlType::String)),
+            ("type", Some(target), []) => {
+                let owner = self.owner(target)?;
+                let ocel_type = owner
+                    .ocel_type
+                    .ok_or_else(|| format!("the type of `{}` is not known", owner.name))?;
+                Ok(Term::Value(string_literal(&ocel_type), SqlType::String))
+            }
+            ("timestamp", None, [Expression::Atom(Atom::String(arg))]) => {
+                match evaluate_literal_call(name, arg) {
+                    Some(Value::Timestamp(t)) => Ok(Term::Value(
+                        self.time_literal(&t.with_timezone(&Utc)),
+                        SqlType::Time,
+                    )),
+                    _ => Err(format!("`{arg}` is no timestamp")),
+                }
+            }
+            ("duration", None, [Expression::Atom(Atom::String(arg))]) => {
+                match evaluate_literal_call(name, arg) {
+                    Some(Value::Duration(d)) => Ok(Term::Value(
+                        format!("{:?}", duration_seconds(d)),
+                        SqlType::Duration,
+                    )),
+                    _ => Err(format!("`{arg}` is no duration")),
+                }
+            }
+            ("size", None, [arg]) | ("size", Some(arg), []) => self.size(arg),
+            (
+                "all" | "exists" | "exists_one" | "filter" | "map",
+                Some(target),
+                [Expression::Ident(var), body],
+            ) => self.comprehension(name, target, var, body),
+            ("sum" | "avg" | "min" | "max", Some(list), []) | ("sum" | "avg", None, [list]) => {
+                self.aggregate(name, list)
+            }
+            // `min(l)` is over the list `l`, `min(a, b)` over the arguments
+            ("min" | "max", None, [list]) => self.aggregate(name, list),
+            ("min" | "max", None, values) if values.len() > 1 => self.extreme(name, values),
+            ("double", None, [arg]) => {
+                let value = self.typed(arg, SqlType::Number)?;
+                let double = match self.sql_parts.database_type {
+                    DatabaseType::SQLite => "REAL",
+                    DatabaseType::DuckDB => "DOUBLE",
+                    DatabaseType::PostgreSQL => "DOUBLE PRECISION",
+                };
+                Ok(Term::Value(
+                    format!("CAST({value} AS {double})"),
+                    SqlType::Number,
+                ))
+            }
+            ("lower" | "upper" | "trim", Some(target), []) => {
+                let s = self.typed(target, SqlType::String)?;
+                Ok(Term::Value(
+                    format!("{}({s})", name.to_uppercase()),
+                    SqlType::String,
+                ))
+            }
+            ("startsWith" | "endsWith" | "contains", Some(target), [arg]) => {
+                let s = self.typed(target, SqlType::String)?;
+                let p = self.typed(arg, SqlType::String)?;
+                let sql = match (name, self.sql_parts.database_type) {
+                    ("startsWith", _) => format!("(substr({s}, 1, length({p})) = {p})"),
+                    ("endsWith", _) => format!(
+                        "(length({s}) >= length({p}) AND substr({s}, length({s}) - length({p}) + 1) = {p})"
+                    ),
+                    (_, DatabaseType::SQLite) => format!("(instr({s}, {p}) > 0)"),
+                    _ => format!("(strpos({s}, {p}) > 0)"),
+                };
+                Ok(Term::Value(sql, SqlType::Bool))
+            }
+            _ => Err(format!("`{name}()` cannot be translated")),
+        }
+    }
+
+    /// The event or object `target` names
+    fn owner(&mut self, target: &Expression) -> Result<VarRef, String> {
+        match self.term(target)? {
+            Term::Var(var) => Ok(var),
+            _ => Err("only events and objects have attributes, times, ids and types".to_string()),
+        }
+    }
+
+    /// The table of the event or object `var`
+    fn table(&self, var: &VarRef) -> Result<String, String> {
+        let ocel_type = var
+            .ocel_type
+            .as_deref()
+            .ok_or_else(|| format!("the type of `{}` is not known", var.name))?;
+        Ok(if var.event {
+            map_eventttables(self.sql_parts, ocel_type)
+        } else {
+            map_objecttables(self.sql_parts, ocel_type)
+        })
+    }
+
+    /// The timestamp column `sql` in seconds. SQLite's `strftime` gives text, which never
+    /// compares equal to a number.
+    fn seconds(&self, sql: String) -> String {
+        let seconds = map_timestamp(self.sql_parts, sql);
+        match self.sql_parts.database_type {
+            DatabaseType::SQLite => format!("CAST({seconds} AS INTEGER)"),
+            DatabaseType::DuckDB | DatabaseType::PostgreSQL => seconds,
+        }
+    }
+
+    /// See [`Self::seconds`].
+    fn time_literal(&self, time: &DateTime<Utc>) -> String {
+        let seconds = map_time_literal(self.sql_parts, time);
+        match self.sql_parts.database_type {
+            DatabaseType::SQLite => format!("CAST({seconds} AS INTEGER)"),
+            DatabaseType::DuckDB | DatabaseType::PostgreSQL => seconds,
+        }
+    }
+
+    fn time_of(&mut self, var: &VarRef) -> Result<String, String> {
+        if !var.event {
+            return Err(format!(
+                "`{}` is an object, only events have a time",
+                var.name
+            ));
+        }
+        if let Some(row) = &var.row {
+            return Ok(self.seconds(format!("{row}.ocel_time")));
+        }
+        let table = self.table(var)?;
+        let alias = self.sql_parts.next_alias("CV");
+        let time = self.seconds(format!("{alias}.ocel_time"));
+        Ok(format!(
+            "(SELECT {time} FROM {table} AS {alias} WHERE {alias}.ocel_id = {})",
+            var.id
+        ))
+    }
+
+    fn attribute(
+        &mut self,
+        attribute: &Attribute,
+        hint: Option<SqlType>,
+    ) -> Result<(String, SqlType), String> {
+        let (attribute_type, ty) = match hint {
+            Some(SqlType::Number) => (AttributeType::Number, SqlType::Number),
+            Some(SqlType::Bool) => (AttributeType::Boolean, SqlType::Bool),
+            Some(SqlType::Time) => (AttributeType::Time, SqlType::Time),
+            Some(ty @ (SqlType::Duration | SqlType::Id)) => {
+                return Err(format!(
+                    "the attribute `{}` cannot be used as a {}",
+                    attribute.name,
+                    ty.name()
+                ))
+            }
+            Some(SqlType::String | SqlType::Null) | None => {
+                (AttributeType::String, SqlType::String)
+            }
+        };
+        let sql = self.attribute_sql(attribute, attribute_type)?;
+        if ty == SqlType::Time {
+            return Ok((self.seconds(sql), ty));
+        }
+        Ok((sql, ty))
+    }
+
+    fn attribute_sql(
+        &mut self,
+        attribute: &Attribute,
+        attribute_type: AttributeType,
+    ) -> Result<String, String> {
+        let owner = &attribute.owner;
+        if let (Some(row), None) = (&owner.row, &attribute.at) {
+            return Ok(attribute_value_as(
+                self.sql_parts,
+                row,
+                &attribute.name,
+                attribute_type,
+            ));
+        }
+        let table = self.table(owner)?;
+        let alias = self.sql_parts.next_alias("CA");
+        let value = attribute_value_as(self.sql_parts, &alias, &attribute.name, attribute_type);
+        let id = &owner.id;
+        Ok(match &attribute.at {
+            None if owner.event => {
+                format!("(SELECT {value} FROM {table} AS {alias} WHERE {alias}.ocel_id = {id})")
+            }
+            None => format!(
+                "(SELECT {value} FROM {table} AS {alias} WHERE {alias}.ocel_id = {id} AND {alias}.ocel_changed_field IS NULL)"
+            ),
+            // The last row that set the attribute, or the canonical row for its first value
+            Some(at) => {
+                let changed = self.seconds(format!("{alias}.ocel_time"));
+                format!(
+                    "(SELECT {value} FROM {table} AS {alias} WHERE {alias}.ocel_id = {id} AND ({alias}.ocel_changed_field IS NULL OR {alias}.ocel_changed_field = {field}) AND {changed} <= {at} ORDER BY {alias}.ocel_time DESC LIMIT 1)",
+                    field = string_literal(&attribute.name),
+                )
+            }
+        })
+    }
+
+    fn relation(
+        &mut self,
+        left: &Expression,
+        op: &RelationOp,
+        right: &Expression,
+    ) -> Result<String, String> {
+        let sql_op = match op {
+            RelationOp::LessThan => "<",
+            RelationOp::LessThanEq => "<=",
+            RelationOp::GreaterThan => ">",
+            RelationOp::GreaterThanEq => ">=",
+            RelationOp::Equals => "=",
+            RelationOp::NotEquals => "<>",
+            RelationOp::In => return self.membership(left, right),
+        };
+        let equality = matches!(op, RelationOp::Equals | RelationOp::NotEquals);
+        let ((left, left_ty), (right, right_ty)) = self.pair(left, right, Some, None)?;
+        if left_ty == SqlType::Null || right_ty == SqlType::Null {
+            let other = if left_ty == SqlType::Null {
+                right
+            } else {
+                left
+            };
+            return match op {
+                RelationOp::Equals => Ok(format!("({other} IS NULL)")),
+                RelationOp::NotEquals => Ok(format!("({other} IS NOT NULL)")),
+                _ => Err("only `==` and `!=` compare with null".to_string()),
+            };
+        }
+        comparable(left_ty, right_ty)?;
+        if !equality && matches!(left_ty, SqlType::Bool | SqlType::Id) {
+            return Err(format!(
+                "a {} can only be compared with `==` and `!=`",
+                left_ty.name()
+            ));
+        }
+        Ok(format!("({left} {sql_op} {right})"))
+    }
+
+    fn membership(&mut self, value: &Expression, list: &Expression) -> Result<String, String> {
+        let value = self.term(value)?;
+        let Term::List(items) = self.term(list)? else {
+            return Err("`in` can only be translated for a list literal".to_string());
+        };
+        let (value, ty) = self.value(value, items.first().map(|(_, ty)| *ty))?;
+        for (_, item_ty) in &items {
+            comparable(ty, *item_ty)?;
+        }
+        if items.is_empty() {
+            return Ok("FALSE".to_string());
+        }
+        let items: Vec<String> = items.into_iter().map(|(sql, _)| sql).collect();
+        Ok(format!("({value} IN ({}))", items.join(", ")))
+    }
+
+    fn arithmetic(
+        &mut self,
+        left: &Expression,
+        op: &ArithmeticOp,
+        right: &Expression,
+    ) -> Result<Term, String> {
+        let ((left, left_ty), (right, right_ty)) = self.pair(
+            left,
+            right,
+            |other| {
+                Some(match (op, other) {
+                    (ArithmeticOp::Add | ArithmeticOp::Subtract, SqlType::Duration)
+                    | (ArithmeticOp::Subtract, SqlType::Time) => SqlType::Time,
+                    (ArithmeticOp::Add, SqlType::String) => SqlType::String,
+                    _ => SqlType::Number,
+                })
+            },
+            Some(SqlType::Number),
+        )?;
+        let (sql_op, ty) = match (op, left_ty, right_ty) {
+            (ArithmeticOp::Add, SqlType::String, SqlType::String) => ("||", SqlType::String),
+            (op, SqlType::Number, SqlType::Number) => (arithmetic_op(op), SqlType::Number),
+            (ArithmeticOp::Subtract, SqlType::Time, SqlType::Time) => ("-", SqlType::Duration),
+            (
+                op @ (ArithmeticOp::Add | ArithmeticOp::Subtract),
+                SqlType::Time,
+                SqlType::Duration,
+            ) => (arithmetic_op(op), SqlType::Time),
+            (ArithmeticOp::Add, SqlType::Duration, SqlType::Time) => ("+", SqlType::Time),
+            (
+                op @ (ArithmeticOp::Add | ArithmeticOp::Subtract),
+                SqlType::Duration,
+                SqlType::Duration,
+            ) => (arithmetic_op(op), SqlType::Duration),
+            (op, left_ty, right_ty) => {
+                return Err(format!(
+                    "a {} and a {} cannot be combined with `{}`",
+                    left_ty.name(),
+                    right_ty.name(),
+                    arithmetic_op(op)
+                ))
+            }
+        };
+        Ok(Term::Value(format!("({left} {sql_op} {right})"), ty))
+    }
+
+    fn unary(&mut self, op: &UnaryOp, inner: &Expression) -> Result<Term, String> {
+        match op {
+            UnaryOp::Not => Ok(Term::Value(
+                format!("(NOT {})", self.condition(inner)?),
+                SqlType::Bool,
+            )),
+            UnaryOp::DoubleNot => Ok(Term::Value(self.condition(inner)?, SqlType::Bool)),
+            UnaryOp::Minus | UnaryOp::DoubleMinus => {
+                let (value, ty) = self.value_of(inner, Some(SqlType::Number))?;
+                if !matches!(ty, SqlType::Number | SqlType::Duration) {
+                    return Err(format!("a {} cannot be negated", ty.name()));
+                }
+                // With a space, as `--` starts a comment
+                let value = match op {
+                    UnaryOp::Minus => format!("(- {value})"),
+                    _ => value,
+                };
+                Ok(Term::Value(value, ty))
+            }
+        }
+    }
+
+    fn size(&mut self, arg: &Expression) -> Result<Term, String> {
+        let size = match self.term(arg)? {
+            Term::Rows(rows) => self.select(&rows, "COUNT(*)", None),
+            Term::List(items) => items.len().to_string(),
+            term => {
+                let (s, ty) = self.value(term, Some(SqlType::String))?;
+                if ty != SqlType::String {
+                    return Err(format!("a {} has no size", ty.name()));
+                }
+                format!("length({s})")
+            }
+        };
+        Ok(Term::Value(size, SqlType::Number))
+    }
+
+    fn comprehension(
+        &mut self,
+        kind: &str,
+        target: &Expression,
+        var: &str,
+        body: &Expression,
+    ) -> Result<Term, String> {
+        let rows = match self.term(target)? {
+            Term::Rows(rows) if rows.value.is_none() => rows,
+            _ => {
+                return Err(format!(
+                    "`{kind}()` can only be translated over the bindings of a child"
+                ))
+            }
+        };
+        self.elements.push(Element {
+            name: var.to_string(),
+            child: rows.child,
+            alias: rows.alias.clone(),
+        });
+        let res = self.comprehension_body(kind, rows, body);
+        self.elements.pop();
+        res
+    }
+
+    fn comprehension_body(
+        &mut self,
+        kind: &str,
+        mut rows: Rows,
+        body: &Expression,
+    ) -> Result<Term, String> {
+        if kind == "map" {
+            rows.value = Some(Box::new(self.term(body)?));
+            return Ok(Term::Rows(rows));
+        }
+        let condition = self.condition(body)?;
+        let sql = match kind {
+            "filter" => {
+                rows.conditions.push(condition);
+                return Ok(Term::Rows(rows));
+            }
+            "all" => format!(
+                "(NOT EXISTS {})",
+                self.select(&rows, "1", Some(format!("NOT {condition}")))
+            ),
+            "exists" => format!("(EXISTS {})", self.select(&rows, "1", Some(condition))),
+            _ => format!("({} = 1)", self.select(&rows, "COUNT(*)", Some(condition))),
+        };
+        Ok(Term::Value(sql, SqlType::Bool))
+    }
+
+    /// `select` over the distinct bindings of a child that pass its conditions and `condition`.
+    /// A child query can return a binding more than once (see `num_childs_count_expr`).
+    fn select(&self, rows: &Rows, select: &str, condition: Option<String>) -> String {
+        let (child_sql, _) = &self.sql_parts.child_sql[rows.child];
+        let mut columns: Vec<String> =
+            child_key_columns(&self.sql_parts.node.children[rows.child].0)
+                .into_iter()
+                .map(|(_, alias)| alias)
+                .collect();
+        columns.push("satisfied".to_string());
+        let conditions: Vec<&str> = rows
+            .conditions
+            .iter()
+            .map(String::as_str)
+            .chain(condition.as_deref())
+            .collect();
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        };
+        format!(
+            "(SELECT {select} FROM (SELECT DISTINCT {} FROM ({child_sql}) AS {alias}_r) AS {alias}{filter})",
+            columns.join(", "),
+            alias = rows.alias,
+        )
+    }
+
+    fn aggregate(&mut self, function: &str, list: &Expression) -> Result<Term, String> {
+        let Term::Rows(mut rows) = self.term(list)? else {
+            return Err(format!(
+                "`{function}()` can only be translated over the results of a child"
+            ));
+        };
+        let Some(value) = rows.value.take() else {
+            return Err(format!(
+                "`{function}()` needs values of the bindings, as in `A.map(c, ...).{function}()`"
+            ));
+        };
+        let (value, ty) = self.value(*value, Some(SqlType::Number))?;
+        let sql = match (function, ty) {
+            ("sum", SqlType::Number) => {
+                format!(
+                    "COALESCE({}, 0)",
+                    self.select(&rows, &format!("SUM({value})"), None)
+                )
+            }
+            ("avg", SqlType::Number) => self.select(&rows, &format!("AVG({value})"), None),
+            (
+                "min" | "max",
+                SqlType::Number | SqlType::String | SqlType::Time | SqlType::Duration,
+            ) => self.select(
+                &rows,
+                &format!("{}({value})", function.to_uppercase()),
+                None,
+            ),
+            _ => {
+                return Err(format!(
+                    "`{function}()` of a {} cannot be translated",
+                    ty.name()
+                ))
+            }
+        };
+        Ok(Term::Value(
+            sql,
+            if function == "avg" {
+                SqlType::Number
+            } else {
+                ty
+            },
+        ))
+    }
+
+    /// `min(a, b, ...)` or `max(a, b, ...)`
+    fn extreme(&mut self, function: &str, args: &[Expression]) -> Result<Term, String> {
+        let mut values = Vec::with_capacity(args.len());
+        let mut ty = None;
+        for arg in args {
+            let (value, value_ty) = self.value_of(arg, ty.or(Some(SqlType::Number)))?;
+            if let Some(ty) = ty {
+                comparable(ty, value_ty)?;
+            }
+            ty = Some(value_ty);
+            values.push(value);
+        }
+        let function = match (function, self.sql_parts.database_type) {
+            ("min", DatabaseType::SQLite) => "MIN",
+            ("max", DatabaseType::SQLite) => "MAX",
+            ("min", _) => "LEAST",
+            _ => "GREATEST",
+        };
+        Ok(Term::Value(
+            format!("{function}({})", values.join(", ")),
+            ty.unwrap_or(SqlType::Null),
+        ))
+    }
+}
+
+fn atom_term(atom: &Atom) -> Result<Term, String> {
+    Ok(match atom {
+        Atom::Int(i) => Term::Value(i.to_string(), SqlType::Number),
+        Atom::UInt(u) => Term::Value(u.to_string(), SqlType::Number),
+        // `{:?}` keeps the `.0` of a whole number
+        Atom::Float(f) if f.is_finite() => Term::Value(format!("{f:?}"), SqlType::Number),
+        Atom::String(s) => Term::Value(string_literal(s), SqlType::String),
+        Atom::Bool(b) => Term::Value(if *b { "TRUE" } else { "FALSE" }.to_string(), SqlType::Bool),
+        Atom::Null => Term::Value("NULL".to_string(), SqlType::Null),
+        _ => return Err("this literal cannot be translated".to_string()),
+    })
+}
+
+fn arithmetic_op(op: &ArithmeticOp) -> &'static str {
+    match op {
+        ArithmeticOp::Add => "+",
+        ArithmeticOp::Subtract => "-",
+        ArithmeticOp::Multiply => "*",
+        ArithmeticOp::Divide => "/",
+        ArithmeticOp::Modulus => "%",
+    }
+}
+
+fn comparable(a: SqlType, b: SqlType) -> Result<(), String> {
+    if a == b {
+        Ok(())
+    } else {
+        Err(format!(
+            "a {} cannot be compared with a {}",
+            a.name(),
+            b.name()
+        ))
+    }
+}
@@ -0,0 +1,400 @@
+//! Running a translated query against an OCEL 2.0 SQLite or DuckDB file (as exported by
+//! `process_mining`), reading its rows back as the situations of the tree's root node and
+//! comparing them with the in-memory evaluation of the same tree (the differential mode).
+//!
+//! The translator only selects the root node's bindings and whether they satisfy its constraints,
+//! so that is all a comparison can cover; the database does not say which constraint failed.
+use std::collections::{BTreeMap, HashMap};
+
+use process_mining::core::event_data::object_centric::linked_ocel::{
+    LinkedOCELAccess, SlimLinkedOCEL,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::{translate_to_sql_shared, DBTranslationInput, DatabaseType, TranslationIssue};
+use crate::{
+    binding_box::{
+        evaluate_box_tree, Binding, EvaluationResultWithCount, EventVariable, ObjectVariable,
+        ViolationReason,
+    },
+    cel::{ev_var_to_name, ob_var_to_name},
+};
+
+/// The column names and the (textual) values of every row of a query
+type QueryRows = (Vec<String>, Vec<Vec<Option<String>>>);
+
+/// What running a translated query against an OCEL database file gave
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DBQueryResult {
+    /// The SQL that was run
+    pub query: String,
+    /// What of the tree the query leaves out, which the comparison can disagree on
+    pub unsupported: Vec<TranslationIssue>,
+    /// The root node's situations, one per row. A violated one has the reason
+    /// [`ViolationReason::UnknownChildSet`], as the query does not say which constraint failed.
+    pub result: EvaluationResultWithCount,
+    /// Only in differential mode, which compares with the in-memory evaluation
+    pub comparison: Option<DBQueryComparison>,
+}
+
+/// How the situations of a query differ from those of the in-memory evaluation
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DBQueryComparison {
+    pub database_situation_count: usize,
+    pub database_violated_count: usize,
+    pub memory_situation_count: usize,
+    pub memory_violated_count: usize,
+    /// Rows the query returned more than once
+    pub duplicate_rows: usize,
+    /// Whether the in-memory evaluation stopped early, so it may miss situations the query has
+    pub memory_bindings_skipped: bool,
+    pub mismatches: Vec<SituationMismatch>,
+}
+
+impl DBQueryComparison {
+    /// Whether both sides found the same situations, violated the same way
+    pub fn agrees(&self) -> bool {
+        self.mismatches.is_empty() && self.duplicate_rows == 0
+    }
+}
+
+/// A situation only one side found, or that only one side found violated
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SituationMismatch {
+    /// The id bound to each variable, by its name (like `e1`)
+    pub binding: BTreeMap<String, String>,
+    /// Whether the database found it violated, or `None` if it did not find it
+    pub database_violated: Option<bool>,
+    /// Whether the in-memory evaluation found it violated, or `None` if it did not find it
+    pub memory_violated: Option<bool>,
+}
+
+/// Translate `input` to SQL, run it against the OCEL database file at `path` and read the rows
+/// back as situations of `ocel`, which must be the OCEL the file holds. With `differential`, the
+/// tree is also evaluated in memory and the two results compared.
+pub fn run_db_query(
+    ocel: &SlimLinkedOCEL,
+    input: DBTranslationInput,
+    path: &str,
+    differential: bool,
+) -> Result<DBQueryResult, String> {
+    let tree = input.tree.bind_parameters(&input.parameters)?;
+    let database = input.database;
+    let translation = translate_to_sql_shared(DBTranslationInput {
+        tree: tree.clone(),
+        parameters: Default::default(),
+        ..input
+    })?;
+    let query = translation.query;
+    let (columns, rows) = query_rows(database, path, &query)?;
+    let result = rows_to_situations(ocel, &columns, rows)?;
+    let comparison = if differential {
+        let memory = evaluate_box_tree(tree, ocel, false)?;
+        let memory_root = memory
+            .evaluation_results
+            .first()
+            .cloned()
+            .unwrap_or_default();
+        Some(compare_situations(
+            ocel,
+            &result,
+            &memory_root,
+            memory.bindings_skipped,
+        ))
+    } else {
+        None
+    };
+    Ok(DBQueryResult {
+        query,
+        unsupported: translation.unsupported,
+        result,
+        comparison,
+    })
+}
+
+fn query_rows(database: DatabaseType, path: &str, query: &str) -> Result<QueryRows, String> {
+    match database {
+        DatabaseType::SQLite => query_sqlite(path, query),
+        DatabaseType::DuckDB => query_duckdb(path, query),
+        DatabaseType::PostgreSQL => {
+            Err("Queries can only be run against SQLite and DuckDB files".to_string())
+        }
+    }
+}
+
+#[cfg(feature = "ocel-sqlite")]
+fn query_sqlite(path: &str, query: &str) -> Result<QueryRows, String> {
+    use rusqlite::{types::ValueRef, Connection, OpenFlags};
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Could not open {path}: {e}"))?;
+    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    let mut res = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let values = (0..columns.len())
+            .map(|i| {
+                Ok(match row.get_ref(i).map_err(|e| e.to_string())? {
+                    ValueRef::Null => None,
+                    ValueRef::Integer(n) => Some(n.to_string()),
+                    ValueRef::Real(f) => Some(f.to_string()),
+                    ValueRef::Text(t) | ValueRef::Blob(t) => {
+                        Some(String::from_utf8_lossy(t).into_owned())
+                    }
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        res.push(values);
+    }
+    Ok((columns, res))
+}
+
+#[cfg(not(feature = "ocel-sqlite"))]
+fn query_sqlite(_path: &str, _query: &str) -> Result<QueryRows, String> {
+    Err(
+        "This build cannot run queries against SQLite (the `ocel-sqlite` feature is off)"
+            .to_string(),
+    )
+}
+
+#[cfg(feature = "extraction-dbcon-duckdb")]
+fn query_duckdb(path: &str, query: &str) -> Result<QueryRows, String> {
+    use duckdb::{types::Value, AccessMode, Config, Connection};
+
+    let config = Config::default()
+        .access_mode(AccessMode::ReadOnly)
+        .map_err(|e| e.to_string())?;
+    let conn = Connection::open_with_flags(path, config)
+        .map_err(|e| format!("Could not open {path}: {e}"))?;
+    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    // DuckDB only knows the columns once the statement ran.
+    let columns = rows
+        .as_ref()
+        .map(|stmt| stmt.column_names())
+        .unwrap_or_default();
+    let mut res = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let values = (0..columns.len())
+            .map(|i| {
+                Ok(match row.get::<_, Value>(i).map_err(|e| e.to_string())? {
+                    Value::Null => None,
+                    Value::Boolean(b) => Some(i64::from(b).to_string()),
+                    Value::TinyInt(n) => Some(n.to_string()),
+                    Value::SmallInt(n) => Some(n.to_string()),
+                    Value::Int(n) => Some(n.to_string()),
+                    Value::BigInt(n) => Some(n.to_string()),
+                    Value::HugeInt(n) => Some(n.to_string()),
+                    Value::UTinyInt(n) => Some(n.to_string()),
+                    Value::USmallInt(n) => Some(n.to_string()),
+                    Value::UInt(n) => Some(n.to_string()),
+                    Value::UBigInt(n) => Some(n.to_string()),
+                    Value::Text(s) => Some(s),
+                    other => return Err(format!("Unexpected value {other:?} in column {i}")),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        res.push(values);
+    }
+    Ok((columns, res))
+}
+
+#[cfg(not(feature = "extraction-dbcon-duckdb"))]
+fn query_duckdb(_path: &str, _query: &str) -> Result<QueryRows, String> {
+    Err(
+        "This build cannot run queries against DuckDB (the `extraction-dbcon-duckdb` feature is off)"
+            .to_string(),
+    )
+}
+
+/// The variable a result column (like `E1` or `O2`) holds the id of
+enum Column {
+    Event(EventVariable),
+    Object(ObjectVariable),
+    Satisfied,
+}
+
+fn parse_column(name: &str) -> Option<Column> {
+    if name == "satisfied" {
+        return Some(Column::Satisfied);
+    }
+    let num = name.get(1..)?.parse::<usize>().ok()?.checked_sub(1)?;
+    match name.chars().next()? {
+        'E' => Some(Column::Event(EventVariable(num))),
+        'O' => Some(Column::Object(ObjectVariable(num))),
+        _ => None,
+    }
+}
+
+fn rows_to_situations(
+    ocel: &SlimLinkedOCEL,
+    columns: &[String],
+    rows: Vec<Vec<Option<String>>>,
+) -> Result<EvaluationResultWithCount, String> {
+    let columns = columns
+        .iter()
+        .map(|name| parse_column(name).ok_or_else(|| format!("Unexpected result column `{name}`")))
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut res = EvaluationResultWithCount::default();
+    for row in rows {
+        let mut binding = Binding::default();
+        let mut violated = false;
+        for (column, value) in columns.iter().zip(row) {
+            let value = value.ok_or("A result column is NULL")?;
+            match column {
+                Column::Event(var) => {
+                    let index = ocel
+                        .get_ev_by_id(value.as_str())
+                        .ok_or_else(|| format!("There is no event `{value}` in the OCEL"))?;
+                    binding = binding.expand_with_ev(*var, index);
+                }
+                Column::Object(var) => {
+                    let index = ocel
+                        .get_ob_by_id(value.as_str())
+                        .ok_or_else(|| format!("There is no object `{value}` in the OCEL"))?;
+                    binding = binding.expand_with_ob(*var, index);
+                }
+                Column::Satisfied => violated = value == "0",
+            }
+        }
+        if violated {
+            res.situation_violated_count += 1;
+        }
+        res.situations.push((
+            binding,
+            violated.then_some(ViolationReason::UnknownChildSet),
+        ));
+    }
+    res.situation_count = res.situations.len();
+    Ok(res)
+}
+
+/// The id bound to each variable of `binding`, by the variable's name
+fn binding_ids(ocel: &SlimLinkedOCEL, binding: &Binding) -> BTreeMap<String, String> {
+    let events = binding
+        .event_map
+        .iter()
+        .map(|(var, index)| (ev_var_to_name(var), ocel.get_ev_id(index).to_string()));
+    let objects = binding
+        .object_map
+        .iter()
+        .map(|(var, index)| (ob_var_to_name(var), ocel.get_ob_id(index).to_string()));
+    events.chain(objects).collect()
+}
+
+fn compare_situations(
+    ocel: &SlimLinkedOCEL,
+    database: &EvaluationResultWithCount,
+    memory: &EvaluationResultWithCount,
+    memory_bindings_skipped: bool,
+) -> DBQueryComparison {
+    let mut comparison = DBQueryComparison {
+        database_situation_count: database.situation_count,
+        database_violated_count: database.situation_violated_count,
+        memory_situation_count: memory.situation_count,
+        memory_violated_count: memory.situation_violated_count,
+        memory_bindings_skipped,
+        ..Default::default()
+    };
+    let mut in_database = HashMap::new();
+    for (binding, reason) in &database.situations {
+        if in_database
+            .insert(binding_ids(ocel, binding), reason.is_some())
+            .is_some()
+        {
+            comparison.duplicate_rows += 1;
+        }
+    }
+    for (binding, reason) in &memory.situations {
+        let ids = binding_ids(ocel, binding);
+        let database_violated = in_database.remove(&ids);
+        if database_violated != Some(reason.is_some()) {
+            comparison.mismatches.push(SituationMismatch {
+                binding: ids,
+                database_violated,
+                memory_violated: Some(reason.is_some()),
+            });
+        }
+    }
+    comparison
+        .mismatches
+        .extend(
+            in_database
+                .into_iter()
+                .map(|(ids, violated)| SituationMismatch {
+                    binding: ids,
+                    database_violated: Some(violated),
+                    memory_violated: None,
+                }),
+        );
+    comparison
+        .mismatches
+        .sort_by(|a, b| a.binding.cmp(&b.binding));
+    comparison
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn result_columns_name_variables_one_based() {
+        assert!(matches!(
+            parse_column("E1"),
+            Some(Column::Event(EventVariable(0)))
+        ));
+        assert!(matches!(
+            parse_column("O12"),
+            Some(Column::Object(ObjectVariable(11)))
+        ));
+        assert!(matches!(parse_column("satisfied"), Some(Column::Satisfied)));
+        assert!(parse_column("E0").is_none());
+        assert!(parse_column("key_e1").is_none());
+    }
+
+    /// The query over the fixture OCEL, exported to SQLite, finds exactly the situations (and
+    /// violations) the in-memory evaluation finds.
+    #[cfg(feature = "ocel-sqlite")]
+    #[test]
+    fn sqlite_export_of_the_fixture_agrees_with_the_evaluation() {
+        use crate::binding_box::test_utils::{order_payment_shipping_tree, OCEL_JSON};
+        use crate::db_translation::TableMappings;
+        use process_mining::core::io::Exportable;
+        use process_mining::OCEL;
+
+        let ocel: OCEL = serde_json::from_str(OCEL_JSON).expect("fixture OCEL parses");
+        let path =
+            std::env::temp_dir().join(format!("ocpq-differential-{}.sqlite", std::process::id()));
+        ocel.export_to_path(&path)
+            .expect("fixture OCEL exports to SQLite");
+        let res = run_db_query(
+            &SlimLinkedOCEL::from_ocel(ocel),
+            DBTranslationInput {
+                tree: order_payment_shipping_tree(),
+                database: DatabaseType::SQLite,
+                table_mappings: TableMappings::default(),
+                parameters: Default::default(),
+            },
+            path.to_str().unwrap(),
+            true,
+        );
+        let _ = std::fs::remove_file(&path);
+        let res = res.unwrap();
+        let comparison = res.comparison.expect("differential mode compares");
+        assert!(comparison.agrees(), "{comparison:?}\n{}", res.query);
+        assert_eq!(comparison.database_situation_count, 2);
+        assert_eq!(comparison.database_violated_count, 1);
+    }
+}
@@ -0,0 +1,408 @@
+//! Export of an OCEL as CSV files for `neo4j-admin database import`, in the property graph schema
+//! the Cypher translation (see [`super::translate_to_cypher_shared`]) queries:
+//!
+//! - `(:Event:<event type> {id, type, time, <attribute>...})`: an event, with each attribute of
+//!   its type as a property of the attribute's declared type
+//! - `(:Object:<object type> {id, type})`: an object
+//! - `(:ObjectAttributeValue {name, value, time})`: a value an attribute of an object changes to at
+//!   `time`, linked from the object by `[:HAS_VALUE]`
+//! - `(:Event)-[:E2O {qualifier}]->(:Object)` and `(:Object)-[:O2O {qualifier}]->(:Object)`: the
+//!   relationships of the OCEL
+//! - `(:Event)-[:DF {object}]->(:Event)`: the second event directly follows the first in the
+//!   lifecycle of the object with the id `object` (see [`object_lifecycle`])
+//!
+//! Times are `datetime`s. Events and objects have ID spaces of their own, so an event and an
+//! object can share an id.
+use std::collections::{btree_map::Entry, BTreeMap, HashSet};
+
+use process_mining::core::event_data::object_centric::{
+    linked_ocel::{LinkedOCELAccess, SlimLinkedOCEL},
+    OCELAttributeValue,
+};
+
+use crate::binding_box::structs::object_lifecycle;
+
+/// A CSV file of the export, named with its extension
+#[derive(Debug, Clone)]
+pub struct GraphCsvFile {
+    pub name: String,
+    /// Whether the file holds relationships rather than nodes
+    pub relationships: bool,
+    pub content: Vec<u8>,
+}
+
+/// A CSV file being written
+struct CsvWriter {
+    name: String,
+    relationships: bool,
+    writer: csv::Writer<Vec<u8>>,
+}
+
+impl CsvWriter {
+    fn new(name: String, relationships: bool, header: &[String]) -> Result<Self, String> {
+        let mut writer = Self {
+            name,
+            relationships,
+            writer: csv::Writer::from_writer(Vec::new()),
+        };
+        writer.row(header)?;
+        Ok(writer)
+    }
+
+    fn row<T: AsRef<[u8]>>(&mut self, fields: impl IntoIterator<Item = T>) -> Result<(), String> {
+        self.writer
+            .write_record(fields)
+            .map_err(|e| format!("write {}: {e}", self.name))
+    }
+
+    fn finish(self) -> Result<GraphCsvFile, String> {
+        let content = self
+            .writer
+            .into_inner()
+            .map_err(|e| format!("write {}: {e}", self.name))?;
+        Ok(GraphCsvFile {
+            name: self.name,
+            relationships: self.relationships,
+            content,
+        })
+    }
+}
+
+/// The kinds of attribute values, each with a CSV file of object attribute values of its own, as
+/// the column of a CSV file has one type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ValueKind {
+    Integer,
+    Float,
+    Boolean,
+    Time,
+    String,
+}
+
+impl ValueKind {
+    /// The kind of `value` and its text in a CSV file, or `None` for a null value (which is left
+    /// out)
+    fn of(value: &OCELAttributeValue) -> Option<(Self, String)> {
+        match value {
+            OCELAttributeValue::Integer(i) => Some((ValueKind::Integer, i.to_string())),
+            OCELAttributeValue::Float(f) => Some((ValueKind::Float, f.to_string())),
+            OCELAttributeValue::Boolean(b) => Some((ValueKind::Boolean, b.to_string())),
+            OCELAttributeValue::Time(t) => Some((ValueKind::Time, t.to_rfc3339())),
+            OCELAttributeValue::String(s) => Some((ValueKind::String, s.clone())),
+            OCELAttributeValue::Null => None,
+        }
+    }
+
+    /// The kind of the attributes declared with `value_type` in the OCEL
+    fn declared(value_type: &str) -> Self {
+        match value_type.to_lowercase().as_str() {
+            "integer" => ValueKind::Integer,
+            "float" => ValueKind::Float,
+            "boolean" => ValueKind::Boolean,
+            "time" => ValueKind::Time,
+            _ => ValueKind::String,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            ValueKind::Integer => "integer",
+            ValueKind::Float => "float",
+            ValueKind::Boolean => "boolean",
+            ValueKind::Time => "time",
+            ValueKind::String => "string",
+        }
+    }
+
+    /// The type of a column of these values in the header of an import file
+    fn neo4j_type(&self) -> &'static str {
+        match self {
+            ValueKind::Integer => "long",
+            ValueKind::Float => "double",
+            ValueKind::Boolean => "boolean",
+            ValueKind::Time => "datetime",
+            ValueKind::String => "string",
+        }
+    }
+}
+
+/// A file name stem for the type `name`, which differs from those in `used`
+fn file_stem(name: &str, used: &mut HashSet<String>) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut unique = stem.clone();
+    let mut n = 2;
+    while !used.insert(unique.clone()) {
+        unique = format!("{stem}_{n}");
+        n += 1;
+    }
+    unique
+}
+
+/// The CSV files of `ocel` in the graph schema of this module: the nodes of each event type, the
+/// objects, the object attribute values of each kind, and then the relationships.
+pub fn export_neo4j_csv(ocel: &SlimLinkedOCEL) -> Result<Vec<GraphCsvFile>, String> {
+    let mut files = Vec::new();
+    let mut e2o = CsvWriter::new(
+        "e2o.csv".to_string(),
+        true,
+        &[":START_ID(Event)", ":END_ID(Object)", "qualifier", ":TYPE"].map(String::from),
+    )?;
+    let mut used_stems = HashSet::new();
+    for ev_type in ocel.get_ev_types() {
+        let attributes: Vec<(String, ValueKind)> = ocel
+            .get_ev_type(ev_type)
+            .map(|t| {
+                t.attributes
+                    .iter()
+                    .map(|a| (a.name.clone(), ValueKind::declared(&a.value_type)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let mut header = ["id:ID(Event)", ":LABEL", "type", "time:datetime"]
+            .map(String::from)
+            .to_vec();
+        header.extend(
+            attributes
+                .iter()
+                .map(|(name, kind)| format!("{name}:{}", kind.neo4j_type())),
+        );
+        let stem = file_stem(ev_type, &mut used_stems);
+        let mut events = CsvWriter::new(format!("events_{stem}.csv"), false, &header)?;
+        for ev in ocel.get_evs_of_type(ev_type) {
+            let event = ocel.get_full_ev(ev);
+            let mut row = vec![
+                event.id.clone(),
+                format!("Event;{ev_type}"),
+                ev_type.to_string(),
+                event.time.to_rfc3339(),
+            ];
+            row.extend(attributes.iter().map(|(name, _)| {
+                event
+                    .attributes
+                    .iter()
+                    .find(|at| &at.name == name)
+                    .and_then(|at| ValueKind::of(&at.value))
+                    .map(|(_, text)| text)
+                    .unwrap_or_default()
+            }));
+            events.row(&row)?;
+            for (qualifier, ob) in ocel.get_e2o(ev) {
+                e2o.row([
+                    event.id.as_str(),
+                    ocel.get_ob_id(ob),
+                    qualifier.to_string().as_str(),
+                    "E2O",
+                ])?;
+            }
+        }
+        files.push(events.finish()?);
+    }
+
+    let mut objects = CsvWriter::new(
+        "objects.csv".to_string(),
+        false,
+        &["id:ID(Object)", ":LABEL", "type"].map(String::from),
+    )?;
+    let mut values: BTreeMap<ValueKind, CsvWriter> = BTreeMap::new();
+    let mut has_value = CsvWriter::new(
+        "has_value.csv".to_string(),
+        true,
+        &[
+            ":START_ID(Object)",
+            ":END_ID(ObjectAttributeValue)",
+            ":TYPE",
+        ]
+        .map(String::from),
+    )?;
+    let mut o2o = CsvWriter::new(
+        "o2o.csv".to_string(),
+        true,
+        &[":START_ID(Object)", ":END_ID(Object)", "qualifier", ":TYPE"].map(String::from),
+    )?;
+    let mut df = CsvWriter::new(
+        "df.csv".to_string(),
+        true,
+        &[":START_ID(Event)", ":END_ID(Event)", "object", ":TYPE"].map(String::from),
+    )?;
+    let mut value_id = 0usize;
+    for ob_type in ocel.get_ob_types() {
+        for ob in ocel.get_obs_of_type(ob_type) {
+            let object = ocel.get_full_ob(ob);
+            objects.row([
+                object.id.as_str(),
+                format!("Object;{ob_type}").as_str(),
+                ob_type.to_string().as_str(),
+            ])?;
+            for attribute in &object.attributes {
+                let Some((kind, text)) = ValueKind::of(&attribute.value) else {
+                    continue;
+                };
+                let writer = match values.entry(kind) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => entry.insert(CsvWriter::new(
+                        format!("object_attribute_values_{}.csv", kind.name()),
+                        false,
+                        &[
+                            ":ID(ObjectAttributeValue)".to_string(),
+                            ":LABEL".to_string(),
+                            "name".to_string(),
+                            format!("value:{}", kind.neo4j_type()),
+                            "time:datetime".to_string(),
+                        ],
+                    )?),
+                };
+                value_id += 1;
+                let id = value_id.to_string();
+                writer.row([
+                    id.as_str(),
+                    "ObjectAttributeValue",
+                    attribute.name.as_str(),
+                    text.as_str(),
+                    attribute.time.to_rfc3339().as_str(),
+                ])?;
+                has_value.row([object.id.as_str(), id.as_str(), "HAS_VALUE"])?;
+            }
+            for (qualifier, other) in ocel.get_o2o(ob) {
+                o2o.row([
+                    object.id.as_str(),
+                    ocel.get_ob_id(other),
+                    qualifier.to_string().as_str(),
+                    "O2O",
+                ])?;
+            }
+            for pair in object_lifecycle(ocel, ob).windows(2) {
+                df.row([
+                    ocel.get_ev_id(&pair[0]),
+                    ocel.get_ev_id(&pair[1]),
+                    object.id.as_str(),
+                    "DF",
+                ])?;
+            }
+        }
+    }
+
+    files.push(objects.finish()?);
+    for writer in values.into_values() {
+        files.push(writer.finish()?);
+    }
+    for writer in [e2o, o2o, has_value, df] {
+        files.push(writer.finish()?);
+    }
+    Ok(files)
+}
+
+/// The `neo4j-admin` command that imports `files` (from the directory they are written to) into
+/// the (new) database `database`
+pub fn neo4j_import_command(files: &[GraphCsvFile], database: &str) -> String {
+    let mut command = "neo4j-admin database import full".to_string();
+    for file in files {
+        let option = if file.relationships {
+            "relationships"
+        } else {
+            "nodes"
+        };
+        command.push_str(&format!(" --{option}={}", file.name));
+    }
+    command.push_str(&format!(" {database}"));
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use process_mining::core::event_data::object_centric::OCEL;
+
+    use super::*;
+
+    /// An order placed and shipped in two parts, once with its item
+    const OCEL_JSON: &str = r#"{
+        "objectTypes": [
+            { "name": "order", "attributes": [{ "name": "status", "type": "string" }] },
+            { "name": "item", "attributes": [] }
+        ],
+        "eventTypes": [
+            { "name": "place order", "attributes": [{ "name": "amount", "type": "integer" }] },
+            { "name": "ship", "attributes": [] }
+        ],
+        "objects": [
+            { "id": "o1", "type": "order", "relationships": [{ "objectId": "i1", "qualifier": "contains" }], "attributes": [
+                { "name": "status", "value": "open", "time": "1970-01-01T00:00:00Z" },
+                { "name": "status", "value": "shipped", "time": "2024-01-03T00:00:00Z" }
+            ] },
+            { "id": "i1", "type": "item", "relationships": [], "attributes": [] }
+        ],
+        "events": [
+            { "id": "e1", "type": "place order", "time": "2024-01-01T00:00:00Z",
+              "attributes": [{ "name": "amount", "value": 100 }],
+              "relationships": [{ "objectId": "o1", "qualifier": "order" }] },
+            { "id": "e2", "type": "ship", "time": "2024-01-02T00:00:00Z", "attributes": [],
+              "relationships": [{ "objectId": "o1", "qualifier": "order" }, { "objectId": "i1", "qualifier": "item" }] },
+            { "id": "e3", "type": "ship", "time": "2024-01-03T00:00:00Z", "attributes": [],
+              "relationships": [{ "objectId": "o1", "qualifier": "order" }] }
+        ]
+    }"#;
+
+    fn export() -> Vec<GraphCsvFile> {
+        let ocel: OCEL = serde_json::from_str(OCEL_JSON).expect("fixture OCEL parses");
+        export_neo4j_csv(&SlimLinkedOCEL::from_ocel(ocel)).expect("fixture OCEL exports")
+    }
+
+    fn content<'a>(files: &'a [GraphCsvFile], name: &str) -> &'a str {
+        let file = files
+            .iter()
+            .find(|f| f.name == name)
+            .unwrap_or_else(|| panic!("no file {name}"));
+        std::str::from_utf8(&file.content).unwrap()
+    }
+
+    #[test]
+    fn events_objects_and_attribute_values_become_nodes() {
+        let files = export();
+        assert_eq!(
+            content(&files, "events_place_order.csv"),
+            "id:ID(Event),:LABEL,type,time:datetime,amount:long\ne1,Event;place order,place order,2024-01-01T00:00:00+00:00,100\n"
+        );
+        assert!(content(&files, "objects.csv").contains("o1,Object;order,order\n"));
+        assert_eq!(
+            content(&files, "object_attribute_values_string.csv"),
+            ":ID(ObjectAttributeValue),:LABEL,name,value:string,time:datetime\n1,ObjectAttributeValue,status,open,1970-01-01T00:00:00+00:00\n2,ObjectAttributeValue,status,shipped,2024-01-03T00:00:00+00:00\n"
+        );
+        // Nodes come before the relationships between them
+        let first_relationship = files.iter().position(|f| f.relationships).unwrap();
+        assert!(files[first_relationship..].iter().all(|f| f.relationships));
+    }
+
+    #[test]
+    fn relationships_carry_qualifiers_and_lifecycles_become_df_edges() {
+        let files = export();
+        assert!(content(&files, "e2o.csv").contains("e2,i1,item,E2O\n"));
+        assert_eq!(
+            content(&files, "o2o.csv"),
+            ":START_ID(Object),:END_ID(Object),qualifier,:TYPE\no1,i1,contains,O2O\n"
+        );
+        assert_eq!(
+            content(&files, "has_value.csv"),
+            ":START_ID(Object),:END_ID(ObjectAttributeValue),:TYPE\no1,1,HAS_VALUE\no1,2,HAS_VALUE\n"
+        );
+        assert_eq!(
+            content(&files, "df.csv"),
+            ":START_ID(Event),:END_ID(Event),object,:TYPE\ne1,e2,o1,DF\ne2,e3,o1,DF\n"
+        );
+
+        let command = neo4j_import_command(&files, "neo4j");
+        assert!(command.starts_with("neo4j-admin database import full --nodes="));
+        assert!(command.contains(" --nodes=objects.csv "), "{command}");
+        assert!(
+            command.ends_with(" --relationships=df.csv neo4j"),
+            "{command}"
+        );
+    }
+}
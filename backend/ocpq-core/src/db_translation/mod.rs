@@ -25,15 +25,28 @@
 // IN THE
 // SOFTWARE.
 
+mod cel_sql;
+pub mod execute;
+pub mod graph_export;
+
 use crate::binding_box::structs::EventVariable;
 use crate::binding_box::structs::NewEventVariables;
 use crate::binding_box::structs::NewObjectVariables;
 use crate::binding_box::structs::ObjectVariable;
 use crate::binding_box::structs::Qualifier;
 use crate::binding_box::{
-    structs::{Constraint, Filter, ObjectValueFilterTimepoint, SizeFilter, ValueFilter},
+    aggregate::{AggregateFunction, AggregateValue, ChildAggregate},
+    parameters::{ParameterValues, TreeParameter},
+    structs::{
+        AllenRelation, Constraint, Filter, LabelFunction, ObjectValueFilterTimepoint, ObjectWindow,
+        SizeFilter, ValueFilter, Variable,
+    },
+    validate::TreeLocation,
     BindingBoxTree,
 };
+use crate::cel::lowering::lower_cel_filters;
+use cel_sql::{cel_condition, CelScope};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use ts_rs::TS;
@@ -52,6 +65,8 @@ pub enum DatabaseType {
     SQLite,
 
     DuckDB,
+
+    PostgreSQL,
 }
 
 #[derive(Clone)]
@@ -66,9 +81,16 @@ pub struct SqlParts<'a> {
     used_keys: HashSet<String>,
     database_type: DatabaseType,
     alias_type_map: HashMap<String, String>,
+    warnings: Vec<TranslationIssue>,
+    unsupported: Vec<TranslationIssue>,
 }
 
 impl<'a> SqlParts<'a> {
+    /// Note that the query leaves out `location` of this node, for `reason`.
+    fn leave_out(&mut self, location: TreeLocation, reason: String) {
+        push_issue(&mut self.unsupported, self.node.index, location, reason);
+    }
+
     /// Next free alias of the form `<prefix>{N}` (1-indexed), reserved in `used_keys`.
     fn next_alias(&mut self, prefix: &str) -> String {
         let mut n = 1;
@@ -93,6 +115,9 @@ pub struct TableMappings {
     pub e2o_table: String,
     /// O2O junction table. Defaults to `object_object` (same exporter).
     pub o2o_table: String,
+    /// Column of the event and object tables that holds all attributes as a JSON object (like a
+    /// `jsonb` column in PostgreSQL). Without one, every attribute is a column of its own.
+    pub attribute_json_column: Option<String>,
 }
 
 impl Default for TableMappings {
@@ -102,6 +127,7 @@ impl Default for TableMappings {
             object_tables: HashMap::new(),
             e2o_table: "event_object".to_string(),
             o2o_table: "object_object".to_string(),
+            attribute_json_column: None,
         }
     }
 }
@@ -126,16 +152,104 @@ pub struct DBTranslationInput {
     pub tree: BindingBoxTree,
     pub database: DatabaseType,
     pub table_mappings: TableMappings,
+    /// Values of the tree's parameters; those not given take their default
+    #[serde(default)]
+    pub parameters: ParameterValues,
+}
+
+/// Something of a tree the translation leaves out (or, as a warning, does not return)
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationIssue {
+    pub node_index: usize,
+    pub location: TreeLocation,
+    pub message: String,
+}
+
+/// A query translated from a tree, with what of the tree it does not express
+#[derive(TS)]
+#[ts(export)]
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationResult {
+    pub query: String,
+    /// What the query checks but does not return, like the labels
+    pub warnings: Vec<TranslationIssue>,
+    /// What the query does not check, so it can find bindings (or satisfied bindings) the
+    /// evaluation does not
+    pub unsupported: Vec<TranslationIssue>,
+    /// The value of each of the tree's parameters the query was translated with, defaults included
+    pub parameters: ParameterValues,
+}
+
+impl TranslationResult {
+    fn new(
+        query: String,
+        mut warnings: Vec<TranslationIssue>,
+        mut unsupported: Vec<TranslationIssue>,
+        parameters: ParameterValues,
+    ) -> Self {
+        warnings.sort_by_key(|issue| issue.node_index);
+        unsupported.sort_by_key(|issue| issue.node_index);
+        Self {
+            query,
+            warnings,
+            unsupported,
+            parameters,
+        }
+    }
+
+    /// Whether the query checks all of the tree
+    pub fn is_complete(&self) -> bool {
+        self.unsupported.is_empty()
+    }
+}
+
+/// Add an issue to `issues`, unless it is there already (a CEL filter lowered to several filters
+/// can give the same one twice).
+fn push_issue(
+    issues: &mut Vec<TranslationIssue>,
+    node_index: usize,
+    location: TreeLocation,
+    message: String,
+) {
+    let issue = TranslationIssue {
+        node_index,
+        location,
+        message,
+    };
+    if !issues.contains(&issue) {
+        issues.push(issue);
+    }
+}
+
+/// The value of each parameter of `tree` among `values`, or else its default
+fn parameter_values(
+    tree: &BindingBoxTree,
+    values: &ParameterValues,
+) -> Result<ParameterValues, String> {
+    tree.parameters
+        .iter()
+        .map(|p| Ok((p.name.clone(), p.value(values)?.clone())))
+        .collect()
 }
 
 // Implementation of the General translate to SQL function
-pub fn translate_to_sql_shared(input: DBTranslationInput) -> String {
+pub fn translate_to_sql_shared(input: DBTranslationInput) -> Result<TranslationResult, String> {
+    // Step 0: Bind the parameters, which sets their bounds and the values CEL reads
+    let parameters = parameter_values(&input.tree, &input.parameters)?;
+    let tree = input.tree.bind_parameters(&input.parameters)?;
+
     //Step 1:  Extract Intermediate Representation
-    let inter = convert_to_intermediate(input.tree);
+    let inter = convert_to_intermediate(tree);
 
     // Create SQL Struct
 
-    let sql_parts = SqlParts {
+    let mut sql_parts = SqlParts {
+        warnings: inter.label_warnings(),
+        unsupported: inter.unsupported.clone(),
         node: inter,
         select_fields: vec![],
         base_from: vec![],
@@ -150,7 +264,13 @@ pub fn translate_to_sql_shared(input: DBTranslationInput) -> String {
 
     // Step 2: Translate the Intermediate Representation to SQL
 
-    translate_to_sql_from_intermediate(sql_parts)
+    let query = translate_to_sql_from_intermediate(&mut sql_parts);
+    Ok(TranslationResult::new(
+        query,
+        sql_parts.warnings,
+        sql_parts.unsupported,
+        parameters,
+    ))
 }
 
 pub fn convert_to_intermediate(tree: BindingBoxTree) -> InterMediateNode {
@@ -161,13 +281,41 @@ pub fn convert_to_intermediate(tree: BindingBoxTree) -> InterMediateNode {
 
 #[derive(Clone)]
 pub struct InterMediateNode {
+    /// The index of the node in the tree, to say where a translation leaves something out
+    pub index: usize,
     pub event_vars: NewEventVariables,
     pub object_vars: NewObjectVariables,
     pub relations: Vec<Relation>, // O2O, E2O, TBE Basics have to be included
     pub constraints: Vec<Constraint>,
     pub children: Vec<(InterMediateNode, String)>,
     pub filter: Vec<Filter>,
+    /// For each of `filter`, the index of the box's filter it comes from (lowering can split a
+    /// CEL filter into several)
+    pub filter_origins: Vec<usize>,
+    /// All filters of the box after lowering, each with the index of the filter it comes from
+    pub located_filters: Vec<(usize, Filter)>,
     pub sizefilter: Vec<SizeFilter>,
+    /// Compiled where CEL uses them, as the query does not select them
+    pub labels: Vec<LabelFunction>,
+    /// The parameters of the tree, bound to their defaults, which CEL reads as variables
+    pub parameters: Vec<TreeParameter>,
+    /// What the SQL translation leaves out of the filters (see [`untranslated_filter`])
+    pub unsupported: Vec<TranslationIssue>,
+}
+
+impl InterMediateNode {
+    /// A warning for each label, as no query selects them
+    fn label_warnings(&self) -> Vec<TranslationIssue> {
+        self.labels
+            .iter()
+            .enumerate()
+            .map(|(index, label)| TranslationIssue {
+                node_index: self.index,
+                location: TreeLocation::Label { index },
+                message: format!("The label `{}` is not returned by the query", label.label),
+            })
+            .collect()
+    }
 }
 
 #[derive(Clone)]
@@ -198,18 +346,46 @@ pub fn bindingbox_to_intermediate(tree: &BindingBoxTree, index: usize) -> InterM
     let event_vars = binding_box.new_event_vars.clone();
     let object_vars = binding_box.new_object_vars.clone();
 
+    // The simple comparisons among CEL are native filters in disguise; the rest is compiled to SQL.
+    // Lowered one by one, to know which filter of the box each comes from.
+    let located_filters: Vec<(usize, Filter)> = binding_box
+        .filters
+        .iter()
+        .enumerate()
+        .flat_map(|(i, f)| {
+            lower_cel_filters(std::slice::from_ref(f), &tree.parameters)
+                .into_iter()
+                .map(move |f| (i, f))
+        })
+        .collect();
+    let filters: Vec<Filter> = located_filters.iter().map(|(_, f)| f.clone()).collect();
+
+    let mut unsupported = Vec::new();
+    for (i, filter) in &located_filters {
+        if let Some(reason) = untranslated_filter(filter) {
+            push_issue(
+                &mut unsupported,
+                index,
+                TreeLocation::Filter { index: *i },
+                reason,
+            );
+        }
+    }
+    let filter_origins = located_filters
+        .iter()
+        .filter(|(_, f)| is_where_filter(f))
+        .map(|(i, _)| *i)
+        .collect();
+
     // Extract the relations we HAVE to translate to query language (O2O, E2O, TBE)
-    let relations = extract_basic_relations(binding_box.filters.clone());
+    let relations = extract_basic_relations(filters.clone());
 
     let constraints = binding_box.constraints.clone();
 
     // Handle childs recursively with box to inter function
     let mut children = Vec::new();
 
-    let (filter, sizefilter) = extract_filters(
-        binding_box.filters.clone(),
-        binding_box.size_filters.clone(),
-    );
+    let (filter, sizefilter) = extract_filters(filters, binding_box.size_filters.clone());
 
     // Iterate over all BindingBoxes in tree
     for child_index in child_indices.as_ref() {
@@ -222,16 +398,74 @@ pub fn bindingbox_to_intermediate(tree: &BindingBoxTree, index: usize) -> InterM
     }
 
     InterMediateNode {
+        index,
         event_vars,
         object_vars,
         relations,
         filter,
+        filter_origins,
+        located_filters,
         sizefilter,
         constraints,
         children,
+        labels: binding_box.labels.clone(),
+        parameters: tree.parameters.clone(),
+        unsupported,
     }
 }
 
+/// Why the SQL translation leaves out `filter` (or, of a relation, its qualifier), if it does
+fn untranslated_filter(filter: &Filter) -> Option<String> {
+    let kind = match filter {
+        Filter::O2E {
+            qualifier: Some(qualifier),
+            ..
+        }
+        | Filter::O2O {
+            qualifier: Some(qualifier),
+            ..
+        } => {
+            return Some(format!(
+                "The qualifier `{qualifier}` is not translated, so any qualifier matches"
+            ))
+        }
+        Filter::TimeBetweenEvents {
+            calendar: Some(calendar),
+            ..
+        } => {
+            return Some(format!(
+                "The business calendar `{calendar}` is not available in the database"
+            ))
+        }
+        Filter::O2E { .. }
+        | Filter::O2O { .. }
+        | Filter::TimeBetweenEvents { .. }
+        | Filter::EventAttributeValueFilter { .. }
+        | Filter::ObjectAttributeValueFilter { .. }
+        | Filter::BasicFilterCEL { .. } => return None,
+        Filter::DirectlyFollows { .. } => "DirectlyFollows",
+        Filter::NoEventBetween { .. } => "NoEventBetween",
+        Filter::EventInObjectWindow { .. } => "EventInObjectWindow",
+        Filter::IntervalRelation { .. } => "IntervalRelation",
+        Filter::NotEqual { .. } => "NotEqual",
+    };
+    Some(format!("{kind} filters are not translated"))
+}
+
+/// Why the SQL translation leaves out `size_filter`, if it does
+fn untranslated_size_filter(size_filter: &SizeFilter) -> Option<String> {
+    let kind = match size_filter {
+        SizeFilter::NumChilds { .. }
+        | SizeFilter::NumSatisfiedChilds { .. }
+        | SizeFilter::AdvancedCEL { .. } => return None,
+        SizeFilter::BindingSetEqual { .. } => "BindingSetEqual",
+        SizeFilter::BindingSetProjectionEqual { .. } => "BindingSetProjectionEqual",
+        SizeFilter::NumChildsProj { .. } => "NumChildsProj",
+        SizeFilter::Aggregate { .. } => "Aggregate",
+    };
+    Some(format!("{kind} size filters are not translated"))
+}
+
 // Function to extract BASIC operations (E20,O2O,TBE)
 pub fn extract_basic_relations(filters: Vec<Filter>) -> Vec<Relation> {
     let mut result = Vec::new();
@@ -269,6 +503,7 @@ pub fn extract_basic_relations(filters: Vec<Filter>) -> Vec<Relation> {
                 to_event,
                 min_seconds,
                 max_seconds,
+                calendar: None,
             } => {
                 result.push(Relation::TimeBetweenEvents {
                     from_event,
@@ -286,56 +521,42 @@ pub fn extract_basic_relations(filters: Vec<Filter>) -> Vec<Relation> {
     result
 }
 
-// Extract other meaningful filters (maybe these in relations are enough, but CEL could be considered)
+// Extract the other filters translated to WHERE clauses: attribute values and CEL
 pub fn extract_filters(
     filters: Vec<Filter>,
     size_filters: Vec<SizeFilter>,
 ) -> (Vec<Filter>, Vec<SizeFilter>) {
-    let mut result = Vec::new();
+    let result = filters.into_iter().filter(is_where_filter).collect();
     let result_size: Vec<SizeFilter> = size_filters.to_vec();
 
-    for filter in &filters {
-        match filter {
-            Filter::ObjectAttributeValueFilter {
-                object: _,
-                attribute_name: _,
-                at_time: _,
-                value_filter: _,
-            } => {
-                result.push(filter.clone());
-            }
-
-            Filter::EventAttributeValueFilter {
-                event: _,
-                attribute_name: _,
-                value_filter: _,
-            } => {
-                result.push(filter.clone());
-            }
-
-            _ => {}
-        }
-    }
-
     (result, result_size)
 }
 
+fn is_where_filter(filter: &Filter) -> bool {
+    matches!(
+        filter,
+        Filter::ObjectAttributeValueFilter { .. }
+            | Filter::EventAttributeValueFilter { .. }
+            | Filter::BasicFilterCEL { .. }
+    )
+}
+
 // End of Intermediate
 
 // Start of SQL Translation
 
 // Function which translates Intermediate to SQL
-pub fn translate_to_sql_from_intermediate(mut sql_parts: SqlParts) -> String {
-    sql_parts.select_fields = construct_select_fields_root(&sql_parts);
+pub fn translate_to_sql_from_intermediate(sql_parts: &mut SqlParts) -> String {
+    sql_parts.select_fields = construct_select_fields_root(sql_parts);
 
-    sql_parts.base_from = construct_from_clauses(&mut sql_parts);
+    sql_parts.base_from = construct_from_clauses(sql_parts);
 
-    (sql_parts.join_clauses, sql_parts.where_clauses) = construct_basic_operations(&mut sql_parts);
+    (sql_parts.join_clauses, sql_parts.where_clauses) = construct_basic_operations(sql_parts);
 
-    let childs = construct_childstrings(&sql_parts);
+    let childs = construct_childstrings(sql_parts);
     sql_parts.child_sql = childs;
 
-    let filter_clauses = construct_filter_non_basic(&mut sql_parts);
+    let filter_clauses = construct_filter_non_basic(sql_parts);
     sql_parts.where_clauses.extend(filter_clauses);
 
     let canonical_row_clauses: Vec<String> = sorted_object_vars(&sql_parts.node.object_vars)
@@ -344,7 +565,7 @@ pub fn translate_to_sql_from_intermediate(mut sql_parts: SqlParts) -> String {
         .collect();
     sql_parts.where_clauses.extend(canonical_row_clauses);
 
-    construct_result(&mut sql_parts)
+    construct_result(sql_parts)
 }
 
 // Construct the resulting SQL query with tools given
@@ -513,6 +734,61 @@ fn num_childs_count_expr(
     )
 }
 
+/// SQL condition for `SizeFilter::NumSatisfiedChilds`, or `None` if it has no bounds. Satisfied
+/// and total counts come from one pass over the distinct child bindings, so the child query is
+/// only embedded once.
+fn num_satisfied_childs_clause(
+    filter: &SizeFilter,
+    child_sql: &str,
+    child_label: &str,
+    child_node: &InterMediateNode,
+    i: usize,
+    j: usize,
+) -> Option<String> {
+    let SizeFilter::NumSatisfiedChilds {
+        min,
+        max,
+        min_fraction,
+        max_fraction,
+        ..
+    } = filter
+    else {
+        return None;
+    };
+    let label = child_label.trim();
+    let sat = format!("COALESCE(SUM(child_{i}_{j}_{label}_d.satisfied), 0)");
+    // Same as the in-memory evaluator: a child without bindings counts as fully satisfied.
+    let share = format!(
+        "CASE WHEN COUNT(*) = 0 THEN 1.0 ELSE 1.0 * SUM(child_{i}_{j}_{label}_d.satisfied) / COUNT(*) END"
+    );
+    let mut conditions = Vec::new();
+    if let Some(min) = min {
+        conditions.push(format!("{sat} >= {min}"));
+    }
+    if let Some(max) = max {
+        conditions.push(format!("{sat} <= {max}"));
+    }
+    if let Some(min_fraction) = min_fraction {
+        conditions.push(format!("{share} >= {min_fraction}"));
+    }
+    if let Some(max_fraction) = max_fraction {
+        conditions.push(format!("{share} <= {max_fraction}"));
+    }
+    if conditions.is_empty() {
+        return None;
+    }
+    let mut distinct_list: Vec<String> = child_key_columns(child_node)
+        .into_iter()
+        .map(|(_, alias)| alias)
+        .collect();
+    distinct_list.push("satisfied".to_string());
+    Some(format!(
+        "(SELECT CASE WHEN {} THEN 1 ELSE 0 END FROM (SELECT DISTINCT {} FROM ({child_sql}) AS child_{i}_{j}_{label}) AS child_{i}_{j}_{label}_d) = 1",
+        conditions.join(" AND "),
+        distinct_list.join(", ")
+    ))
+}
+
 pub fn get_object_type(node: InterMediateNode, index: usize) -> String {
     for (obj_var, types) in sorted_object_vars(&node.object_vars) {
         if obj_var.0 == index {
@@ -543,8 +819,8 @@ pub fn construct_from_clauses(sql_parts: &mut SqlParts) -> Vec<String> {
     let mut is_first_join = true;
 
     // Snapshotted here since `sql_parts` gets mutably borrowed via `next_alias` below.
-    let e2o_tbl = format!("\"{}\"", sql_parts.table_mappings.e2o_table);
-    let o2o_tbl = format!("\"{}\"", sql_parts.table_mappings.o2o_table);
+    let e2o_tbl = quote_ident(&sql_parts.table_mappings.e2o_table);
+    let o2o_tbl = quote_ident(&sql_parts.table_mappings.o2o_table);
 
     // Clone the relation list so we can mutably borrow `sql_parts` inside the
     // loop body (e.g. via `next_alias`).
@@ -1022,7 +1298,7 @@ pub fn construct_basic_operations(sql_parts: &mut SqlParts) -> (Vec<String>, Vec
     (join_clauses, where_clauses)
 }
 
-pub fn construct_childstrings(sql_parts: &SqlParts) -> Vec<(String, String)> {
+pub fn construct_childstrings(sql_parts: &mut SqlParts) -> Vec<(String, String)> {
     let mut result = Vec::new();
 
     for (inter_node, node_label) in &sql_parts.node.children {
@@ -1037,10 +1313,16 @@ pub fn construct_childstrings(sql_parts: &SqlParts) -> Vec<(String, String)> {
             used_keys: sql_parts.used_keys.clone(),
             database_type: sql_parts.database_type,
             alias_type_map: sql_parts.alias_type_map.clone(),
+            warnings: inter_node.label_warnings(),
+            unsupported: inter_node.unsupported.clone(),
         };
 
         let child_sql = translate_to_sql_from_child(&mut child_sql_parts);
         result.push((child_sql, node_label.clone()));
+        sql_parts.warnings.append(&mut child_sql_parts.warnings);
+        sql_parts
+            .unsupported
+            .append(&mut child_sql_parts.unsupported);
     }
 
     result
@@ -1049,13 +1331,14 @@ pub fn construct_childstrings(sql_parts: &SqlParts) -> Vec<(String, String)> {
 pub fn construct_child_constraints(sql_parts: &mut SqlParts) -> String {
     let mut result_string = Vec::new();
 
-    let e2o_tbl = format!("\"{}\"", sql_parts.table_mappings.e2o_table);
-    let o2o_tbl = format!("\"{}\"", sql_parts.table_mappings.o2o_table);
+    let e2o_tbl = quote_ident(&sql_parts.table_mappings.e2o_table);
+    let o2o_tbl = quote_ident(&sql_parts.table_mappings.o2o_table);
 
     // Clone the constraint list so the loop body can mutably borrow `sql_parts`
     // (e.g. via `next_alias`).
     let constraints = sql_parts.node.constraints.clone();
     for (i, constraint) in constraints.iter().enumerate() {
+        let location = TreeLocation::Constraint { index: i };
         match constraint {
             Constraint::ANY { child_names } => {
                 let mut parts = Vec::new();
@@ -1172,83 +1455,122 @@ pub fn construct_child_constraints(sql_parts: &mut SqlParts) -> String {
                         }
                     }
                 }
+                if let SizeFilter::NumSatisfiedChilds { child_name, .. } = filter {
+                    for (j, (child_sql, child_label)) in sql_parts.child_sql.iter().enumerate() {
+                        if child_label == child_name {
+                            if let Some(clause) = num_satisfied_childs_clause(
+                                filter,
+                                child_sql,
+                                child_label,
+                                &sql_parts.node.children[j].0,
+                                i,
+                                j,
+                            ) {
+                                result_string.push(clause);
+                            }
+                        }
+                    }
+                }
+                if let SizeFilter::AdvancedCEL { cel } = filter {
+                    match cel_condition(sql_parts, cel, CelScope::SizeFilter) {
+                        Ok(clause) => result_string.push(clause),
+                        Err(e) => sql_parts.leave_out(location, e),
+                    }
+                }
+                if let Some(reason) = untranslated_size_filter(filter) {
+                    sql_parts.leave_out(location, reason);
+                }
             }
 
-            Constraint::Filter { filter } => match filter {
-                Filter::O2E { object, event, .. } => {
-                    let alias = sql_parts.next_alias("ER");
-                    result_string.push(format!(
+            Constraint::Filter { filter } => {
+                if let Some(reason) = untranslated_filter(filter) {
+                    sql_parts.leave_out(location, reason);
+                }
+                match filter {
+                    Filter::O2E { object, event, .. } => {
+                        let alias = sql_parts.next_alias("ER");
+                        result_string.push(format!(
                             "EXISTS (SELECT 1 FROM {e2o_tbl} AS {} WHERE {}.ocel_event_id = E{}.ocel_id AND {}.ocel_object_id = O{}.ocel_id)",
                             alias, alias, e_alias(event.0), alias, o_alias(object.0)
                         ));
-                }
+                    }
 
-                Filter::O2O {
-                    object,
-                    other_object,
-                    ..
-                } => {
-                    let alias = sql_parts.next_alias("OR");
+                    Filter::O2O {
+                        object,
+                        other_object,
+                        ..
+                    } => {
+                        let alias = sql_parts.next_alias("OR");
 
-                    result_string.push(format!(
+                        result_string.push(format!(
                             "EXISTS (SELECT 1 FROM {o2o_tbl} AS {} WHERE {}.ocel_source_id = O{}.ocel_id AND {}.ocel_target_id = O{}.ocel_id)",
                             alias, alias, o_alias(object.0), alias, o_alias(other_object.0)
                         ));
-                }
-
-                Filter::TimeBetweenEvents {
-                    from_event,
-                    to_event,
-                    min_seconds,
-                    max_seconds,
-                } => {
-                    if let Some(min) = min_seconds {
-                        result_string.push(format!(
-                            "{time_left} - {time_right} >= {min}",
-                            time_left = map_timestamp_event(sql_parts, to_event.0),
-                            time_right = map_timestamp_event(sql_parts, from_event.0)
-                        ));
                     }
-                    if let Some(max) = max_seconds {
-                        result_string.push(format!(
-                            "{time_left} - {time_right} <= {max}",
-                            time_left = map_timestamp_event(sql_parts, to_event.0),
-                            time_right = map_timestamp_event(sql_parts, from_event.0)
-                        ));
+
+                    // Business calendars are not available in the database.
+                    Filter::TimeBetweenEvents {
+                        from_event,
+                        to_event,
+                        min_seconds,
+                        max_seconds,
+                        calendar: None,
+                    } => {
+                        if let Some(min) = min_seconds {
+                            result_string.push(format!(
+                                "{time_left} - {time_right} >= {min}",
+                                time_left = map_timestamp_event(sql_parts, to_event.0),
+                                time_right = map_timestamp_event(sql_parts, from_event.0)
+                            ));
+                        }
+                        if let Some(max) = max_seconds {
+                            result_string.push(format!(
+                                "{time_left} - {time_right} <= {max}",
+                                time_left = map_timestamp_event(sql_parts, to_event.0),
+                                time_right = map_timestamp_event(sql_parts, from_event.0)
+                            ));
+                        }
                     }
-                }
 
-                Filter::EventAttributeValueFilter {
-                    event,
-                    attribute_name,
-                    value_filter,
-                } => {
-                    result_string.push(event_attr_value_filter_clause(
-                        sql_parts,
+                    Filter::EventAttributeValueFilter {
                         event,
                         attribute_name,
                         value_filter,
-                    ));
-                }
+                    } => {
+                        result_string.push(event_attr_value_filter_clause(
+                            sql_parts,
+                            event,
+                            attribute_name,
+                            value_filter,
+                        ));
+                    }
 
-                Filter::ObjectAttributeValueFilter {
-                    object,
-                    attribute_name,
-                    at_time,
-                    value_filter,
-                } => {
-                    result_string.push(object_attr_value_filter_clause(
-                        sql_parts,
+                    Filter::ObjectAttributeValueFilter {
                         object,
                         attribute_name,
                         at_time,
                         value_filter,
-                        i,
-                    ));
-                }
+                    } => {
+                        result_string.push(object_attr_value_filter_clause(
+                            sql_parts,
+                            object,
+                            attribute_name,
+                            at_time,
+                            value_filter,
+                            i,
+                        ));
+                    }
 
-                _ => {}
-            },
+                    Filter::BasicFilterCEL { cel } => {
+                        match cel_condition(sql_parts, cel, CelScope::ConstraintFilter) {
+                            Ok(clause) => result_string.push(clause),
+                            Err(e) => sql_parts.leave_out(location, e),
+                        }
+                    }
+
+                    _ => {}
+                }
+            }
         }
     }
 
@@ -1326,7 +1648,9 @@ pub fn construct_result_child(sql_parts: &SqlParts) -> String {
 pub fn construct_filter_non_basic(sql_parts: &mut SqlParts) -> Vec<String> {
     let mut result = Vec::new();
 
-    for (i, sizefilter) in sql_parts.node.sizefilter.iter().enumerate() {
+    // Cloned so CEL can be compiled with a mutable borrow of `sql_parts` (for `next_alias`).
+    let size_filters = sql_parts.node.sizefilter.clone();
+    for (i, sizefilter) in size_filters.iter().enumerate() {
         if let SizeFilter::NumChilds {
             child_name,
             min,
@@ -1354,9 +1678,36 @@ pub fn construct_filter_non_basic(sql_parts: &mut SqlParts) -> Vec<String> {
                 }
             }
         }
+        if let SizeFilter::NumSatisfiedChilds { child_name, .. } = sizefilter {
+            for (j, (child_sql, child_label)) in sql_parts.child_sql.iter().enumerate() {
+                if child_label == child_name {
+                    if let Some(clause) = num_satisfied_childs_clause(
+                        sizefilter,
+                        child_sql,
+                        child_label,
+                        &sql_parts.node.children[j].0,
+                        i,
+                        j,
+                    ) {
+                        result.push(clause);
+                    }
+                }
+            }
+        }
+        let location = TreeLocation::SizeFilter { index: i };
+        if let SizeFilter::AdvancedCEL { cel } = sizefilter {
+            match cel_condition(sql_parts, cel, CelScope::SizeFilter) {
+                Ok(clause) => result.push(clause),
+                Err(e) => sql_parts.leave_out(location, e),
+            }
+        }
+        if let Some(reason) = untranslated_size_filter(sizefilter) {
+            sql_parts.leave_out(location, reason);
+        }
     }
 
-    for (i, filter) in sql_parts.node.filter.iter().enumerate() {
+    let filters = sql_parts.node.filter.clone();
+    for (i, filter) in filters.iter().enumerate() {
         match filter {
             Filter::EventAttributeValueFilter {
                 event,
@@ -1387,6 +1738,15 @@ pub fn construct_filter_non_basic(sql_parts: &mut SqlParts) -> Vec<String> {
                 ));
             }
 
+            Filter::BasicFilterCEL { cel } => match cel_condition(sql_parts, cel, CelScope::Filter)
+            {
+                Ok(clause) => result.push(clause),
+                Err(e) => {
+                    let index = sql_parts.node.filter_origins[i];
+                    sql_parts.leave_out(TreeLocation::Filter { index }, e);
+                }
+            },
+
             _ => {}
         }
     }
@@ -1402,7 +1762,13 @@ fn event_attr_value_filter_clause(
     attribute_name: &str,
     value_filter: &ValueFilter,
 ) -> String {
-    let col = format!("E{}.\"{}\"", e_alias(event.0), attribute_name);
+    // The id and time of an event are its `ocel_id` and `ocel_time` columns.
+    let alias = format!("E{}", e_alias(event.0));
+    let col = match attribute_name {
+        "ocel:id" => format!("{alias}.\"ocel_id\""),
+        "ocel:time" => format!("{alias}.\"ocel_time\""),
+        name => attribute_value(sql_parts, &alias, name, value_filter),
+    };
     match value_filter {
         ValueFilter::String { is_in } => {
             let values = is_in
@@ -1437,10 +1803,10 @@ fn event_attr_value_filter_clause(
             let mut parts = vec![];
             let ts = map_timestamp(sql_parts, col.clone());
             if let Some(from) = from {
-                parts.push(format!("{ts} >= '{from}'"));
+                parts.push(format!("{ts} >= {}", map_time_literal(sql_parts, from)));
             }
             if let Some(to) = to {
-                parts.push(format!("{ts} <= '{to}'"));
+                parts.push(format!("{ts} <= {}", map_time_literal(sql_parts, to)));
             }
             parts.join(" AND ")
         }
@@ -1458,9 +1824,8 @@ fn object_attr_value_filter_clause(
     iter_id: usize,
 ) -> String {
     let object_alias = format!("O{}", o_alias(object.0));
-    let attr = format!("\"{attribute_name}\"");
-    let attr = attr.as_str();
     let temp_alias = format!("OA{}", iter_id);
+    let attr = attribute_value(sql_parts, &temp_alias, attribute_name, value_filter);
     // Checks every snapshot via alias `OA{iter_id}` (latest snapshot before the event for `AtEvent`).
     let value_sql = match value_filter {
         ValueFilter::String { is_in } => {
@@ -1469,44 +1834,44 @@ fn object_attr_value_filter_clause(
                 .map(|v| format!("'{}'", v.replace('\'', "''")))
                 .collect::<Vec<_>>()
                 .join(", ");
-            format!("{}.{} IN ({})", temp_alias, attr, values)
+            format!("{attr} IN ({values})")
         }
         ValueFilter::Boolean { is_true } => {
-            format!("{}.{} = {}", temp_alias, attr, is_true)
+            format!("{attr} = {is_true}")
         }
         ValueFilter::Integer { min, max } => {
             let mut parts = vec![];
             if let Some(min) = min {
-                parts.push(format!("{}.{} >= {}", temp_alias, attr, min));
+                parts.push(format!("{attr} >= {min}"));
             }
             if let Some(max) = max {
-                parts.push(format!("{}.{} <= {}", temp_alias, attr, max));
+                parts.push(format!("{attr} <= {max}"));
             }
             parts.join(" AND ")
         }
         ValueFilter::Float { min, max } => {
             let mut parts = vec![];
             if let Some(min) = min {
-                parts.push(format!("{}.{} >= {}", temp_alias, attr, min));
+                parts.push(format!("{attr} >= {min}"));
             }
             if let Some(max) = max {
-                parts.push(format!("{}.{} <= {}", temp_alias, attr, max));
+                parts.push(format!("{attr} <= {max}"));
             }
             parts.join(" AND ")
         }
         ValueFilter::Time { from, to } => {
             let mut parts = vec![];
-            let ts = map_timestamp(sql_parts, format!("{}.{}", temp_alias, attr));
+            let ts = map_timestamp(sql_parts, attr.clone());
             if let Some(from) = from {
                 parts.push(format!(
                     "{ts} >= {time_right}",
-                    time_right = map_timestamp(sql_parts, format!("'{from}'")),
+                    time_right = map_time_literal(sql_parts, from),
                 ));
             }
             if let Some(to) = to {
                 parts.push(format!(
                     "{ts} <= {time_right}",
-                    time_right = map_timestamp(sql_parts, format!("'{to}'")),
+                    time_right = map_time_literal(sql_parts, to),
                 ));
             }
             parts.join(" AND ")
@@ -1551,40 +1916,44 @@ fn object_attr_value_filter_clause(
 pub fn map_objecttables(sql_parts: &SqlParts, object_type: &str) -> String {
     match sql_parts.database_type {
         // Case SQLLite
-        DatabaseType::SQLite => {
-            format!(
-                "\"object_{}\"",
-                sql_parts.table_mappings.object_table(object_type)
-            )
-        }
+        DatabaseType::SQLite => quote_ident(&format!(
+            "object_{}",
+            sql_parts.table_mappings.object_table(object_type)
+        )),
 
         //Case DuckDB
-        DatabaseType::DuckDB => {
-            format!(
-                "\"object_{}\"",
-                sql_parts.table_mappings.object_table(object_type)
-            )
-        }
+        DatabaseType::DuckDB => quote_ident(&format!(
+            "object_{}",
+            sql_parts.table_mappings.object_table(object_type)
+        )),
+
+        //Case PostgreSQL
+        DatabaseType::PostgreSQL => quote_ident(&format!(
+            "object_{}",
+            sql_parts.table_mappings.object_table(object_type)
+        )),
     }
 }
 
 pub fn map_eventttables(sql_parts: &SqlParts, event_type: &str) -> String {
     match sql_parts.database_type {
         // Case SQLLite
-        DatabaseType::SQLite => {
-            format!(
-                "\"event_{}\"",
-                sql_parts.table_mappings.event_table(event_type)
-            )
-        }
+        DatabaseType::SQLite => quote_ident(&format!(
+            "event_{}",
+            sql_parts.table_mappings.event_table(event_type)
+        )),
 
         //Case DuckDB
-        DatabaseType::DuckDB => {
-            format!(
-                "\"event_{}\"",
-                sql_parts.table_mappings.event_table(event_type)
-            )
-        }
+        DatabaseType::DuckDB => quote_ident(&format!(
+            "event_{}",
+            sql_parts.table_mappings.event_table(event_type)
+        )),
+
+        //Case PostgreSQL
+        DatabaseType::PostgreSQL => quote_ident(&format!(
+            "event_{}",
+            sql_parts.table_mappings.event_table(event_type)
+        )),
     }
 }
 
@@ -1597,6 +1966,10 @@ pub fn map_timestamp_event(sql_parts: &SqlParts, event_count: usize) -> String {
         DatabaseType::DuckDB => {
             format!("EPOCH(E{}.ocel_time)", e_alias(event_count))
         }
+
+        DatabaseType::PostgreSQL => {
+            format!("EXTRACT(EPOCH FROM E{}.ocel_time)", e_alias(event_count))
+        }
     }
 }
 
@@ -1607,292 +1980,889 @@ pub fn map_timestamp(sql_parts: &SqlParts, alias: String) -> String {
         DatabaseType::DuckDB => {
             format!("EPOCH({})", alias)
         }
+
+        // Seconds with their fraction, like DuckDB's `EPOCH`
+        DatabaseType::PostgreSQL => {
+            format!("EXTRACT(EPOCH FROM {})", alias)
+        }
+    }
+}
+
+/// The seconds of the time literal `time`, to compare with [`map_timestamp`]. PostgreSQL does not
+/// guess the type of an untyped literal there, so it is given one.
+pub fn map_time_literal(sql_parts: &SqlParts, time: &DateTime<Utc>) -> String {
+    match sql_parts.database_type {
+        DatabaseType::SQLite | DatabaseType::DuckDB => {
+            map_timestamp(sql_parts, format!("'{time}'"))
+        }
+
+        DatabaseType::PostgreSQL => {
+            map_timestamp(sql_parts, format!("TIMESTAMPTZ '{}'", time.to_rfc3339()))
+        }
+    }
+}
+
+/// `name` as a quoted identifier, which keeps its case in every dialect (PostgreSQL lowercases
+/// unquoted ones).
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The type an attribute is read as, which a JSON field is cast to (see [`attribute_value`])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttributeType {
+    String,
+    Boolean,
+    Number,
+    Time,
+}
+
+impl AttributeType {
+    fn of(value_filter: &ValueFilter) -> Self {
+        match value_filter {
+            ValueFilter::String { .. } => AttributeType::String,
+            ValueFilter::Boolean { .. } => AttributeType::Boolean,
+            ValueFilter::Integer { .. } | ValueFilter::Float { .. } => AttributeType::Number,
+            ValueFilter::Time { .. } => AttributeType::Time,
+        }
     }
 }
 
+/// The value of attribute `attribute_name` of the row `alias`, for `value_filter` to compare
+/// with: its column, or its field of the JSON column if `TableMappings::attribute_json_column`
+/// names one. JSON fields are read as text, so they are cast to the type the filter compares with.
+fn attribute_value(
+    sql_parts: &SqlParts,
+    alias: &str,
+    attribute_name: &str,
+    value_filter: &ValueFilter,
+) -> String {
+    attribute_value_as(
+        sql_parts,
+        alias,
+        attribute_name,
+        AttributeType::of(value_filter),
+    )
+}
+
+/// [`attribute_value`], read as `attribute_type`
+fn attribute_value_as(
+    sql_parts: &SqlParts,
+    alias: &str,
+    attribute_name: &str,
+    attribute_type: AttributeType,
+) -> String {
+    let Some(json_column) = &sql_parts.table_mappings.attribute_json_column else {
+        return format!("{alias}.{}", quote_ident(attribute_name));
+    };
+    let json = format!("{alias}.{}", quote_ident(json_column));
+    let field = format!("'{}'", attribute_name.replace('\'', "''"));
+    let number_type = match sql_parts.database_type {
+        // `json_extract` already gives SQLite values of the field's own type
+        DatabaseType::SQLite => {
+            return format!("json_extract({json}, '$.' || json_quote({field}))");
+        }
+        DatabaseType::DuckDB => "DOUBLE",
+        DatabaseType::PostgreSQL => "NUMERIC",
+    };
+    let cast = match attribute_type {
+        AttributeType::String => return format!("({json} ->> {field})"),
+        AttributeType::Boolean => "BOOLEAN",
+        AttributeType::Number => number_type,
+        AttributeType::Time => "TIMESTAMPTZ",
+    };
+    format!("CAST({json} ->> {field} AS {cast})")
+}
+
 // Cypher Translation
 
 pub struct CypherParts<'a> {
     node: InterMediateNode,
     match_clauses: Vec<String>,
-    child_queries: Vec<(String, String)>,
+    children: Vec<CypherChild>,
     where_clauses: Vec<String>,
     return_clauses: Vec<String>,
     used_alias: HashSet<String>,
     table_mappings: &'a TableMappings,
-    alias_type: HashMap<String, String>,
+    warnings: Vec<TranslationIssue>,
+    unsupported: Vec<TranslationIssue>,
 }
 
-/// Translates a `BindingBoxTree` to Cypher. `table_mappings` maps OCEL type names to graph
-/// labels; types absent from it keep their raw name.
-pub fn translate_to_cypher_shared(tree: BindingBoxTree, table_mappings: &TableMappings) -> String {
-    let inter = convert_to_intermediate(tree);
+impl<'a> CypherParts<'a> {
+    /// Note that the query leaves out `location` of this node, for `reason`.
+    fn leave_out(&mut self, location: TreeLocation, reason: String) {
+        push_issue(&mut self.unsupported, self.node.index, location, reason);
+    }
+
+    /// The node pattern of `variable`, with the labels of its types the first time it is bound
+    /// (later patterns refer to the node bound then).
+    fn bind(&mut self, variable: &Variable) -> String {
+        let name = cypher_var(variable);
+        if !self.used_alias.insert(name.clone()) {
+            return format!("({name})");
+        }
+        let labels = match variable {
+            Variable::Event(event) => type_labels(
+                self.node
+                    .event_vars
+                    .get(event)
+                    .into_iter()
+                    .flat_map(sorted_types)
+                    .map(|t| self.table_mappings.event_table(t)),
+                "Event",
+            ),
+            Variable::Object(object) => type_labels(
+                self.node
+                    .object_vars
+                    .get(object)
+                    .into_iter()
+                    .flat_map(sorted_types)
+                    .map(|t| self.table_mappings.object_table(t)),
+                "Object",
+            ),
+        };
+        format!("({name}:{labels})")
+    }
+}
+
+/// A translated child. Its bindings are the rows of its `MATCH` and `WHERE` clauses, which the
+/// parent runs in subqueries.
+pub struct CypherChild {
+    label: String,
+    index: usize,
+    match_clauses: Vec<String>,
+    where_clauses: Vec<String>,
+    /// Whether a binding satisfies the constraints of the child, `None` if it has none
+    satisfied: Option<String>,
+    /// The new variables of the child, which tell its bindings apart
+    keys: Vec<String>,
+}
+
+impl CypherChild {
+    /// The clauses matching the bindings of the child (that pass `condition`)
+    fn pattern(&self, condition: Option<&str>) -> String {
+        let mut clauses = self.match_clauses.clone();
+        if clauses.is_empty() {
+            clauses.push(format!("WITH *, 1 AS dummy_{}", self.index));
+        }
+        let conditions: Vec<&str> = self
+            .where_clauses
+            .iter()
+            .map(String::as_str)
+            .chain(condition)
+            .collect();
+        if !conditions.is_empty() {
+            clauses.push(format!("WHERE {}", conditions.join(" AND ")));
+        }
+        clauses.join("\n")
+    }
+
+    /// The number of bindings of the child (that pass `condition`)
+    fn count(&self, condition: Option<&str>) -> String {
+        let keys = if self.keys.is_empty() {
+            "1".to_string()
+        } else {
+            self.keys.join(", ")
+        };
+        format!(
+            "COUNT {{ {} RETURN DISTINCT {keys} }}",
+            self.pattern(condition)
+        )
+    }
+
+    /// The keys to start a `WITH DISTINCT` with, for one row per binding
+    fn distinct_keys(&self) -> String {
+        self.keys.iter().map(|key| format!("{key}, ")).collect()
+    }
+}
+
+/// Translates a `BindingBoxTree`, with its parameters bound to `parameters`, to Cypher over the
+/// property graph an OCEL is exported to (see [`graph_export`]). `table_mappings` maps OCEL type
+/// names to graph labels; types absent from it keep their raw name.
+pub fn translate_to_cypher_shared(
+    tree: BindingBoxTree,
+    parameters: &ParameterValues,
+    table_mappings: &TableMappings,
+) -> Result<TranslationResult, String> {
+    let values = parameter_values(&tree, parameters)?;
+    let inter = convert_to_intermediate(tree.bind_parameters(parameters)?);
 
     let mut cypher_parts = CypherParts {
+        warnings: inter.label_warnings(),
+        unsupported: vec![],
         node: inter,
         match_clauses: vec![],
-        child_queries: vec![],
+        children: vec![],
         where_clauses: vec![],
         return_clauses: vec![],
         used_alias: HashSet::new(),
         table_mappings,
-        alias_type: HashMap::new(),
     };
 
-    convert_to_cypher_from_inter(&mut cypher_parts)
+    let query = convert_to_cypher_from_inter(&mut cypher_parts);
+    Ok(TranslationResult::new(
+        query,
+        cypher_parts.warnings,
+        cypher_parts.unsupported,
+        values,
+    ))
 }
 
 // For root node in particular
 pub fn convert_to_cypher_from_inter(cypher_parts: &mut CypherParts) -> String {
     construct_match_clauses(cypher_parts);
 
-    if !cypher_parts.node.sizefilter.is_empty() || cypher_parts.node.filter.is_empty() {
-        construct_childstrings_cypher(cypher_parts);
+    construct_childstrings_cypher(cypher_parts);
 
-        construct_filter_clauses(cypher_parts);
-    }
+    construct_filter_clauses(cypher_parts);
 
     construct_return_clauses(cypher_parts);
 
     construct_result_cypher(cypher_parts)
 }
 
-// Start with E2O and O2O
-pub fn construct_match_clauses(cypher_parts: &mut CypherParts) {
-    for relation in &cypher_parts.node.relations {
-        match relation {
-            Relation::E2O {
-                event,
-                object,
-                qualifier: _,
-            } => {
-                let event_alias = format!("e{}", e_alias(event.0));
-                let object_alias = format!("o{}", o_alias(object.0));
-
-                let event_object_alias = "E2O".to_string();
-
-                let event_type = get_event_type(cypher_parts.node.clone(), event.0);
-                let object_type = get_object_type(cypher_parts.node.clone(), object.0);
-
-                // Falls back to the alias_type recorded by an earlier match clause when the type
-                // can't be derived locally (variable inherited from outer scope).
-                let mut mapped_event_type = cypher_parts
-                    .table_mappings
-                    .event_table(&event_type)
-                    .to_string();
-                if mapped_event_type == event_type && event_type == "no type found event" {
-                    mapped_event_type = cypher_parts
-                        .alias_type
-                        .get(&event_alias)
-                        .cloned()
-                        .unwrap_or_else(|| "unknown".to_string());
-                }
-
-                let mut mapped_object_type = cypher_parts
-                    .table_mappings
-                    .object_table(&object_type)
-                    .to_string();
-                if mapped_object_type == object_type && object_type == "no type found object" {
-                    mapped_object_type = cypher_parts
-                        .alias_type
-                        .get(&object_alias)
-                        .cloned()
-                        .unwrap_or_else(|| "unknown".to_string());
-                }
-
-                cypher_parts.used_alias.insert(event_alias.clone());
-                cypher_parts.used_alias.insert(object_alias.clone());
-
-                cypher_parts
-                    .alias_type
-                    .insert(event_alias.clone(), mapped_event_type.to_string());
-                cypher_parts
-                    .alias_type
-                    .insert(object_alias.clone(), mapped_object_type.to_string());
+/// A name (label or property key) as a quoted Cypher identifier
+fn cypher_name(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
 
-                cypher_parts.match_clauses.push(format!("({event_alias}:{mapped_event_type})-[:{event_object_alias}]->({object_alias}:{mapped_object_type})", 
-            ));
-            }
+/// A Cypher string literal
+fn cypher_string(value: &str) -> String {
+    format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+}
 
-            Relation::O2O {
-                object_1,
-                object_2,
-                qualifier: _,
-            } => {
-                let object1_alias = format!("o{}", o_alias(object_1.0));
-                let object2_alias = format!("o{}", o_alias(object_2.0));
-
-                let object_object_alias = "O2O".to_string();
-
-                let object1_type = get_object_type(cypher_parts.node.clone(), object_1.0);
-                let object2_type = get_object_type(cypher_parts.node.clone(), object_2.0);
-
-                let mut mapped_object1_type = cypher_parts
-                    .table_mappings
-                    .object_table(&object1_type)
-                    .to_string();
-                if mapped_object1_type == object1_type && object1_type == "no type found object" {
-                    mapped_object1_type = cypher_parts
-                        .alias_type
-                        .get(&object1_alias)
-                        .cloned()
-                        .unwrap_or_else(|| "unknown".to_string());
-                }
+fn event_var(event: &EventVariable) -> String {
+    format!("e{}", e_alias(event.0))
+}
 
-                let mut mapped_object2_type = cypher_parts
-                    .table_mappings
-                    .object_table(&object2_type)
-                    .to_string();
-                if mapped_object2_type == object2_type && object2_type == "no type found object" {
-                    mapped_object2_type = cypher_parts
-                        .alias_type
-                        .get(&object2_alias)
-                        .cloned()
-                        .unwrap_or_else(|| "unknown".to_string());
-                }
+fn object_var(object: &ObjectVariable) -> String {
+    format!("o{}", o_alias(object.0))
+}
 
-                cypher_parts.used_alias.insert(object1_alias.clone());
-                cypher_parts.used_alias.insert(object2_alias.clone());
+fn cypher_var(variable: &Variable) -> String {
+    match variable {
+        Variable::Event(event) => event_var(event),
+        Variable::Object(object) => object_var(object),
+    }
+}
 
-                cypher_parts
-                    .alias_type
-                    .insert(object1_alias.clone(), mapped_object1_type.clone());
-                cypher_parts
-                    .alias_type
-                    .insert(object2_alias.clone(), mapped_object2_type.clone());
+/// The label expression matching a node with any of `labels`, or any `kind` node without them
+fn type_labels<'a>(labels: impl IntoIterator<Item = &'a str>, kind: &str) -> String {
+    let labels: Vec<String> = labels.into_iter().map(cypher_name).collect();
+    if labels.is_empty() {
+        kind.to_string()
+    } else {
+        labels.join("|")
+    }
+}
 
-                cypher_parts.match_clauses.push(format!(
-                    "({object1_alias}:{mapped_object1_type})-[:{object_object_alias}]->({object2_alias}:{mapped_object2_type})"
-                ));
-            }
+/// The properties of an `E2O` or `O2O` relationship with `qualifier`
+fn qualifier_properties(qualifier: &Qualifier) -> String {
+    match qualifier {
+        Some(qualifier) => format!(" {{qualifier: {}}}", cypher_string(qualifier)),
+        None => String::new(),
+    }
+}
 
-            _ => {}
-        }
+/// `parts` joined by `operator`, `None` if there are none
+fn join_conditions(mut parts: Vec<String>, operator: &str) -> Option<String> {
+    match parts.len() {
+        0 => None,
+        1 => Some(parts.remove(0)),
+        _ => Some(format!("({})", parts.join(operator))),
     }
+}
 
-    // Check for Variables which are not included in a Relation
+/// The condition that `value` lies in the interval (min,max), `None` if it is unbounded
+fn bounded<T: std::fmt::Display>(value: &str, min: Option<T>, max: Option<T>) -> Option<String> {
+    match (min, max) {
+        (Some(min), Some(max)) => Some(format!("{min} <= {value} <= {max}")),
+        (Some(min), None) => Some(format!("{value} >= {min}")),
+        (None, Some(max)) => Some(format!("{value} <= {max}")),
+        (None, None) => None,
+    }
+}
 
-    for (obj_var, types) in sorted_object_vars(&cypher_parts.node.object_vars) {
-        for object_type in sorted_types(types) {
-            let key = format!("o{}", o_alias(obj_var.0));
-            if !cypher_parts.used_alias.contains(&key) {
-                let mapped = cypher_parts
-                    .table_mappings
-                    .object_table(object_type)
-                    .to_string();
-                cypher_parts
-                    .match_clauses
-                    .push(format!("({}:{})", key, mapped));
-                cypher_parts.used_alias.insert(key.clone());
-                cypher_parts.alias_type.insert(key, mapped);
-            }
-        }
+/// One `MATCH` clause per relationship, so that no pattern has to use a relationship twice, and
+/// one for each new variable no relationship binds
+pub fn construct_match_clauses(cypher_parts: &mut CypherParts) {
+    let relations = cypher_parts.node.relations.clone();
+    for relation in &relations {
+        let (from, relationship, qualifier, to) = match relation {
+            Relation::E2O {
+                event,
+                object,
+                qualifier,
+            } => (
+                Variable::Event(*event),
+                "E2O",
+                qualifier,
+                Variable::Object(*object),
+            ),
+            Relation::O2O {
+                object_1,
+                object_2,
+                qualifier,
+            } => (
+                Variable::Object(*object_1),
+                "O2O",
+                qualifier,
+                Variable::Object(*object_2),
+            ),
+            // See `construct_filter_clauses`
+            Relation::TimeBetweenEvents { .. } => continue,
+        };
+        let from = cypher_parts.bind(&from);
+        let to = cypher_parts.bind(&to);
+        cypher_parts.match_clauses.push(format!(
+            "MATCH {from}-[:{relationship}{}]->{to}",
+            qualifier_properties(qualifier)
+        ));
     }
 
-    for (event_var, types) in sorted_event_vars(&cypher_parts.node.event_vars) {
-        for event_type in sorted_types(types) {
-            let key = format!("e{}", e_alias(event_var.0));
-            if !cypher_parts.used_alias.contains(&key) {
-                let mapped = cypher_parts
-                    .table_mappings
-                    .event_table(event_type)
-                    .to_string();
-                cypher_parts
-                    .match_clauses
-                    .push(format!("({}:{})", key, mapped));
-                cypher_parts.used_alias.insert(key.clone());
-                cypher_parts.alias_type.insert(key, mapped);
-            }
+    // Check for Variables which are not included in a Relation
+    let variables: Vec<Variable> = sorted_object_vars(&cypher_parts.node.object_vars)
+        .into_iter()
+        .map(|(object, _)| Variable::Object(*object))
+        .chain(
+            sorted_event_vars(&cypher_parts.node.event_vars)
+                .into_iter()
+                .map(|(event, _)| Variable::Event(*event)),
+        )
+        .collect();
+    for variable in variables {
+        if !cypher_parts.used_alias.contains(&cypher_var(&variable)) {
+            let node = cypher_parts.bind(&variable);
+            cypher_parts.match_clauses.push(format!("MATCH {node}"));
         }
     }
 }
 
-// Construct return clauses
+/// The ids of the root variables, and whether the binding satisfies the root constraints
 pub fn construct_return_clauses(cypher_parts: &mut CypherParts) {
-    for obj_var in cypher_parts.node.object_vars.keys() {
+    for (object, _) in sorted_object_vars(&cypher_parts.node.object_vars) {
+        let alias = object_var(object);
         cypher_parts
             .return_clauses
-            .push(format!("o{}.id", o_alias(obj_var.0)));
+            .push(format!("{alias}.id AS {alias}"));
     }
 
-    for event_var in cypher_parts.node.event_vars.keys() {
+    for (event, _) in sorted_event_vars(&cypher_parts.node.event_vars) {
+        let alias = event_var(event);
         cypher_parts
             .return_clauses
-            .push(format!("e{}.id", e_alias(event_var.0)));
+            .push(format!("{alias}.id AS {alias}"));
     }
+
+    let satisfied =
+        construct_constraints_cypher(cypher_parts).unwrap_or_else(|| "true".to_string());
+    cypher_parts
+        .return_clauses
+        .push(format!("{satisfied} AS satisfied"));
 }
 
 pub fn construct_result_cypher(cypher_parts: &mut CypherParts) -> String {
     let mut result = String::new();
 
     //  MATCH
-    if !cypher_parts.match_clauses.is_empty() {
-        result.push_str("MATCH ");
-        result.push_str(&cypher_parts.match_clauses.join(", "));
+    for clause in &cypher_parts.match_clauses {
+        result.push_str(clause);
         result.push('\n');
     }
+
     //  WHERE
     if !cypher_parts.where_clauses.is_empty() {
         result.push_str(&format!(
             "WHERE {}\n",
-            cypher_parts.where_clauses.join(" AND ")
+            cypher_parts.where_clauses.join("\nAND ")
         ));
     }
 
     //  RETURN
-    result.push_str(&format!("RETURN {}", cypher_parts.return_clauses.join(",")));
+    result.push_str(&format!(
+        "RETURN DISTINCT {}",
+        cypher_parts.return_clauses.join(", ")
+    ));
     result
 }
 
+/// The filters and size filters of the node, as `WHERE` conditions. `O2E` and `O2O` filters are
+/// matched already (see `construct_match_clauses`).
 pub fn construct_filter_clauses(cypher_parts: &mut CypherParts) {
-    for sizefilter in cypher_parts.node.sizefilter.iter() {
-        if let SizeFilter::NumChilds {
-            child_name,
-            min,
-            max,
-        } = sizefilter
-        {
-            for (child_cypher, child_label) in cypher_parts.child_queries.iter() {
-                if child_label == child_name {
-                    let clause = match (min, max) {
-                        (Some(min), Some(max)) => format!("BETWEEN {min} AND {max}"),
-                        (Some(min), None) => format!(">= {min}"),
-                        (None, Some(max)) => format!("<= {max}"),
-                        (None, None) => continue,
-                    };
+    let filters = cypher_parts.node.located_filters.clone();
+    for (index, filter) in &filters {
+        if matches!(filter, Filter::O2E { .. } | Filter::O2O { .. }) {
+            continue;
+        }
+        match filter_condition(cypher_parts.table_mappings, filter) {
+            Ok(condition) => cypher_parts.where_clauses.push(condition),
+            Err(reason) => cypher_parts.leave_out(TreeLocation::Filter { index: *index }, reason),
+        }
+    }
 
-                    cypher_parts
-                        .where_clauses
-                        .push(format!("COUNT {{{child_cypher}}} {clause}"));
-                }
+    let size_filters = cypher_parts.node.sizefilter.clone();
+    for (index, size_filter) in size_filters.iter().enumerate() {
+        match size_filter_condition(&cypher_parts.children, size_filter) {
+            Ok(Some(condition)) => cypher_parts.where_clauses.push(condition),
+            Ok(None) => {}
+            Err(reason) => cypher_parts.leave_out(TreeLocation::SizeFilter { index }, reason),
+        }
+    }
+}
+
+/// Whether a binding of the node satisfies its constraints, `None` if it has none (or none of
+/// them is translated)
+fn construct_constraints_cypher(cypher_parts: &mut CypherParts) -> Option<String> {
+    let mut conditions = Vec::new();
+    let constraints = cypher_parts.node.constraints.clone();
+    for (index, constraint) in constraints.iter().enumerate() {
+        let condition = match constraint {
+            Constraint::Filter { filter } => {
+                filter_condition(cypher_parts.table_mappings, filter).map(Some)
+            }
+            Constraint::SizeFilter { filter } => {
+                size_filter_condition(&cypher_parts.children, filter)
             }
+            _ => Ok(child_constraint_condition(
+                &cypher_parts.children,
+                constraint,
+            )),
+        };
+        match condition {
+            Ok(condition) => conditions.extend(condition),
+            Err(reason) => cypher_parts.leave_out(TreeLocation::Constraint { index }, reason),
         }
     }
+    join_conditions(conditions, " AND ").map(|condition| format!("coalesce({condition}, false)"))
+}
 
-    for filter in &cypher_parts.node.relations {
-        if let Relation::TimeBetweenEvents {
+/// The condition of a `SAT`, `ANY`, `NOT`, `OR` or `AND` constraint over the translated
+/// `children`, `None` if it always holds
+fn child_constraint_condition(children: &[CypherChild], constraint: &Constraint) -> Option<String> {
+    let named = |names: &Vec<String>| {
+        children
+            .iter()
+            .filter(|child| names.contains(&child.label))
+            .collect::<Vec<_>>()
+    };
+    // Every binding of the child satisfies its constraints (`None`: always)
+    let all_satisfied = |child: &CypherChild| {
+        child.satisfied.as_ref().map(|satisfied| {
+            format!(
+                "NOT EXISTS {{ {} }}",
+                child.pattern(Some(&format!("NOT {satisfied}")))
+            )
+        })
+    };
+    // Some binding of the child satisfies its constraints
+    let some_satisfied =
+        |child: &CypherChild| format!("EXISTS {{ {} }}", child.pattern(child.satisfied.as_deref()));
+    match constraint {
+        Constraint::SAT { child_names } | Constraint::AND { child_names } => join_conditions(
+            named(child_names)
+                .into_iter()
+                .filter_map(all_satisfied)
+                .collect(),
+            " AND ",
+        ),
+        Constraint::OR { child_names } => {
+            let parts: Option<Vec<String>> =
+                named(child_names).into_iter().map(all_satisfied).collect();
+            join_conditions(parts?, " OR ")
+        }
+        Constraint::ANY { child_names } => join_conditions(
+            named(child_names).into_iter().map(some_satisfied).collect(),
+            " AND ",
+        ),
+        Constraint::NOT { child_names } => join_conditions(
+            named(child_names)
+                .into_iter()
+                .map(|child| format!("NOT {}", some_satisfied(child)))
+                .collect(),
+            " OR ",
+        ),
+        Constraint::Filter { .. } | Constraint::SizeFilter { .. } => None,
+    }
+}
+
+/// The Cypher condition of `filter`, or why there is none
+fn filter_condition(table_mappings: &TableMappings, filter: &Filter) -> Result<String, String> {
+    Ok(match filter {
+        Filter::O2E {
+            object,
+            event,
+            qualifier,
+            filter_label: _,
+        } => format!(
+            "EXISTS {{ ({})-[:E2O{}]->({}) }}",
+            event_var(event),
+            qualifier_properties(qualifier),
+            object_var(object)
+        ),
+        Filter::O2O {
+            object,
+            other_object,
+            qualifier,
+            filter_label: _,
+        } => format!(
+            "EXISTS {{ ({})-[:O2O{}]->({}) }}",
+            object_var(object),
+            qualifier_properties(qualifier),
+            object_var(other_object)
+        ),
+        Filter::TimeBetweenEvents {
+            calendar: Some(calendar),
+            ..
+        } => {
+            return Err(format!(
+                "The business calendar `{calendar}` is not available in the database"
+            ))
+        }
+        Filter::TimeBetweenEvents {
             from_event,
             to_event,
             min_seconds,
             max_seconds,
-        } = filter
-        {
-            let alias_eventto = format!("e{}", e_alias(to_event.0));
-            let alias_eventfrom = format!("e{}", e_alias(from_event.0));
-
+            calendar: None,
+        } => {
+            let (from, to) = (event_var(from_event), event_var(to_event));
+            let mut parts = Vec::new();
             if let Some(min) = min_seconds {
-                cypher_parts.where_clauses.push(format!(
-                    "{alias_eventto}.time >= {alias_eventfrom}.time + INTERVAL('{min} SECONDS')",
+                parts.push(format!(
+                    "{to}.time >= {from}.time + duration({{seconds: {min}}})"
                 ));
             }
-
             if let Some(max) = max_seconds {
-                cypher_parts.where_clauses.push(format!(
-                    "{alias_eventto}.time <= {alias_eventfrom}.time + INTERVAL('{max} SECONDS')"
+                parts.push(format!(
+                    "{to}.time <= {from}.time + duration({{seconds: {max}}})"
                 ));
             }
+            join_conditions(parts, " AND ").unwrap_or_else(|| "true".to_string())
+        }
+        Filter::DirectlyFollows {
+            from_event,
+            to_event,
+            object,
+        } => format!(
+            "EXISTS {{ ({})-[:DF {{object: {}.id}}]->({}) }}",
+            event_var(from_event),
+            object_var(object),
+            event_var(to_event)
+        ),
+        Filter::NoEventBetween {
+            from_event,
+            to_event,
+            event_types,
+            object,
+        } => {
+            let (from, to) = (event_var(from_event), event_var(to_event));
+            let labels = type_labels(
+                event_types.iter().map(|t| table_mappings.event_table(t)),
+                "Event",
+            );
+            let pattern = match object {
+                Some(object) => format!("(between:{labels})-[:E2O]->({})", object_var(object)),
+                None => format!("(between:{labels})"),
+            };
+            format!(
+                "NOT EXISTS {{ MATCH {pattern} WHERE {from}.time < between.time < {to}.time OR {to}.time < between.time < {from}.time }}"
+            )
+        }
+        Filter::EventInObjectWindow {
+            event,
+            object,
+            window,
+        } => {
+            let (event, object) = (event_var(event), object_var(object));
+            match window {
+                ObjectWindow::Lifespan => format!(
+                    "(EXISTS {{ MATCH (earlier:Event)-[:E2O]->({object}) WHERE earlier.time <= {event}.time }} AND EXISTS {{ MATCH (later:Event)-[:E2O]->({object}) WHERE later.time >= {event}.time }})"
+                ),
+                // In a window of the attribute exactly when its last change before the event is
+                ObjectWindow::WhileAttribute {
+                    attribute_name,
+                    value_filter,
+                } => object_value_at(
+                    &object,
+                    attribute_name,
+                    &format!("{event}.time"),
+                    value_filter,
+                ),
+            }
+        }
+        Filter::IntervalRelation {
+            first_start,
+            first_end,
+            second_start,
+            second_end,
+            relation,
+        } => {
+            let time = |event: &EventVariable| format!("{}.time", event_var(event));
+            allen_condition(
+                *relation,
+                (&time(first_start), &time(first_end)),
+                (&time(second_start), &time(second_end)),
+            )
+        }
+        Filter::NotEqual { var_1, var_2 } => {
+            format!("{} <> {}", cypher_var(var_1), cypher_var(var_2))
+        }
+        Filter::EventAttributeValueFilter {
+            event,
+            attribute_name,
+            value_filter,
+        } => {
+            let event = event_var(event);
+            let value = match attribute_name.as_str() {
+                "ocel:id" => format!("{event}.id"),
+                "ocel:time" => format!("{event}.time"),
+                name => format!("{event}.{}", cypher_name(name)),
+            };
+            value_condition(&value, value_filter)
+        }
+        Filter::ObjectAttributeValueFilter {
+            object,
+            attribute_name,
+            at_time,
+            value_filter,
+        } => {
+            let object = object_var(object);
+            if attribute_name == "ocel:id" {
+                return Ok(value_condition(&format!("{object}.id"), value_filter));
+            }
+            let values = format!(
+                "({object})-[:HAS_VALUE]->(attribute_value:ObjectAttributeValue {{name: {}}})",
+                cypher_string(attribute_name)
+            );
+            let condition = value_condition("attribute_value.value", value_filter);
+            match at_time {
+                ObjectValueFilterTimepoint::Sometime => {
+                    format!("EXISTS {{ MATCH {values} WHERE {condition} }}")
+                }
+                ObjectValueFilterTimepoint::Always => {
+                    format!(
+                        "NOT EXISTS {{ MATCH {values} WHERE NOT coalesce({condition}, false) }}"
+                    )
+                }
+                ObjectValueFilterTimepoint::AtEvent { event } => object_value_at(
+                    &object,
+                    attribute_name,
+                    &format!("{}.time", event_var(event)),
+                    value_filter,
+                ),
+            }
+        }
+        Filter::BasicFilterCEL { .. } => {
+            return Err("CEL filters are not translated to Cypher".to_string())
+        }
+    })
+}
+
+/// The condition that the last value the attribute `attribute_name` of `object` changed to at or
+/// before `time` passes `value_filter`
+fn object_value_at(
+    object: &str,
+    attribute_name: &str,
+    time: &str,
+    value_filter: &ValueFilter,
+) -> String {
+    let name = cypher_string(attribute_name);
+    format!(
+        "EXISTS {{ MATCH ({object})-[:HAS_VALUE]->(attribute_value:ObjectAttributeValue {{name: {name}}}) WHERE attribute_value.time <= {time} AND NOT EXISTS {{ MATCH ({object})-[:HAS_VALUE]->(later_value:ObjectAttributeValue {{name: {name}}}) WHERE attribute_value.time < later_value.time <= {time} }} AND {} }}",
+        value_condition("attribute_value.value", value_filter)
+    )
+}
+
+/// The condition that `value` passes `value_filter`
+fn value_condition(value: &str, value_filter: &ValueFilter) -> String {
+    let mut parts = Vec::new();
+    match value_filter {
+        ValueFilter::String { is_in } => parts.push(format!(
+            "{value} IN [{}]",
+            is_in
+                .iter()
+                .map(|v| cypher_string(v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        ValueFilter::Boolean { is_true } => parts.push(format!("{value} = {is_true}")),
+        ValueFilter::Integer { min, max } => parts.extend(bounded(value, *min, *max)),
+        ValueFilter::Float { min, max } => parts.extend(bounded(value, *min, *max)),
+        ValueFilter::Time { from, to } => {
+            let time = |t: &DateTime<Utc>| format!("datetime('{}')", t.to_rfc3339());
+            parts.extend(bounded(value, from.map(|t| time(&t)), to.map(|t| time(&t))));
         }
     }
+    join_conditions(parts, " AND ").unwrap_or_else(|| format!("{value} IS NOT NULL"))
+}
+
+/// The condition that the interval from the first two times stands in `relation` to the one from
+/// the last two (see [`AllenRelation::holds`])
+fn allen_condition(relation: AllenRelation, first: (&str, &str), second: (&str, &str)) -> String {
+    let ((s1, e1), (s2, e2)) = (first, second);
+    let relative = match relation {
+        AllenRelation::Before => format!("{e1} < {s2}"),
+        AllenRelation::Meets => format!("{e1} = {s2}"),
+        AllenRelation::Overlaps => format!("{s1} < {s2} < {e1} < {e2}"),
+        AllenRelation::Starts => format!("{s1} = {s2} AND {e1} < {e2}"),
+        AllenRelation::During => format!("{s2} < {s1} AND {e1} < {e2}"),
+        AllenRelation::Finishes => format!("{e1} = {e2} AND {s2} < {s1}"),
+        AllenRelation::Equals => format!("{s1} = {s2} AND {e1} = {e2}"),
+        inverse => return allen_condition(inverse.inverse(), second, first),
+    };
+    format!("({s1} <= {e1} AND {s2} <= {e2} AND {relative})")
+}
+
+/// The Cypher condition of `size_filter` over the translated `children`, `None` if it always
+/// holds, or why there is none
+fn size_filter_condition(
+    children: &[CypherChild],
+    size_filter: &SizeFilter,
+) -> Result<Option<String>, String> {
+    let child = |name: &str| children.iter().find(|child| child.label == name);
+    let kind = match size_filter {
+        SizeFilter::NumChilds {
+            child_name,
+            min,
+            max,
+        } => {
+            return Ok(match child(child_name) {
+                Some(child) => bounded(&child.count(None), *min, *max),
+                None => Some("false".to_string()),
+            })
+        }
+        SizeFilter::NumChildsProj {
+            child_name,
+            var_name,
+            min,
+            max,
+        } => {
+            return Ok(match child(child_name) {
+                Some(child) => bounded(
+                    &format!(
+                        "COUNT {{ {} RETURN DISTINCT {} }}",
+                        child.pattern(None),
+                        cypher_var(var_name)
+                    ),
+                    *min,
+                    *max,
+                ),
+                None => Some("false".to_string()),
+            })
+        }
+        SizeFilter::NumSatisfiedChilds {
+            child_name,
+            min,
+            max,
+            min_fraction,
+            max_fraction,
+        } => {
+            return Ok(match child(child_name) {
+                Some(child) => {
+                    num_satisfied_childs_condition(child, *min, *max, *min_fraction, *max_fraction)
+                }
+                None => Some("false".to_string()),
+            })
+        }
+        SizeFilter::Aggregate {
+            aggregate,
+            min,
+            max,
+        } => {
+            return match child(&aggregate.child_name) {
+                Some(child) => aggregate_condition(child, aggregate, *min, *max).map(Some),
+                None => Ok(Some("false".to_string())),
+            }
+        }
+        SizeFilter::AdvancedCEL { .. } => "CEL",
+        SizeFilter::BindingSetEqual { .. } => "BindingSetEqual",
+        SizeFilter::BindingSetProjectionEqual { .. } => "BindingSetProjectionEqual",
+    };
+    Err(format!("{kind} size filters are not translated to Cypher"))
+}
+
+/// Counts the satisfied and all bindings of `child` in one subquery. A child without bindings
+/// has a share of 1.
+fn num_satisfied_childs_condition(
+    child: &CypherChild,
+    min: Option<usize>,
+    max: Option<usize>,
+    min_fraction: Option<f64>,
+    max_fraction: Option<f64>,
+) -> Option<String> {
+    let conditions: Vec<String> = bounded("satisfied", min, max)
+        .into_iter()
+        .chain(bounded(
+            "CASE WHEN total = 0 THEN 1.0 ELSE 1.0 * satisfied / total END",
+            min_fraction,
+            max_fraction,
+        ))
+        .collect();
+    if conditions.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "EXISTS {{ {} WITH DISTINCT {}{} AS binding_satisfied WITH count(*) AS total, sum(CASE WHEN binding_satisfied THEN 1 ELSE 0 END) AS satisfied WHERE {} RETURN total }}",
+        child.pattern(None),
+        child.distinct_keys(),
+        child.satisfied.as_deref().unwrap_or("true"),
+        conditions.join(" AND ")
+    ))
+}
+
+/// Aggregates over the bindings of `child`, skipping null values (see [`ChildAggregate::compute`])
+fn aggregate_condition(
+    child: &CypherChild,
+    aggregate: &ChildAggregate,
+    min: Option<f64>,
+    max: Option<f64>,
+) -> Result<String, String> {
+    let value = match &aggregate.value {
+        None => None,
+        Some(AggregateValue::EventAttribute {
+            event,
+            attribute_name,
+        }) => Some(format!("{}.{}", event_var(event), cypher_name(attribute_name))),
+        Some(AggregateValue::ObjectAttribute {
+            object,
+            attribute_name,
+        }) => Some(format!(
+            "COLLECT {{ MATCH ({})-[:HAS_VALUE]->(attribute_value:ObjectAttributeValue {{name: {}}}) RETURN attribute_value.value ORDER BY attribute_value.time DESC LIMIT 1 }}[0]",
+            object_var(object),
+            cypher_string(attribute_name)
+        )),
+        Some(AggregateValue::Variable { variable }) => Some(format!("{}.id", cypher_var(variable))),
+        Some(AggregateValue::Label { label }) => {
+            return Err(format!(
+                "The label `{label}` is not available to aggregate in Cypher"
+            ))
+        }
+    };
+    let function = match (aggregate.function, value.is_some()) {
+        (AggregateFunction::Count, false) => "count(*)",
+        (_, false) => {
+            return Err(format!(
+                "Aggregate {:?} over child {} needs a value",
+                aggregate.function, aggregate.child_name
+            ))
+        }
+        (AggregateFunction::Count, true) => "count(aggregate_value)",
+        (AggregateFunction::DistinctCount, true) => "count(DISTINCT aggregate_value)",
+        (AggregateFunction::Sum, true) => "CASE WHEN count(toFloatOrNull(aggregate_value)) = 0 THEN null ELSE sum(toFloatOrNull(aggregate_value)) END",
+        (AggregateFunction::Avg, true) => "avg(toFloatOrNull(aggregate_value))",
+        (AggregateFunction::Min, true) => "min(aggregate_value)",
+        (AggregateFunction::Max, true) => "max(aggregate_value)",
+    };
+    let conditions: Vec<String> = std::iter::once("aggregate IS NOT NULL".to_string())
+        .chain(bounded("aggregate", min, max))
+        .collect();
+    Ok(format!(
+        "EXISTS {{ {} WITH DISTINCT {}{} AS aggregate_value WITH {function} AS aggregate WHERE {} RETURN aggregate }}",
+        child.pattern(None),
+        child.distinct_keys(),
+        value.as_deref().unwrap_or("1"),
+        conditions.join(" AND ")
+    ))
 }
 
 pub fn construct_childstrings_cypher(cypher_parts: &mut CypherParts) {
@@ -1900,63 +2870,57 @@ pub fn construct_childstrings_cypher(cypher_parts: &mut CypherParts) {
         let mut child_cypher_parts = CypherParts {
             node: inter_node.clone(),
             match_clauses: vec![],
-            child_queries: vec![],
+            children: vec![],
             return_clauses: vec![],
             where_clauses: vec![],
             table_mappings: cypher_parts.table_mappings,
             used_alias: cypher_parts.used_alias.clone(),
-            alias_type: cypher_parts.alias_type.clone(),
+            warnings: inter_node.label_warnings(),
+            unsupported: vec![],
         };
 
-        let child_cypher = translate_to_cypher_from_child(&mut child_cypher_parts);
+        let child = translate_to_cypher_from_child(&mut child_cypher_parts, node_label);
+        cypher_parts.children.push(child);
+        cypher_parts
+            .warnings
+            .append(&mut child_cypher_parts.warnings);
         cypher_parts
-            .child_queries
-            .push((child_cypher, node_label.clone()));
+            .unsupported
+            .append(&mut child_cypher_parts.unsupported);
     }
 }
 
-pub fn translate_to_cypher_from_child(cypher_parts: &mut CypherParts) -> String {
+pub fn translate_to_cypher_from_child(cypher_parts: &mut CypherParts, label: &str) -> CypherChild {
     construct_match_clauses(cypher_parts);
 
-    if !cypher_parts.node.sizefilter.is_empty() || cypher_parts.node.filter.is_empty() {
-        construct_childstrings_cypher(cypher_parts);
-        construct_filter_clauses(cypher_parts);
-    }
-
-    construct_result_child_cypher(cypher_parts)
-}
-
-pub fn construct_result_child_cypher(cypher_parts: &mut CypherParts) -> String {
-    let mut result = String::new();
-
-    //  MATCH
-    if !cypher_parts.match_clauses.is_empty() {
-        result.push_str("MATCH ");
-        result.push_str(&cypher_parts.match_clauses.join(", "));
-        result.push('\n');
-    }
+    construct_childstrings_cypher(cypher_parts);
+    construct_filter_clauses(cypher_parts);
 
-    //  WHERE
-    if !cypher_parts.where_clauses.is_empty() {
-        result.push_str(&format!(
-            "WHERE {}\n",
-            cypher_parts.where_clauses.join(" AND ")
-        ));
-    }
-
-    //  RETURN
-
-    if !cypher_parts.return_clauses.is_empty() {
-        result.push_str(&format!("RETURN {}", cypher_parts.return_clauses.join(",")));
+    let satisfied = construct_constraints_cypher(cypher_parts);
+    let keys = sorted_object_vars(&cypher_parts.node.object_vars)
+        .into_iter()
+        .map(|(object, _)| object_var(object))
+        .chain(
+            sorted_event_vars(&cypher_parts.node.event_vars)
+                .into_iter()
+                .map(|(event, _)| event_var(event)),
+        )
+        .collect();
+    CypherChild {
+        label: label.to_string(),
+        index: cypher_parts.node.index,
+        match_clauses: std::mem::take(&mut cypher_parts.match_clauses),
+        where_clauses: std::mem::take(&mut cypher_parts.where_clauses),
+        satisfied,
+        keys,
     }
-
-    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::binding_box::structs::{ObjectValueFilterTimepoint, Variable};
+    use crate::binding_box::parameters::{ParameterType, ParameterValue, TreeParameter};
+    use crate::binding_box::structs::{BindingBoxTreeNode, ObjectValueFilterTimepoint, Variable};
     use std::collections::BTreeMap;
 
     /// The seven BPIC2017 query trees from `backend/test/Q*/ocpq-tree.json`, inlined because
@@ -2003,7 +2967,11 @@ mod tests {
         ),
     ];
 
-    const DATABASES: [DatabaseType; 2] = [DatabaseType::SQLite, DatabaseType::DuckDB];
+    const DATABASES: [DatabaseType; 3] = [
+        DatabaseType::SQLite,
+        DatabaseType::DuckDB,
+        DatabaseType::PostgreSQL,
+    ];
 
     fn parse(tree_json: &str) -> BindingBoxTree {
         serde_json::from_str(tree_json).expect("fixture tree parses")
@@ -2014,7 +2982,10 @@ mod tests {
             tree: parse(tree_json),
             database,
             table_mappings: TableMappings::default(),
+            parameters: ParameterValues::new(),
         })
+        .expect("fixture tree translates")
+        .query
     }
 
     fn is_ident_char(c: u8) -> bool {
@@ -2294,6 +3265,106 @@ mod tests {
         );
     }
 
+    /// PostgreSQL reads the seconds of a timestamp with `EXTRACT`; the rest of the query is the
+    /// same as DuckDB's.
+    #[test]
+    fn postgres_and_duckdb_differ_only_in_the_timestamp_function() {
+        for (name, tree_json) in FIXTURES {
+            let duckdb = translate(tree_json, DatabaseType::DuckDB);
+            let postgres = translate(tree_json, DatabaseType::PostgreSQL);
+            assert_eq!(
+                duckdb.replace("EPOCH(", "EXTRACT(EPOCH FROM "),
+                postgres,
+                "{name} differs between the dialects beyond the timestamp function"
+            );
+        }
+    }
+
+    /// Time literals are typed for PostgreSQL, and with an attribute JSON column its fields are
+    /// cast to the type the filter compares with.
+    #[test]
+    fn postgres_types_time_literals_and_casts_json_attributes() {
+        let mut tree = parse(
+            r#"{"nodes":[{"Box":[{"newEventVars":{"0":["place"]},"newObjectVars":{"0":["order"]},"filters":[],"sizeFilters":[],"constraints":[],"evVarLabels":{},"obVarLabels":{},"labels":[]},[]]}],"edgeNames":[]}"#,
+        );
+        let time = "2024-01-02T00:00:00Z".parse().unwrap();
+        if let BindingBoxTreeNode::Box(bbox, _) = &mut tree.nodes[0] {
+            bbox.filters = vec![
+                Filter::EventAttributeValueFilter {
+                    event: EventVariable(0),
+                    attribute_name: "amount".to_string(),
+                    value_filter: ValueFilter::Integer {
+                        min: Some(100),
+                        max: None,
+                    },
+                },
+                Filter::EventAttributeValueFilter {
+                    event: EventVariable(0),
+                    attribute_name: "ocel:time".to_string(),
+                    value_filter: ValueFilter::Time {
+                        from: Some(time),
+                        to: None,
+                    },
+                },
+                Filter::ObjectAttributeValueFilter {
+                    object: ObjectVariable(0),
+                    attribute_name: "status".to_string(),
+                    at_time: ObjectValueFilterTimepoint::Sometime,
+                    value_filter: ValueFilter::String {
+                        is_in: vec!["open".to_string()],
+                    },
+                },
+            ];
+        }
+        let translate_with = |database, attribute_json_column: Option<&str>| {
+            translate_to_sql_shared(DBTranslationInput {
+                tree: tree.clone(),
+                database,
+                table_mappings: TableMappings {
+                    attribute_json_column: attribute_json_column.map(str::to_string),
+                    ..Default::default()
+                },
+                parameters: ParameterValues::new(),
+            })
+            .unwrap()
+            .query
+        };
+
+        let sql = translate_with(DatabaseType::PostgreSQL, None);
+        assert!(
+            sql.contains("EXTRACT(EPOCH FROM E1.\"ocel_time\") >= EXTRACT(EPOCH FROM TIMESTAMPTZ '2024-01-02T00:00:00+00:00')"),
+            "{sql}"
+        );
+        assert!(sql.contains("E1.\"amount\" >= 100"), "{sql}");
+        assert!(sql.contains("OA2.\"status\" IN ('open')"), "{sql}");
+
+        let sql = translate_with(DatabaseType::PostgreSQL, Some("attributes"));
+        assert!(
+            sql.contains("CAST(E1.\"attributes\" ->> 'amount' AS NUMERIC) >= 100"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("(OA2.\"attributes\" ->> 'status') IN ('open')"),
+            "{sql}"
+        );
+        // The id and time stay columns of their own.
+        assert!(
+            sql.contains("EXTRACT(EPOCH FROM E1.\"ocel_time\")"),
+            "{sql}"
+        );
+
+        let sql = translate_with(DatabaseType::SQLite, Some("attributes"));
+        assert!(
+            sql.contains("json_extract(E1.\"attributes\", '$.' || json_quote('amount')) >= 100"),
+            "{sql}"
+        );
+        // Both sides of a time comparison are seconds.
+        assert!(
+            sql.contains("strftime('%s', E1.\"ocel_time\") >= strftime('%s', "),
+            "{sql}"
+        );
+    }
+
     /// `TableMappings` renames types, not tables — the `object_`/`event_` prefix is added after.
     /// Junction entries are whole table names; mixing the two up queries a nonexistent table.
     #[test]
@@ -2310,8 +3381,12 @@ mod tests {
                     .collect(),
                 e2o_table: "my_e2o".to_string(),
                 o2o_table: "my_o2o".to_string(),
+                attribute_json_column: None,
             },
-        });
+            parameters: ParameterValues::new(),
+        })
+        .unwrap()
+        .query;
         assert!(sql.contains("\"object_app\""), "{sql}");
         assert!(sql.contains("\"event_acc\""), "{sql}");
         assert!(sql.contains("\"my_e2o\""), "{sql}");
@@ -2364,6 +3439,7 @@ mod tests {
                 to_event: EventVariable(1),
                 min_seconds: Some(1.0),
                 max_seconds: None,
+                calendar: None,
             },
         ]);
         assert_eq!(
@@ -2398,7 +3474,7 @@ mod tests {
     }
 
     /// The complement of the above: what `extract_basic_relations` leaves behind is what the WHERE
-    /// builder gets, and only the two attribute filters are translatable there.
+    /// builder gets, and only the attribute filters and CEL are translatable there.
     #[test]
     fn extract_filters_keeps_only_the_attribute_filters() {
         let (filters, size_filters) = extract_filters(
@@ -2435,7 +3511,7 @@ mod tests {
                 max: None,
             }],
         );
-        assert_eq!(filters.len(), 2);
+        assert_eq!(filters.len(), 3);
         assert!(matches!(
             filters[0],
             Filter::EventAttributeValueFilter { .. }
@@ -2444,9 +3520,64 @@ mod tests {
             filters[1],
             Filter::ObjectAttributeValueFilter { .. }
         ));
+        assert!(matches!(filters[2], Filter::BasicFilterCEL { .. }));
         assert_eq!(size_filters.len(), 1, "size filters pass through untouched");
     }
 
+    /// Simple CEL comparisons are lowered to native filters first; the rest is compiled to SQL.
+    #[test]
+    fn simple_cel_comparisons_are_translated_as_native_filters() {
+        let tree_json = r#"{"nodes":[{"Box":[{"newEventVars":{"0":["place"],"1":["ship"]},"newObjectVars":{},"filters":[{"type":"BasicFilterCEL","cel":"e1.attr('amount') >= 100 && e1.time() < e2.time() && e1.attr('note').startsWith('x')"},{"type":"BasicFilterCEL","cel":"e1.time() >= timestamp('2024-01-02T00:00:00Z')"}],"sizeFilters":[],"constraints":[],"evVarLabels":{},"obVarLabels":{},"labels":[]},[]]}],"edgeNames":[]}"#;
+        let inter = convert_to_intermediate(parse(tree_json));
+        assert!(matches!(
+            inter.relations.as_slice(),
+            [Relation::TimeBetweenEvents {
+                from_event: EventVariable(0),
+                to_event: EventVariable(1),
+                min_seconds: Some(_),
+                max_seconds: None,
+            }]
+        ));
+        assert_eq!(inter.filter.len(), 2, "the startsWith conjunct stays CEL");
+
+        let sql = translate(tree_json, DatabaseType::SQLite);
+        assert!(sql.contains("E1.\"amount\" >= 100"), "{sql}");
+        assert!(sql.contains("E1.\"ocel_time\""), "{sql}");
+        assert!(
+            sql.contains("(substr(E1.\"note\", 1, length('x')) = 'x')"),
+            "{sql}"
+        );
+    }
+
+    /// CEL reads parameters as variables, so the translation puts in the values they are bound to,
+    /// both where it lowers a comparison and where it compiles CEL.
+    #[test]
+    fn cel_parameters_are_translated_as_their_values() {
+        let tree_json = r#"{"nodes":[{"Box":[{"newEventVars":{"0":["place"]},"newObjectVars":{},"filters":[{"type":"BasicFilterCEL","cel":"e1.attr('amount') >= min_amount && e1.attr('amount') * 2 < min_amount - offset"}],"sizeFilters":[],"constraints":[],"evVarLabels":{},"obVarLabels":{},"labels":[]},[]]}],"edgeNames":[]}"#;
+        let mut tree = parse(tree_json);
+        for (name, default) in [("min_amount", 100), ("offset", -5)] {
+            tree.parameters.push(TreeParameter {
+                name: name.to_string(),
+                parameter_type: ParameterType::Int,
+                default: Some(ParameterValue::Int(default)),
+                uses: vec![],
+            });
+        }
+        let res = translate_to_sql_shared(DBTranslationInput {
+            tree,
+            database: DatabaseType::SQLite,
+            table_mappings: TableMappings::default(),
+            parameters: ParameterValues::from([(
+                "min_amount".to_string(),
+                ParameterValue::Int(120),
+            )]),
+        })
+        .unwrap();
+        assert!(res.is_complete(), "{:?}", res.unsupported);
+        assert!(res.query.contains("E1.\"amount\" >= 120"), "{}", res.query);
+        assert!(res.query.contains("120 - (-5)"), "{}", res.query);
+    }
+
     /// A box with no relations still has to name its tables; the child inherits the parent's used
     /// aliases, so its own object table is a CROSS JOIN, not a second binding of `O1`.
     #[test]
@@ -2459,4 +3590,348 @@ mod tests {
             "the root has no object variable:\n{sql}"
         );
     }
+
+    /// Both counts come from a single scan of the child's distinct bindings, so the child query
+    /// is embedded once, and an empty child passes any fraction bound (its share is 1).
+    #[test]
+    fn num_satisfied_childs_counts_satisfied_and_total_in_one_subquery() {
+        let tree_json = FIXTURES[0].1.replace(
+            r#"{"type":"NumChilds","child_name":"A","min":1,"max":1}"#,
+            r#"{"type":"NumSatisfiedChilds","child_name":"A","min":2,"max":null,"min_fraction":0.5,"max_fraction":null}"#,
+        );
+        let sql = translate(&tree_json, DatabaseType::SQLite);
+        assert_eq!(sql.matches("\"event_A_Submitted\"").count(), 1, "{sql}");
+        assert!(
+            sql.contains("COALESCE(SUM(child_0_0_A_d.satisfied), 0) >= 2"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("CASE WHEN COUNT(*) = 0 THEN 1.0 ELSE 1.0 * SUM(child_0_0_A_d.satisfied) / COUNT(*) END >= 0.5"),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("SELECT DISTINCT key_e1, satisfied FROM"),
+            "{sql}"
+        );
+    }
+
+    fn translation(tree_json: &str, database: DatabaseType) -> TranslationResult {
+        translate_to_sql_shared(DBTranslationInput {
+            tree: parse(tree_json),
+            database,
+            table_mappings: TableMappings::default(),
+            parameters: ParameterValues::new(),
+        })
+        .expect("tree translates")
+    }
+
+    fn issues(issues: &[TranslationIssue]) -> Vec<(usize, TreeLocation, &str)> {
+        issues
+            .iter()
+            .map(|i| (i.node_index, i.location, i.message.as_str()))
+            .collect()
+    }
+
+    /// A CEL filter that is no simple comparison is compiled to a WHERE clause, reading attributes
+    /// as the type they are computed with.
+    #[test]
+    fn cel_filters_are_compiled_to_where_clauses() {
+        let tree_json = r#"{"nodes":[{"Box":[{"newEventVars":{"0":["place"]},"newObjectVars":{"0":["order"]},"filters":[{"type":"O2E","object":0,"event":0,"qualifier":null,"filterLabel":null},{"type":"BasicFilterCEL","cel":"e1.attr('price') * 2 > o1.attr('limit') || o1.attrAt('status', e1.time()) in ['open', 'late']"}],"sizeFilters":[],"constraints":[],"evVarLabels":{},"obVarLabels":{},"labels":[]},[]]}],"edgeNames":[]}"#;
+        let sql = translate(tree_json, DatabaseType::SQLite);
+        assert!(sql.contains("((E1.\"price\" * 2) > O1.\"limit\")"), "{sql}");
+        assert!(sql.contains("AS CA1 WHERE CA1.ocel_id = O1.ocel_id AND (CA1.ocel_changed_field IS NULL OR CA1.ocel_changed_field = 'status') AND CAST(strftime('%s', CA1.ocel_time) AS INTEGER) <= CAST(strftime('%s', E1.ocel_time) AS INTEGER) ORDER BY CA1.ocel_time DESC LIMIT 1) IN ('open', 'late'))"), "{sql}");
+
+        let sql = translate_to_sql_shared(DBTranslationInput {
+            tree: parse(tree_json),
+            database: DatabaseType::PostgreSQL,
+            table_mappings: TableMappings {
+                attribute_json_column: Some("attrs".to_string()),
+                ..Default::default()
+            },
+            parameters: ParameterValues::new(),
+        })
+        .unwrap()
+        .query;
+        assert!(
+            sql.contains("(CAST(E1.\"attrs\" ->> 'price' AS NUMERIC) * 2)"),
+            "{sql}"
+        );
+    }
+
+    /// Size filters and constraints see the child results and the labels, which are compiled where
+    /// they are used.
+    #[test]
+    fn cel_over_child_results_and_labels_is_compiled() {
+        let tree_json = FIXTURES[1]
+            .1
+            .replace(
+                r#""sizeFilters":[],"constraints":[{"type":"SizeFilter","filter":{"type":"NumChilds","child_name":"A","min":1,"max":null}}],"evVarLabels":{},"obVarLabels":{},"labels":[]"#,
+                r#""sizeFilters":[{"type":"AdvancedCEL","cel":"size(A) >= 1"}],"constraints":[{"type":"Filter","filter":{"type":"BasicFilterCEL","cel":"slow < 2"}},{"type":"SizeFilter","filter":{"type":"AdvancedCEL","cel":"A.all(c, c.satisfied && c.e2.time() - e1.time() <= duration('24h'))"}}],"evVarLabels":{},"obVarLabels":{},"labels":[{"label":"slow","cel":"A.filter(c, c.e2.time() - e1.time() > duration('1h')).size()"}]"#,
+            );
+        let sql = translate(&tree_json, DatabaseType::DuckDB);
+        assert!(
+            sql.contains("(SELECT COUNT(*) FROM (SELECT DISTINCT key_e2, satisfied FROM ("),
+            "{sql}"
+        );
+        assert!(sql.contains(") AS CC1_r) AS CC1) >= 1)"), "{sql}");
+        // The label, inlined in the filter constraint
+        assert!(
+            sql.contains("WHERE (((SELECT EPOCH(CV1.ocel_time) FROM \"event_O_Returned\" AS CV1 WHERE CV1.ocel_id = CC2.key_e2) - EPOCH(E1.ocel_time)) > 3600.0)) < 2)"),
+            "{sql}"
+        );
+        assert!(sql.contains("(NOT EXISTS (SELECT 1 FROM"), "{sql}");
+        assert!(sql.contains("WHERE NOT ((CC3.satisfied = 1) AND"), "{sql}");
+    }
+
+    /// CEL that cannot be compiled is left out of the query and reported where it is.
+    #[test]
+    fn cel_that_cannot_be_translated_is_reported() {
+        let with_filter = |cel: &str| {
+            FIXTURES[6].1.replace(
+                r#""filters":[{"type":"O2O""#,
+                &format!(r#""filters":[{{"type":"BasicFilterCEL","cel":"{cel}"}},{{"type":"O2O""#),
+            )
+        };
+        for (cel, reason) in [
+            ("e2.time() < now", "`now`"),
+            ("e2.attr('a').matches('x')", "`matches()`"),
+            ("size(A) > 0", "`A` is not available here"),
+            ("e9.id() == 'x'", "`e9` is not bound here"),
+            (
+                "e2.time() > 5",
+                "a timestamp cannot be compared with a number",
+            ),
+        ] {
+            for database in DATABASES {
+                let res = translation(&with_filter(cel), database);
+                let [issue] = res.unsupported.as_slice() else {
+                    panic!("{cel}: {:?}", res.unsupported);
+                };
+                assert_eq!(
+                    (issue.node_index, issue.location),
+                    (0, TreeLocation::Filter { index: 0 })
+                );
+                assert!(
+                    issue.message.contains(cel) && issue.message.contains(reason),
+                    "{cel}: {}",
+                    issue.message
+                );
+                assert_eq!(res.query, translate(FIXTURES[6].1, database), "{cel}");
+            }
+        }
+        // Labels are only compiled where they are used, so the fixture's string label is fine
+        // until a constraint compares it.
+        let used = FIXTURES[5].1.replacen(
+            r#""constraints":[]"#,
+            r#""constraints":[{"type":"Filter","filter":{"type":"BasicFilterCEL","cel":"max_dur == '1h'"}}]"#,
+            1,
+        );
+        let res = translation(&used, DatabaseType::SQLite);
+        let [issue] = res.unsupported.as_slice() else {
+            panic!("{:?}", res.unsupported);
+        };
+        assert_eq!(issue.location, TreeLocation::Constraint { index: 0 });
+        assert!(
+            issue.message.contains("in the label `max_dur`"),
+            "{}",
+            issue.message
+        );
+        assert_eq!(
+            issues(&res.warnings),
+            vec![(
+                0,
+                TreeLocation::Label { index: 0 },
+                "The label `max_dur` is not returned by the query"
+            )]
+        );
+    }
+
+    /// Filters and size filters the translations leave out are reported where they are in the
+    /// tree, with the parameter values, and the rest is still translated.
+    #[test]
+    fn untranslated_parts_of_the_tree_are_reported() {
+        let tree_json = FIXTURES[1]
+            .1
+            .replace(
+                r#""sizeFilters":[],"constraints":[{"type":"SizeFilter""#,
+                r#""sizeFilters":[{"type":"BindingSetEqual","child_names":["A"]}],"constraints":[{"type":"SizeFilter""#,
+            )
+            .replace(
+                r#"{"type":"O2E","object":0,"event":1,"qualifier":null,"filterLabel":null}"#,
+                r#"{"type":"O2E","object":0,"event":1,"qualifier":"creates","filterLabel":null},{"type":"DirectlyFollows","from_event":0,"to_event":1,"object":0}"#,
+            );
+        let mut tree = parse(&tree_json);
+        tree.parameters.push(TreeParameter {
+            name: "min_offers".to_string(),
+            parameter_type: ParameterType::Int,
+            default: Some(ParameterValue::Int(1)),
+            uses: vec![],
+        });
+        let child = [
+            (
+                1,
+                TreeLocation::Filter { index: 0 },
+                "The qualifier `creates` is not translated, so any qualifier matches",
+            ),
+            (
+                1,
+                TreeLocation::Filter { index: 1 },
+                "DirectlyFollows filters are not translated",
+            ),
+        ];
+
+        let res = translate_to_sql_shared(DBTranslationInput {
+            tree: tree.clone(),
+            database: DatabaseType::SQLite,
+            table_mappings: TableMappings::default(),
+            parameters: ParameterValues::new(),
+        })
+        .unwrap();
+        assert!(!res.is_complete());
+        let mut expected = vec![(
+            0,
+            TreeLocation::SizeFilter { index: 0 },
+            "BindingSetEqual size filters are not translated",
+        )];
+        expected.extend(child);
+        assert_eq!(issues(&res.unsupported), expected);
+        assert!(res.warnings.is_empty());
+        assert_eq!(
+            res.parameters,
+            ParameterValues::from([("min_offers".to_string(), ParameterValue::Int(1))])
+        );
+        assert!(res.query.contains("satisfied"), "{}", res.query);
+
+        let res =
+            translate_to_cypher_shared(tree, &ParameterValues::new(), &TableMappings::default())
+                .unwrap();
+        // Cypher matches the qualifier and the directly-follows relationship of the child.
+        assert_eq!(
+            issues(&res.unsupported),
+            vec![(
+                0,
+                TreeLocation::SizeFilter { index: 0 },
+                "BindingSetEqual size filters are not translated to Cypher",
+            )]
+        );
+        assert!(res.query.starts_with("MATCH "), "{}", res.query);
+
+        // The complete fixtures leave nothing out.
+        for (name, tree_json) in FIXTURES {
+            let res = translation(tree_json, DatabaseType::SQLite);
+            assert!(res.is_complete(), "{name}: {:?}", res.unsupported);
+        }
+    }
+
+    fn cypher(tree_json: &str) -> TranslationResult {
+        translate_to_cypher_shared(
+            parse(tree_json),
+            &ParameterValues::new(),
+            &TableMappings::default(),
+        )
+        .expect("tree translates to Cypher")
+    }
+
+    /// Each relationship gets a `MATCH` of its own, labelling a node only where it is first bound,
+    /// and a SAT constraint asks that no binding of the child violates the child's constraints.
+    #[test]
+    fn cypher_checks_child_constraints_in_subqueries() {
+        let res = cypher(FIXTURES[4].1);
+        assert!(res.is_complete(), "{:?}", res.unsupported);
+        assert_eq!(
+            res.query,
+            "MATCH (e1:`A_Accepted`)-[:E2O]->(o1:`Application`)\n\
+             MATCH (e1)-[:E2O]->(o2:`Case_R`)\n\
+             RETURN DISTINCT o1.id AS o1, o2.id AS o2, e1.id AS e1, \
+             coalesce(NOT EXISTS { MATCH (o1)-[:O2O]->(o3:`Offer`)\n\
+             MATCH (e2:`O_Created`)-[:E2O]->(o3)\n\
+             WHERE NOT coalesce(EXISTS { (e2)-[:E2O]->(o2) }, false) }, false) AS satisfied"
+        );
+
+        // NOT holds if no binding of the child satisfies its constraints.
+        let res = cypher(&FIXTURES[4].1.replace(r#""type":"SAT""#, r#""type":"NOT""#));
+        assert!(
+            res.query.contains("coalesce(NOT EXISTS { MATCH (o1)-[:O2O]->(o3:`Offer`)\nMATCH (e2:`O_Created`)-[:E2O]->(o3)\nWHERE coalesce(EXISTS { (e2)-[:E2O]->(o2) }, false) }, false) AS satisfied"),
+            "{}",
+            res.query
+        );
+
+        // OR over a child without constraints always holds.
+        let res = cypher(&FIXTURES[0].1.replace(
+            r#"{"type":"SizeFilter","filter":{"type":"NumChilds","child_name":"A","min":1,"max":1}}"#,
+            r#"{"type":"OR","child_names":["A"]}"#,
+        ));
+        assert!(res.query.ends_with(", true AS satisfied"), "{}", res.query);
+    }
+
+    /// Qualifiers become relationship properties, attribute filters read the node properties and
+    /// the attribute value nodes, and the time filters compare the `time` properties.
+    #[test]
+    fn cypher_translates_attribute_and_time_filters() {
+        let tree_json = r#"{"nodes":[{"Box":[{"newEventVars":{"0":["place order"],"1":["ship"]},"newObjectVars":{"0":["order"]},"filters":[{"type":"O2E","object":0,"event":0,"qualifier":"creates","filterLabel":null},{"type":"O2E","object":0,"event":1,"qualifier":null,"filterLabel":null},{"type":"DirectlyFollows","from_event":0,"to_event":1,"object":0},{"type":"EventAttributeValueFilter","event":0,"attribute_name":"amount","value_filter":{"type":"Integer","min":2,"max":null}},{"type":"ObjectAttributeValueFilter","object":0,"attribute_name":"status","at_time":{"type":"AtEvent","event":1},"value_filter":{"type":"String","is_in":["open"]}},{"type":"TimeBetweenEvents","from_event":0,"to_event":1,"min_seconds":null,"max_seconds":86400.0},{"type":"BasicFilterCEL","cel":"e1.attr('amount') * 2 > 3"}],"sizeFilters":[],"constraints":[],"evVarLabels":{},"obVarLabels":{},"labels":[]},[]]}],"edgeNames":[]}"#;
+        let res = cypher(tree_json);
+        let query = &res.query;
+        assert!(
+            query.starts_with("MATCH (e1:`place order`)-[:E2O {qualifier: 'creates'}]->(o1:`order`)\nMATCH (e2:`ship`)-[:E2O]->(o1)\nWHERE EXISTS { (e1)-[:DF {object: o1.id}]->(e2) }\n"),
+            "{query}"
+        );
+        assert!(query.contains("\nAND e1.`amount` >= 2\n"), "{query}");
+        assert!(query.contains("\nAND EXISTS { MATCH (o1)-[:HAS_VALUE]->(attribute_value:ObjectAttributeValue {name: 'status'}) WHERE attribute_value.time <= e2.time AND NOT EXISTS { MATCH (o1)-[:HAS_VALUE]->(later_value:ObjectAttributeValue {name: 'status'}) WHERE attribute_value.time < later_value.time <= e2.time } AND attribute_value.value IN ['open'] }\n"), "{query}");
+        assert!(
+            query.contains("\nAND e2.time <= e1.time + duration({seconds: 86400})\n"),
+            "{query}"
+        );
+        assert!(
+            query.ends_with(
+                "RETURN DISTINCT o1.id AS o1, e1.id AS e1, e2.id AS e2, true AS satisfied"
+            ),
+            "{query}"
+        );
+        assert_eq!(
+            issues(&res.unsupported),
+            vec![(
+                0,
+                TreeLocation::Filter { index: 6 },
+                "CEL filters are not translated to Cypher"
+            )]
+        );
+    }
+
+    /// Size filters count the distinct bindings of the child, or aggregate over them.
+    #[test]
+    fn cypher_size_filters_count_and_aggregate_child_bindings() {
+        let with_size_filter = |size_filter: &str| {
+            FIXTURES[0].1.replace(
+                r#"{"type":"NumChilds","child_name":"A","min":1,"max":1}"#,
+                size_filter,
+            )
+        };
+        let res = cypher(FIXTURES[0].1);
+        assert!(res.query.contains("coalesce(1 <= COUNT { MATCH (e1:`A_Submitted`)-[:E2O]->(o1) RETURN DISTINCT e1 } <= 1, false) AS satisfied"), "{}", res.query);
+
+        let res = cypher(&with_size_filter(
+            r#"{"type":"NumSatisfiedChilds","child_name":"A","min":2,"max":null,"min_fraction":0.5,"max_fraction":null}"#,
+        ));
+        assert!(res.query.contains("EXISTS { MATCH (e1:`A_Submitted`)-[:E2O]->(o1) WITH DISTINCT e1, true AS binding_satisfied WITH count(*) AS total, sum(CASE WHEN binding_satisfied THEN 1 ELSE 0 END) AS satisfied WHERE satisfied >= 2 AND CASE WHEN total = 0 THEN 1.0 ELSE 1.0 * satisfied / total END >= 0.5 RETURN total }"), "{}", res.query);
+
+        let res = cypher(&with_size_filter(
+            r#"{"type":"Aggregate","aggregate":{"childName":"A","function":"Avg","value":{"type":"EventAttribute","event":0,"attribute_name":"amount"}},"min":10.0,"max":null}"#,
+        ));
+        assert!(res.is_complete(), "{:?}", res.unsupported);
+        assert!(res.query.contains("WITH DISTINCT e1, e1.`amount` AS aggregate_value WITH avg(toFloatOrNull(aggregate_value)) AS aggregate WHERE aggregate IS NOT NULL AND aggregate >= 10 RETURN aggregate }"), "{}", res.query);
+    }
+
+    /// Inverse relations are checked with the intervals swapped, and only hold between intervals
+    /// that end after they start, like [`AllenRelation::holds`].
+    #[test]
+    fn allen_relations_compare_the_interval_bounds() {
+        assert_eq!(
+            allen_condition(AllenRelation::Overlaps, ("a", "b"), ("c", "d")),
+            "(a <= b AND c <= d AND a < c < b < d)"
+        );
+        assert_eq!(
+            allen_condition(AllenRelation::After, ("a", "b"), ("c", "d")),
+            "(c <= d AND a <= b AND d < a)"
+        );
+    }
 }
@@ -395,20 +395,18 @@ impl CountConstraint {
         let inner_child_name = "A".to_string();
         let inner_variable = 0;
         let mut subtree = self.to_subtree(inner_child_name.clone(), inner_variable, 1);
-        match &mut subtree.nodes[0] {
-            BindingBoxTreeNode::Box(bbox, _) => {
-                match &self.root_type {
-                    EventOrObjectType::Event(et) => bbox.new_event_vars.insert(
-                        EventVariable(inner_variable),
-                        vec![et.clone()].into_iter().collect(),
-                    ),
-                    EventOrObjectType::Object(ot) => bbox.new_object_vars.insert(
-                        ObjectVariable(inner_variable),
-                        vec![ot.clone()].into_iter().collect(),
-                    ),
-                };
-            }
-            _ => todo!("Expected a BindingBox"),
+        // `to_subtree` always starts with a box.
+        if let BindingBoxTreeNode::Box(bbox, _) = &mut subtree.nodes[0] {
+            match &self.root_type {
+                EventOrObjectType::Event(et) => bbox.new_event_vars.insert(
+                    EventVariable(inner_variable),
+                    vec![et.clone()].into_iter().collect(),
+                ),
+                EventOrObjectType::Object(ot) => bbox.new_object_vars.insert(
+                    ObjectVariable(inner_variable),
+                    vec![ot.clone()].into_iter().collect(),
+                ),
+            };
         }
         subtree
     }
@@ -498,6 +496,8 @@ impl CountConstraint {
         BindingBoxTree {
             nodes: vec![bbox0, bbox1],
             edge_names: vec![((0, 1), child_name)].into_iter().collect(),
+            cel_libraries: Vec::new(),
+            parameters: Vec::new(),
         }
     }
 }
@@ -661,14 +661,12 @@ impl EFConstraint {
         let inner_child_name = "A".to_string();
         let inner_variable = 0;
         let mut subtree = self.to_subtree(inner_child_name.clone(), inner_variable, 1, 2);
-        match &mut subtree.nodes[0] {
-            BindingBoxTreeNode::Box(bbox, _) => {
-                bbox.new_object_vars.insert(
-                    ObjectVariable(inner_variable),
-                    vec![self.for_object_type.clone()].into_iter().collect(),
-                );
-            }
-            _ => todo!("Expected a BindingBox"),
+        // `to_subtree` always starts with a box.
+        if let BindingBoxTreeNode::Box(bbox, _) = &mut subtree.nodes[0] {
+            bbox.new_object_vars.insert(
+                ObjectVariable(inner_variable),
+                vec![self.for_object_type.clone()].into_iter().collect(),
+            );
         }
         subtree
     }
@@ -728,6 +726,7 @@ impl EFConstraint {
                         to_event: EventVariable(new_to_ev_var),
                         min_seconds: self.min_duration_sec,
                         max_seconds: self.max_duration_sec,
+                        calendar: None,
                     },
                 ],
                 size_filters: vec![],
@@ -739,6 +738,8 @@ impl EFConstraint {
         BindingBoxTree {
             nodes: vec![bbox0, bbox1],
             edge_names: vec![((0, 1), child_name)].into_iter().collect(),
+            cel_libraries: Vec::new(),
+            parameters: Vec::new(),
         }
     }
 }
@@ -1007,6 +1008,8 @@ pub fn merge_or_tree(
     let mut or_tree = BindingBoxTree {
         nodes: vec![or_box],
         edge_names: HashMap::default(),
+        cel_libraries: Vec::new(),
+        parameters: Vec::new(),
     };
     for tn in &tree1.nodes {
         if let BindingBoxTreeNode::Box(tn_box, tn_children) = tn {
@@ -23,6 +23,7 @@ use serde::{Deserialize, Serialize};
 
 pub mod binding_box;
 pub mod timing;
+pub mod calendar;
 pub mod db_translation;
 pub mod discovery;
 pub mod ocel_graph;
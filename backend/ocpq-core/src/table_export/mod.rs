@@ -13,7 +13,12 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
-use crate::binding_box::EvaluationResultWithCount;
+use crate::binding_box::{
+    control::EvaluationControl,
+    explain::{ViolationExplainer, ViolationExplanation},
+    Binding, BindingBoxTree, EvaluationResultWithCount, EventVariable, ObjectVariable,
+    ViolationReason,
+};
 
 pub enum CellContent<'a> {
     String(Cow<'a, str>),
@@ -107,7 +112,6 @@ pub trait TableWriter<'a, W: std::io::Write> {
     fn save(self) -> Result<(), Error>;
 }
 
-
 #[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[derive(TS)]
@@ -118,6 +122,10 @@ pub struct TableExportOptions {
     pub omit_header: bool,
     pub labels: Vec<String>,
     pub format: TableExportFormat,
+    /// Add a column explaining each violated situation. Situations of a stored evaluation only
+    /// have one if it was run with explanations; streamed ones are explained as they are written.
+    #[serde(default)]
+    pub include_explanation: bool,
 }
 #[derive(Debug, Serialize, Deserialize, JsonSchema, TS)]
 #[ts(export)]
@@ -134,19 +142,33 @@ impl Default for TableExportOptions {
             omit_header: false,
             labels: Vec::default(),
             format: TableExportFormat::CSV,
+            include_explanation: false,
         }
     }
 }
 
-pub fn export_bindings_to_table_writer<'a, W: std::io::Write>(
-    ocel: &'a SlimLinkedOCEL,
-    bindings: &EvaluationResultWithCount,
-    mut w: impl TableWriter<'a, W> + 'a,
-    options: &'a TableExportOptions,
-) -> Result<(), Error> {
-    if let Some((b, _)) = bindings.situations.first() {
-        let ev_vars = b.get_all_ev_vars().sorted().collect_vec();
-        let ob_vars = b.get_all_ob_vars().sorted().collect_vec();
+/// The variables a table has a block of columns for, and the attributes each block lists.
+struct TableColumns {
+    ob_vars: Vec<ObjectVariable>,
+    ob_attrs: Vec<Vec<String>>,
+    ev_vars: Vec<EventVariable>,
+    ev_attrs: Vec<Vec<String>>,
+}
+
+impl TableColumns {
+    /// Columns for the variables of the first situation, listing every attribute any situation
+    /// has a value for.
+    fn from_situations(ocel: &SlimLinkedOCEL, bindings: &EvaluationResultWithCount) -> Self {
+        let Some((b, _)) = bindings.situations.first() else {
+            return Self {
+                ob_vars: Vec::new(),
+                ob_attrs: Vec::new(),
+                ev_vars: Vec::new(),
+                ev_attrs: Vec::new(),
+            };
+        };
+        let ev_vars = b.get_all_ev_vars().sorted().copied().collect_vec();
+        let ob_vars = b.get_all_ob_vars().sorted().copied().collect_vec();
 
         let ev_attrs = ev_vars
             .iter()
@@ -159,6 +181,7 @@ pub fn export_bindings_to_table_writer<'a, W: std::io::Write>(
                         Some(ocel.get_ev_attrs(ev))
                     })
                     .flatten()
+                    .map(|attr| attr.to_string())
                     .collect::<HashSet<_>>()
                     .into_iter()
                     .collect_vec()
@@ -176,118 +199,243 @@ pub fn export_bindings_to_table_writer<'a, W: std::io::Write>(
                         Some(ocel.get_ob_attrs(ob))
                     })
                     .flatten()
+                    .map(|attr| attr.to_string())
                     .collect::<HashSet<_>>()
                     .into_iter()
                     .collect_vec()
             })
             .collect_vec();
-        // Write Headers
-        if !options.omit_header {
-            // First object/event ID, then attributes, then next object/event ID, ..
-            for (ob, ob_attrs) in ob_vars.iter().zip(&ob_attrs) {
-                if options.include_ids {
-                    w.write_cell(format!("o{}", ob.0 + 1), CellType::HEADER(true))?;
-                }
-                for attr in ob_attrs {
-                    w.write_cell(format!("o{}.{}", ob.0 + 1, attr), CellType::HEADER(false))?;
-                }
+        Self {
+            ob_vars,
+            ob_attrs,
+            ev_vars,
+            ev_attrs,
+        }
+    }
+
+    /// Columns for the variables in scope at `node_index`, listing the attributes declared for
+    /// their types. Needs no situation, so the header can be written before any is known.
+    fn from_tree(ocel: &SlimLinkedOCEL, tree: &BindingBoxTree, node_index: usize) -> Self {
+        let (ev_types, ob_types) = tree.variables_in_scope(node_index);
+        let ev_vars = ev_types.keys().sorted().copied().collect_vec();
+        let ob_vars = ob_types.keys().sorted().copied().collect_vec();
+        let ev_attrs = ev_vars
+            .iter()
+            .map(|v| {
+                ev_types[v]
+                    .iter()
+                    .filter_map(|t| ocel.get_ev_type(t.as_str()))
+                    .flat_map(|t| t.attributes.iter().map(|a| a.name.clone()))
+                    .unique()
+                    .collect_vec()
+            })
+            .collect_vec();
+        let ob_attrs = ob_vars
+            .iter()
+            .map(|v| {
+                ob_types[v]
+                    .iter()
+                    .filter_map(|t| ocel.get_ob_type(t.as_str()))
+                    .flat_map(|t| t.attributes.iter().map(|a| a.name.clone()))
+                    .unique()
+                    .collect_vec()
+            })
+            .collect_vec();
+        Self {
+            ob_vars,
+            ob_attrs,
+            ev_vars,
+            ev_attrs,
+        }
+    }
+
+    fn write_header<'w, W: std::io::Write>(
+        &self,
+        w: &mut impl TableWriter<'w, W>,
+        options: &TableExportOptions,
+    ) -> Result<(), Error> {
+        // First object/event ID, then attributes, then next object/event ID, ..
+        for (ob, ob_attrs) in self.ob_vars.iter().zip(&self.ob_attrs) {
+            if options.include_ids {
+                w.write_cell(format!("o{}", ob.0 + 1), CellType::HEADER(true))?;
             }
-            for (ev, ev_attrs) in ev_vars.iter().zip(&ev_attrs) {
-                if options.include_ids {
-                    w.write_cell(format!("e{}", ev.0 + 1), CellType::HEADER(true))?;
-                }
-                for attr in ev_attrs {
-                    w.write_cell(format!("e{}.{}", ev.0 + 1, attr), CellType::HEADER(false))?;
-                }
+            for attr in ob_attrs {
+                w.write_cell(format!("o{}.{}", ob.0 + 1, attr), CellType::HEADER(false))?;
             }
-
-            for label in &options.labels {
-                w.write_cell(label, CellType::HEADER(true))?;
+        }
+        for (ev, ev_attrs) in self.ev_vars.iter().zip(&self.ev_attrs) {
+            if options.include_ids {
+                w.write_cell(format!("e{}", ev.0 + 1), CellType::HEADER(true))?;
             }
-
-            if options.include_violation_status {
-                w.write_cell("Satisfied", CellType::HEADER(true))?;
+            for attr in ev_attrs {
+                w.write_cell(format!("e{}.{}", ev.0 + 1, attr), CellType::HEADER(false))?;
             }
-            w.new_row()?;
         }
 
-        for (b, v) in &bindings.situations {
-            for (ob_v, ob_attrs) in ob_vars.iter().zip(&ob_attrs) {
-                if let Some(ob) = b.get_ob(ob_v, ocel) {
-                    if options.include_ids {
-                        w.write_cell(&ob.id, CellType::DEFAULT)?;
-                    }
-                    for attr in ob_attrs {
-                        if let Some(val) = ob
-                            .attributes
-                            .iter()
-                            .filter(|a| &a.name == attr)
-                            .sorted_by_key(|a| a.time)
-                            .next()
-                        {
-                            w.write_cell(
-                                CellContent::Value(&val.value),
-                                CellType::ValueType(val.value.get_type()),
-                            )?;
-                        } else {
-                            w.write_cell("", CellType::DEFAULT)?;
-                        }
-                    }
-                } else {
-                    if options.include_ids {
-                        w.write_cell("", CellType::DEFAULT)?;
-                    }
-                    for _attr in ob_attrs {
+        for label in &options.labels {
+            w.write_cell(label, CellType::HEADER(true))?;
+        }
+
+        if options.include_violation_status {
+            w.write_cell("Satisfied", CellType::HEADER(true))?;
+        }
+        if options.include_explanation {
+            w.write_cell("Explanation", CellType::HEADER(true))?;
+        }
+        w.new_row()?;
+        Ok(())
+    }
+
+    fn write_row<'w, W: std::io::Write>(
+        &self,
+        w: &mut impl TableWriter<'w, W>,
+        ocel: &SlimLinkedOCEL,
+        b: &Binding,
+        v: &Option<ViolationReason>,
+        explanation: Option<&ViolationExplanation>,
+        options: &TableExportOptions,
+    ) -> Result<(), Error> {
+        for (ob_v, ob_attrs) in self.ob_vars.iter().zip(&self.ob_attrs) {
+            if let Some(ob) = b.get_ob(ob_v, ocel) {
+                if options.include_ids {
+                    w.write_cell(&ob.id, CellType::DEFAULT)?;
+                }
+                for attr in ob_attrs {
+                    if let Some(val) = ob
+                        .attributes
+                        .iter()
+                        .filter(|a| &a.name == attr)
+                        .sorted_by_key(|a| a.time)
+                        .next()
+                    {
+                        w.write_cell(
+                            CellContent::Value(&val.value),
+                            CellType::ValueType(val.value.get_type()),
+                        )?;
+                    } else {
                         w.write_cell("", CellType::DEFAULT)?;
                     }
                 }
+            } else {
+                if options.include_ids {
+                    w.write_cell("", CellType::DEFAULT)?;
+                }
+                for _attr in ob_attrs {
+                    w.write_cell("", CellType::DEFAULT)?;
+                }
             }
-            for (ev_v, ev_attrs) in ev_vars.iter().zip(&ev_attrs) {
-                if let Some(ev) = b.get_ev(ev_v, ocel) {
-                    if options.include_ids {
-                        w.write_cell(&ev.id, CellType::DEFAULT)?;
-                    }
-                    for attr in ev_attrs {
-                        if let Some(val) = ev.attributes.iter().find(|a| &a.name == attr) {
-                            w.write_cell(
-                                CellContent::Value(&val.value),
-                                CellType::ValueType(val.value.get_type()),
-                            )?;
-                        } else {
-                            w.write_cell("", CellType::DEFAULT)?;
-                        }
-                    }
-                } else {
-                    if options.include_ids {
-                        w.write_cell("", CellType::DEFAULT)?;
-                    }
-                    for _attr in ev_attrs {
+        }
+        for (ev_v, ev_attrs) in self.ev_vars.iter().zip(&self.ev_attrs) {
+            if let Some(ev) = b.get_ev(ev_v, ocel) {
+                if options.include_ids {
+                    w.write_cell(&ev.id, CellType::DEFAULT)?;
+                }
+                for attr in ev_attrs {
+                    if let Some(val) = ev.attributes.iter().find(|a| &a.name == attr) {
+                        w.write_cell(
+                            CellContent::Value(&val.value),
+                            CellType::ValueType(val.value.get_type()),
+                        )?;
+                    } else {
                         w.write_cell("", CellType::DEFAULT)?;
                     }
                 }
+            } else {
+                if options.include_ids {
+                    w.write_cell("", CellType::DEFAULT)?;
+                }
+                for _attr in ev_attrs {
+                    w.write_cell("", CellType::DEFAULT)?;
+                }
             }
+        }
 
-            for label in &options.labels {
-                match b.get_label_value(label) {
-                    // TODO: Also represent label values with correct types
-                    Some(val) => w.write_cell(val.to_string(), CellType::DEFAULT)?,
-                    None => w.write_cell("null", CellType::DEFAULT)?,
-                }
+        for label in &options.labels {
+            match b.get_label_value(label) {
+                // TODO: Also represent label values with correct types
+                Some(val) => w.write_cell(val.to_string(), CellType::DEFAULT)?,
+                None => w.write_cell("null", CellType::DEFAULT)?,
             }
+        }
 
-            if options.include_violation_status {
-                w.write_cell(
-                    format!("{}", v.is_none()),
-                    CellType::ViolationStatus(v.is_none()),
-                )?;
+        if options.include_violation_status {
+            w.write_cell(
+                format!("{}", v.is_none()),
+                CellType::ViolationStatus(v.is_none()),
+            )?;
+        }
+        if options.include_explanation {
+            match explanation {
+                Some(explanation) => w.write_cell(explanation.to_string(), CellType::DEFAULT)?,
+                None => w.write_cell("", CellType::DEFAULT)?,
             }
-            w.new_row()?;
+        }
+        w.new_row()?;
+        Ok(())
+    }
+}
+
+pub fn export_bindings_to_table_writer<'a, W: std::io::Write>(
+    ocel: &'a SlimLinkedOCEL,
+    bindings: &EvaluationResultWithCount,
+    mut w: impl TableWriter<'a, W> + 'a,
+    options: &'a TableExportOptions,
+) -> Result<(), Error> {
+    if !bindings.situations.is_empty() {
+        let columns = TableColumns::from_situations(ocel, bindings);
+        if !options.omit_header {
+            columns.write_header(&mut w, options)?;
+        }
+        for (i, (b, v)) in bindings.situations.iter().enumerate() {
+            columns.write_row(&mut w, ocel, b, v, bindings.explanations.get(&i), options)?;
         }
     }
     w.save()?;
     Ok(())
 }
 
+/// Evaluate `tree` and write the situations of `node_index` to `w` as they are decided, without
+/// keeping them. The attribute columns are those the OCEL declares for the variables' types, as
+/// the values present are not known up front. Returns the number of rows written and whether
+/// bindings were skipped.
+pub fn export_tree_node_to_table_writer<'a, W: std::io::Write>(
+    ocel: &'a SlimLinkedOCEL,
+    tree: &BindingBoxTree,
+    node_index: usize,
+    mut w: impl TableWriter<'a, W> + 'a,
+    options: &'a TableExportOptions,
+    control: &EvaluationControl,
+) -> Result<(usize, bool), Error> {
+    if node_index >= tree.nodes.len() {
+        return Err(Error::msg(format!("node_index {node_index} out of range")));
+    }
+    let columns = TableColumns::from_tree(ocel, tree, node_index);
+    if !options.omit_header {
+        columns.write_header(&mut w, options)?;
+    }
+    let explainer = options
+        .include_explanation
+        .then(|| ViolationExplainer::new(tree, ocel).with_as_of(control.as_of(ocel)));
+    let mut rows = 0;
+    let bindings_skipped = tree
+        .evaluate_streaming(ocel, control, &mut |index, b, v| {
+            if index != node_index {
+                return std::result::Result::Ok(());
+            }
+            rows += 1;
+            let explanation = match (&explainer, v) {
+                (Some(explainer), Some(_)) => explainer.explain(node_index, &b)?,
+                _ => None,
+            };
+            columns
+                .write_row(&mut w, ocel, &b, &v, explanation.as_ref(), options)
+                .map_err(|e| e.to_string())
+        })
+        .map_err(Error::msg)?;
+    w.save()?;
+    Ok((rows, bindings_skipped))
+}
+
 /// Plain-text CSV, one field per cell; cell roles (`CellType`) carry no formatting here.
 struct CSVTableWriter<'a, W: std::io::Write> {
     writer: csv::Writer<&'a mut W>,
@@ -364,9 +512,7 @@ impl<'a, W: std::io::Write + std::io::Seek + std::marker::Send> TableWriter<'a,
                         OCELAttributeType::Integer => Format::new().set_num_format("#,##0"),
                         OCELAttributeType::Float => Format::new().set_num_format("#,##0.00"),
                         OCELAttributeType::Boolean => Format::new(),
-                        OCELAttributeType::Time => {
-                            Format::new().set_num_format("dd/mm/yyyy HH:mm")
-                        }
+                        OCELAttributeType::Time => Format::new().set_num_format("dd/mm/yyyy HH:mm"),
                         OCELAttributeType::String => Format::new(),
                         OCELAttributeType::Null => Format::new(),
                     },
@@ -441,6 +587,36 @@ pub fn export_bindings_to_writer<'a, W: std::io::Write + std::io::Seek + std::ma
     }
 }
 
+/// Streaming counterpart of [`export_bindings_to_writer`]; see
+/// [`export_tree_node_to_table_writer`].
+pub fn export_tree_node_to_writer<'a, W: std::io::Write + std::io::Seek + std::marker::Send>(
+    ocel: &'a SlimLinkedOCEL,
+    tree: &BindingBoxTree,
+    node_index: usize,
+    w: &mut W,
+    options: &'a TableExportOptions,
+    control: &EvaluationControl,
+) -> Result<(usize, bool), Error> {
+    match options.format {
+        TableExportFormat::CSV => export_tree_node_to_table_writer(
+            ocel,
+            tree,
+            node_index,
+            CSVTableWriter::new(w),
+            options,
+            control,
+        ),
+        TableExportFormat::XLSX => export_tree_node_to_table_writer(
+            ocel,
+            tree,
+            node_index,
+            XLSXTableWriter::new(w),
+            options,
+            control,
+        ),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -453,7 +629,7 @@ mod tests {
     use super::*;
     use crate::binding_box::{
         evaluate_box_tree,
-        structs::{BindingBoxTreeNode, Filter},
+        structs::{BindingBoxTreeNode, Constraint, Filter},
         BindingBox, BindingBoxTree, EventVariable, ObjectVariable,
     };
 
@@ -483,10 +659,12 @@ mod tests {
         ]
     }"#;
 
-    fn fixture() -> (SlimLinkedOCEL, EvaluationResultWithCount) {
+    fn fixture_ocel() -> SlimLinkedOCEL {
         let ocel: OCEL = serde_json::from_str(OCEL_JSON).expect("fixture OCEL parses");
-        let ocel = SlimLinkedOCEL::from_ocel(ocel);
+        SlimLinkedOCEL::from_ocel(ocel)
+    }
 
+    fn fixture_tree() -> BindingBoxTree {
         let node = BindingBoxTreeNode::Box(
             BindingBox {
                 new_object_vars: HashMap::from([(
@@ -507,11 +685,17 @@ mod tests {
             },
             Vec::new(),
         );
-        let tree = BindingBoxTree {
+        BindingBoxTree {
             nodes: vec![node],
             edge_names: HashMap::new(),
-        };
-        let mut res = evaluate_box_tree(tree, &ocel, false).expect("evaluation succeeds");
+            cel_libraries: Vec::new(),
+            parameters: Vec::new(),
+        }
+    }
+
+    fn fixture() -> (SlimLinkedOCEL, EvaluationResultWithCount) {
+        let ocel = fixture_ocel();
+        let mut res = evaluate_box_tree(fixture_tree(), &ocel, false).expect("evaluation succeeds");
         (ocel, res.evaluation_results.remove(0))
     }
 
@@ -528,7 +712,10 @@ mod tests {
         assert!(header.contains("Satisfied"), "header: {header}");
         let row = lines.next().unwrap();
         assert!(row.contains("o1"), "row: {row}");
-        assert!(row.contains("true"), "row (no constraints -> satisfied): {row}");
+        assert!(
+            row.contains("true"),
+            "row (no constraints -> satisfied): {row}"
+        );
     }
 
     #[test]
@@ -547,5 +734,87 @@ mod tests {
         assert!(bytes.len() > 100, "workbook should not be trivially empty");
         assert_eq!(&bytes[0..2], b"PK", "workbook should be a zip archive");
     }
-}
 
+    #[test]
+    fn streamed_csv_export_declares_the_type_attributes_and_writes_every_row() {
+        let ocel = fixture_ocel();
+        let mut buf = Vec::new();
+        let (rows, skipped) = export_tree_node_to_table_writer(
+            &ocel,
+            &fixture_tree(),
+            0,
+            CSVTableWriter::new(&mut buf),
+            &TableExportOptions::default(),
+            &EvaluationControl::default(),
+        )
+        .unwrap();
+        assert_eq!((rows, skipped), (1, false));
+        let text = String::from_utf8(buf).unwrap();
+        let mut lines = text.lines();
+        let header = lines.next().unwrap();
+        assert_eq!(
+            header.split(',').collect::<HashSet<_>>(),
+            HashSet::from([
+                "o1",
+                "o1.total",
+                "o1.rush",
+                "o1.due",
+                "e1",
+                "e1.amount",
+                "Satisfied"
+            ])
+        );
+        assert!(header.starts_with("o1,"), "ids lead their block: {header}");
+        let row = lines.next().unwrap();
+        assert!(row.contains("e1") && row.contains("100"), "row: {row}");
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn streamed_export_of_a_missing_node_fails() {
+        let ocel = fixture_ocel();
+        let mut buf = Vec::new();
+        let err = export_tree_node_to_table_writer(
+            &ocel,
+            &fixture_tree(),
+            3,
+            CSVTableWriter::new(&mut buf),
+            &TableExportOptions::default(),
+            &EvaluationControl::default(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("out of range"), "got: {err}");
+    }
+
+    #[test]
+    fn streamed_export_explains_violated_rows() {
+        let ocel = fixture_ocel();
+        let mut tree = fixture_tree();
+        if let BindingBoxTreeNode::Box(b, _) = &mut tree.nodes[0] {
+            b.constraints.push(Constraint::Filter {
+                filter: Filter::BasicFilterCEL {
+                    cel: "e1.attr('amount') > 200".to_string(),
+                },
+            });
+        }
+        let mut buf = Vec::new();
+        export_tree_node_to_table_writer(
+            &ocel,
+            &tree,
+            0,
+            CSVTableWriter::new(&mut buf),
+            &TableExportOptions {
+                include_explanation: true,
+                ..Default::default()
+            },
+            &EvaluationControl::default(),
+        )
+        .unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        let mut lines = text.lines();
+        assert!(lines.next().unwrap().ends_with(",Satisfied,Explanation"));
+        let row = lines.next().unwrap();
+        assert!(row.contains("Constraint 0 not satisfied"), "row: {row}");
+        assert!(row.contains("e1.attr('amount') = 100"), "row: {row}");
+    }
+}
@@ -9,13 +9,20 @@ use std::{
     time::{Instant, SystemTime},
 };
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, Utc};
 use clap::{Parser, Subcommand, ValueEnum};
 use ocpq_core::{
-    binding_box::{evaluate_box_tree, Binding, BindingBoxTree},
+    binding_box::{
+        control::EvaluationControl, evaluate_box_tree_controlled, plan_stats::PlannerStatistics,
+        validate::TreeLocation, Binding, BindingBoxTree,
+    },
+    calendar::load_calendar_file,
+    cel::library::load_cel_library_file,
     db_translation::{
+        execute::run_db_query as run_db_query_shared,
+        graph_export::{export_neo4j_csv, neo4j_import_command},
         translate_to_cypher_shared, translate_to_sql_shared, DBTranslationInput, DatabaseType,
-        TableMappings,
+        TableMappings, TranslationIssue,
     },
     process_mining::{core::event_data::object_centric::linked_ocel::SlimLinkedOCEL, Importable},
 };
@@ -33,9 +40,17 @@ enum Command {
     /// to a timestamped JSON file.
     Evaluate(EvaluateArgs),
 
-    /// Translate a BindingBoxTree to SQL (SQLite/DuckDB) or Cypher.
+    /// Translate a BindingBoxTree to SQL (SQLite/DuckDB/PostgreSQL) or Cypher.
     Translate(TranslateArgs),
 
+    /// Run the SQL translation of a BindingBoxTree against an OCEL 2.0 SQLite or DuckDB file,
+    /// optionally comparing its situations with the in-memory evaluation.
+    RunDbQuery(RunDbQueryArgs),
+
+    /// Export an OCEL 2.0 file as Neo4j-importable CSV files, in the graph schema the Cypher
+    /// translation queries.
+    ExportGraph(ExportGraphArgs),
+
     /// Benchmark BindingBoxTree evaluation across one or more queries.
     Bench(BenchArgs),
 
@@ -45,7 +60,7 @@ enum Command {
     /// Summarize a JSONL file produced by bench or bench-root.
     BenchSummary(BenchSummaryArgs),
 
-    /// Profile the current binding-step plan by prefix cardinality.
+    /// Profile the current binding-step plan by prefix cardinality, estimated and actual.
     PlanProfile(PlanProfileArgs),
 }
 
@@ -58,6 +73,25 @@ struct EvaluateArgs {
     /// Path to the input BindingBoxTree JSON file.
     #[arg(short, long)]
     bbox_tree: PathBuf,
+
+    /// Optional JSON file with named business calendars (working hours, holidays) for the CEL
+    /// calendar functions and TimeBetweenEvents filters.
+    #[arg(long)]
+    calendars: Option<PathBuf>,
+
+    /// JSON or YAML file with a library of CEL functions the tree calls; can be repeated.
+    #[arg(long)]
+    cel_library: Vec<PathBuf>,
+
+    /// Evaluate as of this RFC 3339 time: CEL's `now` is set to it and later events are ignored.
+    /// Defaults to the time of the latest event.
+    #[arg(long, value_parser = DateTime::parse_from_rfc3339)]
+    as_of: Option<DateTime<FixedOffset>>,
+
+    /// Value of a tree parameter, as `name=value`; can be repeated. Parameters not given take
+    /// their default.
+    #[arg(long, value_name = "NAME=VALUE")]
+    param: Vec<String>,
 }
 
 #[derive(Parser, Debug)]
@@ -77,12 +111,82 @@ struct TranslateArgs {
     /// Write output to this file. Defaults to stdout.
     #[arg(short, long)]
     output: Option<PathBuf>,
+
+    /// Value of a tree parameter, as `name=value`; can be repeated. Parameters not given take
+    /// their default.
+    #[arg(long, value_name = "NAME=VALUE")]
+    param: Vec<String>,
+
+    /// Fail without writing the query if it leaves out part of the tree (which is otherwise only
+    /// listed on stderr).
+    #[arg(long)]
+    strict: bool,
+}
+
+#[derive(Parser, Debug)]
+struct RunDbQueryArgs {
+    /// Path to the OCEL 2.0 SQLite or DuckDB file to query.
+    #[arg(short, long)]
+    database: PathBuf,
+
+    /// Kind of database file.
+    #[arg(short = 'T', long, value_enum, default_value_t = DatabaseFile::Sqlite)]
+    target: DatabaseFile,
+
+    /// Path to the same OCEL in a format the CLI can import, to map the ids the query returns.
+    /// Defaults to the database file itself.
+    #[arg(long)]
+    ocel: Option<PathBuf>,
+
+    /// Path to a BindingBoxTree JSON file.
+    #[arg(short, long)]
+    tree: PathBuf,
+
+    /// Optional JSON file mapping OCEL types to table names.
+    #[arg(short, long)]
+    mappings: Option<PathBuf>,
+
+    /// Value of a tree parameter, as `name=value`; can be repeated. Parameters not given take
+    /// their default.
+    #[arg(long, value_name = "NAME=VALUE")]
+    param: Vec<String>,
+
+    /// Also evaluate the tree in memory and report every situation the two disagree on. Exits
+    /// with failure if they do.
+    #[arg(long)]
+    differential: bool,
+
+    /// Write the full result as JSON to this file.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+struct ExportGraphArgs {
+    /// Path to the input OCEL 2.0 file.
+    #[arg(short, long)]
+    ocel: PathBuf,
+
+    /// Directory to write the CSV files to; created if it does not exist.
+    #[arg(short = 'O', long)]
+    output: PathBuf,
+
+    /// Name of the Neo4j database in the printed import command.
+    #[arg(long, default_value = "neo4j")]
+    database: String,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum DatabaseFile {
+    Sqlite,
+    Duckdb,
 }
 
 #[derive(Copy, Clone, Debug, ValueEnum)]
 enum Target {
     Sqlite,
     Duckdb,
+    Postgres,
     Cypher,
 }
 
@@ -115,6 +219,11 @@ struct BenchArgs {
     /// Query names to run (default: all).
     #[arg(long, num_args = 0..)]
     only: Vec<String>,
+
+    /// After the timed runs, evaluate each query once more with CEL profiling and print its
+    /// most expensive CEL programs (full mode only).
+    #[arg(long)]
+    cel_profile: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -144,14 +253,30 @@ struct PlanProfileArgs {
 }
 
 fn run_evaluate(args: EvaluateArgs) {
+    if let Some(path) = &args.calendars {
+        let names = load_calendar_file(path).expect("Could not load calendar file");
+        println!("Loaded calendars {}", names.join(", "));
+    }
+    for path in &args.cel_library {
+        let name = load_cel_library_file(path).expect("Could not load CEL library file");
+        println!("Loaded CEL library {name}");
+    }
     let bbox_reader = File::open(args.bbox_tree).expect("Could not find input bbox tree file");
     let bbox_tree: BindingBoxTree =
         serde_json::from_reader(bbox_reader).expect("Could not parse bbox_tree JSON");
+    let bbox_tree = bbox_tree
+        .parse_parameter_values(args.param.iter().map(String::as_str))
+        .and_then(|values| bbox_tree.bind_parameters(&values))
+        .expect("Could not bind tree parameters");
     let now = Instant::now();
     let index_linked_ocel =
         SlimLinkedOCEL::import_from_path(args.ocel).expect("Could not import OCEL 2.0 file");
     println!("Imported OCEL 2.0 in {:?}", now.elapsed());
-    let res = evaluate_box_tree(bbox_tree, &index_linked_ocel, true);
+    let control = match args.as_of {
+        Some(as_of) => EvaluationControl::default().with_as_of(as_of),
+        None => EvaluationControl::default(),
+    };
+    let res = evaluate_box_tree_controlled(bbox_tree, &index_linked_ocel, true, &control);
 
     let now = Instant::now();
     // Avoid colons in the timestamp. Windows treats them as illegal path
@@ -179,27 +304,172 @@ fn run_translate(args: TranslateArgs) -> Result<(), String> {
         }
     };
 
-    let output = match args.target {
-        Target::Cypher => translate_to_cypher_shared(tree, &mappings),
+    let parameters = tree.parse_parameter_values(args.param.iter().map(String::as_str))?;
+
+    let res = match args.target {
+        Target::Cypher => translate_to_cypher_shared(tree, &parameters, &mappings)?,
         Target::Sqlite => translate_to_sql_shared(DBTranslationInput {
             tree,
             database: DatabaseType::SQLite,
             table_mappings: mappings,
-        }),
+            parameters,
+        })?,
         Target::Duckdb => translate_to_sql_shared(DBTranslationInput {
             tree,
             database: DatabaseType::DuckDB,
             table_mappings: mappings,
-        }),
+            parameters,
+        })?,
+        Target::Postgres => translate_to_sql_shared(DBTranslationInput {
+            tree,
+            database: DatabaseType::PostgreSQL,
+            table_mappings: mappings,
+            parameters,
+        })?,
     };
 
+    for issue in &res.warnings {
+        eprintln!("Warning: {}", describe_issue(issue));
+    }
+    for issue in &res.unsupported {
+        eprintln!("Not translated: {}", describe_issue(issue));
+    }
+    if args.strict && !res.is_complete() {
+        return Err(format!(
+            "{} parts of the tree are not translated",
+            res.unsupported.len()
+        ));
+    }
+
     match args.output {
-        Some(p) => fs::write(&p, output).map_err(|e| format!("write output {p:?}: {e}"))?,
-        None => print!("{output}"),
+        Some(p) => fs::write(&p, res.query).map_err(|e| format!("write output {p:?}: {e}"))?,
+        None => print!("{}", res.query),
     }
     Ok(())
 }
 
+/// Where in the tree `issue` is, and what it is
+fn describe_issue(issue: &TranslationIssue) -> String {
+    let location = match issue.location {
+        TreeLocation::Node => String::new(),
+        TreeLocation::Filter { index } => format!(", filter {index}"),
+        TreeLocation::SizeFilter { index } => format!(", size filter {index}"),
+        TreeLocation::Constraint { index } => format!(", constraint {index}"),
+        TreeLocation::Label { index } => format!(", label {index}"),
+    };
+    format!("node {}{location}: {}", issue.node_index, issue.message)
+}
+
+/// Number of mismatching situations `run-db-query --differential` prints.
+const PRINTED_MISMATCHES: usize = 20;
+
+fn run_db_query(args: RunDbQueryArgs) -> Result<bool, String> {
+    let tree_content =
+        fs::read_to_string(&args.tree).map_err(|e| format!("read tree {:?}: {e}", args.tree))?;
+    let tree: BindingBoxTree =
+        serde_json::from_str(&tree_content).map_err(|e| format!("parse tree JSON: {e}"))?;
+    let parameters = tree.parse_parameter_values(args.param.iter().map(String::as_str))?;
+    let mappings = match &args.mappings {
+        None => TableMappings::default(),
+        Some(p) => {
+            let content = fs::read_to_string(p).map_err(|e| format!("read mappings {p:?}: {e}"))?;
+            serde_json::from_str(&content).map_err(|e| format!("parse mappings JSON: {e}"))?
+        }
+    };
+    let ocel_path = args.ocel.as_ref().unwrap_or(&args.database);
+    let ocel = SlimLinkedOCEL::import_from_path(ocel_path)
+        .map_err(|e| format!("import OCEL {ocel_path:?}: {e:?}"))?;
+    let database = match args.target {
+        DatabaseFile::Sqlite => DatabaseType::SQLite,
+        DatabaseFile::Duckdb => DatabaseType::DuckDB,
+    };
+
+    let now = Instant::now();
+    let res = run_db_query_shared(
+        &ocel,
+        DBTranslationInput {
+            tree,
+            database,
+            table_mappings: mappings,
+            parameters,
+        },
+        &args.database.to_string_lossy(),
+        args.differential,
+    )?;
+    println!(
+        "{} situations ({} violated) in {:?}",
+        res.result.situation_count,
+        res.result.situation_violated_count,
+        now.elapsed()
+    );
+    for issue in &res.unsupported {
+        println!("Not translated: {}", describe_issue(issue));
+    }
+
+    let mut agrees = true;
+    if let Some(comparison) = &res.comparison {
+        println!(
+            "In memory: {} situations ({} violated)",
+            comparison.memory_situation_count, comparison.memory_violated_count
+        );
+        if comparison.memory_bindings_skipped {
+            println!("The in-memory evaluation skipped bindings, so it may miss situations");
+        }
+        if comparison.duplicate_rows > 0 {
+            println!(
+                "The query returned {} duplicate rows",
+                comparison.duplicate_rows
+            );
+        }
+        println!("{} mismatching situations", comparison.mismatches.len());
+        let violated = |v: Option<bool>| match v {
+            None => "missing",
+            Some(true) => "violated",
+            Some(false) => "satisfied",
+        };
+        for mismatch in comparison.mismatches.iter().take(PRINTED_MISMATCHES) {
+            let binding = mismatch
+                .binding
+                .iter()
+                .map(|(var, id)| format!("{var}={id}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!(
+                "  {binding}: database {}, in memory {}",
+                violated(mismatch.database_violated),
+                violated(mismatch.memory_violated)
+            );
+        }
+        agrees = comparison.agrees();
+    }
+
+    if let Some(p) = &args.output {
+        let file = File::create(p).map_err(|e| format!("create output {p:?}: {e}"))?;
+        serde_json::to_writer(BufWriter::new(file), &res)
+            .map_err(|e| format!("write output {p:?}: {e}"))?;
+    }
+    Ok(agrees)
+}
+
+fn run_export_graph(args: ExportGraphArgs) -> Result<(), String> {
+    let ocel = SlimLinkedOCEL::import_from_path(&args.ocel)
+        .map_err(|e| format!("import OCEL {:?}: {e:?}", args.ocel))?;
+    let files = export_neo4j_csv(&ocel)?;
+    fs::create_dir_all(&args.output)
+        .map_err(|e| format!("create output directory {:?}: {e}", args.output))?;
+    for file in &files {
+        let path = args.output.join(&file.name);
+        fs::write(&path, &file.content).map_err(|e| format!("write {path:?}: {e}"))?;
+    }
+    println!(
+        "Wrote {} files to {:?}. Import them from that directory with:",
+        files.len(),
+        args.output
+    );
+    println!("{}", neo4j_import_command(&files, &args.database));
+    Ok(())
+}
+
 struct Stats {
     mean: f64,
     median: f64,
@@ -349,12 +619,41 @@ fn run_bench_inner(args: BenchArgs, root_only: bool) -> Result<(), String> {
             "{:<8} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>10}",
             qname, s.mean, s.median, s.min, s.max, s.stddev, row_count
         );
+        if args.cel_profile && !root_only {
+            print_cel_profile(&tree, &linked).map_err(|e| format!("profile {qname}: {e}"))?;
+        }
     }
 
     println!("\nResults appended to {:?}", args.results);
     Ok(())
 }
 
+/// Programs of the CEL profile printed per query
+const CEL_PROFILE_TOP: usize = 10;
+
+/// Evaluate `tree` with CEL profiling and print the positions of its most expensive CEL programs.
+/// Profiling locks per call, so this is a separate run from the timed ones.
+fn print_cel_profile(tree: &BindingBoxTree, ocel: &SlimLinkedOCEL) -> Result<(), String> {
+    let control = EvaluationControl::default().with_cel_profiling();
+    tree.evaluate_controlled(ocel, &control)?;
+    let profile = control.cel_profile(tree).unwrap_or_default();
+    if profile.positions.is_empty() {
+        println!("  (no CEL programs evaluated)");
+        return Ok(());
+    }
+    println!(
+        "  {:<6} {:>10} {:>10} {:>8}  cel",
+        "node", "total(ms)", "calls", "errors"
+    );
+    for p in profile.positions.iter().take(CEL_PROFILE_TOP) {
+        println!(
+            "  {:<6} {:>10.2} {:>10} {:>8}  {}",
+            p.node_index, p.total_ms, p.calls, p.errors, p.cel
+        );
+    }
+    Ok(())
+}
+
 fn run_bench_summary(args: BenchSummaryArgs) -> Result<(), String> {
     let content = fs::read_to_string(&args.results)
         .map_err(|e| format!("read results {:?}: {e}", args.results))?;
@@ -520,6 +819,9 @@ fn run_plan_profile(args: PlanProfileArgs) -> Result<(), String> {
     let linked =
         SlimLinkedOCEL::import_from_path(&args.ocel).map_err(|e| format!("import OCEL: {e:?}"))?;
     println!("Loaded and linked in {:.2?}", load_start.elapsed());
+    let stats_start = Instant::now();
+    let stats = PlannerStatistics::for_ocel(&linked, None);
+    println!("Planner statistics in {:.2?}", stats_start.elapsed());
 
     for (qname, qdir) in &queries {
         let tree_path = qdir.join("ocpq-tree.json");
@@ -527,7 +829,7 @@ fn run_plan_profile(args: PlanProfileArgs) -> Result<(), String> {
             fs::read_to_string(&tree_path).map_err(|e| format!("read {:?}: {e}", tree_path))?;
         let tree: BindingBoxTree =
             serde_json::from_str(&tree_str).map_err(|e| format!("parse {:?}: {e}", tree_path))?;
-        let step_cache = tree.compute_step_cache(&linked);
+        let step_cache = tree.compute_step_cache_with(&linked, &stats);
         let parents = parent_by_child(&tree);
         let mut memo = HashMap::new();
 
@@ -555,6 +857,8 @@ fn run_plan_profile(args: PlanProfileArgs) -> Result<(), String> {
                 continue;
             }
 
+            // Estimated bindings per input binding after each prefix
+            let estimates = stats.estimate_cardinalities(&bbox, steps);
             let mut prev_count = inputs.len();
             for prefix_len in 1..=steps.len() {
                 let start = Instant::now();
@@ -571,12 +875,14 @@ fn run_plan_profile(args: PlanProfileArgs) -> Result<(), String> {
                 } else {
                     out_count as f64 / prev_count as f64
                 };
+                let estimated = estimates[prefix_len - 1] * inputs.len() as f64;
                 println!(
-                    "  {:>2}. {:<80} in={:<8} out={:<8} x={:<8.2} cum_time={:>8.2?}{}",
+                    "  {:>2}. {:<80} in={:<8} out={:<8} est={:<10.1} x={:<8.2} cum_time={:>8.2?}{}",
                     prefix_len,
                     format!("{:?}", steps[prefix_len - 1]),
                     prev_count,
                     out_count,
+                    estimated,
                     factor,
                     start.elapsed(),
                     if skipped { " skipped" } else { "" }
@@ -603,6 +909,21 @@ fn main() -> ExitCode {
                 ExitCode::FAILURE
             }
         },
+        Command::RunDbQuery(args) => match run_db_query(args) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("ocpq_cli run-db-query: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Command::ExportGraph(args) => match run_export_graph(args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("ocpq_cli export-graph: {e}");
+                ExitCode::FAILURE
+            }
+        },
         Command::Bench(bench_args) => match run_bench(bench_args) {
             Ok(()) => ExitCode::SUCCESS,
             Err(e) => {
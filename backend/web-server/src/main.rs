@@ -340,6 +340,20 @@ async fn export_bindings_table(
     Ok(([(header::CONTENT_TYPE, "application/octet-stream")], bytes))
 }
 
+#[derive(Deserialize)]
+struct CancelEvaluationParams {
+    id: String,
+}
+
+/// Stops an evaluation started with an `evaluation_id`. A route of its own rather than a `/call`,
+/// so it is not queued behind the blocking pool the evaluation itself is occupying.
+async fn cancel_evaluation(
+    Query(p): Query<CancelEvaluationParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    backend_shared::cancel_evaluation(&p.id).map_err(err)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// Server-Sent Events stream of engine events (`objects-changed`, `*-import-finished`, ...), so the
 /// http transport live-reconciles like wasm and tauri. Each engine `emit` is forwarded as a named
 /// SSE event.
@@ -460,6 +474,7 @@ async fn hpc_job_status(
 #[tokio::main]
 async fn main() {
     let state = WebBackend::default();
+    backend_shared::forward_evaluation_progress(Arc::new(state.clone()));
 
     let api = Router::new()
         .route("/call", post(call))
@@ -480,6 +495,7 @@ async fn main() {
         .route("/unload-artifact", post(unload_artifact))
         .route("/export-artifact", get(export_artifact))
         .route("/export-bindings-table", post(export_bindings_table))
+        .route("/cancel-evaluation", post(cancel_evaluation))
         .route("/events", get(events))
         .route("/available-local", get(available_local))
         .route("/load-local", post(load_local))
@@ -46,6 +46,7 @@ import type { PathSchemaOptions } from "./types/generated/PathSchemaOptions";
 import type { PathSchemaResult } from "./types/generated/PathSchemaResult";
 import type { PathTypeGraph } from "./types/generated/PathTypeGraph";
 import type { TableExportOptions } from "./types/generated/TableExportOptions";
+import type { TranslationResult } from "./types/generated/TranslationResult";
 import type { ConnectionConfig, JobStatus } from "./types/hpc-backend";
 import type { OCELEvent, OCELInfo, OCELObject, SampleIds } from "./types/ocel";
 
@@ -129,7 +130,7 @@ export type BackendProvider = {
 	"hpc/start": (jobOptions: OCPQJobOptions) => Promise<string>;
 	"hpc/job-status": (jobID: string) => Promise<JobStatus>;
 	"download-blob": (blob: Blob, fileName: string) => unknown;
-	"ocel/create-db-query": (req: DBTranslationInput) => Promise<string>;
+	"ocel/create-db-query": (req: DBTranslationInput) => Promise<TranslationResult>;
 	// Register drag/drop listener, returns unregister function
 	"drag-drop-listener"?: (
 		f: (
@@ -425,7 +426,9 @@ export function createBackendProvider(backend: BackendContext): BackendProvider
 			await backend.saveBytes(bytes, fileName, blob.type === "" ? undefined : blob.type);
 		},
 		"ocel/create-db-query": async (req) =>
-			await call("app_bindings::query::create_db_query", { input: twin(req) }),
+			twin<TranslationResult>(
+				await call("app_bindings::query::create_db_query", { input: twin(req) }),
+			),
 		"oc-declare/template-string": async (arcs) =>
 			await call("app_bindings::oc_declare::oc_declare_template_string", { arcs: twin(arcs) }),
 		"ocel/discover-oc-declare": async (options) =>
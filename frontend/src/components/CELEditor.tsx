@@ -47,14 +47,14 @@ export default function CELEditor({
 					},
 				],
 				description:
-					"Retrieve an object/event attribute value.\n\nExample:\n`o1.attr('price') >= 100`<br/><br/>For objects the first encountered attribute value is picked, regardless of the associated timestamp. Use `attrAt` to retrieve the attribute value of an object at a specific timestamp. ",
+					"Retrieve an object/event attribute value.\n\nExample:\n`o1.attr('price') >= 100`<br/><br/>For objects the first encountered attribute value set by `now` is picked, regardless of its timestamp otherwise. Use `attrAt` to retrieve the attribute value of an object at a specific timestamp. ",
 			},
 			{
 				name: "attrs",
 				for_type: ["object", "event", "value"],
 				insertTemplate: "attrs()",
 				description:
-					"Retrieves all attributes of an event or object. Returns a list containing all attributes represented as lists of size 3: name, value, timestamp. The timestamp is only present for object attributes, otherwise it is set to `null`.\n\nExamples:\n`e1.attrs() == [['resource',1000.0,null]]`<br/>`o1.attrs().filter(x,x[0] == 'price').all(x,x[1] >= 100)`",
+					"Retrieves all attributes of an event or object. Returns a list containing all attributes represented as lists of size 3: name, value, timestamp. The timestamp is only present for object attributes, otherwise it is set to `null`. Object attribute values set after `now` are left out.\n\nExamples:\n`e1.attrs() == [['resource',1000.0,null]]`<br/>`o1.attrs().filter(x,x[0] == 'price').all(x,x[1] >= 100)`",
 			},
 			{
 				name: "attrAt",
@@ -105,17 +105,155 @@ export default function CELEditor({
 			},
 			{
 				name: "events",
-				for_type: ["standalone"],
+				for_type: ["standalone", "object", "value"],
 				insertTemplate: "events()",
+				signatureLabel: "list events(type?: string, qualifier?: string)",
+				parameters: [
+					{
+						label: "type?: string",
+						documentation: "Only events of this type (empty string: any type).",
+					},
+					{
+						label: "qualifier?: string",
+						documentation: "Only events related through this E2O qualifier (empty string: any qualifier).",
+					},
+				],
 				description:
-					"Retrieves a list of **all events** in the loaded OCEL.\n\nExample:\n`events().all(e,e.time() >= timestamp('2020-01-01T00:00:00+00:00'))`",
+					"Retrieves a list of **all events** in the loaded OCEL or, called on an object, the **events of that object** (ordered by time).\n\nExamples:\n`events().all(e,e.time() >= timestamp('2020-01-01T00:00:00+00:00'))`<br/>`o1.events('pay').size() >= 1`",
 			},
 			{
 				name: "objects",
-				for_type: ["standalone"],
+				for_type: ["standalone", "object", "event", "value"],
 				insertTemplate: "objects()",
+				signatureLabel: "list objects(type?: string, qualifier?: string)",
+				parameters: [
+					{
+						label: "type?: string",
+						documentation: "Only objects of this type (empty string: any type).",
+					},
+					{
+						label: "qualifier?: string",
+						documentation: "Only objects related through this E2O/O2O qualifier (empty string: any qualifier).",
+					},
+				],
+				description:
+					"Retrieves a list of **all objects** in the loaded OCEL or, called on an event, its **E2O objects** and, called on an object, the objects it relates to (**O2O**).\n\nExamples:\n`objects().all(e,e.attr('price') >= 100)`<br/>`e1.objects('item').all(i, i.attr('price') >= 100)`",
+			},
+			{
+				name: "related",
+				for_type: ["object", "value"],
+				insertTemplate: "related(${1:qualifier})",
+				signatureLabel: "list related(qualifier?: string, type?: string)",
+				parameters: [
+					{
+						label: "qualifier?: string",
+						documentation: "Only objects related through this O2O qualifier (empty string: any qualifier).",
+					},
+					{
+						label: "type?: string",
+						documentation: "Only objects of this type (empty string: any type).",
+					},
+				],
+				description:
+					"Retrieves the objects an object is **O2O-related** with, in either direction.\n\nExample:\n`o1.related('contains').exists(i, i.events('ship').size() > 0)`",
+			},
+			{
+				name: "businessDuration",
+				for_type: ["standalone"],
+				insertTemplate: "businessDuration(${1:from}, ${2:to})",
+				signatureLabel: "duration businessDuration(from: timestamp, to: timestamp, calendar?: string)",
+				parameters: [
+					{
+						label: "from: timestamp",
+						documentation: "Start of the measured time.",
+					},
+					{
+						label: "to: timestamp",
+						documentation: "End of the measured time.",
+					},
+					{
+						label: "calendar?: string",
+						documentation: "The name of a registered business calendar (default: `default`, Monday to Friday, 9:00 to 17:00 UTC).",
+					},
+				],
+				description:
+					"The **working time** between two timestamps: only the working hours of business days (no weekends or holidays) count.\n\nExample:\n`businessDuration(e1.time(), e2.time(), 'office') <= duration('16h')`",
+			},
+			{
+				name: "addBusinessDays",
+				for_type: ["standalone"],
+				insertTemplate: "addBusinessDays(${1:time}, ${2:days})",
+				signatureLabel: "timestamp addBusinessDays(time: timestamp, days: int, calendar?: string)",
+				parameters: [
+					{
+						label: "time: timestamp",
+						documentation: "The timestamp to start from.",
+					},
+					{
+						label: "days: int",
+						documentation: "The number of business days to move (backwards, if negative).",
+					},
+					{
+						label: "calendar?: string",
+						documentation: "The name of a registered business calendar (default: `default`, Monday to Friday, 9:00 to 17:00 UTC).",
+					},
+				],
 				description:
-					"Retrieves a list of **all objects** in the loaded OCEL.\n\nExample:\n`objects().all(e,e.attr('price') >= 100)`",
+					"Moves a timestamp by a number of **business days**, keeping the time of day.\n\nExample (within 2 business days):\n`e2.time() <= addBusinessDays(e1.time(), 2, 'office')`",
+			},
+			{
+				name: "isBusinessDay",
+				for_type: ["standalone"],
+				insertTemplate: "isBusinessDay(${1:time})",
+				signatureLabel: "bool isBusinessDay(time: timestamp, calendar?: string)",
+				parameters: [
+					{
+						label: "time: timestamp",
+						documentation: "The timestamp to check the day of.",
+					},
+					{
+						label: "calendar?: string",
+						documentation: "The name of a registered business calendar (default: `default`, Monday to Friday, 9:00 to 17:00 UTC).",
+					},
+				],
+				description:
+					"Returns if a timestamp is on a **business day** (a working day that is not a holiday) of the calendar.\n\nExample:\n`isBusinessDay(e1.time(), 'office')`",
+			},
+			{
+				name: "weekday",
+				for_type: ["standalone"],
+				insertTemplate: "weekday(${1:time})",
+				signatureLabel: "int weekday(time: timestamp, timezone?: string)",
+				parameters: [
+					{
+						label: "time: timestamp",
+						documentation: "The timestamp to get the weekday of.",
+					},
+					{
+						label: "timezone?: string",
+						documentation: "A UTC offset like `+01:00` or the name of a business calendar (default: UTC).",
+					},
+				],
+				description:
+					"Returns the **ISO weekday** of a timestamp (1 is Monday, 7 is Sunday).\n\nExample:\n`weekday(e1.time(), '+01:00') <= 5`",
+			},
+			{
+				name: "startOfDay",
+				for_type: ["standalone"],
+				insertTemplate: "startOfDay(${1:time})",
+				signatureLabel: "timestamp startOfDay(time: timestamp, timezone?: string)",
+				parameters: [
+					{
+						label: "time: timestamp",
+						documentation: "The timestamp to get the day of.",
+					},
+					{
+						label: "timezone?: string",
+						documentation: "A UTC offset like `+01:00` or the name of a business calendar (default: UTC).",
+					},
+				],
+				description:
+					"Returns **midnight at the start of the day** of a timestamp.\n\nExample:\n`e2.time() < startOfDay(e1.time(), 'office') + duration('24h')`",
 			},
 		],
 		[],
@@ -373,7 +511,7 @@ export default function CELEditor({
 					},
 				],
 				description:
-					"Returns the maximum value of either all provided arguments or, if the first argument is a list, the maximum value in this list.\n\nExamples:<br/>`max([3,4,5]) == 5`<br/>`max(3,4,5) == 5`",
+					"Returns the maximum value of either all provided arguments or, if the first argument is a list, the maximum value in this list. Numbers, strings, timestamps and durations can be compared.\n\nExamples:<br/>`max([3,4,5]) == 5`<br/>`max(3,4,5) == 5`",
 			},
 			// CUSTOM implementation (but is pretty standard, not sure why it does not exist)
 			{
@@ -392,7 +530,7 @@ export default function CELEditor({
 					},
 				],
 				description:
-					"Returns the minimal value of either all provided arguments or, if the first argument is a list, the minimal value in this list.\n\nExamples:<br/>`min([3,4,5]) == 3`<br/>`min(3,4,5) == 3`",
+					"Returns the minimal value of either all provided arguments or, if the first argument is a list, the minimal value in this list. Numbers, strings, timestamps and durations can be compared.\n\nExamples:<br/>`min([3,4,5]) == 3`<br/>`min(3,4,5) == 3`",
 			},
 			{
 				name: "sum",
@@ -408,6 +546,199 @@ export default function CELEditor({
 				description:
 					"Computes the average of all entries in a list. Integer and floats are considered while other values are handled as 0.\n\nExamples:<br/>`[3,4,5].avg() == 3`",
 			},
+			{
+				name: "regexFind",
+				for_type: ["value"],
+				insertTemplate: "regexFind(${1:regex})",
+				signatureLabel: "string|null regexFind(regex: string)",
+				parameters: [
+					{
+						label: "regex: string",
+						documentation: "The regular expression to search for.",
+					},
+				],
+				description:
+					"Returns the **first match** of a regular expression in a string, or `null`.\n\nExamples:<br/>`'ORD-2024-17'.regexFind('[0-9]+') == '2024'`",
+			},
+			{
+				name: "regexFindAll",
+				for_type: ["value"],
+				insertTemplate: "regexFindAll(${1:regex})",
+				signatureLabel: "list regexFindAll(regex: string)",
+				parameters: [
+					{
+						label: "regex: string",
+						documentation: "The regular expression to search for.",
+					},
+				],
+				description:
+					"Returns **all matches** of a regular expression in a string.\n\nExamples:<br/>`'a1b22'.regexFindAll('[0-9]+') == ['1', '22']`",
+			},
+			{
+				name: "regexCapture",
+				for_type: ["value"],
+				insertTemplate: "regexCapture(${1:regex})",
+				signatureLabel: "list regexCapture(regex: string)",
+				parameters: [
+					{
+						label: "regex: string",
+						documentation: "The regular expression with capture groups.",
+					},
+				],
+				description:
+					"Returns the **capture groups** of the first match of a regular expression (`''` for groups that did not take part, `[]` without a match).\n\nExamples:<br/>`'ORD-2024'.regexCapture('([A-Z]+)-(\\\\d+)') == ['ORD', '2024']`",
+			},
+			{
+				name: "regexReplace",
+				for_type: ["value"],
+				insertTemplate: "regexReplace(${1:regex}, ${2:replacement})",
+				signatureLabel: "string regexReplace(regex: string, replacement: string)",
+				parameters: [
+					{
+						label: "regex: string",
+						documentation: "The regular expression to replace.",
+					},
+					{
+						label: "replacement: string",
+						documentation: "The replacement; `$1` or `${name}` refer to capture groups.",
+					},
+				],
+				description:
+					"Replaces **all matches** of a regular expression.\n\nExamples:<br/>`'a-b'.regexReplace('-', '+') == 'a+b'`",
+			},
+			{
+				name: "lower",
+				for_type: ["value"],
+				insertTemplate: "lower()",
+				description:
+					"Converts a string to **lower case**.\n\nExamples:<br/>`'Paid'.lower() == 'paid'`",
+			},
+			{
+				name: "upper",
+				for_type: ["value"],
+				insertTemplate: "upper()",
+				description:
+					"Converts a string to **upper case**.\n\nExamples:<br/>`'Paid'.upper() == 'PAID'`",
+			},
+			{
+				name: "trim",
+				for_type: ["value"],
+				insertTemplate: "trim()",
+				description:
+					"Removes **leading and trailing whitespace** of a string.\n\nExamples:<br/>`' x '.trim() == 'x'`",
+			},
+			{
+				name: "equalsIgnoreCase",
+				for_type: ["value"],
+				insertTemplate: "equalsIgnoreCase(${1:other})",
+				signatureLabel: "bool equalsIgnoreCase(other: string)",
+				parameters: [
+					{
+						label: "other: string",
+						documentation: "The string to compare to.",
+					},
+				],
+				description:
+					"Returns if two strings are **equal, ignoring case**.\n\nExamples:<br/>`'Paid'.equalsIgnoreCase('PAID') == true`",
+			},
+			{
+				name: "split",
+				for_type: ["value"],
+				insertTemplate: "split(${1:separator})",
+				signatureLabel: "list split(separator: string)",
+				parameters: [
+					{
+						label: "separator: string",
+						documentation: "The separator to split at.",
+					},
+				],
+				description:
+					"**Splits** a string at a separator.\n\nExamples:<br/>`'a,b'.split(',') == ['a', 'b']`",
+			},
+			{
+				name: "join",
+				for_type: ["value"],
+				insertTemplate: "join(${1:separator})",
+				signatureLabel: "string join(separator?: string)",
+				parameters: [
+					{
+						label: "separator?: string",
+						documentation: "The separator to put between the strings (default: none).",
+					},
+				],
+				description:
+					"**Joins** a list of strings.\n\nExamples:<br/>`['a', 'b'].join(', ') == 'a, b'`",
+			},
+			{
+				name: "distinct",
+				for_type: ["value"],
+				insertTemplate: "distinct()",
+				description:
+					"Removes **repeated values** of a list, keeping the order of first occurrence.\n\nExamples:<br/>`[3,1,3].distinct() == [3,1]`",
+			},
+			{
+				name: "union",
+				for_type: ["value"],
+				insertTemplate: "union(${1:other})",
+				signatureLabel: "list union(other: list)",
+				parameters: [
+					{
+						label: "other: list",
+						documentation: "The list to unite with.",
+					},
+				],
+				description:
+					"The distinct values of **both lists**.\n\nExamples:<br/>`[1,2].union([2,3]) == [1,2,3]`",
+			},
+			{
+				name: "intersect",
+				for_type: ["value"],
+				insertTemplate: "intersect(${1:other})",
+				signatureLabel: "list intersect(other: list)",
+				parameters: [
+					{
+						label: "other: list",
+						documentation: "The list to intersect with.",
+					},
+				],
+				description:
+					"The distinct values **in both lists**.\n\nExamples:<br/>`[1,2,3].intersect([3,2]) == [2,3]`",
+			},
+			{
+				name: "difference",
+				for_type: ["value"],
+				insertTemplate: "difference(${1:other})",
+				signatureLabel: "list difference(other: list)",
+				parameters: [
+					{
+						label: "other: list",
+						documentation: "The list of values to remove.",
+					},
+				],
+				description:
+					"The distinct values of a list that are **not in the other list**.\n\nExamples:<br/>`[1,2,3].difference([2]) == [1,3]`",
+			},
+			{
+				name: "median",
+				for_type: ["value"],
+				insertTemplate: "median()",
+				description:
+					"The **median** of a list of numbers or of durations (`null` for an empty list).\n\nExamples:<br/>`[4,1,3,2].median() == 2.5`",
+			},
+			{
+				name: "percentile",
+				for_type: ["value"],
+				insertTemplate: "percentile(${1:p})",
+				signatureLabel: "double|duration percentile(p: number)",
+				parameters: [
+					{
+						label: "p: number",
+						documentation: "The percentile, from 0 to 100.",
+					},
+				],
+				description:
+					"The **p-th percentile** of a list of numbers or of durations, interpolating linearly between the closest ranks.\n\nExamples:<br/>`[1,2,3,4,5].percentile(25) == 2.0`",
+			},
 		],
 		[],
 	);
@@ -88,7 +88,9 @@ export function columnsForBindingRow(
 				const constraintIdx =
 					r !== null && typeof r === "object" && "ConstraintNotSatisfied" in r
 						? r.ConstraintNotSatisfied
-						: undefined;
+						: r !== null && typeof r === "object" && "SatisfiedChildsOutOfRange" in r
+							? r.SatisfiedChildsOutOfRange.constraint
+							: undefined;
 				if (constraintIdx === undefined) {
 					return (
 						<div className="h-4 flex items-center gap-x-2 w-[7.66rem]">
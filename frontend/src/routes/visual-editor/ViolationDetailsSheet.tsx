@@ -97,6 +97,7 @@ const ViolationDetailsSheet = memo(function ViolationDetailsSheet({
 												omitHeader: false,
 												labels,
 												format: "CSV",
+												includeExplanation: false,
 											} satisfies TableExportOptions as TableExportOptions
 										}
 										trigger={
@@ -20,6 +20,8 @@ import {
 	SelectValue,
 } from "@/components/ui/select";
 import { useBackend, useOcelInfo } from "@/hooks";
+import type { TranslationIssue } from "@/types/generated/TranslationIssue";
+import type { TranslationResult } from "@/types/generated/TranslationResult";
 import { evaluateConstraints } from "./evaluation/evaluate-constraints";
 import type { EventTypeLinkData, EventTypeNodeData, GateNodeData } from "./types";
 
@@ -46,12 +48,52 @@ function getTranslationValue(
 
 	return ret;
 }
+const LOCATION_NAMES = {
+	Filter: "filter",
+	SizeFilter: "size filter",
+	Constraint: "constraint",
+	Label: "label",
+} as const;
+
+function describeIssue({ nodeIndex, location, message }: TranslationIssue): string {
+	const where =
+		location.type === "Node" ? "" : `, ${LOCATION_NAMES[location.type]} ${location.index + 1}`;
+	return `Node ${nodeIndex}${where}: ${message}`;
+}
+
+function TranslationIssues({
+	title,
+	description,
+	issues,
+	className,
+}: {
+	title: string;
+	description: string;
+	issues: TranslationIssue[];
+	className: string;
+}) {
+	if (issues.length === 0) {
+		return null;
+	}
+	return (
+		<div className={`mt-2 rounded-md border p-2 text-sm ${className}`}>
+			<h4 className="font-semibold">{title}</h4>
+			<p className="text-xs text-muted-foreground mb-1">{description}</p>
+			<ul className="list-disc ml-4">
+				{issues.map((issue, i) => (
+					<li key={i}>{describeIssue(issue)}</li>
+				))}
+			</ul>
+		</div>
+	);
+}
+
 export default function DatabaseTranslationButton({
 	instance,
 }: {
 	instance: ReactFlowInstance<Node<EventTypeNodeData | GateNodeData>, Edge<EventTypeLinkData>>;
 }) {
-	const [result, setResult] = useState<string>();
+	const [result, setResult] = useState<TranslationResult>();
 
 	const ocelInfo = useOcelInfo();
 
@@ -74,7 +116,7 @@ export default function DatabaseTranslationButton({
 			}
 			title="Generate Database Query"
 			initialData={{
-				dialect: "SQLite" as "SQLite" | "DuckDB",
+				dialect: "SQLite" as "SQLite" | "DuckDB" | "PostgreSQL",
 				objectMapping: getTranslationValue(ocelInfo.object_types.map((ot) => ot.name)),
 				eventMapping: getTranslationValue(ocelInfo.event_types.map((et) => et.name)),
 			}}
@@ -98,6 +140,7 @@ export default function DatabaseTranslationButton({
 								<SelectContent>
 									<SelectItem value="SQLite">SQLite</SelectItem>
 									<SelectItem value="DuckDB">DuckDB</SelectItem>
+									<SelectItem value="PostgreSQL">PostgreSQL</SelectItem>
 								</SelectContent>
 							</Select>
 						</div>
@@ -359,7 +402,7 @@ export default function DatabaseTranslationButton({
 									title="Copy to clipboard"
 									className="absolute top-0 right-0"
 									onClick={() => {
-										navigator.clipboard.writeText(result);
+										navigator.clipboard.writeText(result.query);
 										toast.success("Copied Query");
 									}}
 								>
@@ -367,8 +410,20 @@ export default function DatabaseTranslationButton({
 								</Button>
 								<h3 className="font-semibold text-base mb-2">Generated Query</h3>
 								<pre className="bg-gray-100 p-2 rounded-md overflow-x-auto">
-									<code>{result}</code>
+									<code>{result.query}</code>
 								</pre>
+								<TranslationIssues
+									title="Not checked by the query"
+									description="The query can therefore find situations the evaluation does not."
+									issues={result.unsupported}
+									className="border-amber-300 bg-amber-50"
+								/>
+								<TranslationIssues
+									title="Not returned by the query"
+									description="The query checks these, but does not select them."
+									issues={result.warnings}
+									className="border-gray-200 bg-gray-50"
+								/>
 							</div>
 						)}
 					</div>
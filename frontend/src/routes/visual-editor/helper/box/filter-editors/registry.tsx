@@ -382,6 +382,12 @@ registerEditor<Filter & { type: "TimeBetweenEvents" }>(
 						})
 					}
 				/>
+				<Input
+					placeholder="Business Calendar (Optional)"
+					title="Only count the working time of this business calendar"
+					value={value.calendar ?? ""}
+					onChange={(ev) => updateValue({ ...value, calendar: ev.currentTarget.value || undefined })}
+				/>
 			</>
 		);
 	},
@@ -394,6 +400,7 @@ registerEditor<Filter & { type: "TimeBetweenEvents" }>(
 					{formatSeconds(value.min_seconds ?? Number.NEGATIVE_INFINITY)}{" "}
 					<span className="mx-1">-</span>{" "}
 					{formatSeconds(value.max_seconds ?? Number.POSITIVE_INFINITY)}
+					{value.calendar && <span className="ml-1">(working time: {value.calendar})</span>}
 				</div>
 			</div>
 		);
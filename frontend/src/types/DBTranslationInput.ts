@@ -1,12 +1,15 @@
 import type { BindingBoxTree } from "./generated/BindingBoxTree";
+import type { ParameterValue } from "./generated/ParameterValue";
 
 export type DBTranslationInput = {
 	tree: BindingBoxTree;
-	database: "SQLite" | "DuckDB";
+	database: "SQLite" | "DuckDB" | "PostgreSQL";
 	table_mappings: TableMappings;
+	parameters?: Record<string, ParameterValue>;
 };
 
 export type TableMappings = {
 	event_tables: Record<string, string>;
 	object_tables: Record<string, string>;
+	attribute_json_column?: string;
 };
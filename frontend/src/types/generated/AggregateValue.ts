@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { EventVariable } from "./EventVariable";
+import type { ObjectVariable } from "./ObjectVariable";
+import type { Variable } from "./Variable";
+
+/**
+ * What an aggregate reads from every child binding.
+ */
+export type AggregateValue = { "type": "EventAttribute", event: EventVariable, attribute_name: string, } | { "type": "ObjectAttribute", object: ObjectVariable, attribute_name: string, } | { "type": "Label", label: string, } | { "type": "Variable", variable: Variable, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The 13 relations of Allen's interval algebra, read as "first `relation` second".
+ */
+export type AllenRelation = "Before" | "Meets" | "Overlaps" | "Starts" | "During" | "Finishes" | "Equals" | "After" | "MetBy" | "OverlappedBy" | "StartedBy" | "Contains" | "FinishedBy";
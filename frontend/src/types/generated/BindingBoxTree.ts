@@ -1,4 +1,14 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { BindingBoxTreeNode } from "./BindingBoxTreeNode";
+import type { TreeParameter } from "./TreeParameter";
 
-export type BindingBoxTree = { nodes: Array<BindingBoxTreeNode>, edgeNames: Array<[[number, number], string]>, };
+export type BindingBoxTree = { nodes: Array<BindingBoxTreeNode>, edgeNames: Array<[[number, number], string]>, 
+/**
+ * Names of the CEL libraries (see [`crate::cel::library`]) the tree's expressions call
+ * functions of; evaluating fails if one of them is not loaded.
+ */
+celLibraries?: Array<string>, 
+/**
+ * Named values the tree is evaluated with (see [`super::parameters`])
+ */
+parameters?: Array<TreeParameter>, };
@@ -2,6 +2,11 @@
 import type { EventVariable } from "./EventVariable";
 import type { LabelValue } from "./LabelValue";
 import type { ObjectVariable } from "./ObjectVariable";
+import type { ViolationExplanation } from "./ViolationExplanation";
 import type { ViolationReason } from "./ViolationReason";
 
-export type BindingRow = { objects: Array<[ObjectVariable, string]>, events: Array<[EventVariable, string]>, labels: Array<[string, LabelValue]>, violation: ViolationReason | null, };
+export type BindingRow = { objects: Array<[ObjectVariable, string]>, events: Array<[EventVariable, string]>, labels: Array<[string, LabelValue]>, violation: ViolationReason | null, 
+/**
+ * Only for violated situations of an evaluation run with explanations
+ */
+explanation?: ViolationExplanation, };
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WorkingHours } from "./WorkingHours";
+
+/**
+ * A business calendar as it is written down (see [`BusinessCalendar`])
+ */
+export type CalendarSpec = { 
+/**
+ * Fixed UTC offset the working hours and days are in, like `+01:00` (or `UTC`)
+ */
+timezone: string, 
+/**
+ * ISO weekday numbers (1 is Monday, 7 is Sunday)
+ */
+workingDays: Array<number>, workingHours: Array<WorkingHours>, holidays: Array<string>, 
+/**
+ * `.ics` or `.json` (a list of dates) files with more holidays, relative to the calendar
+ * file; only read by [`load_calendar_file`]
+ */
+holidayFiles?: Array<string>, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A CEL function of a [`CelLibrary`]: `body`, evaluated with `params` bound to the arguments
+ */
+export type CelFunctionDef = { name: string, params: Array<string>, body: string, description?: string, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CelFunctionDef } from "./CelFunctionDef";
+
+/**
+ * A named set of CEL functions (see the module documentation)
+ */
+export type CelLibrary = { name: string, functions: Array<CelFunctionDef>, };
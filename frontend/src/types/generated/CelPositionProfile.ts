@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TreeLocation } from "./TreeLocation";
+
+/**
+ * Calls of one CEL program of one node
+ */
+export type CelPositionProfile = { nodeIndex: number, 
+/**
+ * Where the program is in the node; several if it is written there more than once, which
+ * then share these counts.
+ */
+locations: Array<TreeLocation>, cel: string, calls: number, errors: number, totalMs: number, };
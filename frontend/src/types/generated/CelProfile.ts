@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CelPositionProfile } from "./CelPositionProfile";
+import type { CelProgramProfile } from "./CelProgramProfile";
+
+/**
+ * The CEL profile of an evaluation, both sorted by descending total time.
+ */
+export type CelProfile = { programs: Array<CelProgramProfile>, positions: Array<CelPositionProfile>, };
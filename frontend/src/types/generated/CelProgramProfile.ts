@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Calls of one CEL program, over all positions it has in the tree
+ */
+export type CelProgramProfile = { cel: string, calls: number, errors: number, totalMs: number, };
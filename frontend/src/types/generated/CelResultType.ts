@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CelType } from "./CelType";
+import type { TreeLocation } from "./TreeLocation";
+
+/**
+ * The inferred result type of one CEL expression of the tree
+ */
+export type CelResultType = { nodeIndex: number, location: TreeLocation, cel: string, resultType: CelType, };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { LabelValue } from "./LabelValue";
+
+export type CelSubExpressionValue = { expression: string, 
+/**
+ * `None` if evaluating the expression failed
+ */
+value: LabelValue | null, error: string | null, };
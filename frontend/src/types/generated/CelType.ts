@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Result type of a CEL expression, as far as it can be told without evaluating it
+ */
+export type CelType = "Bool" | "Int" | "Float" | "String" | "Timestamp" | "Duration" | "List" | "Map" | "Null" | "Unknown";
@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AggregateFunction } from "./AggregateFunction";
+import type { AggregateValue } from "./AggregateValue";
+
+/**
+ * An aggregate of `value` over the bindings of the child `child_name`.
+ */
+export type ChildAggregate = { childName: string, function: AggregateFunction, 
+/**
+ * Not needed for [`AggregateFunction::Count`]
+ */
+value?: AggregateValue, };
@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { WitnessBinding } from "./WitnessBinding";
+
+export type ChildWitness = { childName: string, bindingCount: number, satisfiedCount: number, 
+/**
+ * Child bindings that made the constraint fail (e.g., the violated ones for `SAT`), at most
+ * [`MAX_WITNESSES_PER_CHILD`]
+ */
+offending: Array<WitnessBinding>, 
+/**
+ * How many more (satisfied) child bindings the constraint needed
+ */
+missing: number, };
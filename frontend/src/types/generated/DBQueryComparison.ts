@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SituationMismatch } from "./SituationMismatch";
+
+/**
+ * How the situations of a query differ from those of the in-memory evaluation
+ */
+export type DBQueryComparison = { databaseSituationCount: number, databaseViolatedCount: number, memorySituationCount: number, memoryViolatedCount: number, 
+/**
+ * Rows the query returned more than once
+ */
+duplicateRows: number, 
+/**
+ * Whether the in-memory evaluation stopped early, so it may miss situations the query has
+ */
+memoryBindingsSkipped: boolean, mismatches: Array<SituationMismatch>, };
@@ -0,0 +1,26 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DBQueryComparison } from "./DBQueryComparison";
+import type { EvaluationResultWithCount } from "./EvaluationResultWithCount";
+import type { TranslationIssue } from "./TranslationIssue";
+
+/**
+ * What running a translated query against an OCEL database file gave
+ */
+export type DBQueryResult = { 
+/**
+ * The SQL that was run
+ */
+query: string, 
+/**
+ * What of the tree the query leaves out, which the comparison can disagree on
+ */
+unsupported: Array<TranslationIssue>, 
+/**
+ * The root node's situations, one per row. A violated one has the reason
+ * [`ViolationReason::UnknownChildSet`], as the query does not say which constraint failed.
+ */
+result: EvaluationResultWithCount, 
+/**
+ * Only in differential mode, which compares with the in-memory evaluation
+ */
+comparison: DBQueryComparison | null, };
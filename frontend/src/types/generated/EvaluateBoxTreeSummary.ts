@@ -1,13 +1,23 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CelProfile } from "./CelProfile";
+import type { LimitHit } from "./LimitHit";
 import type { NodeSummary } from "./NodeSummary";
 
 export type EvaluateBoxTreeSummary = { 
 /**
  * One entry per evaluation node, index-aligned with `evaluation_results`.
  */
-nodeSummaries: Array<NodeSummary>, bindingsSkipped: boolean, 
+nodeSummaries: Array<NodeSummary>, bindingsSkipped: boolean, limitHits: Array<LimitHit>, 
 /**
  * Monotonic version counter. Every new evaluation bumps it; page requests
  * must carry the version they think is current, or the server rejects.
  */
-evalVersion: number, };
+evalVersion: number, 
+/**
+ * The time the evaluation treated as "now"; events after it were not bound.
+ */
+asOf?: string, 
+/**
+ * Calls of each CEL program, if the evaluation was profiled.
+ */
+celProfile?: CelProfile, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type EvaluationLimits = { 
+/**
+ * Bindings a single expansion of a node may produce; `None` for no limit.
+ */
+maxBindingsPerNode: number | null, 
+/**
+ * Estimated size of all bindings materialized during the evaluation, in MiB.
+ */
+maxMemoryMb: number | null, 
+/**
+ * Wall-clock time the evaluation may take. Not enforced on wasm, which has no clock.
+ */
+timeoutSeconds: number | null, };
@@ -0,0 +1,15 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type EvaluationProgress = { 
+/**
+ * Id the evaluation was started under, if any (see [`register_running_evaluation`]).
+ */
+evaluationId: string | null, 
+/**
+ * Node whose expansion crossed the last reporting threshold.
+ */
+nodeIndex: number, 
+/**
+ * Bindings expanded so far, over all nodes.
+ */
+bindingsExpanded: number, };
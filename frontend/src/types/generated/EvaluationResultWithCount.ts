@@ -1,5 +1,11 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { Binding } from "./Binding";
+import type { ViolationExplanation } from "./ViolationExplanation";
 import type { ViolationReason } from "./ViolationReason";
 
-export type EvaluationResultWithCount = { situations: Array<[Binding, ViolationReason | null]>, situationCount: number, situationViolatedCount: number, };
+export type EvaluationResultWithCount = { situations: Array<[Binding, ViolationReason | null]>, situationCount: number, situationViolatedCount: number, 
+/**
+ * Explanations of the violated situations, by their index in `situations`. Only filled in
+ * by [`EvaluateBoxTreeResult::explain_violations`].
+ */
+explanations: { [key in number]?: ViolationExplanation }, };
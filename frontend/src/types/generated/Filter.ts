@@ -1,9 +1,15 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AllenRelation } from "./AllenRelation";
 import type { EventVariable } from "./EventVariable";
 import type { FilterLabel } from "./FilterLabel";
 import type { ObjectValueFilterTimepoint } from "./ObjectValueFilterTimepoint";
 import type { ObjectVariable } from "./ObjectVariable";
+import type { ObjectWindow } from "./ObjectWindow";
 import type { ValueFilter } from "./ValueFilter";
 import type { Variable } from "./Variable";
 
-export type Filter = { "type": "O2E", object: ObjectVariable, event: EventVariable, qualifier: string | null, filterLabel?: FilterLabel, } | { "type": "O2O", object: ObjectVariable, other_object: ObjectVariable, qualifier: string | null, filterLabel?: FilterLabel, } | { "type": "TimeBetweenEvents", from_event: EventVariable, to_event: EventVariable, min_seconds: number | null, max_seconds: number | null, } | { "type": "NotEqual", var_1: Variable, var_2: Variable, } | { "type": "EventAttributeValueFilter", event: EventVariable, attribute_name: string, value_filter: ValueFilter, } | { "type": "ObjectAttributeValueFilter", object: ObjectVariable, attribute_name: string, at_time: ObjectValueFilterTimepoint, value_filter: ValueFilter, } | { "type": "BasicFilterCEL", cel: string, };
+export type Filter = { "type": "O2E", object: ObjectVariable, event: EventVariable, qualifier: string | null, filterLabel?: FilterLabel, } | { "type": "O2O", object: ObjectVariable, other_object: ObjectVariable, qualifier: string | null, filterLabel?: FilterLabel, } | { "type": "TimeBetweenEvents", from_event: EventVariable, to_event: EventVariable, min_seconds: number | null, max_seconds: number | null, 
+/**
+ * Measure only the working time of this business calendar (see [`crate::calendar`])
+ */
+calendar?: string, } | { "type": "DirectlyFollows", from_event: EventVariable, to_event: EventVariable, object: ObjectVariable, } | { "type": "NoEventBetween", from_event: EventVariable, to_event: EventVariable, event_types: Array<string>, object?: ObjectVariable, } | { "type": "EventInObjectWindow", event: EventVariable, object: ObjectVariable, window: ObjectWindow, } | { "type": "IntervalRelation", first_start: EventVariable, first_end: EventVariable, second_start: EventVariable, second_end: EventVariable, relation: AllenRelation, } | { "type": "NotEqual", var_1: Variable, var_2: Variable, } | { "type": "EventAttributeValueFilter", event: EventVariable, attribute_name: string, value_filter: ValueFilter, } | { "type": "ObjectAttributeValueFilter", object: ObjectVariable, attribute_name: string, at_time: ObjectValueFilterTimepoint, value_filter: ValueFilter, } | { "type": "BasicFilterCEL", cel: string, };
@@ -1,3 +1,12 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChildAggregate } from "./ChildAggregate";
 
-export type LabelFunction = { label: string, cel: string, };
+export type LabelFunction = { label: string, 
+/**
+ * CEL expression computing the label; not used if `aggregate` is set
+ */
+cel: string, 
+/**
+ * Native aggregate over a child's bindings, computed instead of `cel`
+ */
+aggregate?: ChildAggregate, };
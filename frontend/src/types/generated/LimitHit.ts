@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { EvaluationLimitKind } from "./EvaluationLimitKind";
+
+export type LimitHit = { nodeIndex: number, limit: EvaluationLimitKind, 
+/**
+ * Bindings that were enumerated but then discarded. Enumeration stops at the limit, so the
+ * number of bindings missing from the result can be higher.
+ */
+bindingsDropped: number, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ValueFilter } from "./ValueFilter";
+
+/**
+ * Time windows derived from the history of an object.
+ */
+export type ObjectWindow = { "type": "Lifespan" } | { "type": "WhileAttribute", attribute_name: string, value_filter: ValueFilter, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Which bound of its target a parameter sets
+ */
+export type ParameterBound = "Min" | "Max" | "MinFraction" | "MaxFraction" | "Value";
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * The type of a tree parameter
+ */
+export type ParameterType = "Int" | "Float" | "String" | "Bool" | "Timestamp" | "Duration";
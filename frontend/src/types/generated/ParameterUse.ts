@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ParameterBound } from "./ParameterBound";
+import type { TreeLocation } from "./TreeLocation";
+
+/**
+ * A bound of a filter, size filter or constraint that a parameter sets
+ */
+export type ParameterUse = { nodeIndex: number, location: TreeLocation, bound: ParameterBound, };
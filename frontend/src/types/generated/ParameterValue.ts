@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A value of a tree parameter
+ */
+export type ParameterValue = { "type": "Int", "value": number } | { "type": "Float", "value": number } | { "type": "String", "value": string } | { "type": "Bool", "value": boolean } | { "type": "Timestamp", "value": string } | { "type": "Duration", "value": number };
@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A situation only one side found, or that only one side found violated
+ */
+export type SituationMismatch = { 
+/**
+ * The id bound to each variable, by its name (like `e1`)
+ */
+binding: { [key in string]: string }, 
+/**
+ * Whether the database found it violated, or `None` if it did not find it
+ */
+databaseViolated: boolean | null, 
+/**
+ * Whether the in-memory evaluation found it violated, or `None` if it did not find it
+ */
+memoryViolated: boolean | null, };
@@ -1,4 +1,9 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { TableExportFormat } from "./TableExportFormat";
 
-export type TableExportOptions = { includeViolationStatus: boolean, includeIds: boolean, omitHeader: boolean, labels: Array<string>, format: TableExportFormat, };
+export type TableExportOptions = { includeViolationStatus: boolean, includeIds: boolean, omitHeader: boolean, labels: Array<string>, format: TableExportFormat, 
+/**
+ * Add a column explaining each violated situation. Situations of a stored evaluation only
+ * have one if it was run with explanations; streamed ones are explained as they are written.
+ */
+includeExplanation: boolean, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TreeLocation } from "./TreeLocation";
+
+/**
+ * Something of a tree the translation leaves out (or, as a warning, does not return)
+ */
+export type TranslationIssue = { nodeIndex: number, location: TreeLocation, message: string, };
@@ -0,0 +1,21 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ParameterValue } from "./ParameterValue";
+import type { TranslationIssue } from "./TranslationIssue";
+
+/**
+ * A query translated from a tree, with what of the tree it does not express
+ */
+export type TranslationResult = { query: string, 
+/**
+ * What the query checks but does not return, like the labels
+ */
+warnings: Array<TranslationIssue>, 
+/**
+ * What the query does not check, so it can find bindings (or satisfied bindings) the
+ * evaluation does not
+ */
+unsupported: Array<TranslationIssue>, 
+/**
+ * The value of each of the tree's parameters the query was translated with, defaults included
+ */
+parameters: { [key in string]: ParameterValue }, };
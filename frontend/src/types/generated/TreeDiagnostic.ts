@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DiagnosticSeverity } from "./DiagnosticSeverity";
+import type { TreeLocation } from "./TreeLocation";
+
+export type TreeDiagnostic = { nodeIndex: number, location: TreeLocation, severity: DiagnosticSeverity, message: string, };
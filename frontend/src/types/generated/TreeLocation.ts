@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Where in a node a diagnostic or CEL expression is
+ */
+export type TreeLocation = { "type": "Node" } | { "type": "Filter", index: number, } | { "type": "SizeFilter", index: number, } | { "type": "Constraint", index: number, } | { "type": "Label", index: number, };
@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ParameterType } from "./ParameterType";
+import type { ParameterUse } from "./ParameterUse";
+import type { ParameterValue } from "./ParameterValue";
+
+/**
+ * A named value of a tree, given when it is evaluated
+ */
+export type TreeParameter = { 
+/**
+ * Also the name of the CEL variable
+ */
+name: string, parameterType: ParameterType, 
+/**
+ * Used if an evaluation does not give a value; without one, the parameter must be given.
+ */
+default?: ParameterValue, uses?: Array<ParameterUse>, };
@@ -0,0 +1,5 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CelResultType } from "./CelResultType";
+import type { TreeDiagnostic } from "./TreeDiagnostic";
+
+export type TreeValidation = { diagnostics: Array<TreeDiagnostic>, celTypes: Array<CelResultType>, };
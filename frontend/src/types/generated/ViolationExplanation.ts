@@ -0,0 +1,18 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CelSubExpressionValue } from "./CelSubExpressionValue";
+import type { ChildWitness } from "./ChildWitness";
+import type { ViolationReason } from "./ViolationReason";
+
+export type ViolationExplanation = { 
+/**
+ * Index of the first constraint of the box that is not satisfied
+ */
+constraint: number, reason: ViolationReason, 
+/**
+ * The children the failed constraint looks at
+ */
+children: Array<ChildWitness>, 
+/**
+ * The failed CEL expression and its parts, each with the value it evaluated to
+ */
+celValues: Array<CelSubExpressionValue>, };
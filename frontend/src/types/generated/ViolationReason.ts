@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export type ViolationReason = { "TooFewMatchingEvents": number } | { "TooManyMatchingEvents": number } | "NoChildrenOfORSatisfied" | "LeftChildOfANDUnsatisfied" | "RightChildOfANDUnsatisfied" | "BothChildrenOfANDUnsatisfied" | "ChildrenOfNOTSatisfied" | "ChildNotSatisfied" | { "ConstraintNotSatisfied": number } | "UnknownChildSet";
+export type ViolationReason = { "TooFewMatchingEvents": number } | { "TooManyMatchingEvents": number } | "NoChildrenOfORSatisfied" | "LeftChildOfANDUnsatisfied" | "RightChildOfANDUnsatisfied" | "BothChildrenOfANDUnsatisfied" | "ChildrenOfNOTSatisfied" | "ChildNotSatisfied" | { "ConstraintNotSatisfied": number } | "UnknownChildSet" | { "SatisfiedChildsOutOfRange": { constraint: number, satisfied: number, total: number, } };
@@ -0,0 +1,8 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { EventVariable } from "./EventVariable";
+import type { ObjectVariable } from "./ObjectVariable";
+
+/**
+ * A child binding, with only the variables the child binds itself.
+ */
+export type WitnessBinding = { objects: Array<[ObjectVariable, string]>, events: Array<[EventVariable, string]>, satisfied: boolean, };
@@ -0,0 +1,6 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * Working hours of a day, like `09:00` to `17:00` (`24:00` ends at midnight)
+ */
+export type WorkingHours = { start: string, end: string, };
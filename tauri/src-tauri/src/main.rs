@@ -360,13 +360,15 @@ fn main() {
                         }
                     }
                 }
-                app.manage(Arc::new(TauriBackend {
+                let backend = Arc::new(TauriBackend {
                     state: ExtendedAppState::default(),
                     app: app.handle().clone(),
                     hpc_client: RwLock::new(None),
                     hpc_jobs: JobForwards::default(),
                     initial_files: Mutex::new(Some(files)),
-                }));
+                });
+                backend_shared::forward_evaluation_progress(Arc::clone(&backend));
+                app.manage(backend);
                 Ok(())
             },
         )